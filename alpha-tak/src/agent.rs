@@ -1,36 +1,202 @@
-use std::sync::mpsc::{Receiver, Sender};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    str::FromStr,
+    sync::mpsc::{Receiver, Sender},
+};
 
 use tak::*;
+#[cfg(feature = "torch")]
 use tch::Device;
 
+#[cfg(feature = "torch")]
 use crate::{model::network::Network, repr::game_repr};
+use crate::{rng, search::turn_map::Lut};
 
 pub trait Agent<const N: usize> {
     fn policy_and_eval(&self, game: &Game<N>) -> (Vec<f32>, f32);
+
+    /// Policies and evals of many positions, which agents that can evaluate
+    /// them together should do in one batch.
+    fn policy_and_eval_batch(&self, games: &[Game<N>]) -> (Vec<Vec<f32>>, Vec<f32>) {
+        games.iter().map(|game| self.policy_and_eval(game)).unzip()
+    }
 }
 
+#[cfg(feature = "torch")]
 impl<const N: usize> Agent<N> for Network<N> {
     fn policy_and_eval(&self, game: &Game<N>) -> (Vec<f32>, f32) {
         let input = game_repr(game).to_device(Device::cuda_if_available());
         let (policy, eval) = self.forward_mcts(input.unsqueeze(0));
         (policy.into(), eval.into())
     }
+
+    fn policy_and_eval_batch(&self, games: &[Game<N>]) -> (Vec<Vec<f32>>, Vec<f32>) {
+        self.policy_eval_batch(games)
+    }
 }
 
+/// Agent of one worker of a thread pool, which sends its positions tagged
+/// with the worker to the thread evaluating the batches.
 pub struct Batcher<const N: usize> {
-    tx: Sender<Game<N>>,
+    worker: usize,
+    tx: Sender<(usize, Game<N>)>,
     rx: Receiver<(Vec<f32>, f32)>,
 }
 
 impl<const N: usize> Batcher<N> {
-    pub fn new(tx: Sender<Game<N>>, rx: Receiver<(Vec<f32>, f32)>) -> Self {
-        Batcher { tx, rx }
+    pub fn new(worker: usize, tx: Sender<(usize, Game<N>)>, rx: Receiver<(Vec<f32>, f32)>) -> Self {
+        Batcher { worker, tx, rx }
     }
 }
 
 impl<const N: usize> Agent<N> for Batcher<N> {
     fn policy_and_eval(&self, game: &Game<N>) -> (Vec<f32>, f32) {
-        self.tx.send(game.clone()).unwrap();
+        self.tx.send((self.worker, game.clone())).unwrap();
         self.rx.recv().unwrap()
     }
 }
+
+/// How an [`Ensemble`] combines its agents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnsembleMode {
+    /// Average the policies and evals of every agent.
+    Average,
+    /// Ask a single agent per position, chosen by the position, so each node
+    /// of the tree sees one agent and the search mixes them.
+    Interleave,
+}
+
+impl FromStr for EnsembleMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "average" => Ok(EnsembleMode::Average),
+            "interleave" => Ok(EnsembleMode::Interleave),
+            _ => Err(format!(
+                "unknown ensemble mode `{s}`, expected average or interleave"
+            )),
+        }
+    }
+}
+
+/// Several agents searched as one, for example an old stable network with a
+/// new sharp one.
+pub struct Ensemble<A> {
+    agents: Vec<A>,
+    mode: EnsembleMode,
+}
+
+impl<A> Ensemble<A> {
+    pub fn new(agents: Vec<A>, mode: EnsembleMode) -> Self {
+        assert!(!agents.is_empty(), "an ensemble needs at least one agent");
+        Ensemble { agents, mode }
+    }
+
+    /// The first agent, which is the main one of the ensemble.
+    pub fn main(&self) -> &A {
+        &self.agents[0]
+    }
+}
+
+impl<const N: usize, A: Agent<N>> Agent<N> for Ensemble<A> {
+    fn policy_and_eval(&self, game: &Game<N>) -> (Vec<f32>, f32) {
+        match self.mode {
+            _ if self.agents.len() == 1 => self.agents[0].policy_and_eval(game),
+            EnsembleMode::Average => {
+                let count = self.agents.len() as f32;
+                let mut outputs = self.agents.iter().map(|agent| agent.policy_and_eval(game));
+                let (mut policy, mut eval) = outputs.next().unwrap();
+                for (other_policy, other_eval) in outputs {
+                    policy
+                        .iter_mut()
+                        .zip(other_policy)
+                        .for_each(|(p, other)| *p += other);
+                    eval += other_eval;
+                }
+                policy.iter_mut().for_each(|p| *p /= count);
+                (policy, eval / count)
+            }
+            EnsembleMode::Interleave => {
+                let mut hasher = DefaultHasher::new();
+                game.hash(&mut hasher);
+                let index = hasher.finish() as usize % self.agents.len();
+                self.agents[index].policy_and_eval(game)
+            }
+        }
+    }
+
+    fn policy_and_eval_batch(&self, games: &[Game<N>]) -> (Vec<Vec<f32>>, Vec<f32>) {
+        match self.mode {
+            _ if self.agents.len() == 1 => self.agents[0].policy_and_eval_batch(games),
+            EnsembleMode::Average => {
+                let count = self.agents.len() as f32;
+                let mut outputs = self.agents.iter().map(|agent| agent.policy_and_eval_batch(games));
+                let (mut policies, mut evals) = outputs.next().unwrap();
+                for (other_policies, other_evals) in outputs {
+                    for (policy, other_policy) in policies.iter_mut().zip(other_policies) {
+                        policy
+                            .iter_mut()
+                            .zip(other_policy)
+                            .for_each(|(p, other)| *p += other);
+                    }
+                    evals
+                        .iter_mut()
+                        .zip(other_evals)
+                        .for_each(|(e, other)| *e += other);
+                }
+                policies.iter_mut().flatten().for_each(|p| *p /= count);
+                evals.iter_mut().for_each(|e| *e /= count);
+                (policies, evals)
+            }
+            // each position goes to its own agent
+            EnsembleMode::Interleave => games.iter().map(|game| self.policy_and_eval(game)).unzip(),
+        }
+    }
+}
+
+/// Evaluates every position under a random symmetry of the board and maps
+/// the policy back, so that the search does not inherit a bias of the
+/// network towards one orientation.
+pub struct RandomSymmetry<'a, A> {
+    agent: &'a A,
+    /// For each symmetry, the index of the symmetric turn for every index of
+    /// the policy vector.
+    indices: [Vec<usize>; 8],
+}
+
+impl<'a, A> RandomSymmetry<'a, A> {
+    pub fn new<const N: usize>(agent: &'a A) -> Self
+    where
+        A: Agent<N>,
+        Turn<N>: Lut,
+    {
+        let mut indices: [Vec<usize>; 8] = Default::default();
+        for turn in Turn::<N>::all_turns() {
+            for (i, symmetry) in turn.symmetries().into_iter().enumerate() {
+                indices[i].push(symmetry.turn_map());
+            }
+        }
+        RandomSymmetry { agent, indices }
+    }
+}
+
+impl<const N: usize, A: Agent<N>> Agent<N> for RandomSymmetry<'_, A>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    fn policy_and_eval(&self, game: &Game<N>) -> (Vec<f32>, f32) {
+        let symmetry = rng::random::<usize>() % 8;
+        if symmetry == 0 {
+            return self.agent.policy_and_eval(game);
+        }
+        let symmetric = &game.clone().symmetries()[symmetry];
+        let (policy, eval) = self.agent.policy_and_eval(symmetric);
+        let policy = self.indices[symmetry]
+            .iter()
+            .map(|&index| policy[index])
+            .collect();
+        (policy, eval)
+    }
+}
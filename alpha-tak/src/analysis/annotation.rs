@@ -0,0 +1,164 @@
+use std::fmt::{self, Display};
+
+use tak::*;
+
+use crate::search::children::Children;
+
+/// Fewest visits a move needs for its evaluation to be trusted.
+const MIN_VISITS: u32 = 20;
+/// Evaluation lost compared to the best move for each grade.
+const INACCURACY_LOSS: f32 = 0.1;
+const MISTAKE_LOSS: f32 = 0.25;
+const BLUNDER_LOSS: f32 = 0.5;
+/// How much better the best move has to be than every other one for
+/// finding it to count as good.
+const GOOD_MARGIN: f32 = 0.3;
+
+/// How bad a move is by the evaluation it loses compared to the best move,
+/// from the perspective of its player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Grade {
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Grade {
+    pub fn from_loss(loss: f32) -> Self {
+        if loss >= BLUNDER_LOSS {
+            Grade::Blunder
+        } else if loss >= MISTAKE_LOSS {
+            Grade::Mistake
+        } else if loss >= INACCURACY_LOSS {
+            Grade::Inaccuracy
+        } else {
+            Grade::Good
+        }
+    }
+}
+
+impl Display for Grade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Grade::Good => "good",
+            Grade::Inaccuracy => "inaccuracy",
+            Grade::Mistake => "mistake",
+            Grade::Blunder => "blunder",
+        };
+        f.pad(name)
+    }
+}
+
+/// Judgement of a played move from the search, written after it in the PTN.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Annotation {
+    Good,
+    Dubious,
+    Mistake,
+    Blunder,
+}
+
+impl Annotation {
+    /// Judge the played move by how much of the evaluation of the most
+    /// visited move it gives up. Moves which the search did not look at
+    /// enough are not judged.
+    pub fn judge<const N: usize>(children: &Children<N>, played: &Turn<N>) -> Option<Self> {
+        let played_node = children.get(played)?;
+        let mut ranked: Vec<_> = children
+            .iter()
+            .filter(|(_, node)| node.visited_count >= MIN_VISITS)
+            .collect();
+        ranked.sort_by_key(|(_, node)| std::cmp::Reverse(node.visited_count));
+        let (best, best_node) = ranked.first()?;
+        if played_node.visited_count < MIN_VISITS {
+            return None;
+        }
+
+        if *best == played {
+            let margin = ranked
+                .get(1)
+                .map(|(_, second)| best_node.expected_reward - second.expected_reward)?;
+            return if margin >= GOOD_MARGIN {
+                Some(Annotation::Good)
+            } else {
+                None
+            };
+        }
+        match Grade::from_loss(best_node.expected_reward - played_node.expected_reward) {
+            Grade::Good => None,
+            Grade::Inaccuracy => Some(Annotation::Dubious),
+            Grade::Mistake => Some(Annotation::Mistake),
+            Grade::Blunder => Some(Annotation::Blunder),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Annotation::Good => "good",
+            Annotation::Dubious => "dubious",
+            Annotation::Mistake => "mistake",
+            Annotation::Blunder => "blunder",
+        }
+    }
+}
+
+impl ToPTN for Annotation {
+    fn to_ptn(&self) -> String {
+        match self {
+            Annotation::Good => "!",
+            Annotation::Dubious => "?!",
+            Annotation::Mistake => "?",
+            Annotation::Blunder => "??",
+        }
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tak::*;
+
+    use super::{Annotation, Grade};
+    use crate::search::{children::Children, node::Node};
+
+    fn children(stats: &[(&str, u32, f32)]) -> Children<5> {
+        stats
+            .iter()
+            .map(|&(ptn, visited_count, expected_reward)| {
+                let node = Node {
+                    visited_count,
+                    expected_reward,
+                    ..Default::default()
+                };
+                (Turn::from_ptn(ptn).unwrap(), node)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn judge_moves() {
+        let turn = |ptn| Turn::from_ptn(ptn).unwrap();
+        let only_move = children(&[("a1", 500, 0.5), ("b1", 100, -0.2), ("c1", 5, 0.9)]);
+        assert_eq!(Annotation::judge(&only_move, &turn("a1")), Some(Annotation::Good));
+        assert_eq!(
+            Annotation::judge(&only_move, &turn("b1")),
+            Some(Annotation::Blunder)
+        );
+        // too few visits to trust the evaluation
+        assert_eq!(Annotation::judge(&only_move, &turn("c1")), None);
+
+        let close = children(&[("a1", 300, 0.3), ("b1", 200, 0.15), ("c1", 100, 0.)]);
+        assert_eq!(Annotation::judge(&close, &turn("a1")), None);
+        assert_eq!(Annotation::judge(&close, &turn("b1")), Some(Annotation::Dubious));
+        assert_eq!(Annotation::judge(&close, &turn("c1")), Some(Annotation::Mistake));
+    }
+
+    #[test]
+    fn grade_losses() {
+        assert_eq!(Grade::from_loss(0.), Grade::Good);
+        assert_eq!(Grade::from_loss(0.1), Grade::Inaccuracy);
+        assert_eq!(Grade::from_loss(0.3), Grade::Mistake);
+        assert_eq!(Grade::from_loss(1.5), Grade::Blunder);
+    }
+}
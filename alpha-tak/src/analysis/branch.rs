@@ -1,7 +1,7 @@
 use arrayvec::ArrayVec;
 use tak::*;
 
-use super::{move_info::MoveInfo, MAX_BRANCH_LENGTH};
+use super::{line_ptn, move_info::MoveInfo, MAX_BRANCH_LENGTH};
 
 pub struct Branch<const N: usize> {
     pub ply: usize,
@@ -9,11 +9,16 @@ pub struct Branch<const N: usize> {
     pub info: MoveInfo,
 }
 
-impl<const N: usize> ToPTN for Branch<N> {
-    fn to_ptn(&self) -> String {
+impl<const N: usize> Branch<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// PTN of the branch played from `start`, the position at its ply,
+    /// which is needed to mark crushes.
+    pub fn to_ptn_from(&self, start: Option<Game<N>>) -> String {
         let mut out = format!("{{{}_{}}}\n", self.ply, self.line.first().unwrap().to_ptn());
 
-        let mut turn_iter = self.line.iter().map(|t| t.to_ptn());
+        let mut turn_iter = line_ptn(start, &self.line).into_iter();
         let mut move_num = 1 + self.ply / 2;
 
         // first move includes eval comment so it is handled differently
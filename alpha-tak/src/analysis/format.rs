@@ -0,0 +1,139 @@
+use std::str::FromStr;
+
+use tak::*;
+
+/// Where the result of a finished game is written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResultPlacement {
+    Omit,
+    Header,
+    MoveList,
+    Both,
+}
+
+impl ResultPlacement {
+    pub(super) fn header(self) -> bool {
+        matches!(self, ResultPlacement::Header | ResultPlacement::Both)
+    }
+
+    pub(super) fn move_list(self) -> bool {
+        matches!(self, ResultPlacement::MoveList | ResultPlacement::Both)
+    }
+}
+
+impl FromStr for ResultPlacement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "omit" => Ok(ResultPlacement::Omit),
+            "header" => Ok(ResultPlacement::Header),
+            "moves" => Ok(ResultPlacement::MoveList),
+            "both" => Ok(ResultPlacement::Both),
+            _ => Err(format!(
+                "unknown result placement `{s}`, expected omit, header, moves or both"
+            )),
+        }
+    }
+}
+
+/// Options for writing an analysis as PTN.
+#[derive(Clone, Debug)]
+pub struct PtnFormat {
+    /// Full moves (white and black turn) written on each line.
+    pub moves_per_line: usize,
+    /// Whether each full move starts with its number.
+    pub move_numbers: bool,
+    pub result: ResultPlacement,
+    /// Headers named here are written first in this order,
+    /// the remaining ones follow in their usual order.
+    pub header_order: Vec<String>,
+    /// Longest forced win in plies to look for before each turn and note
+    /// when it was missed, 0 skips the search.
+    pub threat_depth: u32,
+}
+
+impl Default for PtnFormat {
+    fn default() -> Self {
+        PtnFormat {
+            moves_per_line: 1,
+            move_numbers: true,
+            result: ResultPlacement::Omit,
+            header_order: Vec::new(),
+            threat_depth: 0,
+        }
+    }
+}
+
+impl PtnFormat {
+    pub(super) fn headers(&self, mut headers: Vec<(&str, String)>) -> String {
+        let mut ordered = Vec::new();
+        for name in &self.header_order {
+            if let Some(i) = headers.iter().position(|(key, _)| key == name) {
+                ordered.push(headers.remove(i));
+            }
+        }
+        ordered
+            .into_iter()
+            .chain(headers)
+            .map(|(key, value)| format!("[{key} \"{value}\"]\n"))
+            .collect()
+    }
+}
+
+/// PTN notation of a game result, if the game is over.
+pub fn result_ptn(result: GameResult) -> Option<&'static str> {
+    match result {
+        GameResult::Winner { colour, reason } => Some(match (colour, reason) {
+            (Colour::White, WinReason::Road) => "R-0",
+            (Colour::White, WinReason::Flats) => "F-0",
+            (Colour::White, WinReason::Time | WinReason::Resignation) => "1-0",
+            (Colour::Black, WinReason::Road) => "0-R",
+            (Colour::Black, WinReason::Flats) => "0-F",
+            (Colour::Black, WinReason::Time | WinReason::Resignation) => "0-1",
+        }),
+        GameResult::Draw { .. } => Some("1/2-1/2"),
+        GameResult::Ongoing => None,
+    }
+}
+
+/// Date of a UNIX time in seconds as written in the `Date` tag of PTN.
+pub fn ptn_date(time: u64) -> String {
+    // civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (time / 86400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year}.{month:02}.{day:02}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ptn_date, PtnFormat};
+
+    #[test]
+    fn header_order() {
+        let format = PtnFormat {
+            header_order: vec!["Komi".to_string(), "Missing".to_string()],
+            ..Default::default()
+        };
+        let headers = vec![("Size", "5".to_string()), ("Komi", "2".to_string())];
+        assert_eq!(format.headers(headers), "[Komi \"2\"]\n[Size \"5\"]\n");
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(ptn_date(0), "1970.01.01");
+        assert_eq!(ptn_date(951_782_400), "2000.02.29");
+        assert_eq!(ptn_date(1_640_995_199), "2021.12.31");
+    }
+}
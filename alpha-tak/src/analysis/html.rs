@@ -0,0 +1,23 @@
+use tak::*;
+
+use super::Analysis;
+
+/// Viewer page with a placeholder for the analysis data.
+const TEMPLATE: &str = include_str!("report.html");
+const PLACEHOLDER: &str = "/*ANALYSIS*/null";
+
+impl<const N: usize> Analysis<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// The analysis as a standalone HTML page with a board viewer, an eval
+    /// graph and a clickable move list. Everything is inlined, so the file
+    /// can be shared on its own.
+    pub fn to_html(&self, threat_depth: u32) -> String {
+        let mut data = self.to_json(threat_depth);
+        data["positions"] = self.positions().iter().map(|game| game.board.to_tps()).collect();
+        // keep the data from closing the script element
+        let data = data.to_string().replace("</", "<\\/");
+        TEMPLATE.replace(PLACEHOLDER, &data)
+    }
+}
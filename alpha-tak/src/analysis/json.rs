@@ -0,0 +1,94 @@
+use serde_json::{json, Value};
+use tak::*;
+
+use super::{format::result_ptn, move_info::MoveInfo, Analysis};
+use crate::search::profile::Phase;
+
+fn move_info_json(info: &MoveInfo) -> Value {
+    json!({
+        "eval": info.eval,
+        "policy": info.policy,
+        "visits": info.visits,
+    })
+}
+
+impl<const N: usize> Analysis<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// The whole analysis as one JSON document, with the same information
+    /// as the PTN but meant for tools rather than people. Forced wins are
+    /// searched for up to `threat_depth` plies.
+    pub fn to_json(&self, threat_depth: u32) -> Value {
+        let threats = self.threats(threat_depth);
+        let moves: Vec<_> = self
+            .played_turns
+            .iter()
+            .enumerate()
+            .map(|(i, turn)| {
+                let mut entry = json!({
+                    "ply": self.start_ply() + i,
+                    "move": turn.to_ptn(),
+                });
+                if let Some(info) = &self.move_info[i] {
+                    entry["info"] = move_info_json(info);
+                }
+                if let Some(annotation) = self.annotations[i] {
+                    entry["annotation"] = json!(annotation.name());
+                }
+                if let Some(thinking) = &self.thinking_info[i] {
+                    entry["thinking"] = json!({
+                        "time": thinking.time.as_secs_f32(),
+                        "rollouts": thinking.rollouts,
+                        "depth": thinking.depth,
+                        "reused_visits": thinking.reused_visits,
+                    });
+                    if let Some(profile) = &thinking.profile {
+                        entry["thinking"]["profile"] = Phase::ALL
+                            .into_iter()
+                            .map(|phase| (phase.name().to_string(), json!(profile.get(phase).as_secs_f32())))
+                            .collect();
+                    }
+                }
+                if let Some(stats) = &self.search_stats[i] {
+                    entry["search"] = json!({
+                        "nodes": stats.nodes,
+                        "best_share": stats.best_share,
+                        "eval_variance": stats.eval_variance,
+                    });
+                }
+                if let Some(threat) = &threats[i] {
+                    entry["threat"] = json!({
+                        "kind": threat.name(),
+                        "depth": threat.depth,
+                        "wins": threat.wins.iter().map(ToPTN::to_ptn).collect::<Vec<_>>(),
+                        "missed": threat.missed_by(turn),
+                    });
+                }
+                entry
+            })
+            .collect();
+
+        // alternatives the engine considered, with their principal variations
+        let candidates: Vec<_> = self
+            .branches
+            .iter()
+            .map(|branch| {
+                json!({
+                    "ply": branch.ply,
+                    "pv": branch.line.iter().map(ToPTN::to_ptn).collect::<Vec<_>>(),
+                    "info": move_info_json(&branch.info),
+                })
+            })
+            .collect();
+
+        json!({
+            "size": N,
+            "komi": self.komi.flats(),
+            "tps": self.start.as_ref().map(Game::ptn_tps),
+            "result": result_ptn(self.result()),
+            "moves": moves,
+            "candidates": candidates,
+        })
+    }
+}
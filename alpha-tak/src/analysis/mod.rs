@@ -1,10 +1,24 @@
+mod annotation;
 mod branch;
+mod format;
+mod html;
+mod json;
 mod move_info;
+mod search_stats;
+mod thinking;
+mod threats;
 
 use tak::*;
 
+pub use self::{
+    annotation::{Annotation, Grade},
+    format::{ptn_date, result_ptn, PtnFormat, ResultPlacement},
+    search_stats::SearchStats,
+    thinking::ThinkingInfo,
+    threats::Threat,
+};
 use self::{branch::Branch, move_info::MoveInfo};
-use crate::search::node::Node;
+use crate::{game_result, search::node::Node};
 
 const MAX_BRANCH_LENGTH: usize = 10;
 const BRANCH_MIN_VISITS: u32 = 100;
@@ -12,23 +26,74 @@ const CANDIDATE_MOVE_RATIO: f32 = 0.7;
 
 #[derive(Default)]
 pub struct Analysis<const N: usize> {
-    komi: i32,
+    komi: Komi,
+    /// Position the game started from, if not the empty board.
+    start: Option<Game<N>>,
     played_turns: Vec<Turn<N>>,
     move_info: Vec<Option<MoveInfo>>,
+    annotations: Vec<Option<Annotation>>,
+    thinking_info: Vec<Option<ThinkingInfo>>,
+    search_stats: Vec<Option<SearchStats>>,
     branches: Vec<Branch<N>>,
+    /// Clocks of the game, whose first recorded ply is the first played turn.
+    clock: Option<Clock>,
+    /// Player who gave up the game.
+    resigned: Option<Colour>,
+    /// Names of the white and black player.
+    players: Option<(String, String)>,
+    /// UNIX time in seconds when the game was played.
+    date: Option<u64>,
 }
 
 impl<const N: usize> Analysis<N> {
-    pub fn from_opening(opening: Vec<Turn<N>>, komi: i32) -> Self {
+    pub fn from_opening(opening: Vec<Turn<N>>, komi: Komi) -> Self {
         Analysis {
             move_info: vec![None; opening.len()],
+            annotations: vec![None; opening.len()],
+            thinking_info: vec![None; opening.len()],
+            search_stats: vec![None; opening.len()],
             played_turns: opening,
             komi,
             ..Default::default()
         }
     }
 
-    pub fn update(&mut self, node: &Node<N>, played_turn: Turn<N>) {
+    /// Analysis of a game starting from an arbitrary position,
+    /// which is written as the `TPS` tag of the PTN.
+    pub fn from_position(game: Game<N>) -> Self {
+        Analysis {
+            komi: game.komi,
+            start: Some(game),
+            ..Default::default()
+        }
+    }
+
+    /// Attach the clocks of the game, which adds the time left to the PTN
+    /// comments and lets the result be a loss on time.
+    pub fn set_clock(&mut self, clock: Clock) {
+        self.clock = Some(clock);
+    }
+
+    /// Name the white and black player in the PTN.
+    pub fn set_players(&mut self, white: impl Into<String>, black: impl Into<String>) {
+        self.players = Some((white.into(), black.into()));
+    }
+
+    /// Date the game in the PTN, given as a UNIX time in seconds.
+    pub fn set_date(&mut self, time: u64) {
+        self.date = Some(time);
+    }
+
+    /// End the game with the resignation of a player.
+    pub fn resign(&mut self, colour: Colour) {
+        self.resigned = Some(colour);
+    }
+
+    fn start_ply(&self) -> usize {
+        self.start.as_ref().map_or(0, |game| game.ply as usize)
+    }
+
+    pub fn update(&mut self, node: &Node<N>, played_turn: Turn<N>, thinking: ThinkingInfo) {
         // find other candidate moves for branches
         let children = node.children.as_ref().unwrap();
         let top_visits = children
@@ -42,7 +107,7 @@ impl<const N: usize> Analysis<N> {
             .filter(|(_, node)| CANDIDATE_MOVE_RATIO < node.visited_count as f32 / top_visits as f32)
             .collect();
 
-        let ply = self.played_turns.len();
+        let ply = self.start_ply() + self.played_turns.len();
         let eval_perspective = if ply % 2 == 0 { 1. } else { -1. };
         for (candidate, candidate_node) in candidates {
             if candidate == &played_turn {
@@ -70,43 +135,240 @@ impl<const N: usize> Analysis<N> {
             policy: child.policy,
             visits: child.visited_count,
         }));
+        self.annotations.push(Annotation::judge(children, &played_turn));
+        self.thinking_info.push(Some(thinking));
+        self.search_stats.push(Some(SearchStats::from_node(node)));
         self.played_turns.push(played_turn)
     }
+
+    /// Forget the last played turn with everything found in its search.
+    pub fn take_back(&mut self) {
+        if self.played_turns.pop().is_none() {
+            return;
+        }
+        self.move_info.pop();
+        self.annotations.pop();
+        self.thinking_info.pop();
+        self.search_stats.pop();
+        let ply = self.start_ply() + self.played_turns.len();
+        self.branches.retain(|branch| branch.ply < ply);
+    }
+
+    /// Played turns with the statistics of the search that chose them,
+    /// which is missing for the opening.
+    pub fn search_stats(&self) -> impl Iterator<Item = (&Turn<N>, Option<&SearchStats>)> {
+        self.played_turns
+            .iter()
+            .zip(self.search_stats.iter().map(Option::as_ref))
+    }
 }
 
-impl<const N: usize> ToPTN for Analysis<N> {
-    fn to_ptn(&self) -> String {
-        let mut out = format!("[Size \"{N}\"]\n[Komi \"{}\"]\n", self.komi);
-        let mut turn_iter = self.played_turns.iter();
-        let mut info_iter = self.move_info.iter();
-        let mut move_num = 1;
-        while let Some(white) = turn_iter.next() {
-            // add white turn
-            out.push_str(&format!("{move_num}. "));
-            out.push_str(&white.to_ptn());
-
-            // maybe add eval
-            if let Some(Some(info)) = info_iter.next() {
-                out.push_str(&format!(" {{{}}}", info.to_ptn()));
+impl<const N: usize> Analysis<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// Result of the analysed game, found by replaying it, with dead
+    /// positions adjudicated like in [`game_result`]. An unfinished game may
+    /// have been lost on time or by resignation.
+    pub fn result(&self) -> GameResult {
+        let time_result = || match self.resigned {
+            Some(colour) => GameResult::Winner {
+                colour: colour.next(),
+                reason: WinReason::Resignation,
+            },
+            None => self.clock.as_ref().map_or(GameResult::Ongoing, Clock::result),
+        };
+        let mut game = self.start.clone().unwrap_or_else(|| Game::with_komi(self.komi));
+        for turn in &self.played_turns {
+            if game.play(turn.clone()).is_err() {
+                return time_result();
+            }
+        }
+        match game_result(&game) {
+            GameResult::Ongoing => time_result(),
+            result => result,
+        }
+    }
+
+    /// Forced wins the player to move had before each played turn,
+    /// looking up to `max_depth` plies ahead.
+    pub fn threats(&self, max_depth: u32) -> Vec<Option<Threat<N>>> {
+        let mut game = self.start.clone().unwrap_or_else(|| Game::with_komi(self.komi));
+        let mut threats = Vec::with_capacity(self.played_turns.len());
+        for turn in &self.played_turns {
+            threats.push(Threat::find(&game, max_depth));
+            if game.play(turn.clone()).is_err() {
+                break;
+            }
+        }
+        threats.resize(self.played_turns.len(), None);
+        threats
+    }
+
+    /// Every position of the game, starting before the first played turn.
+    fn positions(&self) -> Vec<Game<N>> {
+        let mut game = self.start.clone().unwrap_or_else(|| Game::with_komi(self.komi));
+        let mut positions = vec![game.clone()];
+        for turn in &self.played_turns {
+            if game.play(turn.clone()).is_err() {
+                break;
+            }
+            positions.push(game.clone());
+        }
+        positions
+    }
+
+    /// Write the analysis as PTN formatted according to `format`.
+    pub fn to_ptn_with(&self, format: &PtnFormat) -> String {
+        let result = result_ptn(self.result());
+        let mut headers = vec![("Size", N.to_string()), ("Komi", self.komi.to_string())];
+        if let Some((white, black)) = &self.players {
+            headers.push(("Player1", white.clone()));
+            headers.push(("Player2", black.clone()));
+        }
+        if let Some(date) = self.date {
+            headers.push(("Date", ptn_date(date)));
+        }
+        if let Some(start) = &self.start {
+            let (stones, caps) = start.starting_reserves(Colour::White);
+            let (default_stones, default_caps) = default_starting_stones(N);
+            if (stones, caps) != (default_stones as u32, default_caps as u32) {
+                headers.push(("Flats", stones.to_string()));
+                headers.push(("Caps", caps.to_string()));
             }
-            out.push(' ');
-
-            // maybe add black move
-            if let Some(black) = turn_iter.next() {
-                out.push_str(&black.to_ptn());
-                // maybe add eval
-                if let Some(Some(info)) = info_iter.next() {
-                    out.push_str(&format!(" {{{}}}", info.to_ptn()));
-                }
+            headers.push(("TPS", start.ptn_tps()));
+        }
+        if let Some(clock) = &self.clock {
+            headers.push(("Clock", clock.control.to_ptn()));
+        }
+        if let (true, Some(result)) = (format.result.header(), result) {
+            headers.push(("Result", result.to_string()));
+        }
+        let mut out = format.headers(headers);
+        let threats = self.threats(format.threat_depth);
+        let positions = self.positions();
+        let played = line_ptn(positions.first().cloned(), &self.played_turns);
+
+        let info_iter = self
+            .move_info
+            .iter()
+            .zip(self.thinking_info.iter())
+            .zip(self.search_stats.iter());
+        for (i, (turn, info)) in self.played_turns.iter().zip(info_iter).enumerate() {
+            let ply = self.start_ply() + i;
+            let move_num = ply / 2 + 1;
+            if format.move_numbers && (ply % 2 == 0 || i == 0) {
+                out.push_str(&format!("{move_num}. "));
             }
+            if ply % 2 == 1 && i == 0 {
+                // the game starts with black to move
+                out.push_str("-- ");
+            }
+            out.push_str(&played[i]);
+            if let Some(annotation) = self.annotations[i] {
+                out.push_str(&annotation.to_ptn());
+            }
+
+            // maybe add eval and missed wins
+            let threat = threats[i].as_ref().map(|threat| threat.comment(turn));
+            let clock = self.clock.as_ref().and_then(|clock| clock.comment(i));
+            if let Some(comment) = info_comment(info, threat, clock) {
+                out.push_str(&comment);
+            }
+
+            if ply % 2 == 1 && move_num % format.moves_per_line.max(1) == 0 {
+                out.push('\n');
+            } else {
+                out.push(' ');
+            }
+        }
+        if let (true, Some(result)) = (format.result.move_list(), result) {
+            out.push_str(result);
+            out.push('\n');
+        } else if !out.ends_with('\n') {
             out.push('\n');
-            move_num += 1;
         }
 
         for branch in self.branches.iter() {
             out.push('\n'); // empty line before branch
-            out.push_str(&branch.to_ptn());
+            let start = positions.get(branch.ply - self.start_ply()).cloned();
+            out.push_str(&branch.to_ptn_from(start));
         }
         out
     }
 }
+
+impl<const N: usize> ToPTN for Analysis<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    fn to_ptn(&self) -> String {
+        self.to_ptn_with(&PtnFormat {
+            result: ResultPlacement::Both,
+            ..Default::default()
+        })
+    }
+}
+
+/// PTN of turns played one after another from `game`, marking crushes for
+/// as long as the turns can be played.
+fn line_ptn<'a, const N: usize>(
+    mut game: Option<Game<N>>,
+    turns: impl IntoIterator<Item = &'a Turn<N>>,
+) -> Vec<String>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    turns
+        .into_iter()
+        .map(|turn| {
+            let ptn = game
+                .as_ref()
+                .map_or_else(|| turn.to_ptn(), |game| turn.to_ptn_on(&game.board));
+            game = game
+                .take()
+                .and_then(|mut game| game.play(turn.clone()).ok().map(|()| game));
+            ptn
+        })
+        .collect()
+}
+
+/// Create the comment for a played move, if there is any info on it.
+fn info_comment(
+    ((info, thinking), stats): ((&Option<MoveInfo>, &Option<ThinkingInfo>), &Option<SearchStats>),
+    threat: Option<String>,
+    clock: Option<String>,
+) -> Option<String> {
+    let parts: Vec<_> = [
+        info.as_ref().map(ToPTN::to_ptn),
+        thinking.as_ref().map(ToPTN::to_ptn),
+        stats.as_ref().map(ToPTN::to_ptn),
+        threat,
+        clock,
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    if parts.is_empty() {
+        None
+    } else {
+        Some(format!(" {{{}}}", parts.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tak::*;
+
+    use super::Analysis;
+
+    #[test]
+    fn crushes_are_marked() {
+        let opening = ["a1", "e1", "Cc3", "Sd3", "c3>"]
+            .into_iter()
+            .map(|ptn| Turn::<5>::from_ptn(ptn).unwrap())
+            .collect();
+        let ptn = Analysis::from_opening(opening, Komi::default()).to_ptn();
+        assert!(ptn.contains("3. c3>*"), "{ptn}");
+    }
+}
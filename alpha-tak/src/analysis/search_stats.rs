@@ -0,0 +1,55 @@
+use tak::*;
+
+use crate::search::node::Node;
+
+/// How confident the search was in a decision.
+#[derive(Default, Debug, Clone)]
+pub struct SearchStats {
+    /// Nodes in the search tree.
+    pub nodes: usize,
+    /// Fraction of the rollouts that went to the most visited move.
+    pub best_share: f32,
+    /// Visit weighted variance of the evals of the moves.
+    pub eval_variance: f32,
+}
+
+impl SearchStats {
+    pub fn from_node<const N: usize>(node: &Node<N>) -> Self {
+        let children = node.children.as_ref().expect("you must rollout at least once");
+        let total = children
+            .values()
+            .map(|child| child.visited_count)
+            .sum::<u32>()
+            .max(1) as f32;
+        let best = children
+            .values()
+            .map(|child| child.visited_count)
+            .max()
+            .unwrap_or_default();
+        let mean = children
+            .values()
+            .map(|child| child.visited_count as f32 * child.expected_reward)
+            .sum::<f32>()
+            / total;
+        let eval_variance = children
+            .values()
+            .map(|child| child.visited_count as f32 * (child.expected_reward - mean).powi(2))
+            .sum::<f32>()
+            / total;
+
+        SearchStats {
+            nodes: node.node_count(),
+            best_share: best as f32 / total,
+            eval_variance,
+        }
+    }
+}
+
+impl ToPTN for SearchStats {
+    fn to_ptn(&self) -> String {
+        format!(
+            "n: {}, s: {:.3}, var: {:.4}",
+            self.nodes, self.best_share, self.eval_variance
+        )
+    }
+}
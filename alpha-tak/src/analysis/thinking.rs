@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+use tak::ToPTN;
+
+use crate::search::profile::SearchProfile;
+
+#[derive(Default, Debug, Clone)]
+pub struct ThinkingInfo {
+    pub time: Duration,
+    pub rollouts: usize,
+    pub depth: usize,
+    /// Visits kept in the tree from the previous move.
+    pub reused_visits: u32,
+    /// Where the time went, if profiling is on.
+    pub profile: Option<SearchProfile>,
+}
+
+impl ToPTN for ThinkingInfo {
+    fn to_ptn(&self) -> String {
+        let mut out = format!(
+            "t: {:.3}s, r: {}, d: {}",
+            self.time.as_secs_f32(),
+            self.rollouts,
+            self.depth
+        );
+        if let Some(profile) = &self.profile {
+            out += &format!(", {profile}");
+        }
+        out
+    }
+}
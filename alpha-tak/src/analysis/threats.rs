@@ -0,0 +1,53 @@
+use tak::*;
+
+/// A forced win available to the player to move.
+#[derive(Clone, Debug)]
+pub struct Threat<const N: usize> {
+    /// Plies until the win counting both players, 1 for an immediate win.
+    pub depth: u32,
+    /// Every turn which forces the win.
+    pub wins: Vec<Turn<N>>,
+}
+
+impl<const N: usize> Threat<N> {
+    /// The shortest forced win for the player to move within `max_depth`
+    /// plies, if there is one.
+    pub fn find(game: &Game<N>, max_depth: u32) -> Option<Self> {
+        (1..=max_depth).step_by(2).find_map(|depth| {
+            let wins = game.winning_turns(depth);
+            if wins.is_empty() {
+                None
+            } else {
+                Some(Threat { depth, wins })
+            }
+        })
+    }
+
+    /// Whether the win is immediate rather than a tinue.
+    pub fn is_tak(&self) -> bool {
+        self.depth == 1
+    }
+
+    pub fn name(&self) -> &'static str {
+        if self.is_tak() {
+            "tak"
+        } else {
+            "tinue"
+        }
+    }
+
+    /// Whether `turn` fails to take the forced win.
+    pub fn missed_by(&self, turn: &Turn<N>) -> bool {
+        !self.wins.contains(turn)
+    }
+
+    /// Comment on the turn that was played in the threatened position.
+    pub fn comment(&self, played: &Turn<N>) -> String {
+        if self.missed_by(played) {
+            let wins: Vec<_> = self.wins.iter().map(ToPTN::to_ptn).collect();
+            format!("missed {}: {}", self.name(), wins.join(" "))
+        } else {
+            self.name().to_string()
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use tak::*;
+
+use crate::{config::CALIBRATION_BUCKETS, example::Example, metrics::METRICS, model::network::Network};
+
+/// Number of positions evaluated at once.
+const EVAL_BATCH: usize = 512;
+
+#[derive(Clone, Debug, Default)]
+pub struct Bucket {
+    pub count: usize,
+    pub predicted: f64,
+    pub observed: f64,
+}
+
+/// Comparison of predicted evals and the actual game results.
+/// Evals and results are converted to expected scores between 0 (loss) and 1
+/// (win).
+#[derive(Clone, Debug)]
+pub struct Calibration {
+    pub buckets: Vec<Bucket>,
+    /// Mean squared error of the expected score.
+    pub brier_score: f64,
+}
+
+impl Calibration {
+    /// Bucket `(eval, result)` pairs by eval. Both are from the perspective of
+    /// the player to move.
+    pub fn new(pairs: impl IntoIterator<Item = (f32, f32)>, buckets: usize) -> Self {
+        let mut calibration = Calibration {
+            buckets: vec![Bucket::default(); buckets],
+            brier_score: 0.,
+        };
+        let mut count = 0;
+        for (eval, result) in pairs {
+            let predicted = (eval as f64 + 1.) / 2.;
+            let observed = (result as f64 + 1.) / 2.;
+            let index = ((predicted * buckets as f64) as usize).min(buckets - 1);
+            let bucket = &mut calibration.buckets[index];
+            bucket.count += 1;
+            bucket.predicted += predicted;
+            bucket.observed += observed;
+            calibration.brier_score += (predicted - observed).powi(2);
+            count += 1;
+        }
+        for bucket in &mut calibration.buckets {
+            if bucket.count > 0 {
+                bucket.predicted /= bucket.count as f64;
+                bucket.observed /= bucket.count as f64;
+            }
+        }
+        calibration.brier_score /= count.max(1) as f64;
+        calibration
+    }
+
+    /// Evaluate the example positions with the network and compare against
+    /// their results.
+    pub fn from_examples<const N: usize>(
+        network: &Network<N>,
+        examples: &[Example<N>],
+        buckets: usize,
+    ) -> Self {
+        let mut pairs = Vec::with_capacity(examples.len());
+        for chunk in examples.chunks(EVAL_BATCH) {
+            let games: Vec<Game<N>> = chunk.iter().map(|ex| ex.game.clone()).collect();
+            let (_, evals) = network.policy_eval_batch(&games);
+            pairs.extend(evals.into_iter().zip(chunk.iter().map(|ex| ex.result)));
+        }
+        Self::new(pairs, buckets)
+    }
+
+    /// Mean distance between the predicted and observed score of the buckets,
+    /// weighted by their size. Zero for a perfectly calibrated value head.
+    pub fn expected_error(&self) -> f64 {
+        let total: usize = self.buckets.iter().map(|bucket| bucket.count).sum();
+        let error: f64 = self
+            .buckets
+            .iter()
+            .map(|bucket| bucket.count as f64 * (bucket.predicted - bucket.observed).abs())
+            .sum();
+        error / total.max(1) as f64
+    }
+
+    /// Publish the calibration as gauges, see [`crate::metrics::Metrics`].
+    pub fn report(&self) {
+        METRICS.value_brier_score.set(self.brier_score);
+        METRICS.value_calibration_error.set(self.expected_error());
+        for (i, bucket) in self.buckets.iter().take(CALIBRATION_BUCKETS).enumerate() {
+            METRICS.calibration_predicted[i].set(bucket.predicted);
+            METRICS.calibration_observed[i].set(bucket.observed);
+        }
+    }
+
+    /// Format the calibration as CSV for plotting.
+    pub fn to_csv(&self) -> String {
+        let width = 1. / self.buckets.len() as f64;
+        let mut out = String::from("low,high,count,predicted,observed\n");
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            out.push_str(&format!(
+                "{:.2},{:.2},{},{:.4},{:.4}\n",
+                i as f64 * width,
+                (i + 1) as f64 * width,
+                bucket.count,
+                bucket.predicted,
+                bucket.observed
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Calibration;
+
+    #[test]
+    fn perfectly_calibrated() {
+        let calibration = Calibration::new([(1., 1.), (-1., -1.), (0., 1.), (0., -1.)], 4);
+        let counts: Vec<_> = calibration.buckets.iter().map(|b| b.count).collect();
+        assert_eq!(counts, [1, 0, 2, 1]);
+        assert_eq!(calibration.buckets[2].predicted, 0.5);
+        assert_eq!(calibration.buckets[2].observed, 0.5);
+        assert_eq!(calibration.brier_score, 0.125);
+        assert_eq!(calibration.expected_error(), 0.);
+    }
+
+    #[test]
+    fn overconfident() {
+        // predicts a score of 0.75 and 0.25 where the results are even
+        let calibration = Calibration::new([(0.5, 1.), (0.5, -1.), (-0.5, 1.), (-0.5, -1.)], 2);
+        assert_eq!(calibration.expected_error(), 0.25);
+    }
+}
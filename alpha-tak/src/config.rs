@@ -1,6 +1,8 @@
+use tak::Komi;
+
 // game settings
 pub const N: usize = 5;
-pub const KOMI: i32 = 2;
+pub const KOMI: Komi = Komi::from_flats(2);
 
 // model
 pub const RES_BLOCKS: usize = 8;
@@ -10,23 +12,105 @@ pub const FILTERS: i64 = 128;
 pub const CONTEMPT: f32 = 0.05;
 pub const EXPLORATION_BASE: f32 = 500.0;
 pub const EXPLORATION_INIT: f32 = 4.0;
+/// Value of unvisited children below the value of their parent, `None`
+/// values them as a draw. See [`crate::search::params::SearchParams`].
+pub const FPU_REDUCTION: Option<f32> = None;
+/// Temperature of the policy when a node is expanded.
+pub const POLICY_TEMPERATURE: f32 = 1.0;
+/// Plies the endgame solver searches in positions which are at most that
+/// far from ending on flats, 0 turns it off. See [`tak::Game::solve`].
+pub const SOLVER_DEPTH: u32 = 2;
+/// Positions the endgame solver searches before giving up on a node.
+pub const SOLVER_MAX_NODES: u64 = 10_000;
+/// Leaves each thread of a parallel search walks down to before evaluating
+/// them in one batch, see [`crate::search::parallel`].
+pub const PARALLEL_LEAVES: usize = 8;
+/// Whether self-play and analysis expand transposed positions without asking
+/// the network again, see [`crate::search::transposition`].
+pub const TRANSPOSITIONS: bool = false;
+/// Whether searches measure how long each of their phases takes,
+/// see [`crate::search::profile`].
+pub const PROFILE_SEARCH: bool = false;
+/// Whether self-play and pit games evaluate each position under a random
+/// symmetry, see [`crate::agent::RandomSymmetry`].
+pub const RANDOM_SYMMETRY: bool = false;
 
 // self-play
 pub const SELF_PLAY_GAMES: usize = 500;
+/// Games played at the same time, which bounds the batch size.
+pub const SELF_PLAY_WORKERS: usize = 128;
 pub const ROLLOUTS_PER_MOVE: usize = 1000;
+pub const TEMPERATURE: f32 = 1.0;
 pub const TEMPERATURE_PLIES: u64 = 10;
 
-pub const DIRICHLET_NOISE: f32 = 0.2;
+/// Average number of legal moves in random games of sizes 3 to 8.
+pub const AVERAGE_LEGAL_MOVES: [f32; 6] = [12.7, 23.8, 42.3, 59.6, 86.0, 108.9];
+/// Concentration of the Dirichlet noise, scaled to the number of legal moves
+/// like AlphaZero does, which gives about 0.24 on 5x5.
+pub const DIRICHLET_NOISE: f32 = 10. / AVERAGE_LEGAL_MOVES[N - 3];
 pub const NOISE_RATIO: f32 = 0.5;
 pub const NOISE_PLIES: u64 = 30;
 
+/// Moves sampled at the root by Gumbel search, see
+/// [`crate::search::gumbel`].
+pub const GUMBEL_CONSIDERED_MOVES: usize = 16;
+/// Constants of the transformation of values in Gumbel search, from the
+/// paper.
+pub const GUMBEL_C_VISIT: f32 = 50.;
+pub const GUMBEL_C_SCALE: f32 = 1.;
+
+/// Evaluation below which the player to move resigns a self-play game,
+/// -1 never resigns.
+pub const RESIGN_THRESHOLD: f32 = -0.9;
+/// Consecutive moves of a player below the threshold before it resigns.
+pub const RESIGN_MOVES: usize = 3;
+/// Share of self-play games which are played out instead of resigned, to
+/// measure how often resigning gives up a game that was not lost.
+pub const NO_RESIGN_FRACTION: f64 = 0.1;
+
+/// Share of the self-play games played against an older checkpoint instead
+/// of the current network.
+pub const LEAGUE_FRACTION: f64 = 0.0;
+
+pub const OPENING_TREE_PLIES: u64 = 10;
+pub const OPENING_TREE_MAX_VISITS: u32 = 4 * ROLLOUTS_PER_MOVE as u32;
+pub const OPENING_TREE_MIN_ROLLOUTS: usize = ROLLOUTS_PER_MOVE / 5;
+
 // train
 pub const MAX_EXAMPLES: usize = 250_000;
 pub const MAX_TRAIN_SIZE: usize = 50_000;
 pub const BATCH_SIZE: i64 = 10_000;
 pub const LEARNING_RATE: f64 = 1e-4;
 pub const WEIGHT_DECAY: f64 = 1e-4;
+/// Sampling weight of examples relative to those one generation newer,
+/// 1 samples the window uniformly.
+pub const GENERATION_DECAY: f32 = 1.0;
+/// Buckets of the value head calibration reported after every generation.
+pub const CALIBRATION_BUCKETS: usize = 10;
+/// Whether the policy head factors spreads into origin, direction, and drop
+/// pattern instead of one output per spread, which is much smaller on large
+/// boards. Changing this changes the variables stored in a model.
+pub const FACTORIZED_POLICY: bool = false;
+/// Whether placements and spreads get separate softmaxes, mixed by a learned
+/// weight per position, instead of sharing one softmax.
+/// Changing this changes the variables stored in a model.
+pub const SPLIT_POLICY: bool = false;
+/// Whether games between agents end early in dead positions, where neither
+/// player can make a road any more and the flat winner is already known.
+pub const ADJUDICATE_DEAD_POSITIONS: bool = true;
+/// Whether the network also predicts the reply of the opponent, as an
+/// auxiliary target. Changing this changes the variables stored in a model.
+pub const REPLY_HEAD: bool = false;
+/// Weight of the reply prediction in the loss.
+pub const REPLY_WEIGHT: f64 = 0.5;
+/// Other board sizes trained alongside N with their loss weight.
+/// Changing this changes the variables stored in a model.
+pub const AUX_SIZES: &[(usize, f64)] = &[];
 
 // pit
 pub const WIN_RATE_THRESHOLD: f64 = 0.55;
 pub const PIT_MATCHES: usize = 80;
+/// Standard deviations below the measured win rate that the new network has
+/// to clear the threshold by, 1.96 for 95% confidence. 0 promotes on the win
+/// rate alone.
+pub const PIT_CONFIDENCE_Z: f64 = 1.96;
@@ -7,8 +7,10 @@ use std::{
 };
 
 use tak::*;
+#[cfg(feature = "torch")]
 use tch::Tensor;
 
+#[cfg(feature = "torch")]
 use crate::{
     repr::{game_repr, moves_dims},
     search::turn_map::Lut,
@@ -27,6 +29,7 @@ impl<const N: usize> IncompleteExample<N> {
             game: self.game,
             policy: self.policy,
             result,
+            reply: HashMap::new(),
         }
     }
 }
@@ -36,60 +39,131 @@ pub struct Example<const N: usize> {
     pub game: Game<N>,
     pub policy: HashMap<Turn<N>, u32>,
     pub result: f32,
+    /// Visits of the opponent's replies in the following position,
+    /// empty if it is not known.
+    pub reply: HashMap<Turn<N>, u32>,
 }
 
 impl<const N: usize> Example<N>
 where
-    Turn<N>: Lut,
     [[Option<Tile>; N]; N]: Default,
 {
-    pub fn to_tensors(&self) -> Vec<(Tensor, Tensor, f32)> {
-        let mut pi = [
-            vec![0.; moves_dims(N)],
-            vec![0.; moves_dims(N)],
-            vec![0.; moves_dims(N)],
-            vec![0.; moves_dims(N)],
-            vec![0.; moves_dims(N)],
-            vec![0.; moves_dims(N)],
-            vec![0.; moves_dims(N)],
-            vec![0.; moves_dims(N)],
-        ];
-        let total = self.policy.iter().map(|(_, c)| c).sum::<u32>() as f32;
-        for (turn, &value) in self.policy.iter() {
-            for (i, symm) in turn.clone().symmetries().into_iter().enumerate() {
-                pi[i][symm.turn_map()] = value as f32 / total;
-            }
-        }
-
+    /// The example under each of the eight symmetries of the board, in the
+    /// order of [`Symmetry::symmetries`], with the visits moved along.
+    pub fn symmetries(&self) -> Vec<Example<N>> {
+        let policies = symmetric_visits(&self.policy);
+        let replies = symmetric_visits(&self.reply);
         self.game
             .clone()
             .symmetries()
             .into_iter()
-            .enumerate()
-            .map(|(i, game)| (game_repr(&game), Tensor::of_slice(&pi[i]), self.result))
+            .zip(policies.into_iter().zip(replies))
+            .map(|(game, (policy, reply))| Example {
+                game,
+                policy,
+                result: self.result,
+                reply,
+            })
             .collect()
     }
 }
 
-pub fn save_examples<const N: usize, P: AsRef<Path>>(examples: &[Example<N>], path: P) {
-    if let Ok(mut file) = File::create(path) {
-        let out = examples
-            .iter()
+/// Visit counts of the symmetric turns under each symmetry.
+fn symmetric_visits<const N: usize>(visits: &HashMap<Turn<N>, u32>) -> [HashMap<Turn<N>, u32>; 8] {
+    let mut symmetric: [HashMap<_, _>; 8] = Default::default();
+    for (turn, &value) in visits {
+        for (i, symm) in turn.clone().symmetries().into_iter().enumerate() {
+            symmetric[i].insert(symm, value);
+        }
+    }
+    symmetric
+}
+
+#[cfg(feature = "torch")]
+impl<const N: usize> Example<N>
+where
+    Turn<N>: Lut,
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// Input, policy target, reply target, and result of every symmetry of
+    /// the example. The reply target is all zeros if the reply is unknown.
+    pub fn to_tensors(&self) -> Vec<(Tensor, Tensor, Tensor, f32)> {
+        self.symmetries()
+            .into_iter()
             .map(|example| {
-                format!(
-                    "{};{};{}\n",
-                    example.game.to_tps(),
+                (
+                    game_repr(&example.game),
+                    Tensor::of_slice(&target(&example.policy)),
+                    Tensor::of_slice(&target(&example.reply)),
                     example.result,
-                    example
-                        .policy
-                        .iter()
-                        .map(|(turn, visits)| format!("{} {visits},", turn.to_ptn()))
-                        .collect::<String>()
                 )
             })
-            .collect::<String>();
-        file.write_all(out.as_bytes()).unwrap();
+            .collect()
+    }
+}
+
+/// Visit counts normalized into a policy vector.
+#[cfg(feature = "torch")]
+fn target<const N: usize>(visits: &HashMap<Turn<N>, u32>) -> Vec<f32>
+where
+    Turn<N>: Lut,
+{
+    let mut pi = vec![0.; moves_dims(N)];
+    let total = visits.values().sum::<u32>() as f32;
+    for (turn, &value) in visits {
+        pi[turn.turn_map()] = value as f32 / total;
+    }
+    pi
+}
+
+pub fn save_examples<const N: usize, P: AsRef<Path>>(examples: &[Example<N>], path: P) {
+    if let Ok(mut file) = File::create(path) {
+        file.write_all(examples_to_string(examples).as_bytes()).unwrap();
+    }
+}
+
+/// Serialize examples in the format used by [`save_examples`].
+/// The reply is written as a fourth field only when it is known.
+pub fn examples_to_string<const N: usize>(examples: &[Example<N>]) -> String {
+    let visits = |policy: &HashMap<Turn<N>, u32>| {
+        policy
+            .iter()
+            .map(|(turn, visits)| format!("{} {visits},", turn.to_ptn()))
+            .collect::<String>()
+    };
+    examples
+        .iter()
+        .map(|example| {
+            let mut line = format!(
+                "{};{};{}",
+                example.game.to_tps(),
+                example.result,
+                visits(&example.policy)
+            );
+            if !example.reply.is_empty() {
+                line.push(';');
+                line.push_str(&visits(&example.reply));
+            }
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+/// Parse visit counts written by [`examples_to_string`].
+fn parse_visits<const N: usize>(s: &str) -> HashMap<Turn<N>, u32> {
+    let mut policy = HashMap::new();
+    for line in s.split_terminator(',') {
+        let mut words = line.split(' ');
+        let turn = Turn::from_ptn(words.next().expect("missing turn")).expect("invalid turn");
+        let visited = words
+            .next()
+            .expect("missing visited count")
+            .parse::<u32>()
+            .expect("invalid visited count");
+        policy.insert(turn, visited);
     }
+    policy
 }
 
 // TODO clean this up
@@ -100,7 +174,14 @@ where
     let mut file = File::open(path)?;
     let mut s = String::new();
     file.read_to_string(&mut s)?;
+    parse_examples(&s)
+}
 
+/// Parse examples in the format used by [`save_examples`].
+pub fn parse_examples<const N: usize>(s: &str) -> Result<Vec<Example<N>>, Box<dyn Error>>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
     s.split_terminator('\n')
         .map(|example| {
             let mut chunks = example.split(';');
@@ -121,7 +202,7 @@ where
             let mut black_reserves = tps.next().expect("missing black reserves").split('/');
             let black_stones = black_reserves.next().unwrap()[1..].parse().unwrap();
             let black_caps = black_reserves.next().unwrap().replace(')', "").parse().unwrap();
-            let komi = tps.next().expect("missing komi").parse::<i32>().unwrap();
+            let komi = tps.next().expect("missing komi").parse().unwrap();
 
             let game = Game {
                 board,
@@ -140,24 +221,20 @@ where
                 .parse::<f32>()
                 .expect("game result cannot be parsed");
 
-            let mut policy = HashMap::new();
-            for line in chunks.next().expect("missing turns").split_terminator(',') {
-                let mut words = line.split(' ');
-                let turn = Turn::from_ptn(words.next().expect("missing turn")).expect("invalid turn");
-                let visited = words
-                    .next()
-                    .expect("missing visited count")
-                    .parse::<u32>()
-                    .expect("invalid visited count");
-                policy.insert(turn, visited);
-            }
+            let policy = parse_visits(chunks.next().expect("missing turns"));
+            let reply = chunks.next().map(parse_visits).unwrap_or_default();
 
-            Ok(Example { game, policy, result })
+            Ok(Example {
+                game,
+                policy,
+                result,
+                reply,
+            })
         })
         .collect()
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "torch"))]
 mod test {
     use std::collections::HashMap;
 
@@ -166,6 +243,30 @@ mod test {
 
     use super::Example;
 
+    #[test]
+    fn symmetries_move_visits_along() {
+        let game = Game::<5>::from_ptn("1. a1 e1 2. b2").unwrap();
+        let policy: HashMap<Turn<5>, u32> = [("a2", 3), ("Cc3", 1)]
+            .into_iter()
+            .map(|(ptn, visits)| (Turn::from_ptn(ptn).unwrap(), visits))
+            .collect();
+        let example = Example {
+            game,
+            policy,
+            result: -1.0,
+            reply: HashMap::new(),
+        };
+        let symmetries = example.symmetries();
+        assert_eq!(symmetries.len(), 8);
+        assert_eq!(symmetries[0].policy, example.policy);
+        for symmetry in &symmetries {
+            assert_eq!(symmetry.result, example.result);
+            let turns = symmetry.game.possible_turns();
+            assert!(symmetry.policy.keys().all(|turn| turns.contains(turn)));
+            assert_eq!(symmetry.policy.values().sum::<u32>(), 4);
+        }
+    }
+
     #[bench]
     fn to_tensors_bench(b: &mut Bencher) {
         let game = Game::<5>::from_ptn(
@@ -187,6 +288,7 @@ mod test {
             game,
             policy,
             result: 1.0,
+            reply: HashMap::new(),
         };
         b.iter(|| example.to_tensors())
     }
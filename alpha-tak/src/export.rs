@@ -0,0 +1,82 @@
+use std::{error::Error, fs::write, path::Path};
+
+use serde_json::json;
+use tak::*;
+use tch::Tensor;
+
+use crate::{
+    example::Example,
+    repr::{board_channels, input_channels, moves_dims, place_moves, spread_moves},
+    search::turn_map::Lut,
+};
+
+/// Write examples as numpy shards for use outside of Rust, together with a
+/// `manifest.json` describing their layout. Each `shard_<i>.npz` holds the
+/// encoded `inputs`, the `policy` and `reply` targets, and the `value`
+/// target, exactly as they are fed to the network. With `symmetries` all
+/// eight symmetries of every example are written instead of just the
+/// example itself.
+pub fn export_examples<const N: usize>(
+    examples: &[Example<N>],
+    out_dir: impl AsRef<Path>,
+    shard_size: usize,
+    symmetries: bool,
+) -> Result<(), Box<dyn Error>>
+where
+    Turn<N>: Lut,
+    [[Option<Tile>; N]; N]: Default,
+{
+    let out_dir = out_dir.as_ref();
+    let mut shards = Vec::new();
+    for (i, chunk) in examples.chunks(shard_size.max(1)).enumerate() {
+        let mut inputs = Vec::new();
+        let mut policies = Vec::new();
+        let mut replies = Vec::new();
+        let mut values = Vec::new();
+        for example in chunk {
+            let tensors = example.to_tensors();
+            // the first symmetry is the example itself
+            let count = if symmetries { tensors.len() } else { 1 };
+            for (input, pi, reply, v) in tensors.into_iter().take(count) {
+                inputs.push(input);
+                policies.push(pi);
+                replies.push(reply);
+                values.push(v);
+            }
+        }
+        let file = format!("shard_{i}.npz");
+        Tensor::write_npz(
+            &[
+                ("inputs", Tensor::stack(&inputs, 0)),
+                ("policy", Tensor::stack(&policies, 0)),
+                ("reply", Tensor::stack(&replies, 0)),
+                ("value", Tensor::of_slice(&values)),
+            ],
+            out_dir.join(&file),
+        )?;
+        shards.push(json!({ "file": file, "examples": values.len() }));
+    }
+
+    let manifest = json!({
+        "size": N,
+        "inputs": {
+            "shape": [input_channels(N), N, N],
+            "board_channels": board_channels(N),
+            "perspective": "the player to move",
+        },
+        "policy": {
+            "size": moves_dims(N),
+            "place_moves": place_moves(N),
+            "spread_moves": spread_moves(N),
+        },
+        "reply": "policy of the opponent in the next position, all zeros if unknown",
+        "value": "game result from the perspective of the player to move",
+        "symmetries": symmetries,
+        "shards": shards,
+    });
+    write(
+        out_dir.join("manifest.json"),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Ok(())
+}
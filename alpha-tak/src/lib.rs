@@ -5,16 +5,30 @@ extern crate test;
 
 use std::time::SystemTime;
 
+use tak::{Game, GameResult, Tile};
+#[cfg(feature = "torch")]
 use tch::{Cuda, Device};
+use tracing_subscriber::EnvFilter;
 
 #[macro_use]
 extern crate lazy_static;
 
+#[cfg(feature = "torch")]
 pub mod model;
 pub mod search;
 
 pub mod analysis;
+#[cfg(feature = "torch")]
+pub mod calibration;
 pub mod config;
+#[cfg(feature = "torch")]
+pub mod export;
+pub mod metrics;
+#[cfg(feature = "torch")]
+pub mod relabel;
+pub mod replay;
+pub mod rng;
+#[cfg(feature = "torch")]
 pub mod threadpool;
 
 pub mod agent;
@@ -22,17 +36,47 @@ pub mod example;
 pub mod player;
 pub mod repr;
 
+#[cfg(feature = "torch")]
 lazy_static! {
     static ref DEVICE: Device = Device::cuda_if_available();
 }
 
 /// Try initializing CUDA.
 /// Returns whether CUDA is available.
+#[cfg(feature = "torch")]
 pub fn use_cuda() -> bool {
     tch::maybe_init_cuda();
     Cuda::is_available()
 }
 
+/// Log to stderr, so that protocols on stdout are not disturbed.
+/// Filtered by `RUST_LOG` (e.g. `alpha_tak=debug`), `info` by default.
+/// Logs are JSON lines if `LOG_JSON` is set.
+pub fn init_logging() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    if std::env::var_os("LOG_JSON").is_some() {
+        builder.json().init();
+    } else {
+        builder.init();
+    }
+}
+
+/// The result of a game, which adjudicates dead positions if
+/// [`ADJUDICATE_DEAD_POSITIONS`](config::ADJUDICATE_DEAD_POSITIONS) is set.
+pub fn game_result<const N: usize>(game: &Game<N>) -> GameResult
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    if config::ADJUDICATE_DEAD_POSITIONS {
+        game.adjudicated_winner()
+    } else {
+        game.winner()
+    }
+}
+
 /// Get UNIX time in seconds.
 pub fn sys_time() -> u64 {
     SystemTime::now()
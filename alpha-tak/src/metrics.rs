@@ -0,0 +1,271 @@
+//! Process-wide metrics, served in the Prometheus text format.
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, ToSocketAddrs},
+    sync::atomic::{AtomicU64, Ordering},
+    thread::{self, JoinHandle},
+};
+
+use crate::{config::CALIBRATION_BUCKETS, search::profile::Phase};
+
+#[derive(Default)]
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    pub const fn new() -> Self {
+        Counter(AtomicU64::new(0))
+    }
+
+    pub fn inc_by(&self, amount: u64) {
+        self.0.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Float stored as its bits.
+#[derive(Default)]
+pub struct Gauge(AtomicU64);
+
+impl Gauge {
+    pub const fn new() -> Self {
+        Gauge(AtomicU64::new(0))
+    }
+
+    pub fn set(&self, value: f64) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+pub struct Metrics {
+    pub games_completed: Counter,
+    pub positions_evaluated: Counter,
+    pub batches_evaluated: Counter,
+    pub resignations: Counter,
+    /// Games played out which one player would have resigned.
+    pub resignations_checked: Counter,
+    /// Games played out which the player who would have resigned did not lose.
+    pub false_resignations: Counter,
+    /// Rollouts done by players.
+    pub rollouts: Counter,
+    /// Visits kept in the tree when a move is played.
+    pub reused_visits: Counter,
+    /// Visits dropped with the other moves when a move is played.
+    pub discarded_visits: Counter,
+    /// Nodes whose result was proven by the endgame solver.
+    pub proven_nodes: Counter,
+    /// Nodes expanded from a transposition instead of the network.
+    pub transposed_expansions: Counter,
+    /// Fraction of the workers which were in the last batch.
+    pub batch_occupancy: Gauge,
+    /// Games currently being played.
+    pub active_games: Gauge,
+    /// Examples kept for training.
+    pub examples: Gauge,
+    pub policy_loss: Gauge,
+    pub value_loss: Gauge,
+    /// Mean squared error of the value head on held-out examples.
+    pub value_brier_score: Gauge,
+    /// Expected calibration error of the value head on held-out examples.
+    pub value_calibration_error: Gauge,
+    /// Mean predicted score in each calibration bucket.
+    pub calibration_predicted: [Gauge; CALIBRATION_BUCKETS],
+    /// Mean game result as a score in each calibration bucket.
+    pub calibration_observed: [Gauge; CALIBRATION_BUCKETS],
+    /// Microseconds spent in each search [`Phase`], if profiling is on.
+    pub search_micros: [Counter; 5],
+}
+
+// allows repeating a gauge in array expressions
+#[allow(clippy::declare_interior_mutable_const)]
+const GAUGE: Gauge = Gauge::new();
+
+pub static METRICS: Metrics = Metrics {
+    games_completed: Counter::new(),
+    positions_evaluated: Counter::new(),
+    batches_evaluated: Counter::new(),
+    resignations: Counter::new(),
+    resignations_checked: Counter::new(),
+    false_resignations: Counter::new(),
+    rollouts: Counter::new(),
+    reused_visits: Counter::new(),
+    discarded_visits: Counter::new(),
+    proven_nodes: Counter::new(),
+    transposed_expansions: Counter::new(),
+    batch_occupancy: Gauge::new(),
+    active_games: Gauge::new(),
+    examples: Gauge::new(),
+    policy_loss: Gauge::new(),
+    value_loss: Gauge::new(),
+    value_brier_score: Gauge::new(),
+    value_calibration_error: Gauge::new(),
+    calibration_predicted: [GAUGE; CALIBRATION_BUCKETS],
+    calibration_observed: [GAUGE; CALIBRATION_BUCKETS],
+    search_micros: [
+        Counter::new(),
+        Counter::new(),
+        Counter::new(),
+        Counter::new(),
+        Counter::new(),
+    ],
+};
+
+impl Metrics {
+    pub fn render(&self) -> String {
+        let counters = [
+            (
+                "games_completed_total",
+                "Self-play and pit games completed",
+                &self.games_completed,
+            ),
+            (
+                "positions_evaluated_total",
+                "Positions evaluated by the network",
+                &self.positions_evaluated,
+            ),
+            (
+                "batches_evaluated_total",
+                "Batches evaluated by the network",
+                &self.batches_evaluated,
+            ),
+            (
+                "resignations_total",
+                "Self-play games resigned",
+                &self.resignations,
+            ),
+            (
+                "resignations_checked_total",
+                "Self-play games played out which would have been resigned",
+                &self.resignations_checked,
+            ),
+            (
+                "false_resignations_total",
+                "Self-play games played out which would have been resigned without being lost",
+                &self.false_resignations,
+            ),
+            ("rollouts_total", "Rollouts done by players", &self.rollouts),
+            (
+                "reused_visits_total",
+                "Visits kept in the search tree when a move is played",
+                &self.reused_visits,
+            ),
+            (
+                "discarded_visits_total",
+                "Visits dropped from the search tree when a move is played",
+                &self.discarded_visits,
+            ),
+            (
+                "proven_nodes_total",
+                "Search nodes whose result was proven by the endgame solver",
+                &self.proven_nodes,
+            ),
+            (
+                "transposed_expansions_total",
+                "Search nodes expanded from a transposition instead of the network",
+                &self.transposed_expansions,
+            ),
+        ];
+        let gauges = [
+            (
+                "batch_occupancy",
+                "Fraction of workers in the last batch",
+                &self.batch_occupancy,
+            ),
+            ("active_games", "Games currently being played", &self.active_games),
+            ("examples", "Examples kept for training", &self.examples),
+            (
+                "policy_loss",
+                "Policy loss of the last training batch",
+                &self.policy_loss,
+            ),
+            (
+                "value_loss",
+                "Value loss of the last training batch",
+                &self.value_loss,
+            ),
+            (
+                "value_brier_score",
+                "Mean squared error of the value head on held-out examples",
+                &self.value_brier_score,
+            ),
+            (
+                "value_calibration_error",
+                "Expected calibration error of the value head on held-out examples",
+                &self.value_calibration_error,
+            ),
+        ];
+
+        let mut out = String::new();
+        for (name, help, counter) in counters {
+            out += &format!("# HELP alpha_tak_{name} {help}\n# TYPE alpha_tak_{name} counter\n");
+            out += &format!("alpha_tak_{name} {}\n", counter.get());
+        }
+        for (name, help, gauge) in gauges {
+            out += &format!("# HELP alpha_tak_{name} {help}\n# TYPE alpha_tak_{name} gauge\n");
+            out += &format!("alpha_tak_{name} {}\n", gauge.get());
+        }
+        for (name, help, gauges) in [
+            (
+                "value_calibration_predicted",
+                "Mean predicted score in each value calibration bucket",
+                &self.calibration_predicted,
+            ),
+            (
+                "value_calibration_observed",
+                "Mean observed score in each value calibration bucket",
+                &self.calibration_observed,
+            ),
+        ] {
+            out += &format!("# HELP alpha_tak_{name} {help}\n# TYPE alpha_tak_{name} gauge\n");
+            for (i, gauge) in gauges.iter().enumerate() {
+                let low = i as f64 / CALIBRATION_BUCKETS as f64;
+                let high = (i + 1) as f64 / CALIBRATION_BUCKETS as f64;
+                out += &format!(
+                    "alpha_tak_{name}{{low=\"{low:.2}\",high=\"{high:.2}\"}} {}\n",
+                    gauge.get()
+                );
+            }
+        }
+        out += "# HELP alpha_tak_search_microseconds_total Time spent in each phase of the search\n";
+        out += "# TYPE alpha_tak_search_microseconds_total counter\n";
+        for phase in Phase::ALL {
+            out += &format!(
+                "alpha_tak_search_microseconds_total{{phase=\"{}\"}} {}\n",
+                phase.name(),
+                self.search_micros[phase as usize].get()
+            );
+        }
+        out
+    }
+}
+
+/// Serve [`METRICS`] on `/metrics` from a background thread.
+pub fn serve<A: ToSocketAddrs>(addr: A) -> io::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)?;
+    Ok(thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut request_line = String::new();
+            if BufReader::new(&stream).read_line(&mut request_line).is_err() {
+                continue;
+            }
+            let response = if request_line.starts_with("GET /metrics") {
+                let body = METRICS.render();
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: \
+                     {}\r\n\r\n{body}",
+                    body.len()
+                )
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string()
+            };
+            stream.write_all(response.as_bytes()).ok();
+        }
+    }))
+}
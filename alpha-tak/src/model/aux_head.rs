@@ -0,0 +1,122 @@
+use tak::*;
+use tch::{nn, Kind, Tensor};
+
+use super::network::Network;
+use crate::{
+    config::AUX_SIZES,
+    example::Example,
+    repr::{input_channels, moves_dims},
+    search::turn_map::Lut,
+};
+
+/// Input and output layers for another board size,
+/// trained together with the main network while sharing the residual blocks.
+#[derive(Debug)]
+pub struct AuxHead {
+    pub size: usize,
+    /// Weight of this size in the loss.
+    pub weight: f64,
+    pub initial_conv: nn::Conv2D,
+    pub initial_batch_norm: nn::BatchNorm,
+    pub fully_connected_policy: nn::Linear,
+    pub fully_connected_eval: nn::Linear,
+}
+
+impl AuxHead {
+    pub fn new(root: &nn::Path, size: usize, weight: f64, filters: i64) -> Self {
+        let root = &(root / format!("size_{size}"));
+        let conv_config = nn::ConvConfig {
+            padding: 1,
+            ..Default::default()
+        };
+        AuxHead {
+            size,
+            weight,
+            initial_conv: nn::conv2d(root, input_channels(size) as i64, filters, 3, conv_config),
+            initial_batch_norm: nn::batch_norm2d(root, filters, Default::default()),
+            fully_connected_policy: nn::linear(
+                root,
+                filters * (size * size) as i64,
+                moves_dims(size) as i64,
+                Default::default(),
+            ),
+            fully_connected_eval: nn::linear(root, filters * (size * size) as i64, 1, Default::default()),
+        }
+    }
+}
+
+/// Create the auxiliary heads listed in the config.
+pub fn aux_heads<const N: usize>(root: &nn::Path, filters: i64) -> Vec<AuxHead> {
+    AUX_SIZES
+        .iter()
+        .filter(|(size, _)| *size != N)
+        .map(|&(size, weight)| AuxHead::new(root, size, weight, filters))
+        .collect()
+}
+
+/// Training data for an auxiliary head.
+pub struct AuxExamples {
+    pub size: usize,
+    pub inputs: Tensor,
+    /// Policy and result concatenated, like the main training targets.
+    pub targets: Tensor,
+}
+
+impl AuxExamples {
+    pub fn new<const M: usize>(examples: &[Example<M>]) -> Self
+    where
+        Turn<M>: Lut,
+        [[Option<Tile>; M]; M]: Default,
+    {
+        let mut inputs = Vec::new();
+        let mut policies = Vec::new();
+        let mut results = Vec::new();
+        for (game, pi, _reply, v) in examples.iter().flat_map(|ex| ex.to_tensors()) {
+            inputs.push(game);
+            policies.push(pi);
+            results.push(v);
+        }
+        let pi = Tensor::stack(&policies, 0);
+        let v = Tensor::of_slice(&results).unsqueeze_(1);
+        AuxExamples {
+            size: M,
+            inputs: Tensor::stack(&inputs, 0),
+            targets: Tensor::cat(&[pi, v], 1),
+        }
+    }
+
+    pub fn len(&self) -> i64 {
+        self.inputs.size()[0]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Randomly sample (with replacement) a batch of inputs and targets.
+    pub fn sample(&self, amount: i64) -> (Tensor, Tensor) {
+        let index = Tensor::randint(self.len(), &[amount], (Kind::Int64, self.inputs.device()));
+        (
+            self.inputs.index_select(0, &index),
+            self.targets.index_select(0, &index),
+        )
+    }
+}
+
+impl<const N: usize> Network<N> {
+    /// Forward pass for another board size through its auxiliary head.
+    pub fn forward_training_aux(&self, head: &AuxHead, input: Tensor) -> (Tensor, Tensor) {
+        let s = self
+            .forward_trunk(
+                input
+                    .apply_t(&head.initial_conv, true)
+                    .apply_t(&head.initial_batch_norm, true)
+                    .relu_(),
+                true,
+            )
+            .view([-1, self.filters * (head.size * head.size) as i64]);
+        let policy = s.apply(&head.fully_connected_policy).log_softmax(1, Kind::Float);
+        let eval = s.apply(&head.fully_connected_eval).tanh_();
+        (policy, eval)
+    }
+}
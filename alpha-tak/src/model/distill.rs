@@ -0,0 +1,68 @@
+use rand::{prelude::SliceRandom, thread_rng};
+use tak::*;
+use tch::{
+    nn::{self, OptimizerConfig},
+    Kind,
+    Tensor,
+};
+use tracing::{debug, info};
+
+use super::{network::Network, train::Loss};
+use crate::{
+    config::{BATCH_SIZE, LEARNING_RATE, WEIGHT_DECAY},
+    repr::game_repr,
+    DEVICE,
+};
+
+impl<const N: usize> Network<N> {
+    /// Train this network to imitate the policy and eval of the teacher on the
+    /// positions. Returns the loss of each batch.
+    pub fn distill(&mut self, teacher: &Network<N>, games: &[Game<N>], epochs: usize) -> Vec<Loss>
+    where
+        [[Option<Tile>; N]; N]: Default,
+    {
+        info!(positions = games.len(), "starting distillation");
+
+        let mut opt = nn::Adam {
+            wd: WEIGHT_DECAY,
+            ..Default::default()
+        }
+        .build(&self.vs, LEARNING_RATE)
+        .unwrap();
+
+        // the teacher labels every symmetry separately
+        let mut games: Vec<_> = games.iter().flat_map(|game| game.clone().symmetries()).collect();
+        let mut losses = Vec::new();
+        for epoch in 0..epochs {
+            info!("epoch {}/{epochs}", epoch + 1);
+            games.shuffle(&mut thread_rng());
+            for batch in games.chunks(BATCH_SIZE as usize) {
+                let inputs: Vec<_> = batch.iter().map(game_repr).collect();
+                let input = Tensor::stack(&inputs, 0).to_device_(*DEVICE, Kind::Float, true, false);
+                let batch_size = batch.len() as i64;
+
+                // soft targets from the teacher
+                let (p, z) = tch::no_grad(|| teacher.forward_mcts(input.shallow_clone()));
+                let (policy, eval) = self.forward_training(input);
+
+                let loss_p = -(p * policy).sum(Kind::Float) / batch_size;
+                let loss_z = (z - eval).square_().sum(Kind::Float) / batch_size;
+                let loss = Loss {
+                    policy: loss_p.double_value(&[]) as f32,
+                    value: loss_z.double_value(&[]) as f32,
+                };
+                debug!(
+                    policy_loss = loss.policy,
+                    value_loss = loss.value,
+                    "distillation batch"
+                );
+                losses.push(loss);
+                let total_loss = loss_z + loss_p;
+
+                opt.zero_grad();
+                opt.backward_step(&total_loss);
+            }
+        }
+        losses
+    }
+}
@@ -1,40 +1,116 @@
+use std::{
+    any::Any,
+    panic::{catch_unwind, resume_unwind, AssertUnwindSafe},
+};
+
 use tak::*;
 use tch::{Kind, Tensor};
+use tracing::warn;
 
 use super::network::Network;
-use crate::{config::FILTERS, repr::game_repr, DEVICE};
+use crate::{
+    repr::{game_repr, place_moves, spread_moves},
+    DEVICE,
+};
 
 // Like forward_t in the nn::ModuleT trait,
 // except we return two values (policy, eval)
 impl<const N: usize> Network<N> {
     fn forward_conv(&self, input: Tensor, train: bool) -> Tensor {
+        self.forward_trunk(
+            input
+                .apply_t(&self.initial_conv, train)
+                .apply_t(&self.initial_batch_norm, train)
+                .relu_(),
+            train,
+        )
+        .view([-1, self.filters * (N * N) as i64])
+    }
+
+    /// Apply the residual blocks, which do not depend on the board size.
+    pub(super) fn forward_trunk(&self, input: Tensor, train: bool) -> Tensor {
         self.residual_blocks
             .iter()
-            .fold(
-                input
-                    .apply_t(&self.initial_conv, train)
-                    .apply_t(&self.initial_batch_norm, train)
-                    .relu_(),
-                |prev, res_block| res_block.forward(prev, train),
-            )
-            .view([-1, FILTERS * (N * N) as i64])
+            .fold(input, |prev, res_block| res_block.forward(prev, train))
+    }
+
+    /// Policy logits in the order of the policy vector.
+    fn policy_logits(&self, s: &Tensor) -> Tensor {
+        let output = s.apply(&self.fully_connected_policy);
+        let places = output.narrow(1, 0, place_moves(N) as i64);
+        let spreads = match &self.spread_factors {
+            Some((origins, patterns)) => output.index_select(1, origins) + output.index_select(1, patterns),
+            None => output.narrow(1, place_moves(N) as i64, spread_moves(N) as i64),
+        };
+        match &self.policy_mixing {
+            // log probabilities work as logits of the mixed policy
+            Some(mixing) => {
+                let place_share = s.apply(mixing);
+                let places = places.log_softmax(1, Kind::Float) + place_share.log_sigmoid();
+                let spreads = spreads.log_softmax(1, Kind::Float) + (-place_share).log_sigmoid();
+                Tensor::cat(&[places, spreads], 1)
+            }
+            None => Tensor::cat(&[places, spreads], 1),
+        }
     }
 
     pub fn forward_mcts(&self, input: Tensor) -> (Tensor, Tensor) {
         let s = self.forward_conv(input, false);
-        let policy = s.apply(&self.fully_connected_policy).softmax(1, Kind::Float);
+        let policy = self.policy_logits(&s).softmax(1, Kind::Float);
         let eval = s.apply(&self.fully_connected_eval).tanh_();
         (policy, eval)
     }
 
     pub fn forward_training(&self, input: Tensor) -> (Tensor, Tensor) {
+        let (policy, _, eval) = self.forward_training_with_reply(input);
+        (policy, eval)
+    }
+
+    /// Like [`Self::forward_training`], also returning the log policy of the
+    /// opponent's reply if the network has a reply head.
+    pub fn forward_training_with_reply(&self, input: Tensor) -> (Tensor, Option<Tensor>, Tensor) {
         let s = self.forward_conv(input, true);
-        let policy = s.apply(&self.fully_connected_policy).log_softmax(1, Kind::Float);
+        let policy = self.policy_logits(&s).log_softmax(1, Kind::Float);
+        let reply = self
+            .fully_connected_reply
+            .as_ref()
+            .map(|head| s.apply(head).log_softmax(1, Kind::Float));
         let eval = s.apply(&self.fully_connected_eval).tanh_();
-        (policy, eval)
+        (policy, reply, eval)
+    }
+
+    /// Predicted policy of the opponent after the player to move has played,
+    /// in the order of the policy vector. Can be used to expand the likely
+    /// replies ahead of time while pondering.
+    pub fn reply_policy(&self, game: &Game<N>) -> Option<Vec<f32>> {
+        let head = self.fully_connected_reply.as_ref()?;
+        let input = game_repr(game)
+            .unsqueeze(0)
+            .to_device_(*DEVICE, Kind::Float, true, false);
+        let s = self.forward_conv(input, false);
+        let reply: Vec<Vec<f32>> = s.apply(head).softmax(1, Kind::Float).into();
+        reply.into_iter().next()
     }
 
+    /// Evaluate many positions at once. If the GPU runs out of memory the
+    /// batch is halved and evaluated in parts instead of crashing.
     pub fn policy_eval_batch(&self, games: &[Game<N>]) -> (Vec<Vec<f32>>, Vec<f32>) {
+        match catch_unwind(AssertUnwindSafe(|| self.policy_eval_batch_once(games))) {
+            Ok(output) => output,
+            Err(err) if games.len() > 1 && is_out_of_memory(err.as_ref()) => {
+                warn!(batch = games.len(), "out of GPU memory, halving the batch");
+                let (first, second) = games.split_at(games.len() / 2);
+                let (mut policies, mut evals) = self.policy_eval_batch(first);
+                let (more_policies, more_evals) = self.policy_eval_batch(second);
+                policies.extend(more_policies);
+                evals.extend(more_evals);
+                (policies, evals)
+            }
+            Err(err) => resume_unwind(err),
+        }
+    }
+
+    fn policy_eval_batch_once(&self, games: &[Game<N>]) -> (Vec<Vec<f32>>, Vec<f32>) {
         let game_tensors: Vec<_> = games.iter().map(game_repr).collect();
         let input = Tensor::stack(&game_tensors, 0).to_device_(*DEVICE, Kind::Float, true, false);
         let (policy, eval) = self.forward_mcts(input);
@@ -43,3 +119,11 @@ impl<const N: usize> Network<N> {
         (policies, evals)
     }
 }
+
+/// Whether a panic of libtorch was caused by running out of GPU memory.
+fn is_out_of_memory(err: &(dyn Any + Send)) -> bool {
+    err.downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| err.downcast_ref::<&str>().copied())
+        .map_or(false, |message| message.contains("out of memory"))
+}
@@ -1,7 +1,11 @@
 #[cfg(test)]
 pub mod bench;
 
+pub mod aux_head;
+pub mod distill;
 pub mod forward;
 pub mod network;
+pub mod onnx;
 pub mod res_block;
+pub mod throughput;
 pub mod train;
@@ -1,12 +1,16 @@
-use std::{error::Error, path::Path};
+use std::{error::Error, mem::size_of, path::Path};
 
-use arrayvec::ArrayVec;
-use tch::nn;
+use tak::*;
+use tch::{nn, Tensor};
 
-use super::res_block::ResBlock;
+use super::{
+    aux_head::{aux_heads, AuxHead},
+    res_block::ResBlock,
+};
 use crate::{
-    config::{FILTERS, RES_BLOCKS},
-    repr::{input_channels, moves_dims},
+    config::{FACTORIZED_POLICY, FILTERS, REPLY_HEAD, RES_BLOCKS, SPLIT_POLICY},
+    repr::{factorized_policy_dims, input_channels, moves_dims, place_moves, spread_factors},
+    search::turn_map::Lut,
     DEVICE,
 };
 
@@ -15,26 +19,67 @@ pub struct Network<const N: usize> {
     pub vs: nn::VarStore,
     pub initial_conv: nn::Conv2D,
     pub initial_batch_norm: nn::BatchNorm,
-    pub residual_blocks: ArrayVec<ResBlock, RES_BLOCKS>,
+    pub residual_blocks: Vec<ResBlock>,
+    pub filters: i64,
     pub fully_connected_policy: nn::Linear,
+    /// With a factorized policy head, the indices of the origin and the drop
+    /// pattern outputs which make up the logit of each spread.
+    pub spread_factors: Option<(Tensor, Tensor)>,
+    /// With split placement and spread policies, the logit of the share of
+    /// the policy which goes to placements.
+    pub policy_mixing: Option<nn::Linear>,
+    /// Predicts the policy of the opponent in the following position.
+    pub fully_connected_reply: Option<nn::Linear>,
     pub fully_connected_eval: nn::Linear,
+    /// Heads for other board sizes that share the residual blocks.
+    pub aux_heads: Vec<AuxHead>,
 }
 
 impl<const N: usize> Network<N> {
+    /// Memory taken by the weights of the network.
+    pub fn parameter_bytes(&self) -> usize {
+        self.vs
+            .variables()
+            .values()
+            .map(|tensor| tensor.numel() * size_of::<f32>())
+            .sum()
+    }
+
+    /// Rough estimate of the memory needed to evaluate a batch: the inputs,
+    /// outputs, and the activations of a residual block alive at once.
+    pub fn batch_bytes(&self, batch_size: usize) -> usize {
+        let per_position = (input_channels(N) + 3 * self.filters as usize) * N * N + moves_dims(N) + 1;
+        batch_size * per_position * size_of::<f32>()
+    }
+
     pub fn save<T: AsRef<Path>>(&self, path: T) -> Result<(), Box<dyn Error>> {
         self.vs.save(path)?;
         Ok(())
     }
+}
 
+impl<const N: usize> Network<N>
+where
+    Turn<N>: Lut,
+{
     pub fn load<T: AsRef<Path>>(path: T) -> Result<Network<N>, Box<dyn Error>> {
-        let mut nn = Self::default();
+        Self::load_with_size(path, RES_BLOCKS, FILTERS)
+    }
+
+    /// Load a network which does not have the default size.
+    pub fn load_with_size<T: AsRef<Path>>(
+        path: T,
+        res_blocks: usize,
+        filters: i64,
+    ) -> Result<Network<N>, Box<dyn Error>> {
+        let mut nn = Self::with_size(res_blocks, filters);
         nn.vs.load(path)?;
         Ok(nn)
     }
-}
 
-impl<const N: usize> Default for Network<N> {
-    fn default() -> Self {
+    /// Create a new network with a custom amount of residual blocks and
+    /// filters.
+    pub fn with_size(res_blocks: usize, filters: i64) -> Self {
         let vs = nn::VarStore::new(*DEVICE);
         let root = &vs.root();
 
@@ -43,15 +88,15 @@ impl<const N: usize> Default for Network<N> {
             ..Default::default()
         };
 
-        let initial_conv = nn::conv2d(root, input_channels(N) as i64, FILTERS, 3, conv_config);
-        let initial_batch_norm = nn::batch_norm2d(root, FILTERS, Default::default());
+        let initial_conv = nn::conv2d(root, input_channels(N) as i64, filters, 3, conv_config);
+        let initial_batch_norm = nn::batch_norm2d(root, filters, Default::default());
 
-        let mut residual_blocks = ArrayVec::new();
-        for _ in 0..RES_BLOCKS {
-            let conv1 = nn::conv2d(root, FILTERS, FILTERS, 3, conv_config);
-            let conv2 = nn::conv2d(root, FILTERS, FILTERS, 3, conv_config);
-            let batch_norm1 = nn::batch_norm2d(root, FILTERS, Default::default());
-            let batch_norm2 = nn::batch_norm2d(root, FILTERS, Default::default());
+        let mut residual_blocks = Vec::new();
+        for _ in 0..res_blocks {
+            let conv1 = nn::conv2d(root, filters, filters, 3, conv_config);
+            let conv2 = nn::conv2d(root, filters, filters, 3, conv_config);
+            let batch_norm1 = nn::batch_norm2d(root, filters, Default::default());
+            let batch_norm2 = nn::batch_norm2d(root, filters, Default::default());
             residual_blocks.push(ResBlock {
                 conv1,
                 conv2,
@@ -60,21 +105,72 @@ impl<const N: usize> Default for Network<N> {
             });
         }
 
+        let policy_dims = if FACTORIZED_POLICY {
+            factorized_policy_dims(N)
+        } else {
+            moves_dims(N)
+        };
         let fully_connected_policy = nn::linear(
             root,
-            FILTERS * (N * N) as i64,
-            moves_dims(N) as i64,
+            filters * (N * N) as i64,
+            policy_dims as i64,
             Default::default(),
         );
-        let fully_connected_eval = nn::linear(root, FILTERS * (N * N) as i64, 1, Default::default());
+        let spread_factors = FACTORIZED_POLICY.then(|| {
+            let (origins, patterns): (Vec<_>, Vec<_>) = spread_factors::<N>()
+                .into_iter()
+                .map(|(origin, pattern)| {
+                    // offsets into the whole output of the head
+                    let origin = place_moves(N) + origin;
+                    let pattern = place_moves(N) + 4 * N * N + pattern;
+                    (origin as i64, pattern as i64)
+                })
+                .unzip();
+            (
+                Tensor::of_slice(&origins).to_device(*DEVICE),
+                Tensor::of_slice(&patterns).to_device(*DEVICE),
+            )
+        });
+        let policy_mixing = SPLIT_POLICY.then(|| {
+            nn::linear(
+                root / "policy_mixing",
+                filters * (N * N) as i64,
+                1,
+                Default::default(),
+            )
+        });
+        let fully_connected_reply = REPLY_HEAD.then(|| {
+            nn::linear(
+                root / "reply",
+                filters * (N * N) as i64,
+                moves_dims(N) as i64,
+                Default::default(),
+            )
+        });
+        let fully_connected_eval = nn::linear(root, filters * (N * N) as i64, 1, Default::default());
+        let aux_heads = aux_heads::<N>(root, filters);
 
         Network {
             vs,
             initial_conv,
             initial_batch_norm,
             residual_blocks,
+            filters,
             fully_connected_policy,
+            spread_factors,
+            policy_mixing,
+            fully_connected_reply,
             fully_connected_eval,
+            aux_heads,
         }
     }
 }
+
+impl<const N: usize> Default for Network<N>
+where
+    Turn<N>: Lut,
+{
+    fn default() -> Self {
+        Self::with_size(RES_BLOCKS, FILTERS)
+    }
+}
@@ -0,0 +1,547 @@
+//! Export of the network to ONNX, so it can be run with onnxruntime and
+//! other engines without libtorch, and import of the exported files.
+//!
+//! The graph is written by hand for the known architecture and computes the
+//! same outputs as [`Network::forward_mcts`]: the policy after a softmax and
+//! the evaluation after a tanh, plus the reply policy if there is a reply
+//! head. The weights are stored as initializers named after the variables of
+//! the var store, which is how [`Network::from_onnx`] finds them again.
+
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs,
+    path::Path,
+};
+
+use prost::Message;
+use tak::*;
+use tch::{nn, Kind, Tensor};
+
+use super::network::Network;
+use crate::{
+    repr::{input_channels, moves_dims, place_moves, spread_moves},
+    search::turn_map::Lut,
+};
+
+const IR_VERSION: i64 = 7;
+const OPSET_VERSION: i64 = 13;
+/// Epsilon of the batch norms, the libtorch default.
+const BATCH_NORM_EPSILON: f32 = 1e-5;
+
+const BOARD_SIZE_KEY: &str = "alpha_tak.board_size";
+const RES_BLOCKS_KEY: &str = "alpha_tak.res_blocks";
+const FILTERS_KEY: &str = "alpha_tak.filters";
+
+// The subset of onnx.proto which is needed to describe the network.
+// Field numbers have to match the official schema.
+
+#[derive(Clone, PartialEq, Message)]
+struct ModelProto {
+    #[prost(int64, tag = "1")]
+    ir_version: i64,
+    #[prost(string, tag = "2")]
+    producer_name: String,
+    #[prost(message, optional, tag = "7")]
+    graph: Option<GraphProto>,
+    #[prost(message, repeated, tag = "8")]
+    opset_import: Vec<OperatorSetIdProto>,
+    #[prost(message, repeated, tag = "14")]
+    metadata_props: Vec<StringStringEntryProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct OperatorSetIdProto {
+    #[prost(string, tag = "1")]
+    domain: String,
+    #[prost(int64, tag = "2")]
+    version: i64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct StringStringEntryProto {
+    #[prost(string, tag = "1")]
+    key: String,
+    #[prost(string, tag = "2")]
+    value: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct GraphProto {
+    #[prost(message, repeated, tag = "1")]
+    node: Vec<NodeProto>,
+    #[prost(string, tag = "2")]
+    name: String,
+    #[prost(message, repeated, tag = "5")]
+    initializer: Vec<TensorProto>,
+    #[prost(message, repeated, tag = "11")]
+    input: Vec<ValueInfoProto>,
+    #[prost(message, repeated, tag = "12")]
+    output: Vec<ValueInfoProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct NodeProto {
+    #[prost(string, repeated, tag = "1")]
+    input: Vec<String>,
+    #[prost(string, repeated, tag = "2")]
+    output: Vec<String>,
+    #[prost(string, tag = "3")]
+    name: String,
+    #[prost(string, tag = "4")]
+    op_type: String,
+    #[prost(message, repeated, tag = "5")]
+    attribute: Vec<AttributeProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct AttributeProto {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(float, tag = "2")]
+    f: f32,
+    #[prost(int64, tag = "3")]
+    i: i64,
+    #[prost(int64, repeated, tag = "8")]
+    ints: Vec<i64>,
+    #[prost(int32, tag = "20")]
+    r#type: i32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TensorProto {
+    #[prost(int64, repeated, tag = "1")]
+    dims: Vec<i64>,
+    #[prost(int32, tag = "2")]
+    data_type: i32,
+    #[prost(float, repeated, tag = "4")]
+    float_data: Vec<f32>,
+    #[prost(int64, repeated, tag = "7")]
+    int64_data: Vec<i64>,
+    #[prost(string, tag = "8")]
+    name: String,
+    #[prost(bytes = "vec", tag = "9")]
+    raw_data: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ValueInfoProto {
+    #[prost(string, tag = "1")]
+    name: String,
+    #[prost(message, optional, tag = "2")]
+    r#type: Option<TypeProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TypeProto {
+    #[prost(message, optional, tag = "1")]
+    tensor_type: Option<TensorTypeProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TensorTypeProto {
+    #[prost(int32, tag = "1")]
+    elem_type: i32,
+    #[prost(message, optional, tag = "2")]
+    shape: Option<TensorShapeProto>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct TensorShapeProto {
+    #[prost(message, repeated, tag = "1")]
+    dim: Vec<Dimension>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct Dimension {
+    #[prost(int64, tag = "1")]
+    dim_value: i64,
+    #[prost(string, tag = "2")]
+    dim_param: String,
+}
+
+const FLOAT: i32 = 1;
+const INT64: i32 = 7;
+
+const ATTRIBUTE_FLOAT: i32 = 1;
+const ATTRIBUTE_INT: i32 = 2;
+const ATTRIBUTE_INTS: i32 = 7;
+
+fn float_attribute(name: &str, f: f32) -> AttributeProto {
+    AttributeProto {
+        name: name.to_string(),
+        f,
+        r#type: ATTRIBUTE_FLOAT,
+        ..Default::default()
+    }
+}
+
+fn int_attribute(name: &str, i: i64) -> AttributeProto {
+    AttributeProto {
+        name: name.to_string(),
+        i,
+        r#type: ATTRIBUTE_INT,
+        ..Default::default()
+    }
+}
+
+fn ints_attribute(name: &str, ints: &[i64]) -> AttributeProto {
+    AttributeProto {
+        name: name.to_string(),
+        ints: ints.to_vec(),
+        r#type: ATTRIBUTE_INTS,
+        ..Default::default()
+    }
+}
+
+/// Float tensor with the given shape, where `None` is the batch dimension.
+fn value_info(name: &str, shape: &[Option<i64>]) -> ValueInfoProto {
+    let dim = shape
+        .iter()
+        .map(|size| match size {
+            Some(size) => Dimension {
+                dim_value: *size,
+                ..Default::default()
+            },
+            None => Dimension {
+                dim_param: "batch".to_string(),
+                ..Default::default()
+            },
+        })
+        .collect();
+    ValueInfoProto {
+        name: name.to_string(),
+        r#type: Some(TypeProto {
+            tensor_type: Some(TensorTypeProto {
+                elem_type: FLOAT,
+                shape: Some(TensorShapeProto { dim }),
+            }),
+        }),
+    }
+}
+
+/// Builds the graph node by node, naming every intermediate value.
+struct GraphBuilder {
+    nodes: Vec<NodeProto>,
+    initializers: Vec<TensorProto>,
+    /// Names of the variables of the var store by the address of their data.
+    variable_names: HashMap<usize, String>,
+    exported: HashSet<String>,
+}
+
+impl GraphBuilder {
+    fn new(vs: &nn::VarStore) -> Self {
+        let variable_names = vs
+            .variables()
+            .into_iter()
+            .map(|(name, tensor)| (tensor.data_ptr() as usize, name))
+            .collect();
+        GraphBuilder {
+            nodes: Vec::new(),
+            initializers: Vec::new(),
+            variable_names,
+            exported: HashSet::new(),
+        }
+    }
+
+    /// Add a node with a single output and return the name of the output.
+    fn node(&mut self, op_type: &str, inputs: &[&str], attribute: Vec<AttributeProto>) -> String {
+        let name = format!("{}_{}", op_type.to_lowercase(), self.nodes.len());
+        self.node_named(op_type, inputs, attribute, &name)
+    }
+
+    fn node_named(
+        &mut self,
+        op_type: &str,
+        inputs: &[&str],
+        attribute: Vec<AttributeProto>,
+        output: &str,
+    ) -> String {
+        self.nodes.push(NodeProto {
+            input: inputs.iter().map(|input| input.to_string()).collect(),
+            output: vec![output.to_string()],
+            name: format!("node_{}", self.nodes.len()),
+            op_type: op_type.to_string(),
+            attribute,
+        });
+        output.to_string()
+    }
+
+    /// Add a variable of the network as an initializer named after it.
+    fn variable(&mut self, tensor: &Tensor) -> String {
+        let name = self
+            .variable_names
+            .get(&(tensor.data_ptr() as usize))
+            .cloned()
+            .expect("tensor should belong to the var store");
+        if self.exported.insert(name.clone()) {
+            let values: Vec<f32> = tensor.to_kind(Kind::Float).contiguous().view([-1]).into();
+            self.initializers.push(TensorProto {
+                dims: tensor.size(),
+                data_type: FLOAT,
+                name: name.clone(),
+                raw_data: values.iter().flat_map(|value| value.to_le_bytes()).collect(),
+                ..Default::default()
+            });
+        }
+        name
+    }
+
+    fn constant(&mut self, name: &str, dims: &[i64], values: Vec<i64>) -> String {
+        self.initializers.push(TensorProto {
+            dims: dims.to_vec(),
+            data_type: INT64,
+            name: name.to_string(),
+            int64_data: values,
+            ..Default::default()
+        });
+        name.to_string()
+    }
+
+    fn conv(&mut self, input: &str, conv: &nn::Conv2D) -> String {
+        let mut inputs = vec![input.to_string(), self.variable(&conv.ws)];
+        if let Some(bs) = &conv.bs {
+            inputs.push(self.variable(bs));
+        }
+        let inputs: Vec<_> = inputs.iter().map(String::as_str).collect();
+        self.node("Conv", &inputs, vec![
+            ints_attribute("kernel_shape", &[3, 3]),
+            ints_attribute("pads", &[1, 1, 1, 1]),
+        ])
+    }
+
+    fn batch_norm(&mut self, input: &str, batch_norm: &nn::BatchNorm) -> String {
+        let scale = self.variable(batch_norm.ws.as_ref().expect("batch norm should have a scale"));
+        let bias = self.variable(batch_norm.bs.as_ref().expect("batch norm should have a bias"));
+        let mean = self.variable(&batch_norm.running_mean);
+        let var = self.variable(&batch_norm.running_var);
+        self.node("BatchNormalization", &[input, &scale, &bias, &mean, &var], vec![
+            float_attribute("epsilon", BATCH_NORM_EPSILON),
+        ])
+    }
+
+    fn linear(&mut self, input: &str, linear: &nn::Linear) -> String {
+        let mut inputs = vec![input.to_string(), self.variable(&linear.ws)];
+        if let Some(bs) = &linear.bs {
+            inputs.push(self.variable(bs));
+        }
+        let inputs: Vec<_> = inputs.iter().map(String::as_str).collect();
+        // the weights are stored as (out, in)
+        self.node("Gemm", &inputs, vec![int_attribute("transB", 1)])
+    }
+
+    /// Columns `start..start + len` of a batch of vectors.
+    fn narrow(&mut self, input: &str, start: usize, len: usize) -> String {
+        let index = self.nodes.len();
+        let starts = self.constant(&format!("starts_{index}"), &[1], vec![start as i64]);
+        let ends = self.constant(&format!("ends_{index}"), &[1], vec![(start + len) as i64]);
+        let axes = self.constant(&format!("axes_{index}"), &[1], vec![1]);
+        self.node("Slice", &[input, &starts, &ends, &axes], vec![])
+    }
+
+    /// The logarithm of the sigmoid, which ONNX lacks, as `-softplus(-x)`.
+    fn log_sigmoid(&mut self, input: &str) -> String {
+        let negated = self.node("Neg", &[input], vec![]);
+        let softplus = self.node("Softplus", &[&negated], vec![]);
+        self.node("Neg", &[&softplus], vec![])
+    }
+}
+
+impl<const N: usize> Network<N> {
+    /// Write the network as an ONNX model which takes a batch of inputs
+    /// named `input` and outputs the `policy` and `eval`, along with the
+    /// `reply` policy if the network has a reply head. The auxiliary heads
+    /// for other board sizes are not exported.
+    pub fn export_onnx<T: AsRef<Path>>(&self, path: T) -> Result<(), Box<dyn Error>> {
+        let model = tch::no_grad(|| self.onnx_model());
+        fs::write(path, model.encode_to_vec())?;
+        Ok(())
+    }
+
+    fn onnx_model(&self) -> ModelProto {
+        let mut graph = GraphBuilder::new(&self.vs);
+        let size = N as i64;
+
+        let mut x = graph.conv("input", &self.initial_conv);
+        x = graph.batch_norm(&x, &self.initial_batch_norm);
+        x = graph.node("Relu", &[&x], vec![]);
+        for res_block in &self.residual_blocks {
+            let mut y = graph.conv(&x, &res_block.conv1);
+            y = graph.batch_norm(&y, &res_block.batch_norm1);
+            y = graph.node("Relu", &[&y], vec![]);
+            y = graph.conv(&y, &res_block.conv2);
+            y = graph.batch_norm(&y, &res_block.batch_norm2);
+            y = graph.node("Add", &[&y, &x], vec![]);
+            x = graph.node("Relu", &[&y], vec![]);
+        }
+        let shape = graph.constant("flat_shape", &[2], vec![-1, self.filters * size * size]);
+        let s = graph.node("Reshape", &[&x, &shape], vec![]);
+
+        // policy logits, see `policy_logits`
+        let output = graph.linear(&s, &self.fully_connected_policy);
+        let mut places = graph.narrow(&output, 0, place_moves(N));
+        let mut spreads = match &self.spread_factors {
+            Some((origins, patterns)) => {
+                let origins: Vec<i64> = origins.shallow_clone().into();
+                let patterns: Vec<i64> = patterns.shallow_clone().into();
+                let origins = graph.constant("spread_origins", &[origins.len() as i64], origins);
+                let patterns = graph.constant("spread_patterns", &[patterns.len() as i64], patterns);
+                let origins = graph.node("Gather", &[&output, &origins], vec![int_attribute("axis", 1)]);
+                let patterns = graph.node("Gather", &[&output, &patterns], vec![int_attribute("axis", 1)]);
+                graph.node("Add", &[&origins, &patterns], vec![])
+            }
+            None => graph.narrow(&output, place_moves(N), spread_moves(N)),
+        };
+        if let Some(mixing) = &self.policy_mixing {
+            let place_share = graph.linear(&s, mixing);
+            let negated_share = graph.node("Neg", &[&place_share], vec![]);
+            let place_weight = graph.log_sigmoid(&place_share);
+            let spread_weight = graph.log_sigmoid(&negated_share);
+            places = graph.node("LogSoftmax", &[&places], vec![int_attribute("axis", 1)]);
+            places = graph.node("Add", &[&places, &place_weight], vec![]);
+            spreads = graph.node("LogSoftmax", &[&spreads], vec![int_attribute("axis", 1)]);
+            spreads = graph.node("Add", &[&spreads, &spread_weight], vec![]);
+        }
+        let logits = graph.node("Concat", &[&places, &spreads], vec![int_attribute("axis", 1)]);
+        graph.node_named("Softmax", &[&logits], vec![int_attribute("axis", 1)], "policy");
+
+        let eval = graph.linear(&s, &self.fully_connected_eval);
+        graph.node_named("Tanh", &[&eval], vec![], "eval");
+
+        let moves = moves_dims(N) as i64;
+        let mut outputs = vec![
+            value_info("policy", &[None, Some(moves)]),
+            value_info("eval", &[None, Some(1)]),
+        ];
+        if let Some(head) = &self.fully_connected_reply {
+            let reply = graph.linear(&s, head);
+            graph.node_named("Softmax", &[&reply], vec![int_attribute("axis", 1)], "reply");
+            outputs.push(value_info("reply", &[None, Some(moves)]));
+        }
+
+        let channels = input_channels(N) as i64;
+        let metadata = [
+            (BOARD_SIZE_KEY, N.to_string()),
+            (RES_BLOCKS_KEY, self.residual_blocks.len().to_string()),
+            (FILTERS_KEY, self.filters.to_string()),
+        ];
+        ModelProto {
+            ir_version: IR_VERSION,
+            producer_name: "alpha-tak".to_string(),
+            graph: Some(GraphProto {
+                node: graph.nodes,
+                name: format!("alpha_tak_{N}x{N}"),
+                initializer: graph.initializers,
+                input: vec![value_info("input", &[
+                    None,
+                    Some(channels),
+                    Some(size),
+                    Some(size),
+                ])],
+                output: outputs,
+            }),
+            opset_import: vec![OperatorSetIdProto {
+                domain: String::new(),
+                version: OPSET_VERSION,
+            }],
+            metadata_props: metadata
+                .into_iter()
+                .map(|(key, value)| StringStringEntryProto {
+                    key: key.to_string(),
+                    value,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<const N: usize> Network<N>
+where
+    Turn<N>: Lut,
+{
+    /// Load a network exported with [`Self::export_onnx`]. The auxiliary
+    /// heads are left freshly initialized, since they are not exported.
+    pub fn from_onnx<T: AsRef<Path>>(path: T) -> Result<Network<N>, Box<dyn Error>> {
+        let model = ModelProto::decode(fs::read(path)?.as_slice())?;
+        let metadata = |key: &str| {
+            model
+                .metadata_props
+                .iter()
+                .find(|entry| entry.key == key)
+                .map(|entry| entry.value.as_str())
+                .ok_or_else(|| format!("missing {key}, the model was not exported by alpha-tak"))
+        };
+        let board_size: usize = metadata(BOARD_SIZE_KEY)?.parse()?;
+        if board_size != N {
+            return Err(format!("model is for size {board_size}, not {N}").into());
+        }
+        let res_blocks: usize = metadata(RES_BLOCKS_KEY)?.parse()?;
+        let filters: i64 = metadata(FILTERS_KEY)?.parse()?;
+        let graph = model.graph.as_ref().ok_or("model has no graph")?;
+        let initializers: HashMap<_, _> = graph
+            .initializer
+            .iter()
+            .map(|tensor| (tensor.name.as_str(), tensor))
+            .collect();
+
+        let nn = Self::with_size(res_blocks, filters);
+        let aux_variables: HashSet<_> = nn
+            .aux_heads
+            .iter()
+            .flat_map(|head| {
+                [
+                    Some(&head.initial_conv.ws),
+                    head.initial_conv.bs.as_ref(),
+                    head.initial_batch_norm.ws.as_ref(),
+                    head.initial_batch_norm.bs.as_ref(),
+                    Some(&head.initial_batch_norm.running_mean),
+                    Some(&head.initial_batch_norm.running_var),
+                    Some(&head.fully_connected_policy.ws),
+                    head.fully_connected_policy.bs.as_ref(),
+                    Some(&head.fully_connected_eval.ws),
+                    head.fully_connected_eval.bs.as_ref(),
+                ]
+            })
+            .flatten()
+            .map(|tensor| tensor.data_ptr() as usize)
+            .collect();
+
+        for (name, mut variable) in nn.vs.variables() {
+            let tensor = match initializers.get(name.as_str()) {
+                Some(tensor) => tensor,
+                None if aux_variables.contains(&(variable.data_ptr() as usize)) => continue,
+                None => return Err(format!("missing weights of {name}").into()),
+            };
+            let values = float_values(tensor)?;
+            if tensor.dims != variable.size() {
+                return Err(format!(
+                    "weights of {name} have shape {:?} instead of {:?}",
+                    tensor.dims,
+                    variable.size()
+                )
+                .into());
+            }
+            let values = Tensor::of_slice(&values).view(tensor.dims.as_slice());
+            tch::no_grad(|| variable.copy_(&values));
+        }
+        Ok(nn)
+    }
+}
+
+/// Values of a float initializer, stored either as raw bytes or as floats.
+fn float_values(tensor: &TensorProto) -> Result<Vec<f32>, Box<dyn Error>> {
+    if tensor.data_type != FLOAT {
+        return Err(format!("weights of {} are not floats", tensor.name).into());
+    }
+    if tensor.raw_data.is_empty() {
+        return Ok(tensor.float_data.clone());
+    }
+    if tensor.raw_data.len() % 4 != 0 {
+        return Err(format!("weights of {} are truncated", tensor.name).into());
+    }
+    Ok(tensor
+        .raw_data
+        .chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect())
+}
@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use tak::*;
+
+use super::network::Network;
+
+/// Batch sizes tried when calibrating.
+pub const CALIBRATION_BATCHES: &[usize] = &[1, 8, 32, 64, 128, 256, 512];
+/// Share of the best throughput a smaller batch has to reach to be chosen.
+const GOOD_ENOUGH: f64 = 0.9;
+
+/// Speed of the network for one batch size.
+#[derive(Clone, Copy, Debug)]
+pub struct BatchTiming {
+    pub batch_size: usize,
+    /// Time to evaluate one batch.
+    pub latency: Duration,
+}
+
+impl BatchTiming {
+    /// Positions evaluated per second.
+    pub fn throughput(&self) -> f64 {
+        self.batch_size as f64 / self.latency.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl<const N: usize> Network<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// Time batches of each size, averaged over `repeats` evaluations after
+    /// one warm-up evaluation.
+    pub fn measure_batches(&self, batch_sizes: &[usize], repeats: u32) -> Vec<BatchTiming> {
+        batch_sizes
+            .iter()
+            .map(|&batch_size| {
+                let games = vec![Game::<N>::default(); batch_size];
+                self.policy_eval_batch(&games);
+                let start = Instant::now();
+                for _ in 0..repeats {
+                    self.policy_eval_batch(&games);
+                }
+                BatchTiming {
+                    batch_size,
+                    latency: start.elapsed() / repeats.max(1),
+                }
+            })
+            .collect()
+    }
+}
+
+/// The smallest batch size which comes close to the best throughput, since
+/// larger batches only add latency after that.
+pub fn pick_batch_size(timings: &[BatchTiming]) -> Option<usize> {
+    let best = timings.iter().map(BatchTiming::throughput).fold(0., f64::max);
+    timings
+        .iter()
+        .filter(|timing| timing.throughput() >= GOOD_ENOUGH * best)
+        .map(|timing| timing.batch_size)
+        .min()
+}
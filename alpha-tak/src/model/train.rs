@@ -1,4 +1,7 @@
-use rand::{prelude::SliceRandom, thread_rng};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    prelude::SliceRandom,
+};
 use tak::*;
 use tch::{
     data::Iter2,
@@ -6,61 +9,152 @@ use tch::{
     Kind,
     Tensor,
 };
+use tracing::{debug, info};
 
-use super::network::Network;
+use super::{aux_head::AuxExamples, network::Network};
 use crate::{
-    config::{BATCH_SIZE, LEARNING_RATE, MAX_TRAIN_SIZE, WEIGHT_DECAY},
+    config::{BATCH_SIZE, LEARNING_RATE, MAX_TRAIN_SIZE, REPLY_WEIGHT, WEIGHT_DECAY},
     example::Example,
     repr::moves_dims,
+    rng::with_rng,
     search::turn_map::Lut,
     DEVICE,
 };
 
+/// Settings of the optimizer, which default to the values in the config.
+#[derive(Clone, Copy, Debug)]
+pub struct TrainParams {
+    pub learning_rate: f64,
+    pub batch_size: i64,
+}
+
+impl Default for TrainParams {
+    fn default() -> Self {
+        TrainParams {
+            learning_rate: LEARNING_RATE,
+            batch_size: BATCH_SIZE,
+        }
+    }
+}
+
+/// Loss of a single training batch.
+#[derive(Clone, Copy, Debug)]
+pub struct Loss {
+    pub policy: f32,
+    pub value: f32,
+}
+
 impl<const N: usize> Network<N> {
-    pub fn train(&mut self, examples: &[Example<N>])
+    /// Train the network on the examples and return the loss of each batch.
+    pub fn train(&mut self, examples: &[Example<N>]) -> Vec<Loss>
     where
         Turn<N>: Lut,
         [[Option<Tile>; N]; N]: Default,
     {
-        println!("starting training with {} examples", examples.len());
+        self.train_multi(examples, &[])
+    }
+
+    /// Like [`Network::train`], but also train the auxiliary heads on examples
+    /// of other sizes. The returned losses are only for the main board
+    /// size.
+    pub fn train_multi(&mut self, examples: &[Example<N>], aux_examples: &[AuxExamples]) -> Vec<Loss>
+    where
+        Turn<N>: Lut,
+        [[Option<Tile>; N]; N]: Default,
+    {
+        // shuffle only the references to the examples so that the real storage
+        // of examples preserves order from oldest to newest.
+        let mut refs: Vec<_> = examples.iter().collect();
+        with_rng(|rng| refs.shuffle(rng));
+        self.train_refs(&refs, aux_examples, TrainParams::default())
+    }
+
+    /// Like [`Network::train_multi`], but draw as many examples as there are,
+    /// each with probability proportional to its weight. With equal weights
+    /// every example is used exactly once instead.
+    pub fn train_weighted(
+        &mut self,
+        examples: &[&Example<N>],
+        weights: &[f32],
+        aux_examples: &[AuxExamples],
+        params: TrainParams,
+    ) -> Vec<Loss>
+    where
+        Turn<N>: Lut,
+        [[Option<Tile>; N]; N]: Default,
+    {
+        let refs: Vec<_> = with_rng(|rng| match WeightedIndex::new(weights) {
+            Ok(dist) if weights.windows(2).any(|pair| pair[0] != pair[1]) => {
+                (0..examples.len()).map(|_| examples[dist.sample(rng)]).collect()
+            }
+            _ => {
+                let mut refs = examples.to_vec();
+                refs.shuffle(rng);
+                refs
+            }
+        });
+        self.train_refs(&refs, aux_examples, params)
+    }
+
+    fn train_refs(
+        &mut self,
+        refs: &[&Example<N>],
+        aux_examples: &[AuxExamples],
+        params: TrainParams,
+    ) -> Vec<Loss>
+    where
+        Turn<N>: Lut,
+        [[Option<Tile>; N]; N]: Default,
+    {
+        info!(examples = refs.len(), "starting training");
 
         let mut opt = nn::Adam {
             wd: WEIGHT_DECAY,
             ..Default::default()
         }
-        .build(&self.vs, LEARNING_RATE)
+        .build(&self.vs, params.learning_rate)
         .unwrap();
 
-        // shuffle only the references to the examples so that the real storage
-        // of examples preserves order from oldest to newest.
-        let mut refs: Vec<_> = examples.iter().collect();
-        refs.shuffle(&mut thread_rng());
+        let mut losses = Vec::new();
         for chunk in refs.chunks(MAX_TRAIN_SIZE) {
-            self.train_inner(&mut opt, chunk)
+            self.train_inner(&mut opt, chunk, aux_examples, params.batch_size, &mut losses)
         }
+        losses
     }
 
-    fn train_inner(&mut self, opt: &mut Optimizer, examples: &[&Example<N>])
-    where
+    fn train_inner(
+        &mut self,
+        opt: &mut Optimizer,
+        examples: &[&Example<N>],
+        aux_examples: &[AuxExamples],
+        batch_size: i64,
+        losses: &mut Vec<Loss>,
+    ) where
         Turn<N>: Lut,
         [[Option<Tile>; N]; N]: Default,
     {
         // batch examples
         let mut batch_iter = {
-            println!("creating symmetries");
+            debug!("creating symmetries");
             let symmetries = examples.iter().flat_map(|ex| ex.to_tensors());
             let mut inputs = Vec::new();
             let mut policies = Vec::new();
+            let mut replies = Vec::new();
             let mut results = Vec::new();
-            for (game, pi, v) in symmetries {
+            for (game, pi, reply, v) in symmetries {
                 inputs.push(game);
                 policies.push(pi);
+                replies.push(reply);
                 results.push(v);
             }
             let pi = Tensor::stack(&policies, 0);
             let v = Tensor::of_slice(&results).unsqueeze_(1);
-            let targets = Tensor::cat(&[pi, v], 1);
-            Iter2::new(&Tensor::stack(&inputs, 0), &targets, BATCH_SIZE)
+            let targets = if self.fully_connected_reply.is_some() {
+                Tensor::cat(&[pi, Tensor::stack(&replies, 0), v], 1)
+            } else {
+                Tensor::cat(&[pi, v], 1)
+            };
+            Iter2::new(&Tensor::stack(&inputs, 0), &targets, batch_size)
         };
         let batch_iter = batch_iter.shuffle();
 
@@ -69,18 +163,61 @@ impl<const N: usize> Network<N> {
             target = target.to_device_(*DEVICE, Kind::Float, true, false);
 
             let batch_size = input.size()[0];
-            let (policy, eval) = self.forward_training(input);
+            let (policy, reply, eval) = self.forward_training_with_reply(input);
 
             // get target
             let mut vec = target.split(moves_dims(N) as i64, 1);
             let z = vec.pop().unwrap();
+            let reply_target = reply.is_some().then(|| vec.pop().unwrap());
             let p = vec.pop().unwrap();
 
             // calculate loss
             let loss_p = -(p * policy).sum(Kind::Float) / batch_size;
             let loss_z = (z - eval).square_().sum(Kind::Float) / batch_size;
-            println!("p={loss_p:?}\t z={loss_z:?}");
-            let total_loss = loss_z + loss_p;
+            let loss = Loss {
+                policy: loss_p.double_value(&[]) as f32,
+                value: loss_z.double_value(&[]) as f32,
+            };
+            debug!(
+                policy_loss = loss.policy,
+                value_loss = loss.value,
+                "training batch"
+            );
+            losses.push(loss);
+            let mut total_loss = loss_z + loss_p;
+
+            // examples without a known reply have an all zero target
+            if let (Some(reply), Some(reply_target)) = (reply, reply_target) {
+                let loss_r = -(reply_target * reply).sum(Kind::Float) / batch_size;
+                debug!(reply_loss = loss_r.double_value(&[]), "reply prediction");
+                total_loss = total_loss + loss_r * REPLY_WEIGHT;
+            }
+
+            // other board sizes get a batch of the same size
+            for head in &self.aux_heads {
+                if let Some(aux) = aux_examples
+                    .iter()
+                    .find(|aux| aux.size == head.size && !aux.is_empty())
+                {
+                    let (input, target) = aux.sample(batch_size);
+                    let input = input.to_device_(*DEVICE, Kind::Float, true, false);
+                    let target = target.to_device_(*DEVICE, Kind::Float, true, false);
+                    let (policy, eval) = self.forward_training_aux(head, input);
+
+                    let mut vec = target.split(moves_dims(head.size) as i64, 1);
+                    let z = vec.pop().unwrap();
+                    let p = vec.pop().unwrap();
+                    let loss_p = -(p * policy).sum(Kind::Float) / batch_size;
+                    let loss_z = (z - eval).square_().sum(Kind::Float) / batch_size;
+                    debug!(
+                        size = head.size,
+                        policy_loss = loss_p.double_value(&[]),
+                        value_loss = loss_z.double_value(&[]),
+                        "auxiliary batch"
+                    );
+                    total_loss = total_loss + (loss_z + loss_p) * head.weight;
+                }
+            }
 
             opt.zero_grad();
             opt.backward_step(&total_loss);
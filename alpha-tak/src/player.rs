@@ -1,10 +1,29 @@
+use std::{
+    error::Error,
+    path::Path,
+    time::{Duration, Instant},
+};
+
 use tak::*;
 
 use crate::{
     agent::Agent,
-    analysis::Analysis,
+    analysis::{Analysis, ThinkingInfo},
+    config::TRANSPOSITIONS,
     example::{Example, IncompleteExample},
-    search::{node::Node, turn_map::Lut},
+    metrics::METRICS,
+    search::{
+        event_log::EventLog,
+        gumbel::GumbelParams,
+        node::Node,
+        opening_tree::OpeningTree,
+        params::SearchParams,
+        play::ReuseStats,
+        profile::{self, Phase},
+        step::Descent,
+        transposition::Transpositions,
+        turn_map::Lut,
+    },
 };
 
 // TODO Add ability to disable analysis
@@ -13,53 +32,300 @@ pub struct Player<'a, const N: usize, A: Agent<N>> {
     agent: &'a A,
     examples: Vec<IncompleteExample<N>>,
     analysis: Analysis<N>,
+    thinking_time: Duration,
+    rollouts: usize,
+    event_log: Option<EventLog>,
+    transpositions: Option<Transpositions>,
+    /// Reply of the opponent which is searched while waiting for it.
+    pondering: Option<Turn<N>>,
+    /// Visits kept from the tree of the last move played.
+    reused_visits: u32,
+    params: SearchParams,
+    /// Whether moves are chosen by Gumbel search instead of PUCT.
+    gumbel: Option<GumbelParams>,
 }
 
 impl<'a, const N: usize, A: Agent<N>> Player<'a, N, A>
 where
     Turn<N>: Lut,
 {
-    pub fn new(agent: &'a A, opening: Vec<Turn<N>>, komi: i32) -> Self {
+    pub fn new(agent: &'a A, opening: Vec<Turn<N>>, komi: Komi) -> Self {
         Player {
             node: Node::default(),
             agent,
             examples: Vec::new(),
             analysis: Analysis::from_opening(opening, komi),
+            thinking_time: Duration::ZERO,
+            rollouts: 0,
+            event_log: None,
+            transpositions: TRANSPOSITIONS.then(Transpositions::default),
+            pondering: None,
+            reused_visits: 0,
+            params: SearchParams::default(),
+            gumbel: None,
+        }
+    }
+
+    /// Player for a game which started from an arbitrary position,
+    /// for example one given by a TPS.
+    pub fn from_position(agent: &'a A, game: &Game<N>) -> Self {
+        Player {
+            analysis: Analysis::from_position(game.clone()),
+            ..Player::new(agent, Vec::new(), game.komi)
         }
     }
 
+    /// Record search events, see [`EventLog`].
+    #[must_use]
+    pub fn with_event_log(mut self, event_log: EventLog) -> Self {
+        self.event_log = Some(event_log);
+        self
+    }
+
+    /// Search with other parameters than the ones in the config.
+    #[must_use]
+    pub fn with_params(mut self, params: SearchParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    pub fn params(&self) -> &SearchParams {
+        &self.params
+    }
+
+    /// Choose moves with [`Player::gumbel_move`], whose examples then have
+    /// the completed policy as target, see [`crate::search::gumbel`].
+    #[must_use]
+    pub fn with_gumbel(mut self, gumbel: GumbelParams) -> Self {
+        self.gumbel = Some(gumbel);
+        self
+    }
+
+    /// Save the search tree of the current position, see [`Node::save_tree`].
+    pub fn save_tree<P: AsRef<Path>>(&self, game: &Game<N>, path: P) -> Result<(), Box<dyn Error>>
+    where
+        [[Option<Tile>; N]; N]: Default,
+    {
+        self.node.save_tree(game, path)
+    }
+
+    /// Continue the search from a tree saved for the current position.
+    pub fn load_tree<P: AsRef<Path>>(&mut self, game: &Game<N>, path: P) -> Result<(), Box<dyn Error>>
+    where
+        [[Option<Tile>; N]; N]: Default,
+    {
+        self.node = Node::load_tree(game, path)?;
+        Ok(())
+    }
+
     pub fn debug(&self, limit: Option<usize>) -> String {
         self.node.debug(limit)
     }
 
     /// Do some amount of rollouts.
     pub fn rollout(&mut self, game: &Game<N>, amount: usize) {
+        let start = Instant::now();
         for _ in 0..amount {
-            self.node.rollout(game.clone(), self.agent);
+            match &mut self.event_log {
+                Some(log) => {
+                    // the same rollout, split up to see the path
+                    let (path, value, terminal) = match self.node.descend(game.clone(), &self.params) {
+                        Descent::Leaf(leaf) => {
+                            let timer = profile::start();
+                            let (policy, eval) = self.agent.policy_and_eval(&leaf.game);
+                            timer.stop(Phase::Network);
+                            self.node.ascend(&leaf, &policy, eval, &self.params);
+                            (leaf.path, eval, false)
+                        }
+                        Descent::Terminal { path, value } => (path, value, true),
+                    };
+                    log.rollout(game.ply, &path, value, terminal);
+                }
+                None => match &mut self.transpositions {
+                    Some(table) => {
+                        self.node
+                            .rollout_transposed(game.clone(), self.agent, table, &self.params);
+                    }
+                    None => {
+                        self.node.rollout(game.clone(), self.agent, &self.params);
+                    }
+                },
+            }
         }
+        self.thinking_time += start.elapsed();
+        self.rollouts += amount;
+        METRICS.rollouts.inc_by(amount as u64);
+    }
+
+    /// Like [`Player::rollout`], but with `threads` threads sharing the tree,
+    /// see [`Node::parallel_rollouts`]. The event log and transpositions are
+    /// not used.
+    pub fn rollout_parallel(&mut self, game: &Game<N>, amount: usize, threads: usize)
+    where
+        A: Sync,
+    {
+        let start = Instant::now();
+        self.node
+            .parallel_rollouts(game, self.agent, amount, threads, &self.params);
+        self.thinking_time += start.elapsed();
+        self.rollouts += amount;
+        METRICS.rollouts.inc_by(amount as u64);
+    }
+
+    /// Spend the rollouts on Sequential Halving at the root and return the
+    /// chosen move, which is not played yet. The player has to be created
+    /// [`Player::with_gumbel`].
+    pub fn gumbel_move(&mut self, game: &Game<N>, amount: usize) -> Turn<N> {
+        let gumbel = self.gumbel.expect("the player should use Gumbel search");
+        let start = Instant::now();
+        let turn = self
+            .node
+            .sequential_halving(game, self.agent, amount, &gumbel, &self.params);
+        self.thinking_time += start.elapsed();
+        self.rollouts += amount;
+        METRICS.rollouts.inc_by(amount as u64);
+        turn
+    }
+
+    /// Do rollouts until `budget` has passed, returning how many were done.
+    pub fn think_for(&mut self, game: &Game<N>, budget: Duration) -> usize {
+        self.think_until(game, Instant::now() + budget)
+    }
+
+    /// Do rollouts until the deadline, returning how many were done. The
+    /// clock is checked after every few rollouts, so the search may run over
+    /// by the time of a few network evaluations.
+    pub fn think_until(&mut self, game: &Game<N>, deadline: Instant) -> usize {
+        const CHUNK: usize = 16;
+        let mut rollouts = 0;
+        while Instant::now() < deadline {
+            self.rollout(game, CHUNK);
+            rollouts += CHUNK;
+        }
+        rollouts
+    }
+
+    /// Search the reply the opponent is expected to play on their time, so
+    /// that the search is ahead if they play it. The prediction is the most
+    /// visited reply when pondering starts and stays the same until the next
+    /// move is played. Returns the prediction, or `None` if the game is over.
+    pub fn ponder(&mut self, game: &Game<N>, amount: usize) -> Option<Turn<N>> {
+        if self.node.children.is_none() {
+            self.rollout(game, 1);
+        }
+        if self
+            .node
+            .children
+            .as_ref()
+            .map_or(true, |children| children.is_empty())
+        {
+            return None;
+        }
+        let reply = match &self.pondering {
+            Some(reply) => reply.clone(),
+            None => self.node.pick_move(0.),
+        };
+        let start = Instant::now();
+        for _ in 0..amount {
+            let value = self
+                .node
+                .rollout_forced(game.clone(), &reply, self.agent, &self.params);
+            if let Some(log) = &mut self.event_log {
+                log.forced(game.ply, &reply, value);
+            }
+        }
+        self.thinking_time += start.elapsed();
+        self.rollouts += amount;
+        METRICS.rollouts.inc_by(amount as u64);
+        self.pondering = Some(reply.clone());
+        Some(reply)
+    }
+
+    /// Whether the opponent played the pondered reply. On a hit the pondered
+    /// search is kept by [`Player::play_move`], on a miss it is dropped with
+    /// the other replies and only the normal search of the played reply
+    /// remains.
+    pub fn ponder_hit(&self, turn: &Turn<N>) -> bool {
+        self.pondering.as_ref() == Some(turn)
+    }
+
+    /// Search tree of the current position.
+    pub fn tree(&self) -> &Node<N> {
+        &self.node
+    }
+
+    /// Expected reward of the player to move according to the search so far.
+    pub fn evaluation(&self) -> f32 {
+        -self.node.expected_reward
+    }
+
+    /// Give up the game, which is recorded in the analysis.
+    pub fn resign(&mut self, game: &Game<N>) {
+        self.analysis.resign(game.to_move);
     }
 
     /// Pick a move to play and also play it.
-    pub fn pick_move(&mut self, game: &Game<N>, exploitation: bool) -> Turn<N> {
-        let turn = self.node.pick_move(exploitation);
+    /// See [`Node::pick_move`] for how the temperature is used.
+    pub fn pick_move(&mut self, game: &Game<N>, temperature: f32) -> Turn<N> {
+        let turn = self.node.pick_move(temperature);
         self.play_move(game, &turn);
         turn
     }
 
-    /// Update the search tree, analysis, and create an example.
-    pub fn play_move(&mut self, game: &Game<N>, turn: &Turn<N>) {
-        self.node.rollout(game.clone(), self.agent); // at least one rollout
+    /// Update the search tree, analysis, and create an example. Returns how
+    /// much of the tree is kept for the next move.
+    pub fn play_move(&mut self, game: &Game<N>, turn: &Turn<N>) -> ReuseStats {
+        self.rollout(game, 1); // at least one rollout
         self.save_example(game.clone());
-        self.analysis.update(&self.node, turn.clone());
+        let thinking = ThinkingInfo {
+            time: std::mem::take(&mut self.thinking_time),
+            rollouts: std::mem::take(&mut self.rollouts),
+            depth: self.node.depth(),
+            reused_visits: std::mem::take(&mut self.reused_visits),
+            // rerooting after the previous move counts towards this search
+            profile: profile::take(),
+        };
+        self.analysis.update(&self.node, turn.clone(), thinking);
+        if let Some(log) = &mut self.event_log {
+            let child = &self.node.children.as_ref().unwrap()[turn];
+            log.decision(game.ply, turn, child.visited_count, child.expected_reward);
+        }
 
         let node = std::mem::take(&mut self.node);
-        self.node = node.play(turn);
+        let (node, stats) = node.play_with_stats(turn);
+        self.node = node;
+        self.reused_visits = stats.reused_visits;
+        // the kept subtree is already expanded, most other positions are gone
+        if let Some(table) = &mut self.transpositions {
+            table.clear();
+        }
+        METRICS.reused_visits.inc_by(stats.reused_visits as u64);
+        METRICS.discarded_visits.inc_by(stats.discarded_visits as u64);
+        self.pondering = None;
+        stats
+    }
+
+    /// Take back the last move played, continuing the search from `node`,
+    /// the tree of the position before it. Its example and analysis are
+    /// dropped.
+    pub fn take_back(&mut self, node: Node<N>) {
+        self.examples.pop();
+        self.analysis.take_back();
+        self.node = node;
+        self.reused_visits = 0;
+        self.pondering = None;
+        if let Some(table) = &mut self.transpositions {
+            table.clear();
+        }
     }
 
     fn save_example(&mut self, game: Game<N>) {
         self.examples.push(IncompleteExample {
             game,
-            policy: self.node.improved_policy(),
+            policy: match &self.gumbel {
+                Some(gumbel) => self.node.completed_policy(gumbel),
+                None => self.node.improved_policy(),
+            },
         })
     }
 
@@ -78,7 +344,7 @@ where
             GameResult::Draw { .. } => 0.,
             GameResult::Ongoing { .. } => unreachable!("cannot complete examples with ongoing game"),
         };
-        std::mem::take(&mut self.examples)
+        let mut examples: Vec<_> = std::mem::take(&mut self.examples)
             .into_iter()
             .map(|ex| {
                 let perspective = if ex.game.to_move == Colour::White {
@@ -88,7 +354,15 @@ where
                 };
                 ex.complete(perspective)
             })
-            .collect()
+            .collect();
+
+        // the search of the following position is the reply target
+        for i in 1..examples.len() {
+            if examples[i].game.ply == examples[i - 1].game.ply + 1 {
+                examples[i - 1].reply = examples[i].policy.clone();
+            }
+        }
+        examples
     }
 
     /// Get the analysis of the game
@@ -100,5 +374,49 @@ where
     pub fn apply_dirichlet(&mut self, game: &Game<N>, alpha: f32, ratio: f32) {
         self.rollout(game, 1);
         self.node.apply_dirichlet(alpha, ratio);
+        if let Some(log) = &mut self.event_log {
+            log.dirichlet(game.ply, alpha, ratio);
+        }
+    }
+
+    /// Search the position using statistics shared with other games.
+    /// Shared visits count towards the rollouts, but at least `min_rollouts`
+    /// are done.
+    pub fn rollout_shared(
+        &mut self,
+        game: &Game<N>,
+        tree: &OpeningTree<N>,
+        rollouts: usize,
+        min_rollouts: usize,
+    ) {
+        self.rollout(game, 1);
+        let seeded = tree.get(game);
+        // the shared statistics only go to expanded children
+        let start = Instant::now();
+        let mut expanded = 0;
+        for (turn, _) in seeded.iter().filter(|(_, stats)| stats.visits > 0) {
+            let value = self
+                .node
+                .rollout_forced(game.clone(), turn, self.agent, &self.params);
+            if let Some(log) = &mut self.event_log {
+                log.forced(game.ply, turn, value);
+            }
+            expanded += 1;
+        }
+        self.thinking_time += start.elapsed();
+        self.rollouts += expanded;
+        METRICS.rollouts.inc_by(expanded as u64);
+        let shared_visits = self.node.add_child_stats(&seeded);
+        if let Some(log) = &mut self.event_log {
+            log.shared(game.ply, shared_visits);
+        }
+
+        self.rollout(
+            game,
+            rollouts
+                .saturating_sub(shared_visits as usize + expanded)
+                .max(min_rollouts),
+        );
+        tree.update(game, &seeded, self.node.child_stats());
     }
 }
@@ -0,0 +1,53 @@
+use tak::*;
+
+use crate::{example::Example, model::network::Network, search::turn_map::Lut};
+
+/// Number of positions evaluated at once.
+const EVAL_BATCH: usize = 512;
+/// Visits the policy is spread over for examples without any visits.
+const DEFAULT_VISITS: u32 = 1000;
+
+/// How much of the new targets comes from the network, between 0 (keep the
+/// stored targets) and 1 (replace them).
+#[derive(Clone, Copy, Debug)]
+pub struct Blend {
+    pub value: f32,
+    pub policy: f32,
+}
+
+/// Re-evaluate the example positions with the network and blend its eval
+/// and policy into the value and policy targets. The policy is kept as
+/// visit counts with the same total as before.
+pub fn relabel<const N: usize>(network: &Network<N>, examples: &mut [Example<N>], blend: Blend)
+where
+    Turn<N>: Lut,
+    [[Option<Tile>; N]; N]: Default,
+{
+    for chunk in examples.chunks_mut(EVAL_BATCH) {
+        let games: Vec<Game<N>> = chunk.iter().map(|ex| ex.game.clone()).collect();
+        let (policies, evals) = network.policy_eval_batch(&games);
+        for ((example, policy), eval) in chunk.iter_mut().zip(policies).zip(evals) {
+            example.result = (1. - blend.value) * example.result + blend.value * eval;
+
+            let total = example.policy.values().sum::<u32>();
+            let visits = if total == 0 { DEFAULT_VISITS } else { total } as f32;
+            // the network also puts a little weight on illegal moves
+            let turns = example.game.possible_turns();
+            let legal = turns
+                .iter()
+                .map(|turn| policy[turn.turn_map()])
+                .sum::<f32>()
+                .max(f32::EPSILON);
+            example.policy = turns
+                .into_iter()
+                .map(|turn| {
+                    let old = example.policy.get(&turn).copied().unwrap_or_default() as f32 / visits;
+                    let new = policy[turn.turn_map()] / legal;
+                    let mixed = (1. - blend.policy) * old + blend.policy * new;
+                    (turn, (mixed * visits).round() as u32)
+                })
+                .filter(|(_, visits)| *visits > 0)
+                .collect();
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use rand::distributions::{Distribution, WeightedIndex};
+use tak::*;
+
+use crate::{
+    example::{examples_to_string, parse_examples, Example},
+    rng::with_rng,
+};
+
+struct Entry<const N: usize> {
+    example: Example<N>,
+    generation: u32,
+    hash: u64,
+}
+
+/// The latest self-play examples across generations, which training samples
+/// from. A position that is seen again replaces the stored example, so
+/// common openings do not crowd out the rest of the buffer. The replacement
+/// counts as the newest example, and its old place is left empty until it
+/// is evicted.
+pub struct ReplayBuffer<const N: usize> {
+    capacity: usize,
+    /// Entries from oldest to newest, `None` where one was replaced.
+    entries: VecDeque<Option<Entry<N>>>,
+    /// Number of entries which are not `None`.
+    live: usize,
+    /// Index of each position among all entries ever pushed.
+    positions: HashMap<u64, usize>,
+    /// Entries evicted from the front so far.
+    evicted: usize,
+    generation: u32,
+}
+
+impl<const N: usize> ReplayBuffer<N> {
+    pub fn new(capacity: usize) -> Self {
+        ReplayBuffer {
+            capacity,
+            entries: VecDeque::new(),
+            live: 0,
+            positions: HashMap::new(),
+            evicted: 0,
+            generation: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.live
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live == 0
+    }
+
+    /// Generation that new examples are added to.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Start a new generation, making the stored examples one older.
+    pub fn next_generation(&mut self) {
+        self.generation += 1;
+    }
+
+    /// Add examples to the current generation, evicting the oldest ones
+    /// beyond the capacity.
+    pub fn extend(&mut self, examples: impl IntoIterator<Item = Example<N>>) {
+        for example in examples {
+            self.push(example, self.generation);
+        }
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while let Some(front) = self.entries.front() {
+            if front.is_some() && self.live <= self.capacity {
+                break;
+            }
+            if let Some(entry) = self.entries.pop_front().unwrap() {
+                self.positions.remove(&entry.hash);
+                self.live -= 1;
+            }
+            self.evicted += 1;
+        }
+        // replaced entries in the middle wait for the ones before them, so
+        // drop them once they outnumber the live ones
+        if self.entries.len() > 2 * self.live.max(1) {
+            self.entries.retain(Option::is_some);
+            self.evicted = 0;
+            for (index, entry) in self.entries.iter().flatten().enumerate() {
+                self.positions.insert(entry.hash, index);
+            }
+        }
+    }
+
+    fn push(&mut self, example: Example<N>, generation: u32) {
+        let hash = example.game.zobrist();
+        let entry = Entry {
+            example,
+            generation,
+            hash,
+        };
+        if let Some(index) = self.positions.insert(hash, self.evicted + self.entries.len()) {
+            self.entries[index - self.evicted] = None;
+            self.live -= 1;
+        }
+        self.entries.push_back(Some(entry));
+        self.live += 1;
+    }
+
+    fn live_entries(&self) -> impl Iterator<Item = &Entry<N>> {
+        self.entries.iter().flatten()
+    }
+
+    pub fn examples(&self) -> impl Iterator<Item = &Example<N>> {
+        self.live_entries().map(|entry| &entry.example)
+    }
+
+    /// Sampling weight of each example, `decay` to the power of its age in
+    /// generations.
+    pub fn weights(&self, decay: f32) -> Vec<f32> {
+        self.live_entries()
+            .map(|entry| decay.powi((self.generation - entry.generation) as i32))
+            .collect()
+    }
+
+    /// Draw examples with replacement, weighted like [`Self::weights`].
+    pub fn sample(&self, count: usize, decay: f32) -> Vec<&Example<N>> {
+        let dist = match WeightedIndex::new(self.weights(decay)) {
+            Ok(dist) => dist,
+            Err(_) => return Vec::new(),
+        };
+        let examples: Vec<_> = self.examples().collect();
+        with_rng(|rng| (0..count).map(|_| examples[dist.sample(rng)]).collect())
+    }
+
+    /// Write the buffer to a file, each example prefixed with its generation.
+    /// The file is written next to the path first and then moved there, so a
+    /// crash while saving leaves the last saved buffer intact.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut temp_path = path.as_ref().as_os_str().to_owned();
+        temp_path.push(".tmp");
+        let mut file = BufWriter::new(File::create(&temp_path)?);
+        writeln!(file, "{}", self.generation)?;
+        for entry in self.live_entries() {
+            let example = examples_to_string(std::slice::from_ref(&entry.example));
+            write!(file, "{};{example}", entry.generation)?;
+        }
+        file.into_inner()?.sync_all()?;
+        fs::rename(temp_path, path)
+    }
+}
+
+impl<const N: usize> ReplayBuffer<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// Read a buffer written by [`Self::save`]. If it holds more examples
+    /// than the capacity, the oldest ones are dropped. Examples from a later
+    /// generation than the buffer are an error.
+    pub fn load<P: AsRef<Path>>(path: P, capacity: usize) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        let mut lines = contents.lines();
+        let mut buffer = Self::new(capacity);
+        buffer.generation = lines.next().ok_or("missing generation")?.parse()?;
+        for line in lines {
+            let (generation, example) = line.split_once(';').ok_or("missing generation of example")?;
+            let generation = generation.parse()?;
+            if generation > buffer.generation {
+                return Err(format!(
+                    "example of generation {generation} in a buffer of generation {}",
+                    buffer.generation
+                )
+                .into());
+            }
+            for example in parse_examples(example)? {
+                buffer.push(example, generation);
+            }
+        }
+        buffer.evict();
+        Ok(buffer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use tak::*;
+
+    use super::ReplayBuffer;
+    use crate::example::Example;
+
+    fn example(ptn: &str, result: f32) -> Example<5> {
+        let game = Game::<5>::from_ptn(ptn).unwrap();
+        let policy = game.possible_turns().into_iter().map(|turn| (turn, 1)).collect();
+        Example {
+            game,
+            policy,
+            result,
+            reply: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn duplicates_replace_and_old_examples_are_evicted() {
+        let mut buffer = ReplayBuffer::new(2);
+        buffer.extend([example("1. a1 e1", 1.0), example("1. a1 e2", 0.0)]);
+        buffer.next_generation();
+        buffer.extend([example("1. a1 e1", -1.0)]);
+        assert_eq!(buffer.len(), 2);
+        // the replacement is the newest example
+        assert_eq!(buffer.weights(0.5), vec![0.5, 1.0]);
+        assert_eq!(buffer.examples().last().unwrap().result, -1.0);
+
+        // so the other one is evicted first
+        buffer.extend([example("1. a1 e3", 0.0)]);
+        assert_eq!(buffer.len(), 2);
+        let results: Vec<_> = buffer.examples().map(|example| example.result).collect();
+        assert_eq!(results, vec![-1.0, 0.0]);
+
+        // replacing the same position again and again keeps the others
+        for _ in 0..10 {
+            buffer.extend([example("1. a1 e1", 1.0)]);
+        }
+        assert_eq!(buffer.len(), 2);
+        let results: Vec<_> = buffer.examples().map(|example| example.result).collect();
+        assert_eq!(results, vec![0.0, 1.0]);
+        assert_eq!(buffer.sample(5, 0.5).len(), 5);
+    }
+
+    #[test]
+    fn buffer_round_trip() {
+        let mut buffer = ReplayBuffer::new(10);
+        buffer.extend([example("1. a1 e1", 1.0)]);
+        buffer.next_generation();
+        buffer.extend([example("1. a1 e1 2. c3", -1.0), example("1. a1 e2", 0.0)]);
+
+        let path = std::env::temp_dir().join(format!("replay-round-trip-{}.data", std::process::id()));
+        buffer.save(&path).unwrap();
+        let loaded = ReplayBuffer::<5>::load(&path, 10).unwrap();
+        assert_eq!(loaded.generation(), 1);
+        assert_eq!(loaded.weights(0.5), buffer.weights(0.5));
+        let tps =
+            |buffer: &ReplayBuffer<5>| -> Vec<_> { buffer.examples().map(|e| e.game.to_tps()).collect() };
+        assert_eq!(tps(&loaded), tps(&buffer));
+        assert_eq!(loaded.sample(5, 0.5).len(), 5);
+
+        // a hand-edited file with examples from the future
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, contents.replacen("1\n", "0\n", 1)).unwrap();
+        assert!(ReplayBuffer::<5>::load(&path, 10).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
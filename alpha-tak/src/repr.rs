@@ -1,5 +1,10 @@
+use std::ops::Range;
+
 use tak::*;
-use tch::{kind::FLOAT_CPU, Tensor};
+#[cfg(feature = "torch")]
+use tch::Tensor;
+
+use crate::search::turn_map::Lut;
 
 const STACK_DEPTH_BEYOND_CARRY: usize = 6;
 const COLOUR_CHANNEL: usize = 1;
@@ -14,24 +19,126 @@ pub const fn input_channels(n: usize) -> usize {
     board_channels(n) + COLOUR_CHANNEL + FCD_CHANNEL + 2 * stones as usize + 2 * capstones as usize
 }
 
+/// Size of the policy vector for an `n`x`n` board: every placement followed
+/// by every spread, in the order used by [`Lut`](crate::search::turn_map::Lut).
 pub const fn moves_dims(n: usize) -> usize {
-    match n {
-        3 => 2 * 3 * 3 + 108,   // 126
-        4 => 2 * 4 * 4 + 448,   // 480
-        5 => 3 * 5 * 5 + 1500,  // 1575
-        6 => 3 * 6 * 6 + 4464,  // 4572
-        7 => 3 * 7 * 7 + 12348, // 12495
-        8 => 3 * 8 * 8 + 32512, // 32704
-        _ => unimplemented!(),
+    place_moves(n) + spread_moves(n)
+}
+
+/// Number of distinct placements, one per square and piece shape.
+pub const fn place_moves(n: usize) -> usize {
+    let (_, capstones) = default_starting_stones(n);
+    let shapes = if capstones > 0 { 3 } else { 2 };
+    shapes * n * n
+}
+
+/// Number of distinct spreads, counting every square, direction,
+/// carry, and drop pattern.
+pub const fn spread_moves(n: usize) -> usize {
+    let mut total = 0;
+    let mut x = 0;
+    while x < n {
+        let mut y = 0;
+        while y < n {
+            total += spreads_towards(n, x)
+                + spreads_towards(n, n - 1 - x)
+                + spreads_towards(n, y)
+                + spreads_towards(n, n - 1 - y);
+            y += 1;
+        }
+        x += 1;
     }
+    total
 }
 
-/// Creates a tensor which represents the board
-/// from the perspective of the current player.
-fn board_repr<const N: usize>(board: &Board<N>, to_move: Colour) -> Tensor {
-    // track the positions of all ones in the input tensor
-    let mut positions = Vec::new();
+/// Spreads in one direction with `distance` squares before the edge.
+const fn spreads_towards(n: usize, distance: usize) -> usize {
+    let mut total = 0;
+    let mut carry = 1;
+    while carry <= n {
+        // ways to split the carry into at most `distance` non-empty drops
+        let mut drops = 1;
+        while drops <= carry && drops <= distance {
+            total += binomial(carry - 1, drops - 1);
+            drops += 1;
+        }
+        carry += 1;
+    }
+    total
+}
+
+const fn binomial(n: usize, k: usize) -> usize {
+    let mut result = 1;
+    let mut i = 0;
+    while i < k {
+        result = result * (n - i) / (i + 1);
+        i += 1;
+    }
+    result
+}
 
+/// Indices of placements in the policy vector.
+pub const fn place_range(n: usize) -> Range<usize> {
+    0..place_moves(n)
+}
+
+/// Indices of spreads in the policy vector.
+pub const fn spread_range(n: usize) -> Range<usize> {
+    place_moves(n)..moves_dims(n)
+}
+
+/// Number of distinct drop patterns of a spread, for any carry up to `n`.
+/// A carry of k pieces can be dropped in 2^(k-1) ways.
+pub const fn drop_patterns(n: usize) -> usize {
+    (1 << n) - 1
+}
+
+/// Size of the output of the factorized policy head: placements as in the
+/// policy vector, then spreads split into a logit for each origin square and
+/// direction, and a logit for each drop pattern.
+pub const fn factorized_policy_dims(n: usize) -> usize {
+    place_moves(n) + 4 * n * n + drop_patterns(n)
+}
+
+/// For each spread of the policy vector in order, the index of its origin
+/// square and direction, and the index of its drop pattern, in the spread
+/// part of the factorized policy head.
+pub fn spread_factors<const N: usize>() -> Vec<(usize, usize)>
+where
+    Turn<N>: Lut,
+{
+    Turn::<N>::all_turns()
+        .into_iter()
+        .filter_map(|turn| match turn {
+            Turn::Move {
+                pos,
+                direction,
+                moves,
+            } => {
+                let direction = Direction::ALL.iter().position(|&d| d == direction).unwrap();
+                // the last move is always a drop, the others choose whether to step on
+                let steps = moves[..moves.len() - 1]
+                    .iter()
+                    .enumerate()
+                    .fold(0, |pattern, (i, &step)| pattern | ((step as usize) << i));
+                let pattern = (1 << (moves.len() - 1)) - 1 + steps;
+                Some((4 * (N * pos.y + pos.x) + direction, pattern))
+            }
+            Turn::Place { .. } => None,
+        })
+        .collect()
+}
+
+/// Upper bound on the number of legal turns in any position.
+/// A board covered in tall stacks allows every spread at once,
+/// and there are never more placements than that.
+pub const fn max_legal_moves(n: usize) -> usize {
+    spread_moves(n)
+}
+
+/// Writes the board from the perspective of the current player
+/// into the first `board_channels(N)` channels of `features`.
+fn write_board<const N: usize>(features: &mut [f32], board: &Board<N>, to_move: Colour) {
     // top layer of stack has 6 channels in total
     // 2 for flats (1 per player)
     // 2 for walls (1 per player)
@@ -46,104 +153,117 @@ fn board_repr<const N: usize>(board: &Board<N>, to_move: Colour) -> Tensor {
                     Shape::Wall => 2,
                     Shape::Capstone => 4,
                 } + if tile.top.colour == to_move { 0 } else { 1 };
-                positions.push((board_offset + N * N * channel) as i64);
-            }
-        }
-    }
+                features[board_offset + N * N * channel] = 1.;
 
-    // other layers of stacks
-    // alternating mine and opponent's
-    for n in 0..(N + STACK_DEPTH_BEYOND_CARRY - 1) {
-        for y in 0..N {
-            for x in 0..N {
-                let pos = Pos { x, y };
-                if let Some(tile) = &board[pos] {
-                    let board_offset = N * y + x;
-                    if let Some(&colour) = tile.stack.iter().rev().nth(n) {
-                        positions.push(
-                            (board_offset + N * N * (6 + 2 * n + if to_move == colour { 0 } else { 1 }))
-                                as i64,
-                        );
-                    }
+                // other layers of stacks
+                // alternating mine and opponent's
+                for (n, &colour) in tile
+                    .stack
+                    .iter()
+                    .rev()
+                    .take(N + STACK_DEPTH_BEYOND_CARRY - 1)
+                    .enumerate()
+                {
+                    let channel = 6 + 2 * n + if to_move == colour { 0 } else { 1 };
+                    features[board_offset + N * N * channel] = 1.;
                 }
             }
         }
     }
-
-    let index = Tensor::of_slice(&positions);
-    let ones = Tensor::ones(&[positions.len() as i64], FLOAT_CPU);
-    let mut zeros = Tensor::zeros(&[board_channels(N) as i64, N as i64, N as i64], FLOAT_CPU);
-    zeros.put_(&index, &ones, false)
 }
 
-fn create_reserves_tensor<const N: usize>(stones: u8, max: u8) -> Tensor {
-    let mut reserves = Tensor::zeros(&[max as i64, N as i64, N as i64], FLOAT_CPU);
+/// Writes one-hot encoded reserves, one channel per possible count.
+/// Custom reserves beyond the standard ones of the board size share the last
+/// channel.
+fn write_reserves<const N: usize>(features: &mut [f32], stones: u8, channels: usize) {
+    let stones = (stones as usize).min(channels);
     if stones > 0 {
-        reserves = reserves.index_put_(
-            &[Some(Tensor::of_slice(&[(stones - 1) as i64])), None, None],
-            &Tensor::ones(&[N as i64, N as i64], FLOAT_CPU),
-            false,
-        );
+        let offset = N * N * (stones - 1);
+        features[offset..offset + N * N].fill(1.);
     }
-    reserves
 }
 
-fn reserves_repr<const N: usize>(game: &Game<N>) -> (Tensor, Tensor, Tensor, Tensor) {
-    let (stones, capstones) = default_starting_stones(N);
-    let white_stones = create_reserves_tensor::<N>(game.white_stones, stones);
-    let black_stones = create_reserves_tensor::<N>(game.black_stones, stones);
-    let white_caps = create_reserves_tensor::<N>(game.white_caps, capstones);
-    let black_caps = create_reserves_tensor::<N>(game.black_caps, capstones);
-
-    if game.to_move == Colour::White {
-        (white_stones, black_stones, white_caps, black_caps)
-    } else {
-        (black_stones, white_stones, black_caps, white_caps)
-    }
+/// Flat `[board_channels(N), N, N]` encoding of the board
+/// from the perspective of the current player.
+pub fn board_features<const N: usize>(board: &Board<N>, to_move: Colour) -> Vec<f32> {
+    let mut features = vec![0.; board_channels(N) * N * N];
+    write_board(&mut features, board, to_move);
+    features
 }
 
-pub fn game_repr<const N: usize>(game: &Game<N>) -> Tensor {
-    let board = board_repr(&game.board, game.to_move);
+/// Flat `[input_channels(N), N, N]` encoding of the game,
+/// shared by the tensor representation and non-torch inference backends.
+pub fn game_features<const N: usize>(game: &Game<N>) -> Vec<f32> {
+    let (stones, capstones) = default_starting_stones(N);
+    let (stones, capstones) = (stones as usize, capstones as usize);
+    let layer = N * N;
+    let mut features = vec![0.; input_channels(N) * layer];
 
-    let layer_shape = [1, N as i64, N as i64];
+    write_board(&mut features, &game.board, game.to_move);
 
     // one-hot encoded reserves
-    let (my_stones, en_stones, my_caps, en_caps) = reserves_repr(game);
+    let (my_stones, en_stones, my_caps, en_caps) = if game.to_move == Colour::White {
+        (
+            game.white_stones,
+            game.black_stones,
+            game.white_caps,
+            game.black_caps,
+        )
+    } else {
+        (
+            game.black_stones,
+            game.white_stones,
+            game.black_caps,
+            game.white_caps,
+        )
+    };
+    let mut offset = board_channels(N) * layer;
+    write_reserves::<N>(&mut features[offset..], my_stones, stones);
+    offset += stones * layer;
+    write_reserves::<N>(&mut features[offset..], en_stones, stones);
+    offset += stones * layer;
+    write_reserves::<N>(&mut features[offset..], my_caps, capstones);
+    offset += capstones * layer;
+    write_reserves::<N>(&mut features[offset..], en_caps, capstones);
+    offset += capstones * layer;
 
     // layer for whose turn it is
-    let colour_layer = Tensor::full(
-        &layer_shape,
-        if game.to_move == Colour::White { 1. } else { 0. },
-        FLOAT_CPU,
-    );
+    if game.to_move == Colour::White {
+        features[offset..offset + layer].fill(1.);
+    }
+    offset += layer;
 
     // layer for fcd (+ komi)
-    let fcd = game.board.flat_diff() - game.komi;
-    let relative_fcd = fcd as f64 / (N * N) as f64;
-    let fcd_layer = Tensor::full(&layer_shape, relative_fcd, FLOAT_CPU);
-
-    Tensor::cat(
-        &[
-            board,
-            my_stones,
-            en_stones,
-            my_caps,
-            en_caps,
-            colour_layer,
-            fcd_layer,
-        ],
-        0,
-    )
+    let fcd = game.board.flat_diff() as f32 - game.komi.flats();
+    let relative_fcd = fcd / (N * N) as f32;
+    features[offset..offset + layer].fill(relative_fcd);
+
+    features
+}
+
+/// Creates a tensor which represents the board
+/// from the perspective of the current player.
+#[cfg(feature = "torch")]
+fn board_repr<const N: usize>(board: &Board<N>, to_move: Colour) -> Tensor {
+    Tensor::of_slice(&board_features(board, to_move)).view([board_channels(N) as i64, N as i64, N as i64])
 }
 
-#[cfg(test)]
+#[cfg(feature = "torch")]
+pub fn game_repr<const N: usize>(game: &Game<N>) -> Tensor {
+    Tensor::of_slice(&game_features(game)).view([input_channels(N) as i64, N as i64, N as i64])
+}
+
+#[cfg(all(test, feature = "torch"))]
 mod test {
     use tak::*;
     use tch::{kind::FLOAT_CPU, Tensor};
     use test::Bencher;
 
-    use super::{board_repr, game_repr};
-    use crate::repr::board_channels;
+    use super::{board_repr, game_features, game_repr};
+    use crate::{
+        repr::{board_channels, input_channels, moves_dims},
+        search::turn_map::Lut,
+    };
 
     #[test]
     fn empty_board() {
@@ -243,6 +363,31 @@ mod test {
         assert_eq!(a, b);
     }
 
+    fn encodes_size<const N: usize>()
+    where
+        Turn<N>: Lut,
+        [[Option<Tile>; N]; N]: Default,
+    {
+        assert_eq!(Turn::<N>::all_turns().len(), moves_dims(N));
+        let (stones, caps) = default_starting_stones(N);
+        for game in [
+            Game::<N>::default(),
+            Game::with_reserves(Komi::default(), stones + 5, caps + caps.min(1)).unwrap(),
+        ] {
+            assert_eq!(game_features(&game).len(), input_channels(N) * N * N);
+        }
+    }
+
+    #[test]
+    fn every_size_is_encoded() {
+        encodes_size::<3>();
+        encodes_size::<4>();
+        encodes_size::<5>();
+        encodes_size::<6>();
+        encodes_size::<7>();
+        encodes_size::<8>();
+    }
+
     #[bench]
     fn game_repr_bench(b: &mut Bencher) {
         let game = Game::<5>::from_ptn(
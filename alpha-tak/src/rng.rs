@@ -0,0 +1,88 @@
+//! Seedable randomness, so that a training run can be reproduced exactly.
+//!
+//! Every thread draws from its own generator. Before each task, like a
+//! self-play game, the thread is seeded from the seed of the run, the
+//! iteration, the kind of task, and its index. Tasks then draw the same
+//! numbers no matter which thread runs them or in which order.
+
+use std::{
+    cell::RefCell,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use rand::{
+    distributions::{Distribution, Standard},
+    rngs::StdRng,
+    Rng,
+    SeedableRng,
+};
+
+/// Kinds of tasks, which get separate random streams.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stream {
+    SelfPlay = 0,
+    Pit = 1,
+    League = 2,
+    Training = 3,
+}
+
+static RUN_SEED: AtomicU64 = AtomicU64::new(0);
+static ITERATION: AtomicU64 = AtomicU64::new(0);
+static STREAM: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Set the seed of the run and the iteration it is in.
+pub fn set_run(seed: u64, iteration: u64) {
+    RUN_SEED.store(seed, Ordering::SeqCst);
+    ITERATION.store(iteration, Ordering::SeqCst);
+}
+
+/// Select the kind of task which is started next.
+pub fn set_stream(stream: Stream) {
+    STREAM.store(stream as u64, Ordering::SeqCst);
+}
+
+/// Seed the generator of this thread for the task with `index` of the
+/// current stream, and return the seed.
+pub fn seed_task(index: u64) -> u64 {
+    let seed = derive_seed(&[
+        RUN_SEED.load(Ordering::SeqCst),
+        ITERATION.load(Ordering::SeqCst),
+        STREAM.load(Ordering::SeqCst),
+        index,
+    ]);
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+    seed
+}
+
+/// Seed the generator torch uses for initialisation and dropout.
+#[cfg(feature = "torch")]
+pub fn seed_torch(seed: u64) {
+    tch::manual_seed(seed as i64);
+}
+
+/// Run `f` with the generator of this thread.
+pub fn with_rng<T>(f: impl FnOnce(&mut StdRng) -> T) -> T {
+    RNG.with(|rng| f(&mut rng.borrow_mut()))
+}
+
+/// Like [`rand::random`], but from the generator of this thread.
+pub fn random<T>() -> T
+where
+    Standard: Distribution<T>,
+{
+    with_rng(|rng| rng.gen())
+}
+
+/// Mix numbers into a seed with SplitMix64.
+fn derive_seed(parts: &[u64]) -> u64 {
+    parts.iter().fold(0x9e37_79b9_7f4a_7c15, |state: u64, &part| {
+        let mut z = (state ^ part).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    })
+}
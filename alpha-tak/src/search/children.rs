@@ -0,0 +1,133 @@
+use std::{iter::Zip, ops::Index, slice, vec};
+
+use tak::*;
+
+use super::node::Node;
+
+/// The children of a node in two vectors, one of turns and one of nodes, so
+/// that selection only walks over the statistics it compares. Each node still
+/// owns its children, so a subtree is freed node by node. Looking a child up
+/// by its turn is a linear scan, which the search avoids by remembering the
+/// indices it went through, see [`Children::node_mut`].
+#[derive(Clone, Debug, Default)]
+pub struct Children<const N: usize> {
+    turns: Vec<Turn<N>>,
+    nodes: Vec<Node<N>>,
+}
+
+impl<const N: usize> Children<N> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Children {
+            turns: Vec::with_capacity(capacity),
+            nodes: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Add a child, the turn must not be among the children yet.
+    pub fn insert(&mut self, turn: Turn<N>, node: Node<N>) {
+        debug_assert!(!self.turns.contains(&turn), "duplicate child {turn:?}");
+        self.turns.push(turn);
+        self.nodes.push(node);
+    }
+
+    pub fn index_of(&self, turn: &Turn<N>) -> Option<usize> {
+        self.turns.iter().position(|t| t == turn)
+    }
+
+    pub fn get(&self, turn: &Turn<N>) -> Option<&Node<N>> {
+        self.index_of(turn).map(|index| &self.nodes[index])
+    }
+
+    pub fn get_mut(&mut self, turn: &Turn<N>) -> Option<&mut Node<N>> {
+        self.index_of(turn).map(|index| &mut self.nodes[index])
+    }
+
+    /// Take a child out, which moves the last child into its place.
+    pub fn remove(&mut self, turn: &Turn<N>) -> Option<Node<N>> {
+        let index = self.index_of(turn)?;
+        self.turns.swap_remove(index);
+        Some(self.nodes.swap_remove(index))
+    }
+
+    pub fn turn(&self, index: usize) -> &Turn<N> {
+        &self.turns[index]
+    }
+
+    pub fn node(&self, index: usize) -> &Node<N> {
+        &self.nodes[index]
+    }
+
+    pub fn node_mut(&mut self, index: usize) -> &mut Node<N> {
+        &mut self.nodes[index]
+    }
+
+    pub fn iter(&self) -> Zip<slice::Iter<Turn<N>>, slice::Iter<Node<N>>> {
+        self.turns.iter().zip(self.nodes.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> Zip<slice::Iter<Turn<N>>, slice::IterMut<Node<N>>> {
+        self.turns.iter().zip(self.nodes.iter_mut())
+    }
+
+    pub fn keys(&self) -> slice::Iter<Turn<N>> {
+        self.turns.iter()
+    }
+
+    pub fn values(&self) -> slice::Iter<Node<N>> {
+        self.nodes.iter()
+    }
+
+    pub fn values_mut(&mut self) -> slice::IterMut<Node<N>> {
+        self.nodes.iter_mut()
+    }
+}
+
+impl<const N: usize> Index<&Turn<N>> for Children<N> {
+    type Output = Node<N>;
+
+    fn index(&self, turn: &Turn<N>) -> &Node<N> {
+        self.get(turn).expect("turn should be among the children")
+    }
+}
+
+impl<'a, const N: usize> IntoIterator for &'a Children<N> {
+    type IntoIter = Zip<slice::Iter<'a, Turn<N>>, slice::Iter<'a, Node<N>>>;
+    type Item = (&'a Turn<N>, &'a Node<N>);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, const N: usize> IntoIterator for &'a mut Children<N> {
+    type IntoIter = Zip<slice::Iter<'a, Turn<N>>, slice::IterMut<'a, Node<N>>>;
+    type Item = (&'a Turn<N>, &'a mut Node<N>);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<const N: usize> IntoIterator for Children<N> {
+    type IntoIter = Zip<vec::IntoIter<Turn<N>>, vec::IntoIter<Node<N>>>;
+    type Item = (Turn<N>, Node<N>);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.turns.into_iter().zip(self.nodes)
+    }
+}
+
+impl<const N: usize> FromIterator<(Turn<N>, Node<N>)> for Children<N> {
+    fn from_iter<I: IntoIterator<Item = (Turn<N>, Node<N>)>>(iter: I) -> Self {
+        let (turns, nodes) = iter.into_iter().unzip();
+        Children { turns, nodes }
+    }
+}
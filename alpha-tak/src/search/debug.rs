@@ -51,11 +51,30 @@ impl<const N: usize> Node<N> {
         {
             return VecDeque::new();
         }
-        let turn = self.pick_move(true);
+        let turn = self.pick_move(0.);
         let children = self.children.as_ref().unwrap();
         let node = children.get(&turn).unwrap();
         let mut turns = node.continuation(min_visit_count, depth - 1);
         turns.push_front(turn);
         turns
     }
+
+    /// Count the visited nodes in this subtree.
+    pub fn node_count(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .flat_map(|children| children.values())
+            .filter(|child| child.visited_count > 0)
+            .map(Node::node_count)
+            .sum::<usize>()
+    }
+
+    /// Get the depth of the deepest expanded node in this subtree.
+    pub fn depth(&self) -> usize {
+        self.children
+            .as_ref()
+            .map(|children| 1 + children.values().map(Node::depth).max().unwrap_or_default())
+            .unwrap_or_default()
+    }
 }
@@ -0,0 +1,83 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use serde_json::{json, Value};
+use tak::*;
+
+/// Opt-in log of search events as JSON lines, for finding out why a move was
+/// played without rebuilding.
+pub struct EventLog {
+    writer: BufWriter<File>,
+}
+
+impl EventLog {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(EventLog {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    /// A rollout from the position at `ply` along `path`,
+    /// which backed up `value` for the player at the end of the path.
+    pub fn rollout<const N: usize>(&mut self, ply: u64, path: &[Turn<N>], value: f32, terminal: bool) {
+        let path: Vec<_> = path.iter().map(ToPTN::to_ptn).collect();
+        self.write(json!({
+            "event": "rollout",
+            "ply": ply,
+            "path": path,
+            "value": value,
+            "terminal": terminal,
+        }));
+    }
+
+    /// A rollout from the position at `ply` made to go through `turn`, which
+    /// backed up `value` for the player making the move.
+    pub fn forced<const N: usize>(&mut self, ply: u64, turn: &Turn<N>, value: f32) {
+        self.write(json!({
+            "event": "forced",
+            "ply": ply,
+            "move": turn.to_ptn(),
+            "value": value,
+        }));
+    }
+
+    /// Visits of the position at `ply` taken from statistics shared with
+    /// other games.
+    pub fn shared(&mut self, ply: u64, visits: u32) {
+        self.write(json!({
+            "event": "shared",
+            "ply": ply,
+            "visits": visits,
+        }));
+    }
+
+    pub fn dirichlet(&mut self, ply: u64, alpha: f32, ratio: f32) {
+        self.write(json!({
+            "event": "dirichlet",
+            "ply": ply,
+            "alpha": alpha,
+            "ratio": ratio,
+        }));
+    }
+
+    /// A move was played with `visits` out of the rollouts of the position.
+    /// `eval` is from the perspective of the player who made the move.
+    pub fn decision<const N: usize>(&mut self, ply: u64, turn: &Turn<N>, visits: u32, eval: f32) {
+        self.write(json!({
+            "event": "move",
+            "ply": ply,
+            "move": turn.to_ptn(),
+            "visits": visits,
+            "eval": eval,
+        }));
+    }
+
+    /// Non-finite numbers, which JSON cannot hold, are written as `null`.
+    fn write(&mut self, line: Value) {
+        // the log is a debugging aid, so it should never stop the search
+        writeln!(self.writer, "{line}").ok();
+    }
+}
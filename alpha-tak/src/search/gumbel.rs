@@ -0,0 +1,168 @@
+//! Move selection at the root by Sequential Halving with Gumbel sampling,
+//! from "Policy improvement by planning with Gumbel" (Danihelka et al. 2022).
+//!
+//! Instead of PUCT with Dirichlet noise, a few moves are sampled from the
+//! policy without replacement and the rollouts are split between them in
+//! rounds, each round dropping the worse half. The policy target mixes the
+//! prior with the values found, so it improves on the network even with
+//! a budget of about a hundred rollouts, where visit counts mostly repeat
+//! the prior. Below the root the search is the usual one.
+
+use std::{cmp::Ordering, collections::HashMap};
+
+use tak::*;
+
+use super::{node::Node, params::SearchParams, turn_map::Lut};
+use crate::{
+    agent::Agent,
+    config::{GUMBEL_CONSIDERED_MOVES, GUMBEL_C_SCALE, GUMBEL_C_VISIT},
+    rng,
+};
+
+/// Sum of the completed policy when written as visits. It does not depend on
+/// the visits of the root, so that moves with little probability keep it
+/// when rounding.
+const COMPLETED_POLICY_TOTAL: f32 = 65536.;
+
+/// Settings of [`Node::sequential_halving`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GumbelParams {
+    /// Moves sampled at the root, which are narrowed down to one.
+    pub considered: usize,
+    /// Visits added to the most visits of a move when weighing values against
+    /// the prior.
+    pub c_visit: f32,
+    /// Weight of values against the prior.
+    pub c_scale: f32,
+}
+
+impl Default for GumbelParams {
+    fn default() -> Self {
+        GumbelParams {
+            considered: GUMBEL_CONSIDERED_MOVES,
+            c_visit: GUMBEL_C_VISIT,
+            c_scale: GUMBEL_C_SCALE,
+        }
+    }
+}
+
+impl GumbelParams {
+    /// Monotone transformation of an expected reward, which grows with the
+    /// visits so that the values count for more as they get reliable.
+    fn sigma(&self, expected_reward: f32, max_visits: u32) -> f32 {
+        // rewards are in [-1, 1], the paper expects [0, 1]
+        (self.c_visit + max_visits as f32) * self.c_scale * (expected_reward + 1.) / 2.
+    }
+}
+
+impl<const N: usize> Node<N>
+where
+    Turn<N>: Lut,
+{
+    /// Spend `rollouts` on the root by Sequential Halving over moves sampled
+    /// with the Gumbel-Top-k trick, and return the move which is left. The
+    /// sampling makes the choice random, so it needs no temperature.
+    pub fn sequential_halving<A: Agent<N>>(
+        &mut self,
+        game: &Game<N>,
+        agent: &A,
+        rollouts: usize,
+        gumbel: &GumbelParams,
+        params: &SearchParams,
+    ) -> Turn<N> {
+        if self.children.is_none() {
+            self.rollout(game.clone(), agent, params);
+        }
+        let mut candidates: Vec<_> = self
+            .children
+            .as_ref()
+            .expect("the game should not be over")
+            .iter()
+            .map(|(turn, child)| (turn.clone(), logit(child.policy) + sample_gumbel()))
+            .collect();
+        candidates.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+        candidates.truncate(gumbel.considered.max(1));
+
+        let mut phases = (candidates.len() as f32).log2().ceil() as usize;
+        let mut left = rollouts;
+        while candidates.len() > 1 {
+            let visits = (left / (phases * candidates.len())).max(1);
+            for (turn, _) in &candidates {
+                for _ in 0..visits {
+                    self.rollout_forced(game.clone(), turn, agent, params);
+                }
+            }
+            left = left.saturating_sub(visits * candidates.len());
+            phases -= 1;
+
+            // keep the better half by the Gumbel scores plus the values
+            let children = self.children.as_ref().unwrap();
+            let max_visits = children
+                .values()
+                .map(|child| child.visited_count)
+                .max()
+                .unwrap_or_default();
+            let score = |(turn, gumbel_score): &(Turn<N>, f32)| {
+                gumbel_score + gumbel.sigma(children[turn].expected_reward, max_visits)
+            };
+            candidates.sort_by(|a, b| score(b).partial_cmp(&score(a)).unwrap_or(Ordering::Equal));
+            candidates.truncate((candidates.len() + 1) / 2);
+        }
+        candidates.swap_remove(0).0
+    }
+
+    /// Policy target of a Gumbel search: the softmax of the prior logits
+    /// plus the transformed values, where moves which were not visited take
+    /// the value of the position. Given as visits like
+    /// [`Node::improved_policy`], scaled to a fixed total.
+    pub fn completed_policy(&self, gumbel: &GumbelParams) -> HashMap<Turn<N>, u32> {
+        let children = self.children.as_ref().expect("you must rollout at least once");
+        let max_visits = children
+            .values()
+            .map(|child| child.visited_count)
+            .max()
+            .unwrap_or_default();
+        // the reward of this node is from the perspective of the player who
+        // moved into it
+        let value = -self.expected_reward;
+        let scores: Vec<_> = children
+            .iter()
+            .map(|(turn, child)| {
+                let expected_reward = if child.visited_count == 0 {
+                    value
+                } else {
+                    child.expected_reward
+                };
+                (
+                    turn,
+                    logit(child.policy) + gumbel.sigma(expected_reward, max_visits),
+                )
+            })
+            .collect();
+        let max = scores
+            .iter()
+            .map(|(_, score)| *score)
+            .fold(f32::NEG_INFINITY, f32::max);
+        let total: f32 = scores.iter().map(|(_, score)| (score - max).exp()).sum();
+        scores
+            .into_iter()
+            .map(|(turn, score)| {
+                (
+                    turn.clone(),
+                    ((score - max).exp() / total * COMPLETED_POLICY_TOTAL).round() as u32,
+                )
+            })
+            .filter(|(_, visits)| *visits > 0)
+            .collect()
+    }
+}
+
+fn logit(policy: f32) -> f32 {
+    policy.max(f32::MIN_POSITIVE).ln()
+}
+
+/// Sample from the standard Gumbel distribution.
+fn sample_gumbel() -> f32 {
+    let uniform = rng::random::<f32>().max(f32::MIN_POSITIVE);
+    -(-uniform.ln()).ln()
+}
@@ -1,87 +1,307 @@
-use std::collections::HashMap;
-
 use tak::*;
 
-use super::{node::Node, turn_map::Lut};
-use crate::{agent::Agent, config::CONTEMPT};
+use super::{
+    children::Children,
+    node::Node,
+    params::SearchParams,
+    profile::{self, Phase},
+    transposition::Transpositions,
+    turn_map::Lut,
+};
+use crate::{
+    agent::Agent,
+    config::{CONTEMPT, SOLVER_MAX_NODES},
+    metrics::METRICS,
+};
 
 impl<const N: usize> Node<N>
 where
     Turn<N>: Lut,
 {
-    pub fn rollout<A: Agent<N>>(&mut self, game: Game<N>, agent: &A) -> f32 {
+    pub fn rollout<A: Agent<N>>(&mut self, game: Game<N>, agent: &A, params: &SearchParams) -> f32 {
+        self.rollout_in(game, agent, &mut None, params, true)
+    }
+
+    /// Like [`Node::rollout`], but expanding transposed positions from the
+    /// table instead of the network, see [`Transpositions`].
+    pub fn rollout_transposed<A: Agent<N>>(
+        &mut self,
+        game: Game<N>,
+        agent: &A,
+        table: &mut Transpositions,
+        params: &SearchParams,
+    ) -> f32 {
+        self.rollout_in(game, agent, &mut Some(table), params, true)
+    }
+
+    /// A rollout which goes through the child of `turn`, whatever its upper
+    /// confidence bound. The node has to be expanded.
+    pub fn rollout_forced<A: Agent<N>>(
+        &mut self,
+        mut game: Game<N>,
+        turn: &Turn<N>,
+        agent: &A,
+        params: &SearchParams,
+    ) -> f32 {
         self.visited_count += 1;
+        let child = self
+            .children
+            .as_mut()
+            .expect("do at least one rollout")
+            .get_mut(turn)
+            .expect("turn should be legal");
+        let to_move = game.to_move;
+        game.play(turn.clone()).unwrap();
+        let eval = child.rollout_in(game, agent, &mut None, params, false);
 
-        // cache game result
-        if self.result.is_none() {
-            self.result = Some(game.winner());
-            self.expected_reward = match self.result {
-                Some(GameResult::Winner { colour: winner, .. }) => {
-                    if winner == game.to_move {
-                        // means that the previous player played a losing move
-                        -1.
-                    } else {
-                        1.
-                    }
-                }
-                Some(GameResult::Draw { .. }) => -CONTEMPT,
-                _ => 0.,
-            };
+        // take the mean of the expected reward and eval
+        self.expected_reward =
+            ((self.visited_count - 1) as f32 * self.expected_reward + eval) / (self.visited_count as f32);
+        self.prove_by_children(to_move);
+
+        -eval
+    }
+
+    fn rollout_in<A: Agent<N>>(
+        &mut self,
+        game: Game<N>,
+        agent: &A,
+        table: &mut Option<&mut Transpositions>,
+        params: &SearchParams,
+        root: bool,
+    ) -> f32 {
+        self.visited_count += 1;
+
+        if let Some(value) = self.cache_result(&game) {
+            return value;
         }
-        if let Some(GameResult::Winner { .. }) = self.result {
-            return -self.expected_reward;
-        } else if let Some(GameResult::Draw { .. }) = self.result {
-            return 0.;
+        // the root needs children to pick a move from, even when it is proven
+        if !root {
+            if let Some(value) = self.prove(&game, params) {
+                return value;
+            }
         }
 
         // if it is the first time we are vising this node
         // initialize all children
         if self.children.is_none() {
-            return self.expand_node(game, agent);
+            match table {
+                Some(table) => self.expand_transposed(&game, agent, table, params),
+                None => self.expand_node(&game, agent, params),
+            }
+        } else {
+            // otherwise we have been at this node before
+            self.rollout_next(game, agent, table, params)
+        }
+    }
+
+    /// Cache the game result and return the value of the node if the game is
+    /// over.
+    pub(super) fn cache_result(&mut self, game: &Game<N>) -> Option<f32> {
+        if self.result.is_none() {
+            let timer = profile::start();
+            let result = game.winner();
+            self.result = Some(result);
+            self.expected_reward = final_reward(result, game.to_move);
+            timer.stop(Phase::Expansion);
+        }
+        self.result
+            .and_then(|result| final_value(result, self.expected_reward))
+    }
+
+    /// Solve the position if it is close enough to ending on flats, and
+    /// return the value of the node like [`Node::cache_result`] if its result
+    /// is proven. The solver runs when the node would be expanded, so it is
+    /// not asked again about a node it could not prove.
+    pub(super) fn prove(&mut self, game: &Game<N>, params: &SearchParams) -> Option<f32> {
+        if self.proven.is_none() && self.children.is_none() {
+            if let Some(result) = solve(game, params) {
+                self.set_proven(result, game.to_move);
+            }
+        }
+        self.proven
+            .and_then(|result| final_value(result, self.expected_reward))
+    }
+
+    /// Record the result the solver proved for the node, returning the value
+    /// a rollout backs up from it.
+    pub(super) fn set_proven(&mut self, result: GameResult, to_move: Colour) -> f32 {
+        self.proven = Some(result);
+        self.expected_reward = final_reward(result, to_move);
+        METRICS.proven_nodes.inc_by(1);
+        final_value(result, self.expected_reward).expect("the solver proves finished results")
+    }
+
+    /// Mark the node as proven once its children decide it: a child which
+    /// wins for the player to move, or every child proven and the best of
+    /// them played. Called after backing up through the node, so that proofs
+    /// found deep in the tree reach the root. The root is searched further
+    /// once proven, so its expected reward is put back to the exact one.
+    pub(super) fn prove_by_children(&mut self, to_move: Colour) {
+        if let Some(result) = self.proven {
+            self.expected_reward = final_reward(result, to_move);
+            return;
         }
-        // otherwise we have been at this node before
-        self.rollout_next(game, agent)
+        let children = match &self.children {
+            Some(children) if !children.is_empty() => children,
+            _ => return,
+        };
+        let best = match children.values().find(|child| child.is_proven_win()) {
+            Some(win) => win,
+            None if children.values().all(|child| child.known_result().is_some()) => children
+                .values()
+                .max_by(|a, b| {
+                    a.expected_reward
+                        .partial_cmp(&b.expected_reward)
+                        .expect("tried comparing nan")
+                })
+                .unwrap(),
+            None => return,
+        };
+        let result = best.known_result().unwrap();
+        self.proven = Some(result);
+        self.expected_reward = final_reward(result, to_move);
+        METRICS.proven_nodes.inc_by(1);
     }
 
-    fn expand_node<A: Agent<N>>(&mut self, game: Game<N>, agent: &A) -> f32 {
+    fn expand_node<A: Agent<N>>(&mut self, game: &Game<N>, agent: &A, params: &SearchParams) -> f32 {
         // use the neural network to get initial policy for children
         // and eval for this board
-        let (policy, eval) = agent.policy_and_eval(&game);
+        let timer = profile::start();
+        let (policy, eval) = agent.policy_and_eval(game);
+        timer.stop(Phase::Network);
+        self.expand_with(game, &policy, eval, params)
+    }
 
-        let mut children = HashMap::new();
+    /// Expand the node like a transposition of it was, or with the network
+    /// if its position is new, remembering it for the next transposition.
+    fn expand_transposed<A: Agent<N>>(
+        &mut self,
+        game: &Game<N>,
+        agent: &A,
+        table: &mut Transpositions,
+        params: &SearchParams,
+    ) -> f32 {
+        let hash = game.zobrist();
+        if let Some(eval) = table.expand(hash, self, game) {
+            return eval;
+        }
+        let eval = self.expand_node(game, agent, params);
+        table.record(hash, self, eval);
+        eval
+    }
 
+    /// Initialize children from a policy and set the eval of this node.
+    pub(super) fn expand_with(
+        &mut self,
+        game: &Game<N>,
+        policy: &[f32],
+        eval: f32,
+        params: &SearchParams,
+    ) -> f32 {
+        let timer = profile::start();
         let turns = game.possible_turns();
-        for turn in turns {
-            let move_index = turn.turn_map();
-            children.insert(turn, Node::init(policy[move_index]));
+        let mut priors: Vec<_> = turns.iter().map(|turn| policy[turn.turn_map()]).collect();
+        if params.policy_temperature != 1. {
+            temper(&mut priors, params.policy_temperature);
+        }
+        let mut children = Children::with_capacity(turns.len());
+        for (turn, prior) in turns.into_iter().zip(priors) {
+            children.insert(turn, Node::init(prior));
         }
 
         self.expected_reward = -eval;
         self.children = Some(children);
+        timer.stop(Phase::Expansion);
         eval
     }
 
-    fn rollout_next<A: Agent<N>>(&mut self, mut game: Game<N>, agent: &A) -> f32 {
+    fn rollout_next<A: Agent<N>>(
+        &mut self,
+        mut game: Game<N>,
+        agent: &A,
+        table: &mut Option<&mut Transpositions>,
+        params: &SearchParams,
+    ) -> f32 {
         // pick which node to rollout
+        let timer = profile::start();
         let mut children = self.children.take().unwrap();
         let (turn, next_node) = children
             .iter_mut()
             .max_by(|(_, a), (_, b)| {
-                self.upper_confidence_bound(a)
-                    .partial_cmp(&self.upper_confidence_bound(b))
+                self.upper_confidence_bound(a, params)
+                    .partial_cmp(&self.upper_confidence_bound(b, params))
                     .expect("tried comparing nan")
             })
             .unwrap();
+        timer.stop(Phase::Selection);
 
         // rollout next node
+        let to_move = game.to_move;
         game.play(turn.clone()).unwrap();
-        let eval = next_node.rollout(game, agent);
+        let eval = next_node.rollout_in(game, agent, table, params, false);
         self.children = Some(children);
 
         // take the mean of the expected reward and eval
+        let timer = profile::start();
         self.expected_reward =
             ((self.visited_count - 1) as f32 * self.expected_reward + eval) / (self.visited_count as f32);
+        self.prove_by_children(to_move);
+        timer.stop(Phase::Backup);
 
         -eval
     }
 }
+
+/// Result of the position with best play, if it is close enough to ending on
+/// flats for the solver to try.
+pub(super) fn solve<const N: usize>(game: &Game<N>, params: &SearchParams) -> Option<GameResult> {
+    if params.solver_depth == 0 || game.plies_until_flat_end() > params.solver_depth {
+        return None;
+    }
+    let timer = profile::start();
+    let result = game.solve(params.solver_depth, SOLVER_MAX_NODES);
+    timer.stop(Phase::Expansion);
+    result
+}
+
+/// Expected reward of a node whose game ends in `result`, from the
+/// perspective of the player who moved into it.
+fn final_reward(result: GameResult, to_move: Colour) -> f32 {
+    match result {
+        GameResult::Winner { colour: winner, .. } => {
+            if winner == to_move {
+                // means that the previous player played a losing move
+                -1.
+            } else {
+                1.
+            }
+        }
+        GameResult::Draw { .. } => -CONTEMPT,
+        GameResult::Ongoing => 0.,
+    }
+}
+
+/// Value a rollout backs up from a node whose game ends in `result`.
+fn final_value(result: GameResult, expected_reward: f32) -> Option<f32> {
+    match result {
+        GameResult::Winner { .. } => Some(-expected_reward),
+        GameResult::Draw { .. } => Some(0.),
+        GameResult::Ongoing => None,
+    }
+}
+
+/// Raise the priors to the power of one over the temperature and normalize
+/// them again.
+fn temper(priors: &mut [f32], temperature: f32) {
+    let exponent = 1. / temperature.max(f32::EPSILON);
+    for prior in priors.iter_mut() {
+        *prior = prior.powf(exponent);
+    }
+    let sum: f32 = priors.iter().sum();
+    if sum > 0. {
+        for prior in priors {
+            *prior /= sum;
+        }
+    }
+}
@@ -1,8 +1,18 @@
+pub mod children;
 pub mod debug;
+pub mod event_log;
+pub mod gumbel;
 pub mod mcts;
 pub mod node;
 pub mod noise;
+pub mod opening_tree;
+pub mod parallel;
+pub mod params;
 pub mod play;
+pub mod profile;
+pub mod save;
+pub mod step;
+pub mod transposition;
 pub mod turn_map;
 pub mod ucb;
 
@@ -1,14 +1,20 @@
-use std::collections::HashMap;
-
 use tak::*;
 
+use super::children::Children;
+
 #[derive(Clone, Debug, Default)]
 pub struct Node<const N: usize> {
     pub result: Option<GameResult>,
+    /// Result with best play, if the endgame solver proved it. The node is
+    /// then searched like a finished game, unless it is the root.
+    pub proven: Option<GameResult>,
     pub policy: f32,
     pub expected_reward: f32,
     pub visited_count: u32,
-    pub children: Option<HashMap<Turn<N>, Node<N>>>,
+    /// Rollouts going through this node which are not backed up yet, counted
+    /// as losses by selection so that threads sharing the tree spread out.
+    pub virtual_visits: u32,
+    pub children: Option<Children<N>>,
 }
 
 impl<const N: usize> Node<N> {
@@ -18,4 +24,22 @@ impl<const N: usize> Node<N> {
             ..Default::default()
         }
     }
+
+    /// Result of the game with best play, if the game is over in this node or
+    /// its result was proven.
+    pub fn known_result(&self) -> Option<GameResult> {
+        self.proven
+            .or_else(|| self.result.filter(|result| *result != GameResult::Ongoing))
+    }
+
+    /// Whether the player who moved into this node is known to win. The
+    /// expected reward of a known result is exact.
+    pub fn is_proven_win(&self) -> bool {
+        self.known_result().is_some() && self.expected_reward >= 1.
+    }
+
+    /// Whether the player who moved into this node is known to lose.
+    pub fn is_proven_loss(&self) -> bool {
+        self.known_result().is_some() && self.expected_reward <= -1.
+    }
 }
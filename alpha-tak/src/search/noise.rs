@@ -1,6 +1,7 @@
 use rand_distr::{Dirichlet, Distribution};
 
 use super::node::Node;
+use crate::rng::with_rng;
 
 impl<const N: usize> Node<N> {
     pub fn apply_dirichlet(&mut self, alpha: f32, ratio: f32) {
@@ -10,7 +11,7 @@ impl<const N: usize> Node<N> {
             .expect("you must rollout at least once")
             .len();
         let dirichlet = Dirichlet::new(&vec![alpha; count]).unwrap();
-        let samples = dirichlet.sample(&mut rand::thread_rng());
+        let samples = with_rng(|rng| dirichlet.sample(rng));
         for (node, noise) in self.children.as_mut().unwrap().values_mut().zip(samples) {
             node.policy = noise * ratio + node.policy * (1. - ratio);
         }
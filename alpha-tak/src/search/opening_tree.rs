@@ -0,0 +1,114 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use tak::*;
+
+use super::node::Node;
+
+/// Visit statistics of a single child of a searched position.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChildStats {
+    pub visits: u32,
+    /// Sum of the rewards of all visits.
+    pub reward: f32,
+}
+
+/// Root visit statistics shared between concurrent games,
+/// so that the same opening positions are not searched from scratch every game.
+#[derive(Debug)]
+pub struct OpeningTree<const N: usize> {
+    max_visits: u32,
+    positions: Mutex<HashMap<String, HashMap<Turn<N>, ChildStats>>>,
+}
+
+impl<const N: usize> OpeningTree<N> {
+    /// Create an empty tree. The statistics of a position are scaled down
+    /// whenever their total visits exceed `max_visits`, so that newer searches
+    /// keep an influence.
+    pub fn new(max_visits: u32) -> Self {
+        OpeningTree {
+            max_visits,
+            positions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get the statistics for the position.
+    pub fn get(&self, game: &Game<N>) -> HashMap<Turn<N>, ChildStats> {
+        self.positions
+            .lock()
+            .unwrap()
+            .get(&game.to_tps())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Add the statistics of a search that was seeded with `seeded`.
+    /// Only the visits which were not already in the tree are added.
+    pub fn update(
+        &self,
+        game: &Game<N>,
+        seeded: &HashMap<Turn<N>, ChildStats>,
+        searched: HashMap<Turn<N>, ChildStats>,
+    ) {
+        let mut positions = self.positions.lock().unwrap();
+        let stats = positions.entry(game.to_tps()).or_default();
+        for (turn, child) in searched {
+            let old = seeded.get(&turn).copied().unwrap_or_default();
+            let entry = stats.entry(turn).or_default();
+            entry.visits += child.visits.saturating_sub(old.visits);
+            entry.reward += child.reward - old.reward;
+        }
+
+        let total: u32 = stats.values().map(|child| child.visits).sum();
+        if total > self.max_visits {
+            let factor = self.max_visits as f32 / total as f32;
+            for child in stats.values_mut() {
+                let visits = (child.visits as f32 * factor) as u32;
+                child.reward *= visits as f32 / child.visits.max(1) as f32;
+                child.visits = visits;
+            }
+        }
+    }
+
+    /// Forget everything, for example after the network changed.
+    pub fn clear(&self) {
+        self.positions.lock().unwrap().clear();
+    }
+}
+
+impl<const N: usize> Node<N> {
+    /// Get the visit statistics of the children.
+    pub fn child_stats(&self) -> HashMap<Turn<N>, ChildStats> {
+        self.children
+            .as_ref()
+            .expect("you must rollout at least once")
+            .iter()
+            .map(|(turn, child)| {
+                (turn.clone(), ChildStats {
+                    visits: child.visited_count,
+                    reward: child.expected_reward * child.visited_count as f32,
+                })
+            })
+            .collect()
+    }
+
+    /// Add visit statistics from earlier searches to the children, returning
+    /// the visits added. Children which are not expanded yet are skipped, as
+    /// expanding them would replace their expected reward with a single eval.
+    pub fn add_child_stats(&mut self, stats: &HashMap<Turn<N>, ChildStats>) -> u32 {
+        let children = self.children.as_mut().expect("you must rollout at least once");
+        let mut added = 0;
+        for (turn, child) in children.iter_mut().filter(|(_, child)| child.children.is_some()) {
+            if let Some(stats) = stats.get(turn) {
+                let visits = child.visited_count + stats.visits;
+                if visits > 0 {
+                    child.expected_reward =
+                        (child.expected_reward * child.visited_count as f32 + stats.reward) / visits as f32;
+                }
+                child.visited_count = visits;
+                added += stats.visits;
+            }
+        }
+        self.visited_count += added;
+        added
+    }
+}
@@ -0,0 +1,100 @@
+//! Several threads searching one shared tree. Each thread walks down to a
+//! few leaves at once and evaluates them in one batch, so a local network
+//! gets batches even from a single thread, and the evaluations of the
+//! threads overlap instead of each thread growing a tree of its own.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use tak::*;
+
+use super::{
+    node::Node,
+    params::SearchParams,
+    step::{Descent, PendingLeaf},
+    turn_map::Lut,
+};
+use crate::{agent::Agent, config::PARALLEL_LEAVES};
+
+impl<const N: usize> Node<N>
+where
+    Turn<N>: Lut,
+{
+    /// Do `rollouts` rollouts split over `threads` threads. The tree is only
+    /// locked while walking down and backing up, and the virtual losses of
+    /// the rollouts in flight keep the leaves of a batch and the threads on
+    /// different paths. The endgame solver and the network run without the
+    /// lock.
+    pub fn parallel_rollouts<A: Agent<N> + Sync>(
+        &mut self,
+        game: &Game<N>,
+        agent: &A,
+        rollouts: usize,
+        threads: usize,
+        params: &SearchParams,
+    ) {
+        let remaining = AtomicUsize::new(rollouts);
+        let tree = Mutex::new(std::mem::take(self));
+        // leaves are proven after the tree is unlocked
+        let descent_params = SearchParams {
+            solver_depth: 0,
+            ..*params
+        };
+        thread::scope(|scope| {
+            for _ in 0..threads.max(1) {
+                scope.spawn(|| loop {
+                    let mut leaves: Vec<PendingLeaf<N>> = Vec::with_capacity(PARALLEL_LEAVES);
+                    {
+                        let mut tree = tree.lock().unwrap();
+                        while leaves.len() < PARALLEL_LEAVES
+                            && remaining
+                                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                                    left.checked_sub(1)
+                                })
+                                .is_ok()
+                        {
+                            if let Descent::Leaf(leaf) = tree.descend(game.clone(), &descent_params) {
+                                if leaves.iter().any(|other| other.path == leaf.path) {
+                                    // the tree has to grow before another leaf
+                                    // can be found
+                                    tree.abandon(&leaf);
+                                    remaining.fetch_add(1, Ordering::Relaxed);
+                                    break;
+                                }
+                                leaves.push(leaf);
+                            }
+                        }
+                    }
+                    if leaves.is_empty() && remaining.load(Ordering::Relaxed) == 0 {
+                        break;
+                    }
+
+                    let (proven, leaves): (Vec<_>, Vec<_>) = leaves
+                        .into_iter()
+                        .map(|leaf| (leaf.solve(params), leaf))
+                        .partition(|(result, _)| result.is_some());
+                    let games: Vec<_> = leaves.iter().map(|(_, leaf)| leaf.game.clone()).collect();
+                    let (policies, evals) = if games.is_empty() {
+                        Default::default()
+                    } else {
+                        agent.policy_and_eval_batch(&games)
+                    };
+
+                    let mut tree = tree.lock().unwrap();
+                    for (result, leaf) in proven {
+                        tree.ascend_proven(&leaf, result.unwrap());
+                    }
+                    for (((_, leaf), policy), eval) in leaves.iter().zip(policies).zip(evals) {
+                        tree.ascend(leaf, &policy, eval, params);
+                    }
+                });
+            }
+        });
+        *self = tree.into_inner().unwrap();
+    }
+}
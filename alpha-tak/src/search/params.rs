@@ -0,0 +1,32 @@
+use crate::config::{EXPLORATION_BASE, EXPLORATION_INIT, FPU_REDUCTION, POLICY_TEMPERATURE, SOLVER_DEPTH};
+
+/// Settings of the selection and expansion of the search, so that they can be
+/// tuned without recompiling. The defaults are the values in the config.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchParams {
+    /// Exploration rate of a node without visits, the PUCT constant.
+    pub cpuct: f32,
+    /// Visits after which the exploration rate has grown by about one.
+    pub cpuct_base: f32,
+    /// Value of unvisited children below the value of their parent, `None`
+    /// values them as a draw.
+    pub fpu_reduction: Option<f32>,
+    /// Temperature of the policy when a node is expanded, above 1 flattens
+    /// it and below 1 sharpens it.
+    pub policy_temperature: f32,
+    /// Plies of the endgame solver, which proves the results of nodes close
+    /// to the end of the game instead of asking the network. 0 turns it off.
+    pub solver_depth: u32,
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        SearchParams {
+            cpuct: EXPLORATION_INIT,
+            cpuct_base: EXPLORATION_BASE,
+            fpu_reduction: FPU_REDUCTION,
+            policy_temperature: POLICY_TEMPERATURE,
+            solver_depth: SOLVER_DEPTH,
+        }
+    }
+}
@@ -3,7 +3,25 @@ use std::collections::HashMap;
 use rand_distr::{Distribution, WeightedIndex};
 use tak::*;
 
-use super::node::Node;
+use super::{
+    node::Node,
+    profile::{self, Phase},
+};
+use crate::rng::with_rng;
+
+/// How much of the search survives playing a move, see
+/// [`Node::play_with_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReuseStats {
+    /// Visits of the subtree of the played move, which the next search
+    /// starts from.
+    pub reused_visits: u32,
+    /// Visits of the other moves, which are dropped.
+    pub discarded_visits: u32,
+    /// Nodes dropped with the other moves. Counting them walks the whole
+    /// dropped tree, so it is only done in debug builds.
+    pub discarded_nodes: Option<usize>,
+}
 
 impl<const N: usize> Node<N> {
     pub fn improved_policy(&self) -> HashMap<Turn<N>, u32> {
@@ -18,14 +36,44 @@ impl<const N: usize> Node<N> {
 
     #[must_use]
     pub fn play(self, turn: &Turn<N>) -> Node<N> {
+        self.play_with_stats(turn).0
+    }
+
+    /// Like [`Node::play`], but also report how much of the tree is kept.
+    pub fn play_with_stats(self, turn: &Turn<N>) -> (Node<N>, ReuseStats) {
+        let timer = profile::start();
+        debug_assert_eq!(self.virtual_visits, 0, "a rollout is still in flight");
         let mut children = self.children.expect("do at least one rollout");
-        children.remove(turn).expect("all turns should be in there")
+        let node = children.remove(turn).expect("all turns should be in there");
+        let discarded_visits = children.values().map(|child| child.visited_count).sum::<u32>();
+        debug_assert!(
+            node.visited_count + discarded_visits <= self.visited_count,
+            "children have more visits than their parent"
+        );
+        let stats = ReuseStats {
+            reused_visits: node.visited_count,
+            discarded_visits,
+            discarded_nodes: cfg!(debug_assertions).then(|| children.values().map(Node::node_count).sum()),
+        };
+        drop(children);
+        timer.stop(Phase::Maintenance);
+        (node, stats)
     }
 
-    pub fn pick_move(&self, exploitation: bool) -> Turn<N> {
+    /// Pick a move by sampling visit counts raised to the power of `1 /
+    /// temperature`. A temperature of 0 always picks the most visited move.
+    pub fn pick_move(&self, temperature: f32) -> Turn<N> {
+        // a proven win is played whatever its visits, so that a road found
+        // late in the search is not thrown away
+        if let Some(children) = &self.children {
+            if let Some((turn, _)) = children.iter().find(|(_, child)| child.is_proven_win()) {
+                return turn.clone();
+            }
+        }
         let improved_policy = self.improved_policy();
+        let max_visits = improved_policy.values().copied().max().unwrap_or_default();
 
-        if exploitation {
+        if temperature <= 0. || max_visits == 0 {
             // when exploiting always pick the move with largest policy
             improved_policy
                 .into_iter()
@@ -36,14 +84,14 @@ impl<const N: usize> Node<N> {
             // split into turns and weights
             let mut turns = vec![];
             let mut weights = vec![];
-            for (turn, weight) in improved_policy {
+            for (turn, visits) in improved_policy {
                 turns.push(turn);
-                weights.push(weight);
+                // normalize first so that low temperatures do not overflow
+                weights.push((visits as f32 / max_visits as f32).powf(1. / temperature));
             }
             // randomly pick based on weights from improved policy
-            let mut rng = rand::thread_rng();
             let distr = WeightedIndex::new(&weights).unwrap();
-            let index = distr.sample(&mut rng);
+            let index = with_rng(|rng| distr.sample(rng));
             turns.swap_remove(index)
         }
     }
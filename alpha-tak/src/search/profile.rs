@@ -0,0 +1,118 @@
+//! Where the time of a search goes, measured when
+//! [`PROFILE_SEARCH`](crate::config::PROFILE_SEARCH) is set.
+//!
+//! Timings are accumulated per thread and collected with [`take`] once a
+//! move is decided, which also adds them to the
+//! [`METRICS`](crate::metrics::METRICS).
+
+use std::{
+    cell::RefCell,
+    fmt::{self, Display},
+    time::{Duration, Instant},
+};
+
+use crate::{config::PROFILE_SEARCH, metrics::METRICS};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Picking children by their upper confidence bound.
+    Selection,
+    /// Generating moves and creating the children of a leaf.
+    Expansion,
+    /// Waiting for the network to evaluate a leaf.
+    Network,
+    /// Updating the expected rewards on the way back up.
+    Backup,
+    /// Rerooting the tree and dropping the siblings of a played move.
+    Maintenance,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [
+        Phase::Selection,
+        Phase::Expansion,
+        Phase::Network,
+        Phase::Backup,
+        Phase::Maintenance,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Selection => "selection",
+            Phase::Expansion => "expansion",
+            Phase::Network => "network",
+            Phase::Backup => "backup",
+            Phase::Maintenance => "maintenance",
+        }
+    }
+}
+
+/// Time spent in each [`Phase`] of a search.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchProfile {
+    times: [Duration; 5],
+}
+
+impl SearchProfile {
+    pub fn get(&self, phase: Phase) -> Duration {
+        self.times[phase as usize]
+    }
+
+    pub fn total(&self) -> Duration {
+        self.times.iter().sum()
+    }
+}
+
+impl Display for SearchProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total().as_secs_f32().max(f32::EPSILON);
+        for (i, phase) in Phase::ALL.into_iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(
+                f,
+                "{}: {:.0}%",
+                phase.name(),
+                100. * self.get(phase).as_secs_f32() / total
+            )?;
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    static PROFILE: RefCell<SearchProfile> = RefCell::new(SearchProfile::default());
+}
+
+/// Measures one phase, started by [`start`].
+#[must_use]
+pub struct Timer(Option<Instant>);
+
+/// Start measuring, which does nothing unless profiling is on.
+pub fn start() -> Timer {
+    Timer(PROFILE_SEARCH.then(Instant::now))
+}
+
+impl Timer {
+    /// Add the time since the timer was started to `phase`.
+    pub fn stop(self, phase: Phase) {
+        if let Some(start) = self.0 {
+            let elapsed = start.elapsed();
+            PROFILE.with(|profile| profile.borrow_mut().times[phase as usize] += elapsed);
+        }
+    }
+}
+
+/// Take the timings of this thread since the last call, or `None` if
+/// profiling is off.
+pub fn take() -> Option<SearchProfile> {
+    if !PROFILE_SEARCH {
+        return None;
+    }
+    let profile = PROFILE.with(|profile| std::mem::take(&mut *profile.borrow_mut()));
+    for phase in Phase::ALL {
+        METRICS.search_micros[phase as usize].inc_by(profile.get(phase).as_micros() as u64);
+    }
+    Some(profile)
+}
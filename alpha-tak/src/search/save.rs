@@ -0,0 +1,143 @@
+//! Search trees on disk, so that a long analysis can be paused and resumed
+//! later or on another machine.
+//!
+//! The first line is the TPS of the root position. Every other line is a
+//! node in depth first order: the move leading to it (`-` for the root), its
+//! policy, expected reward, visits, cached result, and the number of
+//! children, or `-` if it was never expanded.
+
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use tak::*;
+
+use super::{children::Children, node::Node};
+
+impl<const N: usize> Node<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// Save the tree searched from `game`.
+    pub fn save_tree<P: AsRef<Path>>(&self, game: &Game<N>, path: P) -> Result<(), Box<dyn Error>> {
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "{}", game.to_tps())?;
+        self.write_nodes(None, &mut file)?;
+        file.flush()?;
+        Ok(())
+    }
+
+    /// Load a tree saved with [`Node::save_tree`], which has to have been
+    /// searched from `game`.
+    pub fn load_tree<P: AsRef<Path>>(game: &Game<N>, path: P) -> Result<Self, Box<dyn Error>> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let tps = lines.next().ok_or("empty tree file")??;
+        if tps != game.to_tps() {
+            return Err(format!("tree was searched from {tps}, not from {}", game.to_tps()).into());
+        }
+        let (_, root) = Node::read_node(&mut lines)?;
+        if lines.next().is_some() {
+            return Err("trailing lines after the tree".into());
+        }
+        Ok(root)
+    }
+
+    fn write_nodes<W: Write>(&self, turn: Option<&Turn<N>>, out: &mut W) -> std::io::Result<()> {
+        let children = match &self.children {
+            Some(children) => children.len().to_string(),
+            None => "-".to_string(),
+        };
+        writeln!(
+            out,
+            "{} {} {} {} {} {children}",
+            turn.map_or_else(|| "-".to_string(), ToPTN::to_ptn),
+            self.policy,
+            self.expected_reward,
+            self.visited_count,
+            result_to_str(self.result),
+        )?;
+        for (turn, child) in self.children.iter().flatten() {
+            child.write_nodes(Some(turn), out)?;
+        }
+        Ok(())
+    }
+
+    fn read_node<B: BufRead>(
+        lines: &mut std::io::Lines<B>,
+    ) -> Result<(Option<Turn<N>>, Self), Box<dyn Error>> {
+        let line = lines
+            .next()
+            .ok_or("tree file ended in the middle of the tree")??;
+        let fields: Vec<_> = line.split(' ').collect();
+        if fields.len() != 6 {
+            return Err(format!("expected 6 fields in tree line, got {}", fields.len()).into());
+        }
+        let turn = match fields[0] {
+            "-" => None,
+            turn => Some(Turn::from_ptn(turn)?),
+        };
+        let mut node = Node {
+            policy: fields[1].parse()?,
+            expected_reward: fields[2].parse()?,
+            visited_count: fields[3].parse()?,
+            virtual_visits: 0,
+            result: result_from_str(fields[4])?,
+            // proven again when visited
+            proven: None,
+            children: None,
+        };
+        if fields[5] != "-" {
+            let count: usize = fields[5].parse()?;
+            let mut children = Children::with_capacity(count);
+            for _ in 0..count {
+                match Node::read_node(lines)? {
+                    (Some(turn), child) => children.insert(turn, child),
+                    (None, _) => return Err("child without a move in tree file".into()),
+                };
+            }
+            node.children = Some(children);
+        }
+        Ok((turn, node))
+    }
+}
+
+fn result_to_str(result: Option<GameResult>) -> &'static str {
+    match result {
+        None => "-",
+        Some(GameResult::Ongoing) => "o",
+        Some(GameResult::Draw { turn_limit: false }) => "d",
+        Some(GameResult::Draw { turn_limit: true }) => "dl",
+        Some(GameResult::Winner { colour, reason }) => match (colour, reason) {
+            (Colour::White, WinReason::Road) => "wr",
+            (Colour::White, WinReason::Flats) => "wf",
+            (Colour::White, WinReason::Time) => "wt",
+            (Colour::White, WinReason::Resignation) => "wg",
+            (Colour::Black, WinReason::Road) => "br",
+            (Colour::Black, WinReason::Flats) => "bf",
+            (Colour::Black, WinReason::Time) => "bt",
+            (Colour::Black, WinReason::Resignation) => "bg",
+        },
+    }
+}
+
+fn result_from_str(s: &str) -> Result<Option<GameResult>, Box<dyn Error>> {
+    let winner = |colour, reason| Some(GameResult::Winner { colour, reason });
+    Ok(match s {
+        "-" => None,
+        "o" => Some(GameResult::Ongoing),
+        "d" => Some(GameResult::Draw { turn_limit: false }),
+        "dl" => Some(GameResult::Draw { turn_limit: true }),
+        "wr" => winner(Colour::White, WinReason::Road),
+        "wf" => winner(Colour::White, WinReason::Flats),
+        "wt" => winner(Colour::White, WinReason::Time),
+        "wg" => winner(Colour::White, WinReason::Resignation),
+        "br" => winner(Colour::Black, WinReason::Road),
+        "bf" => winner(Colour::Black, WinReason::Flats),
+        "bt" => winner(Colour::Black, WinReason::Time),
+        "bg" => winner(Colour::Black, WinReason::Resignation),
+        _ => return Err(format!("unknown result `{s}` in tree file").into()),
+    })
+}
@@ -0,0 +1,169 @@
+//! Rollouts split around the network evaluation,
+//! for agents which cannot answer synchronously (e.g. inference in a browser).
+
+use tak::*;
+
+use super::{
+    mcts,
+    node::Node,
+    params::SearchParams,
+    profile::{self, Phase},
+    turn_map::Lut,
+};
+
+/// A leaf reached by [`Node::descend`] which is waiting for a network
+/// evaluation.
+#[derive(Clone, Debug)]
+pub struct PendingLeaf<const N: usize> {
+    pub path: Vec<Turn<N>>,
+    /// Index of each turn of the path among the children of its node, so
+    /// that the backup does not have to look the turns up again.
+    indices: Vec<usize>,
+    pub game: Game<N>,
+}
+
+impl<const N: usize> PendingLeaf<N> {
+    /// Result of the leaf proven by the endgame solver, to back up with
+    /// [`Node::ascend_proven`] instead of evaluating it. Lets the solver run
+    /// without holding the tree when the leaf was reached by
+    /// [`Node::descend`] with the solver turned off. The root is not proven,
+    /// since it needs children to pick a move from.
+    pub fn solve(&self, params: &SearchParams) -> Option<GameResult> {
+        if self.path.is_empty() {
+            return None;
+        }
+        mcts::solve(&self.game, params)
+    }
+
+    /// The player to move at the root, plies before the leaf.
+    fn root_to_move(&self) -> Colour {
+        if self.path.len() % 2 == 0 {
+            self.game.to_move
+        } else {
+            self.game.to_move.next()
+        }
+    }
+}
+
+/// Where the first half of a rollout ended.
+#[derive(Clone, Debug)]
+pub enum Descent<const N: usize> {
+    /// The leaf needs to be evaluated with [`Node::ascend`].
+    Leaf(PendingLeaf<N>),
+    /// The rollout ended in a finished game and was already backed up.
+    Terminal { path: Vec<Turn<N>>, value: f32 },
+}
+
+impl<const N: usize> Node<N>
+where
+    Turn<N>: Lut,
+{
+    /// First half of a rollout: walk down the tree the same way
+    /// [`Node::rollout`] would. Several leaves may be pending at once, the
+    /// rollouts in flight count as virtual losses until they are backed up.
+    pub fn descend(&mut self, mut game: Game<N>, params: &SearchParams) -> Descent<N> {
+        let to_move = game.to_move;
+        let mut path = Vec::new();
+        let mut indices = Vec::new();
+        let mut node = &mut *self;
+        let value = loop {
+            node.virtual_visits += 1;
+
+            if let Some(value) = node.cache_result(&game) {
+                break value;
+            }
+            // the root needs children to pick a move from, even when it is
+            // proven
+            if !path.is_empty() {
+                if let Some(value) = node.prove(&game, params) {
+                    break value;
+                }
+            }
+            let children = match &node.children {
+                Some(children) => children,
+                None => return Descent::Leaf(PendingLeaf { path, indices, game }),
+            };
+
+            let timer = profile::start();
+            let index = children
+                .values()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| {
+                    node.upper_confidence_bound(a, params)
+                        .partial_cmp(&node.upper_confidence_bound(b, params))
+                        .expect("tried comparing nan")
+                })
+                .unwrap()
+                .0;
+            let turn = children.turn(index).clone();
+            timer.stop(Phase::Selection);
+            game.play(turn.clone()).unwrap();
+            path.push(turn);
+            indices.push(index);
+            node = node.children.as_mut().unwrap().node_mut(index);
+        };
+        self.backup(&indices, to_move, &mut |_| value);
+        Descent::Terminal { path, value }
+    }
+
+    /// Second half of a rollout: expand the pending leaf with the network
+    /// output and back up the eval towards the root.
+    pub fn ascend(&mut self, leaf: &PendingLeaf<N>, policy: &[f32], eval: f32, params: &SearchParams) {
+        let to_move = leaf.root_to_move();
+        self.backup(&leaf.indices, to_move, &mut |node| match node.children {
+            // another rollout reached the same leaf and expanded it first
+            Some(_) => {
+                node.expected_reward = ((node.visited_count - 1) as f32 * node.expected_reward - eval)
+                    / (node.visited_count as f32);
+                eval
+            }
+            None => node.expand_with(&leaf.game, policy, eval, params),
+        });
+    }
+
+    /// Second half of a rollout whose leaf was proven, see
+    /// [`PendingLeaf::solve`].
+    pub fn ascend_proven(&mut self, leaf: &PendingLeaf<N>, result: GameResult) {
+        let to_move = leaf.root_to_move();
+        self.backup(&leaf.indices, to_move, &mut |node| {
+            node.set_proven(result, leaf.game.to_move)
+        });
+    }
+
+    /// Give up on a pending leaf which will not be evaluated, for example
+    /// because a move was played meanwhile, and take back the virtual visits
+    /// its rollout added along the path.
+    pub fn abandon(&mut self, leaf: &PendingLeaf<N>) {
+        let mut node = self;
+        node.virtual_visits -= 1;
+        for &index in &leaf.indices {
+            node = node.children.as_mut().unwrap().node_mut(index);
+            node.virtual_visits -= 1;
+        }
+    }
+
+    fn backup(
+        &mut self,
+        indices: &[usize],
+        to_move: Colour,
+        leaf: &mut impl FnMut(&mut Node<N>) -> f32,
+    ) -> f32 {
+        self.virtual_visits -= 1;
+        self.visited_count += 1;
+        let (&index, rest) = match indices.split_first() {
+            Some(split) => split,
+            None => return leaf(self),
+        };
+        let child = self.children.as_mut().unwrap().node_mut(index);
+        let eval = child.backup(rest, to_move.next(), leaf);
+
+        // take the mean of the expected reward and eval
+        let timer = profile::start();
+        self.expected_reward =
+            ((self.visited_count - 1) as f32 * self.expected_reward + eval) / (self.visited_count as f32);
+        self.prove_by_children(to_move);
+        timer.stop(Phase::Backup);
+
+        -eval
+    }
+}
@@ -1,12 +1,30 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use tak::*;
 
-use crate::{agent::Agent, repr::moves_dims, search::node::Node};
+use crate::{
+    agent::{Agent, RandomSymmetry},
+    config::ADJUDICATE_DEAD_POSITIONS,
+    game_result,
+    player::Player,
+    repr::{drop_patterns, max_legal_moves, moves_dims, place_range, spread_factors, spread_range},
+    search::{
+        event_log::EventLog,
+        gumbel::GumbelParams,
+        node::Node,
+        opening_tree::ChildStats,
+        params::SearchParams,
+        step::Descent,
+        transposition::Transpositions,
+        turn_map::Lut,
+    },
+};
 
 struct TestAgent {}
 impl<const N: usize> Agent<N> for TestAgent {
     fn policy_and_eval(&self, game: &Game<N>) -> (Vec<f32>, f32) {
         let l = game.possible_turns().len() as f32;
-        (vec![1. / l; moves_dims(3)], 0.)
+        (vec![1. / l; moves_dims(N)], 0.)
     }
 }
 
@@ -15,16 +33,65 @@ fn mate_in_one() {
     let mut game = Game::<3>::from_ptn("1. a3 c3 2. c2 a2").unwrap();
     let mut node = Node::default();
     for _ in 0..1000 {
-        node.rollout(game.clone(), &TestAgent {});
+        node.rollout(game.clone(), &TestAgent {}, &SearchParams::default());
+    }
+    let turn = node.pick_move(0.);
+    game.play(turn).unwrap();
+    assert_eq!(game.winner(), GameResult::Winner {
+        colour: Colour::White,
+        reason: WinReason::Road
+    })
+}
+
+#[test]
+fn proven_win_is_played() {
+    let mut game = Game::<3>::from_ptn("1. a3 c3 2. c2 a2").unwrap();
+    let mut node = Node::default();
+    // enough to visit every move once
+    for _ in 0..30 {
+        node.rollout(game.clone(), &TestAgent {}, &SearchParams::default());
     }
-    let turn = node.pick_move(true);
+    assert!(node.proven.is_some());
+    assert_eq!(node.expected_reward, -1.);
+    // even when sampling by visits
+    let turn = node.pick_move(1.);
     game.play(turn).unwrap();
     assert_eq!(game.winner(), GameResult::Winner {
         colour: Colour::White,
-        road: true
+        reason: WinReason::Road
     })
 }
 
+#[test]
+fn proven_losses_are_not_searched() {
+    let game = Game::<3>::from_ptn("1. a3 c3 2. c2").unwrap();
+    let mut node = Node::default();
+    let search = |node: &mut Node<3>| {
+        for _ in 0..300 {
+            node.rollout(game.clone(), &TestAgent {}, &SearchParams::default());
+        }
+    };
+    // replies which let white complete the road are refuted and then left
+    // alone
+    let proven_losses = |node: &Node<3>| -> Vec<_> {
+        node.children
+            .as_ref()
+            .unwrap()
+            .iter()
+            .filter(|(_, child)| child.is_proven_loss())
+            .map(|(turn, child)| (turn.clone(), child.visited_count))
+            .collect()
+    };
+    search(&mut node);
+    let before = proven_losses(&node);
+    assert!(!before.is_empty());
+    search(&mut node);
+    let after = proven_losses(&node);
+    for (turn, visits) in before {
+        assert!(after.contains(&(turn, visits)));
+    }
+}
+
 #[test]
 fn prevent_mate_in_two() {
     let mut game = Game::<3>::from_ptn("1. a3 c3 2. c2").unwrap();
@@ -32,18 +99,18 @@ fn prevent_mate_in_two() {
 
     // black move
     for _ in 0..1000 {
-        node.rollout(game.clone(), &TestAgent {});
+        node.rollout(game.clone(), &TestAgent {}, &SearchParams::default());
     }
-    let turn = node.pick_move(true);
+    let turn = node.pick_move(0.);
     node = node.play(&turn);
     game.play(turn).unwrap();
     assert_eq!(game.winner(), GameResult::Ongoing);
 
     // white move
     for _ in 0..1000 {
-        node.rollout(game.clone(), &TestAgent {});
+        node.rollout(game.clone(), &TestAgent {}, &SearchParams::default());
     }
-    let turn = node.pick_move(true);
+    let turn = node.pick_move(0.);
     let _ = node.play(&turn);
     game.play(turn).unwrap();
     assert_eq!(game.winner(), GameResult::Ongoing);
@@ -56,17 +123,467 @@ fn white_win_3s() {
 
     while matches!(game.winner(), GameResult::Ongoing) {
         for _ in 0..100_000 {
-            node.rollout(game.clone(), &TestAgent {});
+            node.rollout(game.clone(), &TestAgent {}, &SearchParams::default());
         }
         println!("{}", node.debug(None));
 
-        let turn = node.pick_move(true);
+        let turn = node.pick_move(0.);
         node = node.play(&turn);
         game.play(turn).unwrap();
     }
 
     assert_eq!(game.winner(), GameResult::Winner {
         colour: Colour::White,
-        road: true
+        reason: WinReason::Road
     });
 }
+
+#[test]
+fn move_space_matches_lut() {
+    let game = Game::<5> {
+        ply: 4,
+        ..Default::default()
+    };
+    let places = game.possible_turns();
+    assert_eq!(places.len(), place_range(5).len());
+    assert!(places
+        .iter()
+        .all(|turn| place_range(5).contains(&turn.turn_map())));
+
+    let game = Game::<5>::from_ptn("1. a1 e5 2. c3 b3 3. c3< d3").unwrap();
+    for turn in game.possible_turns() {
+        let range = match turn {
+            Turn::Place { .. } => place_range(5),
+            Turn::Move { .. } => spread_range(5),
+        };
+        assert!(range.contains(&turn.turn_map()));
+    }
+
+    assert_eq!(moves_dims(5), 1575);
+    assert_eq!(max_legal_moves(5), spread_range(5).len());
+}
+
+#[test]
+fn spread_factors_are_unique() {
+    let factors = spread_factors::<5>();
+    assert_eq!(factors.len(), spread_range(5).len());
+    assert!(factors
+        .iter()
+        .all(|&(origin, pattern)| origin < 4 * 5 * 5 && pattern < drop_patterns(5)));
+    let mut unique = factors.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(unique.len(), factors.len());
+}
+
+#[test]
+fn parallel_rollouts_find_mate() {
+    let mut game = Game::<3>::from_ptn("1. a3 c3 2. c2 a2").unwrap();
+    let mut node = Node::default();
+    node.parallel_rollouts(&game, &TestAgent {}, 1000, 4, &SearchParams::default());
+    assert_eq!(node.visited_count, 1000);
+
+    // every virtual loss was taken back
+    let mut stack = vec![&node];
+    while let Some(node) = stack.pop() {
+        assert_eq!(node.virtual_visits, 0);
+        stack.extend(node.children.iter().flat_map(|children| children.values()));
+    }
+
+    let turn = node.pick_move(0.);
+    game.play(turn).unwrap();
+    assert_eq!(game.winner(), GameResult::Winner {
+        colour: Colour::White,
+        reason: WinReason::Road
+    })
+}
+
+#[test]
+fn first_play_urgency() {
+    let game = Game::<3>::from_ptn("1. a3 c3").unwrap();
+    let mut node = Node::default();
+    node.rollout(game.clone(), &TestAgent {}, &SearchParams::default());
+    node.expected_reward = -0.5;
+    let unvisited = node.children.as_ref().unwrap().node(0).clone();
+    let neutral = SearchParams::default();
+    let reduced = SearchParams {
+        fpu_reduction: Some(0.2),
+        ..neutral
+    };
+    assert_eq!(node.first_play_urgency(&neutral), 0.);
+    assert!((node.first_play_urgency(&reduced) - 0.3).abs() < 1e-6);
+    assert!(
+        node.upper_confidence_bound(&unvisited, &reduced) > node.upper_confidence_bound(&unvisited, &neutral)
+    );
+}
+
+#[test]
+fn policy_temperature_flattens_priors() {
+    /// Puts three quarters of the policy on the first legal move.
+    struct SkewedAgent {}
+    impl<const N: usize> Agent<N> for SkewedAgent
+    where
+        Turn<N>: Lut,
+    {
+        fn policy_and_eval(&self, game: &Game<N>) -> (Vec<f32>, f32) {
+            let turns = game.possible_turns();
+            let mut policy = vec![0.25 / (turns.len() - 1) as f32; moves_dims(N)];
+            policy[turns[0].turn_map()] = 0.75;
+            (policy, 0.)
+        }
+    }
+
+    let game = Game::<3>::from_ptn("1. a3 c3").unwrap();
+    let largest_prior = |temperature| {
+        let mut node = Node::default();
+        let params = SearchParams {
+            policy_temperature: temperature,
+            ..Default::default()
+        };
+        node.rollout(game.clone(), &SkewedAgent {}, &params);
+        let children = node.children.unwrap();
+        let sum: f32 = children.values().map(|child| child.policy).sum();
+        assert!((sum - 1.).abs() < 1e-4);
+        children.values().map(|child| child.policy).fold(0., f32::max)
+    };
+    assert!((largest_prior(1.) - 0.75).abs() < 1e-6);
+    assert!(largest_prior(2.) < 0.75);
+    assert!(largest_prior(0.5) > 0.75);
+}
+
+#[test]
+fn gumbel_mate_in_one() {
+    let mut game = Game::<3>::from_ptn("1. a3 c3 2. c2 a2").unwrap();
+    let mut node = Node::default();
+    let gumbel = GumbelParams {
+        considered: 64,
+        ..Default::default()
+    };
+    let turn = node.sequential_halving(&game, &TestAgent {}, 200, &gumbel, &SearchParams::default());
+    // the winning move also gets most of the policy target
+    let policy = node.completed_policy(&gumbel);
+    assert_eq!(policy.iter().max_by_key(|(_, visits)| **visits).unwrap().0, &turn);
+    game.play(turn).unwrap();
+    assert_eq!(game.winner(), GameResult::Winner {
+        colour: Colour::White,
+        reason: WinReason::Road
+    })
+}
+
+#[test]
+fn completed_policy_with_few_visits() {
+    let game = Game::<3>::from_ptn("1. a3 c3").unwrap();
+    let mut node = Node::default();
+    node.rollout(game.clone(), &TestAgent {}, &SearchParams::default());
+    // every move keeps its prior instead of rounding to no visits
+    let policy = node.completed_policy(&GumbelParams::default());
+    assert_eq!(policy.len(), game.possible_turns().len());
+    let first = *policy.values().next().unwrap();
+    assert!(first > 0 && policy.values().all(|&visits| visits == first));
+}
+
+#[test]
+fn sequential_halving_spends_budget() {
+    let game = Game::<3>::from_ptn("1. a3 c3").unwrap();
+    let mut node = Node::default();
+    let gumbel = GumbelParams::default();
+    node.sequential_halving(&game, &TestAgent {}, 100, &gumbel, &SearchParams::default());
+    // one visit expands the root, the rest go to the sampled moves
+    assert!((90..=101).contains(&node.visited_count));
+    let visited = node
+        .children
+        .as_ref()
+        .unwrap()
+        .values()
+        .filter(|child| child.visited_count > 0)
+        .count();
+    assert_eq!(visited, gumbel.considered.min(game.possible_turns().len()));
+}
+
+#[test]
+fn solver_proves_endgame() {
+    // the walls leave white nothing but to fill the board and win on flats
+    let game = Game::<3>::from_ptn(r#"[TPS "1,2S,1/2S,1,2S/x,2S,x 2 4"]"#).unwrap();
+    let proven = |solver_depth| {
+        let params = SearchParams {
+            solver_depth,
+            ..Default::default()
+        };
+        let mut node = Node::default();
+        for _ in 0..100 {
+            node.rollout(game.clone(), &TestAgent {}, &params);
+        }
+        assert!(node.proven.is_none());
+        // children proven by the solver, not by backing up from their own
+        // children
+        let children = node.children.unwrap();
+        let solved: Vec<_> = children
+            .values()
+            .filter(|child| child.proven.is_some() && child.children.is_none())
+            .collect();
+        for child in &solved {
+            // black moved into a win for white
+            assert_eq!(child.expected_reward, -1.);
+        }
+        solved.len()
+    };
+    assert_eq!(proven(0), 0);
+    // filling a1 or c1 with a flat or a wall
+    assert_eq!(proven(2), 4);
+}
+
+#[test]
+fn play_reports_reuse() {
+    let game = Game::<3>::from_ptn("1. a3 c3").unwrap();
+    let mut node = Node::default();
+    for _ in 0..300 {
+        node.rollout(game.clone(), &TestAgent {}, &SearchParams::default());
+    }
+    let turn = node.pick_move(0.);
+    let kept = node.children.as_ref().unwrap()[&turn].visited_count;
+    let (child, stats) = node.play_with_stats(&turn);
+    assert_eq!(child.visited_count, kept);
+    assert_eq!(stats.reused_visits, kept);
+    // the root's first visit expanded it without going to a child
+    assert_eq!(stats.reused_visits + stats.discarded_visits, 299);
+    assert!(stats.discarded_nodes.map_or(true, |nodes| nodes > 0));
+}
+
+#[test]
+fn tree_round_trip() {
+    let game = Game::<3>::from_ptn("1. a3 c3 2. c2").unwrap();
+    let mut node = Node::default();
+    for _ in 0..200 {
+        node.rollout(game.clone(), &TestAgent {}, &SearchParams::default());
+    }
+    let path = std::env::temp_dir().join("tree_round_trip.tree");
+    node.save_tree(&game, &path).unwrap();
+    let loaded = Node::load_tree(&game, &path).unwrap();
+    assert_eq!(loaded.node_count(), node.node_count());
+    assert_eq!(loaded.visited_count, node.visited_count);
+    assert_eq!(loaded.expected_reward, node.expected_reward);
+    assert_eq!(loaded.improved_policy(), node.improved_policy());
+
+    let other = Game::<3>::from_ptn("1. a3 c3").unwrap();
+    assert!(Node::<3>::load_tree(&other, &path).is_err());
+}
+
+#[test]
+fn transpositions_are_expanded_without_the_network() {
+    let game = Game::<3>::from_ptn("1. a3 c3").unwrap();
+    let params = SearchParams::default();
+    let agent = CountingAgent::default();
+    let mut node = Node::default();
+    for _ in 0..500 {
+        node.rollout(game.clone(), &agent, &params);
+    }
+    let evaluations = agent.calls.get();
+
+    let transposed_agent = CountingAgent::default();
+    let mut transposed = Node::default();
+    let mut table = Transpositions::default();
+    for _ in 0..500 {
+        transposed.rollout_transposed(game.clone(), &transposed_agent, &mut table, &params);
+    }
+    // the same search, with fewer evaluations
+    assert!(transposed_agent.calls.get() < evaluations);
+    assert_eq!(table.len(), transposed_agent.calls.get());
+    assert_eq!(transposed.improved_policy(), node.improved_policy());
+    assert_eq!(transposed.expected_reward, node.expected_reward);
+}
+
+/// Like [`TestAgent`], counting how often it is asked.
+#[derive(Default)]
+struct CountingAgent {
+    calls: std::cell::Cell<usize>,
+}
+impl<const N: usize> Agent<N> for CountingAgent {
+    fn policy_and_eval(&self, game: &Game<N>) -> (Vec<f32>, f32) {
+        self.calls.set(self.calls.get() + 1);
+        TestAgent {}.policy_and_eval(game)
+    }
+}
+
+/// Puts the whole policy on placing a flat on the occupied square.
+struct OccupiedAgent {}
+impl<const N: usize> Agent<N> for OccupiedAgent
+where
+    Turn<N>: Lut,
+{
+    fn policy_and_eval(&self, game: &Game<N>) -> (Vec<f32>, f32) {
+        let pos = Pos::all().find(|&pos| game.board[pos].is_some()).unwrap();
+        let mut policy = vec![0.; moves_dims(N)];
+        policy[Turn::Place {
+            pos,
+            shape: Shape::Flat,
+        }
+        .turn_map()] = 1.;
+        (policy, 0.)
+    }
+}
+
+#[test]
+fn random_symmetry_maps_policy_back() {
+    let game = Game::<5>::from_ptn("1. b1").unwrap();
+    let expected = Turn::<5>::from_ptn("b1").unwrap().turn_map();
+    let agent = RandomSymmetry::new::<5>(&OccupiedAgent {});
+    for _ in 0..20 {
+        let (policy, _) = agent.policy_and_eval(&game);
+        assert_eq!(policy[expected], 1.);
+    }
+}
+
+#[test]
+fn adjudicated_game_has_a_result() {
+    // the black walls block every road of white, who is too far ahead on
+    // flats once down to a single stone
+    let mut game = Game::<5> {
+        board: Board::from_tps("1,1,2S,1,1/1,1,2S,1,1/2S,2S,2S,2S,2S/1,1,2S,1,x/1,1,2S,1,x").unwrap(),
+        white_stones: 2,
+        white_caps: 0,
+        black_stones: 10,
+        black_caps: 0,
+        ply: 40,
+        ..Default::default()
+    };
+    let mut player = Player::from_position(&TestAgent {}, &game);
+    while game_result(&game) == GameResult::Ongoing {
+        player.rollout(&game, 10);
+        let turn = player.pick_move(&game, 0.);
+        game.play(turn).unwrap();
+    }
+    if ADJUDICATE_DEAD_POSITIONS {
+        assert_eq!(game.winner(), GameResult::Ongoing);
+    }
+    let result = player.get_analysis().result();
+    assert_eq!(result, game_result(&game));
+    assert_eq!(player.get_examples(result).len() as u64, game.ply - 40);
+}
+
+#[test]
+fn abandoned_leaf_leaves_no_virtual_visits() {
+    let game = Game::<3>::from_ptn("1. a3 c3").unwrap();
+    let mut node = Node::default();
+    for _ in 0..50 {
+        node.rollout(game.clone(), &TestAgent {}, &SearchParams::default());
+    }
+    let leaf = match node.descend(game.clone(), &SearchParams::default()) {
+        Descent::Leaf(leaf) => leaf,
+        Descent::Terminal { .. } => panic!("expected a leaf to evaluate"),
+    };
+    assert!(!leaf.path.is_empty());
+    node.abandon(&leaf);
+    assert_eq!(node.virtual_visits, 0);
+    let child = node.play(&leaf.path[0]);
+    assert_eq!(child.virtual_visits, 0);
+    assert!(child
+        .children
+        .iter()
+        .flat_map(|children| children.values())
+        .all(|child| child.virtual_visits == 0));
+}
+
+#[test]
+fn shared_stats_wait_for_expansion() {
+    let game = Game::<3>::from_ptn("1. a3 c3").unwrap();
+    let params = SearchParams::default();
+    let mut node = Node::default();
+    node.rollout(game.clone(), &TestAgent {}, &params);
+    let turn = Turn::from_ptn("b2").unwrap();
+    let stats = [(turn.clone(), ChildStats {
+        visits: 10,
+        reward: 5.,
+    })]
+    .into_iter()
+    .collect();
+    assert_eq!(node.add_child_stats(&stats), 0);
+    assert_eq!(node.visited_count, 1);
+
+    node.rollout_forced(game, &turn, &TestAgent {}, &params);
+    assert_eq!(node.add_child_stats(&stats), 10);
+    let child = node.children.as_ref().unwrap().get(&turn).unwrap();
+    assert_eq!(child.visited_count, 11);
+    assert_eq!(child.expected_reward, 5. / 11.);
+}
+
+#[test]
+fn taken_back_move_restores_the_search() {
+    let mut game = Game::<3>::from_ptn("1. a3 c3").unwrap();
+    let mut player = Player::from_position(&TestAgent {}, &game);
+    player.rollout(&game, 100);
+    game.play(player.pick_move(&game, 0.)).unwrap();
+    player.rollout(&game, 100);
+    let tree = player.tree().clone();
+    player.pick_move(&game, 0.);
+    player.take_back(tree.clone());
+
+    assert_eq!(player.tree().visited_count, tree.visited_count);
+    let result = GameResult::Winner {
+        colour: Colour::White,
+        reason: WinReason::Road,
+    };
+    assert_eq!(player.get_examples(result).len(), 1);
+    assert_eq!(player.get_analysis().search_stats().count(), 1);
+}
+
+#[test]
+fn event_log_writes_valid_json() {
+    let path = std::env::temp_dir().join(format!("event-log-{}.jsonl", std::process::id()));
+    let turn = Turn::<5>::from_ptn("a1").unwrap();
+    {
+        let mut log = EventLog::create(&path).unwrap();
+        log.rollout(3, &[turn.clone()], f32::NAN, false);
+        log.forced(3, &turn, f32::INFINITY);
+        log.decision(3, &turn, 10, 0.5);
+    }
+    let lines = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let events: Vec<serde_json::Value> = lines
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.len(), 3);
+    assert!(events[0]["value"].is_null());
+    assert!(events[1]["value"].is_null());
+    assert_eq!(events[2]["move"], "a1");
+}
+
+#[test]
+fn parallel_rollouts_batch_leaves() {
+    /// Like [`TestAgent`], remembering the largest batch it was asked.
+    #[derive(Default)]
+    struct BatchAgent {
+        largest: AtomicUsize,
+    }
+    impl<const N: usize> Agent<N> for BatchAgent {
+        fn policy_and_eval(&self, game: &Game<N>) -> (Vec<f32>, f32) {
+            TestAgent {}.policy_and_eval(game)
+        }
+
+        fn policy_and_eval_batch(&self, games: &[Game<N>]) -> (Vec<Vec<f32>>, Vec<f32>) {
+            self.largest.fetch_max(games.len(), Ordering::Relaxed);
+            games.iter().map(|game| self.policy_and_eval(game)).unzip()
+        }
+    }
+
+    let agent = BatchAgent::default();
+    let mut node = Node::default();
+    node.parallel_rollouts(&Game::<5>::default(), &agent, 100, 1, &SearchParams::default());
+    assert_eq!(node.visited_count, 100);
+    assert_eq!(node.virtual_visits, 0);
+    assert!(agent.largest.load(Ordering::Relaxed) > 1);
+}
+
+#[test]
+fn parallel_rollouts_prove_leaves() {
+    // one empty square is left, so every leaf is in reach of the solver
+    let game = Game::<3>::from_ptn("1. a1 c3 2. b1 a3 3. Sc1 b3 4. a2 c2").unwrap();
+    let mut node = Node::default();
+    node.parallel_rollouts(&game, &TestAgent {}, 100, 2, &SearchParams::default());
+    assert_eq!(node.visited_count, 100);
+    assert!(node
+        .children
+        .unwrap()
+        .values()
+        .any(|child| child.proven.is_some()));
+}
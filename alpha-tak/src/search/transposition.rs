@@ -0,0 +1,78 @@
+//! Network evaluations shared between nodes of the same position reached by
+//! different move orders, so that a transposition is expanded without asking
+//! the network again.
+//!
+//! Nodes are not shared, so each move order still keeps its own visits. The
+//! table only remembers how each position was expanded, which is the
+//! expensive part of visiting it, see [`Node::rollout_transposed`].
+
+use std::collections::HashMap;
+
+use tak::*;
+
+use super::{children::Children, node::Node};
+use crate::metrics::METRICS;
+
+/// How a position was expanded.
+#[derive(Clone, Debug)]
+struct Entry {
+    eval: f32,
+    /// Priors of the children in the order of [`Game::possible_turns`].
+    priors: Box<[f32]>,
+}
+
+/// Expansions of positions by their Zobrist hash.
+#[derive(Debug, Default)]
+pub struct Transpositions {
+    entries: HashMap<u64, Entry>,
+}
+
+impl Transpositions {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Forget every position, for example once a move is played and most of
+    /// them cannot be reached any more.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Remember how the node was just expanded with `eval`.
+    pub(super) fn record<const N: usize>(&mut self, hash: u64, node: &Node<N>, eval: f32) {
+        if let Some(children) = &node.children {
+            self.entries.insert(hash, Entry {
+                eval,
+                priors: children.values().map(|child| child.policy).collect(),
+            });
+        }
+    }
+
+    /// Expand the node like its position was expanded before, returning the
+    /// eval, or `None` if the position is not known.
+    pub(super) fn expand<const N: usize>(
+        &self,
+        hash: u64,
+        node: &mut Node<N>,
+        game: &Game<N>,
+    ) -> Option<f32> {
+        let entry = self.entries.get(&hash)?;
+        let turns = game.possible_turns();
+        // a hash collision, which is not worth more than a fresh evaluation
+        if turns.len() != entry.priors.len() {
+            return None;
+        }
+        let mut children = Children::with_capacity(turns.len());
+        for (turn, &prior) in turns.into_iter().zip(entry.priors.iter()) {
+            children.insert(turn, Node::init(prior));
+        }
+        node.expected_reward = -entry.eval;
+        node.children = Some(children);
+        METRICS.transposed_expansions.inc_by(1);
+        Some(entry.eval)
+    }
+}
@@ -34,13 +34,16 @@ where
     for y in 0..N {
         for x in 0..N {
             let pos = Pos { x, y };
-            board[pos] = Some(Tile {
-                top: Piece {
-                    colour: Colour::White,
-                    shape: Shape::Flat,
-                },
-                stack: repeat(Colour::White).take(N).collect(),
-            });
+            board.set(
+                pos,
+                Some(Tile {
+                    top: Piece {
+                        colour: Colour::White,
+                        shape: Shape::Flat,
+                    },
+                    stack: repeat(Colour::White).take(N).collect(),
+                }),
+            );
         }
     }
     let game = Game {
@@ -58,8 +61,10 @@ where
     map
 }
 
-pub trait Lut {
+pub trait Lut: Sized {
     fn turn_map(&self) -> usize;
+    /// Every turn of the policy vector, in the order of their indices.
+    fn all_turns() -> Vec<Self>;
 }
 
 macro_rules! impl_lut {
@@ -70,6 +75,12 @@ macro_rules! impl_lut {
                     .get(self)
                     .unwrap_or_else(|| panic!("could not map turn to index. {:?}", self))
             }
+
+            fn all_turns() -> Vec<Self> {
+                let mut turns: Vec<_> = $lut.iter().collect();
+                turns.sort_unstable_by_key(|(_, &index)| index);
+                turns.into_iter().map(|(turn, _)| turn.clone()).collect()
+            }
         }
     };
 }
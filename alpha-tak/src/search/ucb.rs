@@ -1,16 +1,53 @@
-use super::node::Node;
-use crate::config::{EXPLORATION_BASE, EXPLORATION_INIT};
+use super::{node::Node, params::SearchParams};
 
-pub fn exploration_rate(n: f32) -> f32 {
-    ((1.0 + n + EXPLORATION_BASE) / EXPLORATION_BASE).ln() + EXPLORATION_INIT
+pub fn exploration_rate(n: f32, params: &SearchParams) -> f32 {
+    ((1.0 + n + params.cpuct_base) / params.cpuct_base).ln() + params.cpuct
 }
 
 impl<const N: usize> Node<N> {
-    pub fn upper_confidence_bound(&self, child: &Node<N>) -> f32 {
+    pub fn upper_confidence_bound(&self, child: &Node<N>, params: &SearchParams) -> f32 {
+        let expected_reward = if child.visited_count == 0 {
+            self.first_play_urgency(params)
+        } else {
+            child.expected_reward
+        };
+        self.upper_confidence_bound_with(child, expected_reward, params)
+    }
+
+    /// Expected reward of children which were not visited yet, from the
+    /// perspective of the player to move.
+    pub fn first_play_urgency(&self, params: &SearchParams) -> f32 {
+        match params.fpu_reduction {
+            // the reward of this node is from the perspective of the player
+            // who moved into it
+            Some(reduction) => -self.expected_reward - reduction,
+            None => 0.,
+        }
+    }
+
+    /// Upper confidence bound with the expected reward of the child taken from
+    /// elsewhere, like the first play urgency.
+    pub fn upper_confidence_bound_with(
+        &self,
+        child: &Node<N>,
+        expected_reward: f32,
+        params: &SearchParams,
+    ) -> f32 {
+        // there is no need to search a move which is known to lose, unless
+        // every move does
+        if child.is_proven_loss() {
+            return f32::NEG_INFINITY;
+        }
+        let visits = (self.visited_count + self.virtual_visits) as f32;
+        let child_visits = (child.visited_count + child.virtual_visits) as f32;
+        // every rollout still going through the child counts as a loss
+        let expected_reward = if child.virtual_visits == 0 {
+            expected_reward
+        } else {
+            (child.visited_count as f32 * expected_reward - child.virtual_visits as f32) / child_visits
+        };
         // U(s, a) = Q(s, a) + C(s) * P(s, a) * sqrt(N(s)) / (1 + N(s, a))
-        child.expected_reward
-            + exploration_rate(self.visited_count as f32)
-                * child.policy
-                * ((self.visited_count as f32).sqrt() / (1.0 + child.visited_count as f32))
+        expected_reward
+            + exploration_rate(visits, params) * child.policy * (visits.sqrt() / (1.0 + child_visits))
     }
 }
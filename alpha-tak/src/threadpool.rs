@@ -1,125 +1,139 @@
 use std::{
     cmp::min,
-    sync::mpsc::{channel, Receiver, Sender},
+    collections::VecDeque,
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+        Mutex,
+    },
     thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
 use arrayvec::ArrayVec;
 use tak::Game;
+use tracing::info;
 
-use crate::{agent::Batcher, model::network::Network};
+use crate::{agent::Batcher, metrics::METRICS, model::network::Network};
 
 // This code is still ugly
 // TODO rewrite again
 
-pub fn thread_pool<const N: usize, const WORKERS: usize, F, O>(
+/// How long to wait for the rest of the active games before evaluating a
+/// partial batch.
+const BATCH_TIMEOUT: Duration = Duration::from_millis(2);
+
+pub fn thread_pool<const N: usize, F, O>(
     network: &Network<N>,
     number_of_games: usize,
+    workers: usize,
     func: F,
 ) -> Vec<O>
 where
     F: Fn(&Batcher<N>, usize) -> O + Copy + Send + 'static,
     O: Send + 'static,
 {
-    let mut workers: ArrayVec<_, WORKERS> = ArrayVec::new();
-    let mut game_receivers: ArrayVec<_, WORKERS> = ArrayVec::new();
-    let mut policy_senders: ArrayVec<_, WORKERS> = ArrayVec::new();
+    thread_pool_indices(network, (0..number_of_games).collect(), workers, func)
+}
 
-    // initialize workers
-    let mut index = 0;
-    for _ in 0..min(WORKERS, number_of_games) {
-        workers.push(Some(new_worker(
-            func,
-            &mut game_receivers,
-            &mut policy_senders,
-            None,
-            index,
-        )));
-        index += 1;
-    }
+/// Like [`thread_pool`], but only runs `func` for the given game indices.
+///
+/// Each of the `workers` threads takes the next game from a shared queue as
+/// soon as its last one is finished, and positions are gathered until every
+/// thread still playing has asked for one, so batches hold every active game
+/// instead of whichever few arrived first.
+pub fn thread_pool_indices<const N: usize, F, O>(
+    network: &Network<N>,
+    indices: Vec<usize>,
+    workers: usize,
+    func: F,
+) -> Vec<O>
+where
+    F: Fn(&Batcher<N>, usize) -> O + Copy + Send + 'static,
+    O: Send + 'static,
+{
+    let number_of_games = indices.len();
+    let workers = min(workers, number_of_games);
+    let queue = Arc::new(Mutex::new(VecDeque::from(indices)));
+    let (output_tx, output_rx) = channel();
+    let (game_tx, game_rx) = channel();
 
-    let mut completed_games = 0;
-    let mut outputs = Vec::new();
-    while completed_games < number_of_games || workers.iter().any(|worker| worker.is_some()) {
-        // collect game states
-        let mut communicators = [false; WORKERS];
-        let mut batch = Vec::with_capacity(WORKERS);
-        for (i, rx) in game_receivers.iter().enumerate() {
-            if let Ok(game) = rx.try_recv() {
-                communicators[i] = true;
-                batch.push(game);
+    // initialize workers, they play games until the queue is empty
+    let mut handles = Vec::with_capacity(workers);
+    let mut policy_senders = Vec::with_capacity(workers);
+    for worker in 0..workers {
+        let (policy_tx, policy_rx) = channel();
+        policy_senders.push(policy_tx);
+        let queue = queue.clone();
+        let output_tx = output_tx.clone();
+        let batcher = Batcher::new(worker, game_tx.clone(), policy_rx);
+        handles.push(Some(thread::spawn(move || {
+            while let Some(index) = next_index(&queue) {
+                output_tx.send(func(&batcher, index)).unwrap();
             }
+        })));
+    }
+    drop(output_tx);
+    drop(game_tx);
+
+    let mut outputs = Vec::with_capacity(number_of_games);
+    loop {
+        for output in output_rx.try_iter() {
+            outputs.push(output);
+            METRICS.games_completed.inc_by(1);
+            info!("{}/{number_of_games} games completed", outputs.len());
+        }
+        if outputs.len() == number_of_games {
+            break;
         }
 
-        if !batch.is_empty() {
-            // run prediction
-            let (policies, evals) = network.policy_eval_batch(&batch);
+        // track when threads run out of games
+        for maybe_handle in &mut handles {
+            if maybe_handle.as_ref().map_or(false, JoinHandle::is_finished) {
+                maybe_handle.take().unwrap().join().unwrap();
+            }
+        }
+        let active = handles.iter().filter(|handle| handle.is_some()).count();
+        METRICS.active_games.set(active as f64);
 
-            // send out outputs
-            for (i, r) in communicators
-                .into_iter()
-                .enumerate()
-                .filter(|(_, communicated)| *communicated)
-                .map(|(i, _)| i)
-                .zip(policies.into_iter().zip(evals.into_iter()))
-            {
-                policy_senders[i].send(r).unwrap();
+        // collect game states until every active game is waiting, each
+        // worker waits for its output before sending another one
+        let deadline = Instant::now() + BATCH_TIMEOUT;
+        let mut communicators = Vec::with_capacity(active);
+        let mut batch = Vec::with_capacity(active);
+        while batch.len() < active {
+            match game_rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok((worker, game)) => {
+                    communicators.push(worker);
+                    batch.push(game);
+                }
+                Err(_) => break,
             }
         }
+        if batch.is_empty() {
+            continue;
+        }
+        METRICS.positions_evaluated.inc_by(batch.len() as u64);
+        METRICS.batches_evaluated.inc_by(1);
+        METRICS.batch_occupancy.set(batch.len() as f64 / workers as f64);
 
-        for (i, maybe_handle) in workers.iter_mut().enumerate() {
-            // track when threads finish
-            if let Some(handle) = std::mem::take(maybe_handle) {
-                *maybe_handle = if handle.is_finished() {
-                    completed_games += 1;
-                    println!("{completed_games}/{number_of_games}");
-                    outputs.push(handle.join().unwrap());
+        // run prediction
+        let (policies, evals) = network.policy_eval_batch(&batch);
 
-                    // start a new thread when one finishes
-                    if completed_games + WORKERS <= number_of_games + 1 {
-                        index += 1;
-                        Some(new_worker(
-                            func,
-                            &mut game_receivers,
-                            &mut policy_senders,
-                            Some(i),
-                            index - 1,
-                        ))
-                    } else {
-                        None
-                    }
-                } else {
-                    Some(handle)
-                };
-            }
+        // send out outputs
+        for (i, r) in communicators
+            .into_iter()
+            .zip(policies.into_iter().zip(evals.into_iter()))
+        {
+            policy_senders[i].send(r).unwrap();
         }
     }
 
     outputs
 }
 
-fn new_worker<const N: usize, const WORKERS: usize, F, O>(
-    func: F,
-    game_receivers: &mut ArrayVec<Receiver<Game<N>>, WORKERS>,
-    policy_senders: &mut ArrayVec<Sender<(Vec<f32>, f32)>, WORKERS>,
-    overwrite: Option<usize>,
-    index: usize,
-) -> JoinHandle<O>
-where
-    F: Fn(&Batcher<N>, usize) -> O + Send + 'static,
-    O: Send + 'static,
-{
-    let (game_tx, game_rx) = channel();
-    let (policy_tx, policy_rx) = channel();
-    if let Some(i) = overwrite {
-        game_receivers[i] = game_rx;
-        policy_senders[i] = policy_tx;
-    } else {
-        game_receivers.push(game_rx);
-        policy_senders.push(policy_tx);
-    }
-    let batcher = Batcher::new(game_tx, policy_rx);
-    thread::spawn(move || func(&batcher, index))
+fn next_index(queue: &Mutex<VecDeque<usize>>) -> Option<usize> {
+    queue.lock().unwrap().pop_front()
 }
 
 pub fn thread_pool_2<const N: usize, const WORKERS: usize, F, O>(
@@ -132,22 +146,22 @@ where
     F: Fn(&Batcher<N>, &Batcher<N>, usize) -> O + Copy + Send + 'static,
     O: Send + 'static,
 {
+    let (game_tx_1, game_rx_1) = channel();
+    let (game_tx_2, game_rx_2) = channel();
     let mut workers: ArrayVec<_, WORKERS> = ArrayVec::new();
-    let mut game_receivers_1: ArrayVec<_, WORKERS> = ArrayVec::new();
-    let mut game_receivers_2: ArrayVec<_, WORKERS> = ArrayVec::new();
     let mut policy_senders_1: ArrayVec<_, WORKERS> = ArrayVec::new();
     let mut policy_senders_2: ArrayVec<_, WORKERS> = ArrayVec::new();
 
     // initialize workers
     let mut index = 0;
-    for _ in 0..min(WORKERS, number_of_games) {
+    for slot in 0..min(WORKERS, number_of_games) {
         workers.push(Some(new_worker_2(
             func,
-            &mut game_receivers_1,
-            &mut game_receivers_2,
+            &game_tx_1,
+            &game_tx_2,
             &mut policy_senders_1,
             &mut policy_senders_2,
-            None,
+            slot,
             index,
         )));
         index += 1;
@@ -156,62 +170,16 @@ where
     let mut completed_games = 0;
     let mut outputs = Vec::new();
     while completed_games < number_of_games || workers.iter().any(|worker| worker.is_some()) {
-        // collect game states
-        let mut communicators = [false; WORKERS];
-        let mut batch = Vec::with_capacity(WORKERS);
-        for (i, rx) in game_receivers_1.iter().enumerate() {
-            if let Ok(game) = rx.try_recv() {
-                communicators[i] = true;
-                batch.push(game);
-            }
-        }
-        if !batch.is_empty() {
-            // run prediction
-            let (policies, evals) = network_1.policy_eval_batch(&batch);
-
-            // send out outputs
-            for (i, r) in communicators
-                .into_iter()
-                .enumerate()
-                .filter(|(_, communicated)| *communicated)
-                .map(|(i, _)| i)
-                .zip(policies.into_iter().zip(evals.into_iter()))
-            {
-                policy_senders_1[i].send(r).unwrap();
-            }
-        }
-
-        // collect game states
-        let mut communicators = [false; WORKERS];
-        let mut batch = Vec::with_capacity(WORKERS);
-        for (i, rx) in game_receivers_2.iter().enumerate() {
-            if let Ok(game) = rx.try_recv() {
-                communicators[i] = true;
-                batch.push(game);
-            }
-        }
-        if !batch.is_empty() {
-            // run prediction
-            let (policies, evals) = network_2.policy_eval_batch(&batch);
-
-            // send out outputs
-            for (i, r) in communicators
-                .into_iter()
-                .enumerate()
-                .filter(|(_, communicated)| *communicated)
-                .map(|(i, _)| i)
-                .zip(policies.into_iter().zip(evals.into_iter()))
-            {
-                policy_senders_2[i].send(r).unwrap();
-            }
-        }
+        evaluate_waiting(network_1, &game_rx_1, &policy_senders_1);
+        evaluate_waiting(network_2, &game_rx_2, &policy_senders_2);
 
         for (i, maybe_handle) in workers.iter_mut().enumerate() {
             // track when threads finish
             if let Some(handle) = std::mem::take(maybe_handle) {
                 *maybe_handle = if handle.is_finished() {
                     completed_games += 1;
-                    println!("{completed_games}/{number_of_games}");
+                    METRICS.games_completed.inc_by(1);
+                    info!("{completed_games}/{number_of_games} games completed");
                     outputs.push(handle.join().unwrap());
 
                     // start a new thread when one finishes
@@ -219,11 +187,11 @@ where
                         index += 1;
                         Some(new_worker_2(
                             func,
-                            &mut game_receivers_1,
-                            &mut game_receivers_2,
+                            &game_tx_1,
+                            &game_tx_2,
                             &mut policy_senders_1,
                             &mut policy_senders_2,
-                            Some(i),
+                            i,
                             index - 1,
                         ))
                     } else {
@@ -234,40 +202,64 @@ where
                 };
             }
         }
+        METRICS
+            .active_games
+            .set(workers.iter().filter(|worker| worker.is_some()).count() as f64);
     }
 
     outputs
 }
 
+/// Evaluate the positions the workers sent so far.
+fn evaluate_waiting<const N: usize>(
+    network: &Network<N>,
+    game_rx: &Receiver<(usize, Game<N>)>,
+    policy_senders: &[Sender<(Vec<f32>, f32)>],
+) {
+    let (communicators, batch): (Vec<_>, Vec<_>) = game_rx.try_iter().unzip();
+    if batch.is_empty() {
+        return;
+    }
+    METRICS.positions_evaluated.inc_by(batch.len() as u64);
+    METRICS.batches_evaluated.inc_by(1);
+
+    // run prediction
+    let (policies, evals) = network.policy_eval_batch(&batch);
+
+    // send out outputs
+    for (i, r) in communicators
+        .into_iter()
+        .zip(policies.into_iter().zip(evals.into_iter()))
+    {
+        policy_senders[i].send(r).unwrap();
+    }
+}
+
+/// Start a worker in `slot`, replacing the channels of a finished worker
+/// there.
 fn new_worker_2<const N: usize, const WORKERS: usize, F, O>(
     func: F,
-    game_receivers_1: &mut ArrayVec<Receiver<Game<N>>, WORKERS>,
-    game_receivers_2: &mut ArrayVec<Receiver<Game<N>>, WORKERS>,
+    game_tx_1: &Sender<(usize, Game<N>)>,
+    game_tx_2: &Sender<(usize, Game<N>)>,
     policy_senders_1: &mut ArrayVec<Sender<(Vec<f32>, f32)>, WORKERS>,
     policy_senders_2: &mut ArrayVec<Sender<(Vec<f32>, f32)>, WORKERS>,
-    overwrite: Option<usize>,
+    slot: usize,
     index: usize,
 ) -> JoinHandle<O>
 where
     F: Fn(&Batcher<N>, &Batcher<N>, usize) -> O + Send + 'static,
     O: Send + 'static,
 {
-    let (game_tx_1, game_rx_1) = channel();
-    let (game_tx_2, game_rx_2) = channel();
     let (policy_tx_1, policy_rx_1) = channel();
     let (policy_tx_2, policy_rx_2) = channel();
-    if let Some(i) = overwrite {
-        game_receivers_1[i] = game_rx_1;
-        game_receivers_2[i] = game_rx_2;
-        policy_senders_1[i] = policy_tx_1;
-        policy_senders_2[i] = policy_tx_2;
+    if slot < policy_senders_1.len() {
+        policy_senders_1[slot] = policy_tx_1;
+        policy_senders_2[slot] = policy_tx_2;
     } else {
-        game_receivers_1.push(game_rx_1);
-        game_receivers_2.push(game_rx_2);
         policy_senders_1.push(policy_tx_1);
         policy_senders_2.push(policy_tx_2);
     }
-    let batcher_1 = Batcher::new(game_tx_1, policy_rx_1);
-    let batcher_2 = Batcher::new(game_tx_2, policy_rx_2);
+    let batcher_1 = Batcher::new(slot, game_tx_1.clone(), policy_rx_1);
+    let batcher_2 = Batcher::new(slot, game_tx_2.clone(), policy_rx_2);
     thread::spawn(move || func(&batcher_1, &batcher_2, index))
 }
@@ -0,0 +1,162 @@
+use std::{collections::BTreeMap, error::Error};
+
+use alpha_tak::{agent::Agent, search::turn_map::Lut};
+use serde_json::{json, Map, Value};
+use tak::*;
+
+use crate::{effort::Effort, games::read_games};
+
+/// How often moves matched the engine, up to its third choice.
+const TOP_CHOICES: usize = 3;
+
+/// How closely the moves of one player matched the engine.
+#[derive(Default, Debug)]
+pub struct Agreement {
+    pub positions: usize,
+    /// Positions where the move was the engine's first, second, or third
+    /// choice.
+    pub matches: [usize; TOP_CHOICES],
+    /// Sum of the eval lost compared to the engine's choice.
+    pub eval_loss: f32,
+}
+
+impl Agreement {
+    /// Fraction of moves within the engine's top `choices` moves.
+    pub fn match_rate(&self, choices: usize) -> f32 {
+        self.matches[..choices].iter().sum::<usize>() as f32 / self.positions.max(1) as f32
+    }
+
+    /// Average eval lost per move in hundredths of the eval range.
+    pub fn average_centi_eval_loss(&self) -> f32 {
+        100. * self.eval_loss / self.positions.max(1) as f32
+    }
+}
+
+/// Rank the moves in a position, by visits or by the raw policy when the
+/// effort has no rollouts.
+fn ranked_moves<const N: usize, A: Agent<N>>(agent: &A, game: &Game<N>, effort: Effort) -> Vec<Turn<N>>
+where
+    Turn<N>: Lut,
+{
+    let mut moves: Vec<(Turn<N>, f32)> = if effort.is_raw_policy() {
+        let (policy, _) = agent.policy_and_eval(game);
+        game.possible_turns()
+            .into_iter()
+            .map(|turn| {
+                let p = policy[turn.turn_map()];
+                (turn, p)
+            })
+            .collect()
+    } else {
+        effort
+            .search(agent, game)
+            .children
+            .unwrap()
+            .into_iter()
+            .map(|(turn, child)| (turn, child.visited_count as f32))
+            .collect()
+    };
+    moves.sort_by(|a, b| b.1.total_cmp(&a.1));
+    moves.into_iter().map(|(turn, _)| turn).collect()
+}
+
+/// Eval after playing a turn, from the perspective of the player who played
+/// it.
+pub fn eval_after<const N: usize, A: Agent<N>>(agent: &A, game: &Game<N>, turn: &Turn<N>) -> f32 {
+    let mut game = game.clone();
+    game.play(turn.clone()).unwrap();
+    match game.winner() {
+        GameResult::Winner { colour, .. } => {
+            if colour == game.to_move {
+                -1.
+            } else {
+                1.
+            }
+        }
+        GameResult::Draw { .. } => 0.,
+        GameResult::Ongoing => -agent.policy_and_eval(&game).1,
+    }
+}
+
+/// Compare the moves of each player in the games with the engine's choices.
+/// Players are named by the `Player1` and `Player2` tags.
+pub fn agreement<const N: usize, A: Agent<N>>(
+    agent: &A,
+    paths: &[String],
+    effort: Effort,
+) -> Result<BTreeMap<String, Agreement>, Box<dyn Error>>
+where
+    Turn<N>: Lut,
+    [[Option<Tile>; N]; N]: Default,
+{
+    let mut players: BTreeMap<String, Agreement> = BTreeMap::new();
+    for (_, ptn) in read_games::<N>(paths)? {
+        let tags = ptn_tags(&ptn);
+        let name = |tag: &str, default: &str| {
+            tags.iter()
+                .find(|(key, _)| key == tag)
+                .map_or_else(|| default.to_string(), |(_, value)| value.clone())
+        };
+        let (white, black) = (name("Player1", "white"), name("Player2", "black"));
+
+        let (mut game, turns) = Game::<N>::from_ptn_with_turns(&ptn)?;
+        for turn in turns {
+            // the first two plies are placed for the opponent
+            if game.ply >= 2 && matches!(game.winner(), GameResult::Ongoing) {
+                let ranked = ranked_moves(agent, &game, effort);
+                let player = if game.to_move == Colour::White {
+                    &white
+                } else {
+                    &black
+                };
+                let stats = players.entry(player.clone()).or_default();
+                stats.positions += 1;
+                let rank = ranked.iter().position(|t| t == &turn);
+                match rank {
+                    Some(rank) if rank < TOP_CHOICES => stats.matches[rank] += 1,
+                    _ => {}
+                }
+                if rank != Some(0) {
+                    let best = eval_after(agent, &game, &ranked[0]);
+                    let played = eval_after(agent, &game, &turn);
+                    stats.eval_loss += (best - played).max(0.);
+                }
+            }
+            game.play(turn)?;
+        }
+    }
+    Ok(players)
+}
+
+/// The same report as [`print_agreement`] as JSON.
+pub fn agreement_json(players: &BTreeMap<String, Agreement>) -> Value {
+    players
+        .iter()
+        .map(|(name, stats)| {
+            let accuracy = json!({
+                "positions": stats.positions,
+                "top_1": stats.match_rate(1),
+                "top_2": stats.match_rate(2),
+                "top_3": stats.match_rate(3),
+                "centi_eval_loss": stats.average_centi_eval_loss(),
+            });
+            (name.clone(), accuracy)
+        })
+        .collect::<Map<_, _>>()
+        .into()
+}
+
+pub fn print_agreement(players: &BTreeMap<String, Agreement>) {
+    println!("player                 moves    top 1    top 2    top 3   loss");
+    for (name, stats) in players {
+        println!(
+            "{: <20} {: >7} {: >7.1}% {: >7.1}% {: >7.1}% {: >6.2}",
+            name,
+            stats.positions,
+            100. * stats.match_rate(1),
+            100. * stats.match_rate(2),
+            100. * stats.match_rate(3),
+            stats.average_centi_eval_loss()
+        );
+    }
+}
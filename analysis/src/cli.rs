@@ -1,11 +1,178 @@
-use clap::Parser;
+use alpha_tak::{agent::EnsembleMode, analysis::ResultPlacement, search::params::SearchParams};
+use clap::{Parser, Subcommand};
+
+use crate::output::OutputFormat;
 
 /// Train AlphaTak
 #[derive(Parser)]
 pub struct Args {
-    /// Path to model
+    /// Path to model, either saved by training or exported to ONNX
     pub model_path: String,
     /// Disable GPU usage
     #[clap(short, long)]
     pub no_gpu: bool,
+    /// Board size of the model and the games, from 3 to 8
+    #[clap(long, default_value_t = 5)]
+    pub size: usize,
+    /// Paths to more models which are searched together with the main one
+    #[clap(long)]
+    pub ensemble: Vec<String>,
+    /// How the models of an ensemble are combined: average or interleave
+    #[clap(long, default_value = "average")]
+    pub ensemble_mode: EnsembleMode,
+    /// Output format of the commands and the analysis file: text, json, or
+    /// html for a standalone page with a board viewer
+    #[clap(long, default_value = "text")]
+    pub format: OutputFormat,
+    /// PTN file to continue from instead of an empty board, it may start
+    /// from a TPS
+    #[clap(long)]
+    pub start: Option<String>,
+    /// Position to start from instead of an empty board, either a standard
+    /// TPS or one with reserves and komi as printed by the other commands
+    #[clap(long, conflicts_with = "start")]
+    pub tps: Option<String>,
+    /// Set up the starting position in a board editor, beginning from the
+    /// `--start` position if there is one
+    #[clap(long)]
+    pub edit: bool,
+    /// File to resume the search tree of the starting position from, typing
+    /// `save` writes the tree of the current position to it
+    #[clap(long)]
+    pub tree: Option<String>,
+    /// Full moves per line of the written PTN
+    #[clap(long, default_value_t = 1)]
+    pub moves_per_line: usize,
+    /// Leave out move numbers in the written PTN
+    #[clap(long)]
+    pub no_move_numbers: bool,
+    /// Where to write the game result: omit, header, moves or both
+    #[clap(long, default_value = "omit")]
+    pub result: ResultPlacement,
+    /// Comma separated headers to write first, e.g. `Komi,Size`
+    #[clap(long, default_value = "")]
+    pub header_order: String,
+    /// Longest forced win in plies to warn about while playing and to mark
+    /// as missed in the analysis, 0 disables it. 1 only finds immediate
+    /// roads, each 2 more plies multiply the cost of every position by
+    /// about the number of moves squared
+    #[clap(long, default_value_t = 1)]
+    pub threat_depth: u32,
+    /// Threads searching the tree together, more keep a GPU busier
+    #[clap(long, default_value_t = 1)]
+    pub threads: usize,
+    /// Exploration rate of the search at a node without visits
+    #[clap(long)]
+    pub cpuct: Option<f32>,
+    /// Value of unvisited moves below the value of their position, by
+    /// default they are valued as a draw
+    #[clap(long)]
+    pub fpu_reduction: Option<f32>,
+    /// Temperature of the policy in the search, above 1 flattens it
+    #[clap(long)]
+    pub policy_temperature: Option<f32>,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Args {
+    /// Search parameters from the config with the ones given as flags.
+    pub fn search_params(&self) -> SearchParams {
+        let default = SearchParams::default();
+        SearchParams {
+            cpuct: self.cpuct.unwrap_or(default.cpuct),
+            fpu_reduction: self.fpu_reduction.or(default.fpu_reduction),
+            policy_temperature: self.policy_temperature.unwrap_or(default.policy_temperature),
+            ..default
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run a suite of test positions and report the solve rate
+    Suite {
+        /// Path to the suite file, one `tps;best moves;themes` position per
+        /// line
+        path: String,
+        /// Rollouts per position
+        #[clap(short, long, default_value_t = 1000)]
+        rollouts: usize,
+    },
+    /// Find the positions in PTN games where this model and another one
+    /// disagree the most
+    Compare {
+        /// Path to the model to compare against
+        other_model_path: String,
+        /// Paths to PTN files, which may hold several games each
+        games: Vec<String>,
+        /// Rollouts used to pick a move, 0 uses the raw policy
+        #[clap(short, long, default_value_t = 0)]
+        rollouts: usize,
+        /// How many positions to show
+        #[clap(short, long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Find positions with a unique forced win in PTN games and write them as
+    /// a suite
+    Puzzles {
+        /// Path to write the suite to
+        out: String,
+        /// Paths to PTN files, which may hold several games each
+        games: Vec<String>,
+        /// Longest forced win to look for in plies, counting both players
+        #[clap(short, long, default_value_t = 3)]
+        depth: u32,
+    },
+    /// Check that exactly one move wins each puzzle of a suite and write the
+    /// ones that pass
+    Verify {
+        /// Path to the suite of candidate puzzles
+        suite: String,
+        /// Path to write the verified puzzles to
+        out: String,
+        /// Longest forced win the solver looks for in plies
+        #[clap(short, long, default_value_t = 3)]
+        depth: u32,
+        /// Rollouts of the search for longer wins by other moves
+        #[clap(short, long, default_value_t = 10000)]
+        rollouts: usize,
+    },
+    /// Report how often each player's moves matched the engine's top choices
+    Agreement {
+        /// Paths to PTN files, which may hold several games each
+        games: Vec<String>,
+        /// Rollouts used to rank moves, 0 uses the raw policy. With
+        /// `--max-rollouts` this is the least any position gets
+        #[clap(short, long, default_value_t = 0)]
+        rollouts: usize,
+        /// Most rollouts spent on a critical position
+        #[clap(long)]
+        max_rollouts: Option<usize>,
+    },
+    /// Grade the moves of PTN games, refining the grades backwards from the
+    /// end of each game
+    Review {
+        /// Paths to PTN files, which may hold several games each
+        games: Vec<String>,
+        /// Rollouts used to judge moves, 0 uses the raw policy. With
+        /// `--max-rollouts` this is the least any position gets
+        #[clap(short, long, default_value_t = 0)]
+        rollouts: usize,
+        /// Most rollouts spent on a critical position
+        #[clap(long)]
+        max_rollouts: Option<usize>,
+        /// Plies the backward pass solves positions to, giving up on those
+        /// too large to search
+        #[clap(short, long, default_value_t = 3)]
+        depth: u32,
+    },
+    /// Speak the Tak Engine Interface over stdin and stdout, for GUIs and
+    /// tournament managers
+    Tei,
+    /// Export the model to ONNX for use with onnxruntime and other engines
+    ExportOnnx {
+        /// Path to write the ONNX model to
+        out: String,
+    },
 }
@@ -0,0 +1,144 @@
+use std::error::Error;
+
+use alpha_tak::{
+    agent::Agent,
+    search::{node::Node, params::SearchParams, turn_map::Lut},
+};
+use serde_json::{json, Value};
+use tak::*;
+
+use crate::games::read_games;
+
+/// A position where two models disagree.
+pub struct Divergence<const N: usize> {
+    pub source: String,
+    pub game: Game<N>,
+    pub evals: (f32, f32),
+    pub turns: (Turn<N>, Turn<N>),
+}
+
+impl<const N: usize> Divergence<N> {
+    pub fn eval_difference(&self) -> f32 {
+        (self.evals.0 - self.evals.1).abs()
+    }
+}
+
+/// Pick a move using search, or the raw policy when `rollouts` is 0.
+fn best_move<const N: usize, A: Agent<N>>(
+    agent: &A,
+    game: &Game<N>,
+    policy: &[f32],
+    rollouts: usize,
+    params: &SearchParams,
+) -> Turn<N>
+where
+    Turn<N>: Lut,
+{
+    if rollouts == 0 {
+        return game
+            .possible_turns()
+            .into_iter()
+            .max_by(|a, b| policy[a.turn_map()].total_cmp(&policy[b.turn_map()]))
+            .unwrap();
+    }
+    let mut node = Node::default();
+    for _ in 0..rollouts {
+        node.rollout(game.clone(), agent, params);
+    }
+    node.pick_move(0.)
+}
+
+/// Evaluate every position of the games with both agents.
+pub fn compare<const N: usize, A: Agent<N>, B: Agent<N>>(
+    a: &A,
+    b: &B,
+    paths: &[String],
+    rollouts: usize,
+    params: &SearchParams,
+) -> Result<Vec<Divergence<N>>, Box<dyn Error>>
+where
+    Turn<N>: Lut,
+    [[Option<Tile>; N]; N]: Default,
+{
+    let mut divergences = Vec::new();
+    for (source, ptn) in read_games::<N>(paths)? {
+        for position in Game::<N>::ptn_positions(&ptn)? {
+            let (ply, game) = position?;
+            if !matches!(game.winner(), GameResult::Ongoing) {
+                break;
+            }
+            let (policy_a, eval_a) = a.policy_and_eval(&game);
+            let (policy_b, eval_b) = b.policy_and_eval(&game);
+            divergences.push(Divergence {
+                source: format!("{source}:{}", ply + 1),
+                evals: (eval_a, eval_b),
+                turns: (
+                    best_move(a, &game, &policy_a, rollouts, params),
+                    best_move(b, &game, &policy_b, rollouts, params),
+                ),
+                game,
+            });
+        }
+    }
+    divergences.sort_by(|x, y| y.eval_difference().total_cmp(&x.eval_difference()));
+    Ok(divergences)
+}
+
+/// Print the largest eval differences and the positions where the chosen moves
+/// differ.
+pub fn print_comparison<const N: usize>(divergences: &[Divergence<N>], limit: usize) {
+    let move_differences: Vec<_> = divergences.iter().filter(|d| d.turns.0 != d.turns.1).collect();
+    println!(
+        "compared {} positions, models chose different moves in {}",
+        divergences.len(),
+        move_differences.len()
+    );
+
+    println!("\nlargest eval differences:");
+    println!("position                  eval a   eval b   move a   move b | tps");
+    for divergence in divergences.iter().take(limit) {
+        print_divergence(divergence);
+    }
+
+    println!("\ndifferent moves with the largest eval differences:");
+    println!("position                  eval a   eval b   move a   move b | tps");
+    for divergence in move_differences.into_iter().take(limit) {
+        print_divergence(divergence);
+    }
+}
+
+/// The same report as [`print_comparison`] as JSON.
+pub fn comparison_json<const N: usize>(divergences: &[Divergence<N>], limit: usize) -> Value {
+    let move_differences: Vec<_> = divergences.iter().filter(|d| d.turns.0 != d.turns.1).collect();
+    json!({
+        "positions": divergences.len(),
+        "different_moves": move_differences.len(),
+        "largest_eval_differences": divergences.iter().take(limit).map(divergence_json).collect::<Vec<_>>(),
+        "largest_move_differences": move_differences
+            .into_iter()
+            .take(limit)
+            .map(divergence_json)
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn divergence_json<const N: usize>(divergence: &Divergence<N>) -> Value {
+    json!({
+        "source": divergence.source,
+        "tps": divergence.game.to_tps(),
+        "evals": [divergence.evals.0, divergence.evals.1],
+        "moves": [divergence.turns.0.to_ptn(), divergence.turns.1.to_ptn()],
+    })
+}
+
+fn print_divergence<const N: usize>(divergence: &Divergence<N>) {
+    println!(
+        "{: <24} {: >8.4} {: >8.4} {: >8} {: >8} | {}",
+        divergence.source,
+        divergence.evals.0,
+        divergence.evals.1,
+        divergence.turns.0.to_ptn(),
+        divergence.turns.1.to_ptn(),
+        divergence.game.to_tps()
+    );
+}
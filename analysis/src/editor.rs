@@ -0,0 +1,150 @@
+use std::io::{stdin, stdout, Write};
+
+use tak::*;
+
+const HELP: &str = "\
+commands:
+  <square> <stack>         put a stack bottom to top, e.g. `c3 121S`
+  <square> -               remove the stack on a square
+  clear                    remove every stack
+  move <1|2> [number]      set the side to move and the move number
+  reserves <1|2> <stones> <caps>
+                           set the pieces a player has left
+  komi <komi>              set the komi
+  tps                      print the position as TPS
+  done                     validate the position and analyse it
+  quit                     leave without analysing";
+
+/// Edit a position interactively, starting from `game`. Reserves follow the
+/// pieces on the board until they are set by hand. Returns the position once
+/// it is valid, or `None` if the user quits.
+pub fn edit<const N: usize>(mut game: Game<N>) -> Option<Game<N>>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    let mut manual_reserves = false;
+    println!("{HELP}");
+    loop {
+        if !manual_reserves {
+            sync_reserves(&mut game);
+        }
+        println!("{}", game.board);
+        println!("{}", game.ptn_tps());
+        print!("edit> ");
+        stdout().flush().unwrap();
+        let mut line = String::new();
+        if stdin().read_line(&mut line).unwrap_or(0) == 0 {
+            return None;
+        }
+        let words: Vec<_> = line.split_whitespace().collect();
+        let result = match words.as_slice() {
+            [] => Ok(()),
+            ["help"] => {
+                println!("{HELP}");
+                Ok(())
+            }
+            ["quit"] => return None,
+            ["done"] => match game.validate() {
+                Ok(()) => return Some(game),
+                Err(err) => Err(err),
+            },
+            ["tps"] => {
+                println!("{}", game.ptn_tps());
+                Ok(())
+            }
+            ["clear"] => {
+                game.board = Board::default();
+                Ok(())
+            }
+            ["komi", komi] => komi
+                .parse()
+                .map(|komi| game.komi = komi)
+                .map_err(|_| format!("cannot parse komi {komi}")),
+            ["move", colour, rest @ ..] => set_to_move(&mut game, colour, rest.first().copied()),
+            ["reserves", colour, stones, caps] => {
+                manual_reserves = true;
+                set_reserves(&mut game, colour, stones, caps)
+            }
+            [square, stack] => set_square(&mut game, square, stack),
+            _ => Err(format!("unknown command `{}`, try `help`", line.trim())),
+        };
+        if let Err(err) = result {
+            println!("{err}");
+        }
+    }
+}
+
+/// Take the pieces on the board from the starting reserves.
+fn sync_reserves<const N: usize>(game: &mut Game<N>) {
+    let (stones, caps) = default_starting_stones(N);
+    let left = |colour| {
+        let (used_stones, used_caps) = game.board.piece_counts(colour);
+        (
+            (stones as u32).saturating_sub(used_stones) as u8,
+            (caps as u32).saturating_sub(used_caps) as u8,
+        )
+    };
+    (game.white_stones, game.white_caps) = left(Colour::White);
+    (game.black_stones, game.black_caps) = left(Colour::Black);
+}
+
+fn set_to_move<const N: usize>(game: &mut Game<N>, colour: &str, number: Option<&str>) -> StrResult<()> {
+    let colour = Colour::from_ptn(colour)?;
+    let number = match number {
+        Some(number) => number
+            .parse::<u64>()
+            .ok()
+            .filter(|&number| number > 0)
+            .ok_or_else(|| format!("cannot parse move number {number}"))?,
+        None => game.ply / 2 + 1,
+    };
+    game.ply = (number - 1)
+        .checked_mul(2)
+        .and_then(|ply| ply.checked_add((colour == Colour::Black) as u64))
+        .ok_or_else(|| format!("move number {number} out of range"))?;
+    game.to_move = colour;
+    Ok(())
+}
+
+fn set_reserves<const N: usize>(game: &mut Game<N>, colour: &str, stones: &str, caps: &str) -> StrResult<()> {
+    let stones = stones
+        .parse()
+        .map_err(|_| format!("cannot parse stones {stones}"))?;
+    let caps = caps
+        .parse()
+        .map_err(|_| format!("cannot parse capstones {caps}"))?;
+    match Colour::from_ptn(colour)? {
+        Colour::White => (game.white_stones, game.white_caps) = (stones, caps),
+        Colour::Black => (game.black_stones, game.black_caps) = (stones, caps),
+    }
+    Ok(())
+}
+
+/// Replace the stack on a square, written like a square of a TPS.
+fn set_square<const N: usize>(game: &mut Game<N>, square: &str, stack: &str) -> StrResult<()> {
+    let pos = Pos::from_ptn(square)?;
+    if stack == "-" {
+        game.board.set(pos, None);
+        return Ok(());
+    }
+    let (colours, shape) = match stack.strip_suffix(['S', 'C']) {
+        Some(colours) => stack.split_at(colours.len()),
+        None => (stack, ""),
+    };
+    let mut colours = colours
+        .chars()
+        .map(|c| Colour::from_ptn(&c.to_string()))
+        .collect::<StrResult<Stack>>()?;
+    let top = colours.pop().ok_or_else(|| format!("empty stack {stack}"))?;
+    game.board.set(
+        pos,
+        Some(Tile {
+            top: Piece {
+                colour: top,
+                shape: Shape::from_ptn(shape)?,
+            },
+            stack: colours,
+        }),
+    );
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use alpha_tak::{
+    agent::Agent,
+    analysis::Threat,
+    search::{node::Node, params::SearchParams, turn_map::Lut},
+};
+use tak::*;
+
+/// Visit share of the best move in the pre-search above which the move is
+/// taken as obvious.
+const OBVIOUS_SHARE: f32 = 0.8;
+/// Eval gap between the two best moves of the pre-search below which the
+/// position is critical.
+const CRITICAL_GAP: f32 = 0.05;
+
+/// Bounds of the rollouts spent on one position. A quick pre-search of `min`
+/// rollouts decides how much more the position deserves: nothing for forced
+/// or obvious moves, everything up to `max` for critical ones, and an amount
+/// in between by the phase of the game otherwise.
+#[derive(Clone, Copy, Debug)]
+pub struct Effort {
+    pub min: usize,
+    pub max: usize,
+    pub params: SearchParams,
+}
+
+impl Effort {
+    /// The same number of rollouts for every position.
+    pub fn fixed(rollouts: usize, params: SearchParams) -> Self {
+        Effort {
+            min: rollouts,
+            max: rollouts,
+            params,
+        }
+    }
+
+    /// Whether moves are judged by the raw policy instead of a search.
+    pub fn is_raw_policy(&self) -> bool {
+        self.max == 0
+    }
+
+    /// Search the position with the rollouts it deserves.
+    pub fn search<const N: usize, A: Agent<N>>(&self, agent: &A, game: &Game<N>) -> Node<N>
+    where
+        Turn<N>: Lut,
+    {
+        let mut node = Node::default();
+        for _ in 0..self.min.max(1) {
+            node.rollout(game.clone(), agent, &self.params);
+        }
+        let rollouts = self.rollouts(game, &node);
+        for _ in self.min.max(1)..rollouts {
+            node.rollout(game.clone(), agent, &self.params);
+        }
+        node
+    }
+
+    /// Total rollouts for a position given its pre-search.
+    fn rollouts<const N: usize>(&self, game: &Game<N>, node: &Node<N>) -> usize {
+        if self.max <= self.min {
+            return self.min;
+        }
+        let mut children: Vec<_> = node.children.iter().flatten().map(|(_, child)| child).collect();
+        children.sort_by_key(|child| std::cmp::Reverse(child.visited_count));
+        let forced = children.len() <= 1 || Threat::find(game, 1).is_some();
+        let obvious = children.first().map_or(true, |best| {
+            best.visited_count as f32 >= OBVIOUS_SHARE * node.visited_count.max(1) as f32
+        });
+        if forced || obvious {
+            return self.min;
+        }
+        let critical = match (children.first(), children.get(1)) {
+            (Some(first), Some(second)) => {
+                (first.expected_reward - second.expected_reward).abs() < CRITICAL_GAP
+            }
+            _ => false,
+        };
+        let share = match (critical, game.phase()) {
+            (true, _) | (_, Phase::Endgame) => 1.,
+            (_, Phase::Midgame) => 0.5,
+            (_, Phase::Opening) => 0.25,
+        };
+        self.min + ((self.max - self.min) as f32 * share) as usize
+    }
+}
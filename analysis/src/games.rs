@@ -0,0 +1,36 @@
+use std::{fs::read_to_string, io};
+
+use tak::*;
+
+/// Read the games of PTN files, each of which may hold several games.
+/// Games are labelled by their path, and by their number within the file
+/// when there is more than one, e.g. `games.ptn#3`. Games whose recorded
+/// result disagrees with the rules are flagged on stderr but still used.
+pub fn read_games<const N: usize>(paths: &[String]) -> io::Result<Vec<(String, String)>>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    let mut games = Vec::new();
+    for path in paths {
+        let ptn = read_to_string(path)?;
+        let split = split_ptn_games(&ptn);
+        if split.len() == 1 {
+            games.push((path.clone(), split[0].to_string()));
+        } else {
+            games.extend(
+                split
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, game)| (format!("{path}#{}", i + 1), game.to_string())),
+            );
+        }
+    }
+    for (source, ptn) in &games {
+        if let Ok(mismatches) = Game::<N>::ptn_result_mismatches(ptn) {
+            for mismatch in mismatches {
+                eprintln!("{source}: {mismatch}");
+            }
+        }
+    }
+    Ok(games)
+}
@@ -1,16 +1,58 @@
 use std::{
-    fs::File,
-    io::{stdout, Write},
-    sync::mpsc::channel,
-    thread,
+    fs::{read_to_string, File},
+    io::Write,
+    path::Path,
 };
 
-use alpha_tak::{model::network::Network, player::Player, use_cuda};
+use agreement::{agreement, agreement_json, print_agreement};
+use alpha_tak::{
+    agent::Ensemble,
+    analysis::PtnFormat,
+    config::KOMI,
+    model::network::Network,
+    player::Player,
+    search::{params::SearchParams, turn_map::Lut},
+    sys_time,
+    use_cuda,
+};
 use clap::Parser;
-use cli::Args;
+use cli::{Args, Command};
+use compare::{compare, comparison_json, print_comparison};
+use effort::Effort;
+use output::OutputFormat;
+use puzzles::{mine, verify, Puzzle, Verdict};
+use repl::repl;
+use review::{print_reviews, review, reviews_json};
+use serde_json::json;
+use suite::{load_suite, run_suite, suite_lines};
 use tak::*;
+use tei::tei;
 
+mod agreement;
 mod cli;
+mod compare;
+mod editor;
+mod effort;
+mod games;
+mod output;
+mod puzzles;
+mod repl;
+mod review;
+mod suite;
+mod tei;
+
+/// Load a model saved by training, or an ONNX export if the path ends with
+/// `.onnx`.
+fn load_model<const N: usize>(path: &str) -> Result<Network<N>, Box<dyn std::error::Error>>
+where
+    Turn<N>: Lut,
+{
+    if path.ends_with(".onnx") {
+        Network::from_onnx(path)
+    } else {
+        Network::load(path)
+    }
+}
 
 fn main() {
     let args = Args::parse();
@@ -19,58 +61,228 @@ fn main() {
         return;
     }
 
-    let network = Network::<5>::load(&args.model_path)
-        .unwrap_or_else(|_| panic!("could not load model at {}", args.model_path));
-
-    let mut game = Game::<5>::with_komi(2);
-    let mut player = Player::new(&network, vec![], game.komi);
+    match args.size {
+        3 => run::<3>(args),
+        4 => run::<4>(args),
+        5 => run::<5>(args),
+        6 => run::<6>(args),
+        7 => run::<7>(args),
+        8 => run::<8>(args),
+        size => println!("unsupported board size {size}, it must be between 3 and 8"),
+    }
+}
 
-    while matches!(game.winner(), GameResult::Ongoing) {
-        // Get input from user.
-        let (tx, rx) = channel();
-        thread::spawn(move || {
-            tx.send(get_input()).unwrap();
-        });
+/// Everything after parsing the arguments, for a board size picked at
+/// runtime.
+fn run<const N: usize>(args: Args)
+where
+    Turn<N>: Lut,
+    [[Option<Tile>; N]; N]: Default,
+{
+    let agent = Ensemble::new(
+        std::iter::once(&args.model_path)
+            .chain(&args.ensemble)
+            .map(|path| load_model::<N>(path).unwrap_or_else(|_| panic!("could not load model at {path}")))
+            .collect(),
+        args.ensemble_mode,
+    );
 
-        loop {
-            // Do rollouts while we wait for input.
-            player.rollout(&game, 100);
+    let json = args.format == OutputFormat::Json;
+    let params = args.search_params();
+    if let Some(command) = args.command {
+        match command {
+            Command::Suite { path, rollouts } => match load_suite::<N>(&path, KOMI) {
+                Ok(suite) => run_suite(&suite, &agent, rollouts, &params, args.format),
+                Err(err) => println!("could not load suite at {path}: {err}"),
+            },
+            Command::Compare {
+                other_model_path,
+                games,
+                rollouts,
+                limit,
+            } => {
+                let other = Network::<N>::load(&other_model_path)
+                    .unwrap_or_else(|_| panic!("could not load model at {other_model_path}"));
+                match compare(&agent, &other, &games, rollouts, &params) {
+                    Ok(divergences) if json => println!("{}", comparison_json(&divergences, limit)),
+                    Ok(divergences) => print_comparison(&divergences, limit),
+                    Err(err) => println!("could not compare models: {err}"),
+                }
+            }
+            Command::Puzzles { out, games, depth } => match mine::<N>(&games, depth) {
+                Ok(puzzles) => {
+                    let suite: String = puzzles
+                        .iter()
+                        .map(|puzzle| format!("# {}\n{}\n", puzzle.source, puzzle.to_suite_line()))
+                        .collect();
+                    match File::create(&out).and_then(|mut file| file.write_all(suite.as_bytes())) {
+                        Ok(()) if json => {
+                            let puzzles: Vec<_> = puzzles.iter().map(Puzzle::to_json).collect();
+                            println!("{}", json!({ "out": out, "puzzles": puzzles }));
+                        }
+                        Ok(()) => println!("found {} puzzles", puzzles.len()),
+                        Err(err) => println!("could not write puzzles to {out}: {err}"),
+                    }
+                }
+                Err(err) => println!("could not mine puzzles: {err}"),
+            },
+            Command::Verify {
+                suite,
+                out,
+                depth,
+                rollouts,
+            } => match load_suite::<N>(&suite, KOMI) {
+                Ok(positions) => {
+                    let mut verified = String::new();
+                    let mut verdicts = Vec::new();
+                    for (line, position) in suite_lines(&suite)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .zip(&positions)
+                    {
+                        let verdict = verify(position, &agent, depth, rollouts, &params);
+                        if json {
+                            verdicts.push(json!({
+                                "tps": position.game.to_tps(),
+                                "verdict": verdict.to_string(),
+                            }));
+                        } else {
+                            println!("{: <12} {}", verdict.to_string(), position.game.to_tps());
+                        }
+                        if matches!(verdict, Verdict::Unique) {
+                            verified.push_str(&line);
+                            verified.push('\n');
+                        }
+                    }
+                    match File::create(&out).and_then(|mut file| file.write_all(verified.as_bytes())) {
+                        Ok(()) if json => println!("{}", json!({ "out": out, "puzzles": verdicts })),
+                        Ok(()) => println!(
+                            "{} of {} puzzles verified",
+                            verified.lines().count(),
+                            positions.len()
+                        ),
+                        Err(err) => println!("could not write puzzles to {out}: {err}"),
+                    }
+                }
+                Err(err) => println!("could not load suite at {suite}: {err}"),
+            },
+            Command::Agreement {
+                games,
+                rollouts,
+                max_rollouts,
+            } => match agreement::<N, _>(&agent, &games, effort(rollouts, max_rollouts, params)) {
+                Ok(players) if json => println!("{}", agreement_json(&players)),
+                Ok(players) => print_agreement(&players),
+                Err(err) => println!("could not analyse games: {err}"),
+            },
+            Command::Review {
+                games,
+                rollouts,
+                max_rollouts,
+                depth,
+            } => match review::<N, _>(&agent, &games, effort(rollouts, max_rollouts, params), depth) {
+                Ok(reviews) if json => println!("{}", reviews_json(&reviews)),
+                Ok(reviews) => print_reviews(&reviews),
+                Err(err) => println!("could not review games: {err}"),
+            },
+            Command::Tei => tei::<N, _>(&agent, &params),
+            Command::ExportOnnx { out } => {
+                let network = load_model::<N>(&args.model_path).expect("model was loaded before");
+                match network.export_onnx(&out) {
+                    Ok(()) => println!("Exported the model to {out}"),
+                    Err(err) => println!("could not export the model: {err}"),
+                }
+            }
+        }
+        return;
+    }
 
-            if let Ok(input) = rx.try_recv() {
-                clear_screen();
-                if input.chars().all(char::is_whitespace) {
-                    println!("{}", player.debug(Some(5)));
-                } else {
-                    try_play_move(&mut player, &mut game, input).unwrap_or_else(|err| println!("{err}"));
+    let (mut game, mut player) = match (&args.start, &args.tps) {
+        (_, Some(tps)) => {
+            let mut game = match Game::<N>::from_tps(tps) {
+                Ok(game) => game,
+                Err(err) => {
+                    println!("could not read TPS: {err}");
+                    return;
                 }
-                break;
+            };
+            // a standard TPS has no komi
+            if tps.split_whitespace().count() < 6 {
+                game.komi = Komi::from_flats(2);
             }
+            let player = Player::from_position(&agent, &game).with_params(params);
+            (game, player)
+        }
+        (Some(path), None) => {
+            let game = match read_to_string(path)
+                .map_err(|err| err.to_string())
+                .and_then(|ptn| Game::<N>::from_ptn(&ptn))
+            {
+                Ok(game) => game,
+                Err(err) => {
+                    println!("could not load game at {path}: {err}");
+                    return;
+                }
+            };
+            let player = Player::from_position(&agent, &game).with_params(params);
+            (game, player)
+        }
+        (None, None) => {
+            let game = Game::<N>::with_komi(Komi::from_flats(2));
+            let player = Player::new(&agent, vec![], game.komi).with_params(params);
+            (game, player)
+        }
+    };
+
+    if args.edit {
+        match editor::edit(game) {
+            Some(edited) => {
+                player = Player::from_position(&agent, &edited).with_params(params);
+                game = edited;
+            }
+            None => return,
         }
     }
 
-    if let Ok(mut file) = File::create("analysis.ptn") {
-        file.write_all(player.get_analysis().to_ptn().as_bytes()).unwrap();
-        println!("created a file `analysis.ptn` with the analysis of this game");
+    let tree_path = args.tree.clone().unwrap_or_else(|| "analysis.tree".to_string());
+    if args.tree.is_some() && Path::new(&tree_path).exists() {
+        match player.load_tree(&game, &tree_path) {
+            Ok(()) => println!("resumed the search tree from `{tree_path}`"),
+            Err(err) => eprintln!("could not load the search tree at {tree_path}: {err}"),
+        }
     }
-}
 
-fn clear_screen() {
-    print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
-    stdout().flush().unwrap()
-}
+    let mut player = repl(&agent, game, player, &tree_path, args.threat_depth, args.threads);
 
-fn get_input() -> String {
-    print!("[leave empty for network eval] your move: ");
-    std::io::stdout().flush().unwrap();
-    let mut line = String::new();
-    std::io::stdin().read_line(&mut line).unwrap();
-    line
+    let format = PtnFormat {
+        moves_per_line: args.moves_per_line,
+        move_numbers: !args.no_move_numbers,
+        result: args.result,
+        header_order: args
+            .header_order
+            .split(',')
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect(),
+        threat_depth: args.threat_depth,
+    };
+    let mut analysis = player.get_analysis();
+    analysis.set_date(sys_time());
+    let (path, contents) = match args.format {
+        OutputFormat::Json => ("analysis.json", analysis.to_json(args.threat_depth).to_string()),
+        OutputFormat::Html => ("analysis.html", analysis.to_html(args.threat_depth)),
+        OutputFormat::Text => ("analysis.ptn", analysis.to_ptn_with(&format)),
+    };
+    if let Ok(mut file) = File::create(path) {
+        file.write_all(contents.as_bytes()).unwrap();
+        println!("created a file `{path}` with the analysis of this game");
+    }
 }
 
-fn try_play_move(player: &mut Player<'_, 5, Network<5>>, game: &mut Game<5>, input: String) -> StrResult<()> {
-    let turn = Turn::from_ptn(&input)?;
-    let mut copy = game.clone();
-    copy.play(turn.clone())?;
-    player.play_move(game, &turn);
-    game.play(turn)
+fn effort(rollouts: usize, max_rollouts: Option<usize>, params: SearchParams) -> Effort {
+    Effort {
+        min: rollouts,
+        max: max_rollouts.unwrap_or(rollouts).max(rollouts),
+        params,
+    }
 }
@@ -0,0 +1,25 @@
+use std::str::FromStr;
+
+/// How the commands report their results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human readable text.
+    Text,
+    /// A single JSON document on stdout.
+    Json,
+    /// A standalone web page, only for the analysis file.
+    Html,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "html" => Ok(OutputFormat::Html),
+            _ => Err(format!("unknown format `{s}`, expected text, json or html")),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+use std::error::Error;
+
+use alpha_tak::{
+    agent::Agent,
+    search::{node::Node, params::SearchParams, turn_map::Lut},
+};
+use serde_json::{json, Value};
+use tak::*;
+
+use crate::{games::read_games, suite::SuitePosition};
+
+/// A position with a single winning idea, verified by the tinue solver.
+pub struct Puzzle<const N: usize> {
+    pub source: String,
+    pub game: Game<N>,
+    /// Winning turns, which only differ in the stone placed.
+    pub solution: Vec<Turn<N>>,
+    pub themes: Vec<String>,
+}
+
+impl<const N: usize> Puzzle<N> {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "source": self.source,
+            "tps": self.game.to_tps(),
+            "solution": self.solution.iter().map(ToPTN::to_ptn).collect::<Vec<_>>(),
+            "themes": self.themes,
+        })
+    }
+
+    /// Format the puzzle as a line of a suite file.
+    pub fn to_suite_line(&self) -> String {
+        format!(
+            "{};{};{}",
+            self.game.to_tps(),
+            self.solution
+                .iter()
+                .map(ToPTN::to_ptn)
+                .collect::<Vec<_>>()
+                .join(" "),
+            self.themes.join(",")
+        )
+    }
+}
+
+/// Whether all winning turns are the same idea, such as a flat or the capstone
+/// on the same square.
+fn is_unique<const N: usize>(winning: &[Turn<N>]) -> bool {
+    match winning {
+        [] => false,
+        [_] => true,
+        [Turn::Place { pos, .. }, rest @ ..] => rest
+            .iter()
+            .all(|turn| matches!(turn, Turn::Place { pos: other, .. } if other == pos)),
+        _ => false,
+    }
+}
+
+/// Find the shortest forced win in a position, if it is unique.
+pub fn find_puzzle<const N: usize>(game: &Game<N>, max_depth: u32) -> Option<(Vec<Turn<N>>, u32)> {
+    (1..=max_depth).step_by(2).find_map(|depth| {
+        let winning = game.winning_turns(depth);
+        if winning.is_empty() {
+            None
+        } else {
+            // a shorter win with several solutions is not a puzzle at a greater depth
+            // either
+            Some(is_unique(&winning).then(|| (winning, depth)))
+        }
+    })?
+}
+
+/// Scan PTN games for positions with a unique forced win within `max_depth`
+/// plies.
+pub fn mine<const N: usize>(paths: &[String], max_depth: u32) -> Result<Vec<Puzzle<N>>, Box<dyn Error>>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    let mut puzzles = Vec::new();
+    for (source, ptn) in read_games::<N>(paths)? {
+        for position in Game::<N>::ptn_positions(&ptn)? {
+            let (ply, game) = position?;
+            if ply >= 2 && matches!(game.winner(), GameResult::Ongoing) {
+                if let Some((solution, depth)) = find_puzzle(&game, max_depth) {
+                    let length = if depth == 1 {
+                        "win-in-1".to_string()
+                    } else {
+                        format!("tinue-{depth}")
+                    };
+                    let phase = format!("{:?}", game.phase()).to_lowercase();
+                    puzzles.push(Puzzle {
+                        source: format!("{source}:{}", ply + 1),
+                        game,
+                        solution,
+                        themes: vec![length, phase],
+                    });
+                }
+            }
+        }
+    }
+    Ok(puzzles)
+}
+
+/// Eval above which the search is treated as having found a win.
+const WINNING_EVAL: f32 = 0.9;
+
+/// Outcome of checking a candidate puzzle.
+pub enum Verdict<const N: usize> {
+    Unique,
+    /// The solver found no forced win.
+    NoWin,
+    /// The solver found several winning ideas.
+    Duals(Vec<Turn<N>>),
+    /// The solver found a different winning move than the listed one.
+    WrongSolution(Vec<Turn<N>>),
+    /// The search believes another move wins too, possibly with a longer
+    /// win than the solver looks for.
+    SuspectedDual(Turn<N>, f32),
+}
+
+impl<const N: usize> std::fmt::Display for Verdict<N> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let turns = |turns: &[Turn<N>]| turns.iter().map(ToPTN::to_ptn).collect::<Vec<_>>().join(" ");
+        match self {
+            Verdict::Unique => write!(f, "unique"),
+            Verdict::NoWin => write!(f, "no forced win"),
+            Verdict::Duals(winning) => write!(f, "duals: {}", turns(winning)),
+            Verdict::WrongSolution(winning) => write!(f, "wrong solution, winning: {}", turns(winning)),
+            Verdict::SuspectedDual(turn, eval) => {
+                write!(f, "suspected dual: {} (eval {eval:.3})", turn.to_ptn())
+            }
+        }
+    }
+}
+
+/// Check that exactly one move wins a puzzle, with the solver up to `depth`
+/// plies and a search for longer wins.
+pub fn verify<const N: usize, A: Agent<N>>(
+    position: &SuitePosition<N>,
+    agent: &A,
+    depth: u32,
+    rollouts: usize,
+    params: &SearchParams,
+) -> Verdict<N>
+where
+    Turn<N>: Lut,
+{
+    let winning = position.game.winning_turns(depth);
+    if winning.is_empty() {
+        return Verdict::NoWin;
+    }
+    if !is_unique(&winning) {
+        return Verdict::Duals(winning);
+    }
+    if !winning.iter().any(|turn| position.best_moves.contains(turn)) {
+        return Verdict::WrongSolution(winning);
+    }
+
+    let mut node = Node::default();
+    for _ in 0..rollouts {
+        node.rollout(position.game.clone(), agent, params);
+    }
+    let dual = node
+        .children
+        .iter()
+        .flatten()
+        .filter(|(turn, child)| !winning.contains(turn) && child.visited_count > 0)
+        .map(|(turn, child)| (turn, child.expected_reward))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    match dual {
+        Some((turn, eval)) if eval > WINNING_EVAL => Verdict::SuspectedDual(turn.clone(), eval),
+        _ => Verdict::Unique,
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    io::{stdout, Write},
+    str::FromStr,
+    sync::mpsc::channel,
+    thread,
+};
+
+use alpha_tak::{
+    agent::{Agent, Ensemble},
+    analysis::Threat,
+    model::network::Network,
+    player::Player,
+    search::{node::Node, play::ReuseStats, turn_map::Lut},
+};
+use tak::*;
+
+type AnalysisPlayer<'a, const N: usize> = Player<'a, N, Ensemble<Network<N>>>;
+
+/// Rollouts done between checks for input.
+const ROLLOUT_CHUNK: usize = 100;
+/// Moves listed by `topmoves` without a count.
+const DEFAULT_TOP_MOVES: usize = 5;
+const PV_MIN_VISITS: u32 = 10;
+const PV_LENGTH: usize = 20;
+
+const HELP: &str = "\
+<move>        play a move in PTN, e.g. `c3` or `2a1>11`
+undo          take back the last move
+eval          evaluation of the network and of the search
+topmoves [n]  the n most visited moves
+pv            principal variation of the search
+go <n>        search n more rollouts before showing the top moves
+save          save the search tree
+quit          stop and write the analysis
+(empty)       show the search tree";
+
+/// A line typed at the prompt.
+enum Input {
+    Show,
+    Move(String),
+    Undo,
+    Eval,
+    TopMoves(usize),
+    Pv,
+    Go(usize),
+    Save,
+    Help,
+    Quit,
+}
+
+impl FromStr for Input {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<_> = s.split_whitespace().collect();
+        let count = |word: Option<&&str>, default: Option<usize>| match word {
+            Some(word) => word.parse().map_err(|_| format!("`{word}` is not a number")),
+            None => default.ok_or_else(|| "missing number of rollouts".to_string()),
+        };
+        Ok(match words.as_slice() {
+            [] => Input::Show,
+            ["undo"] => Input::Undo,
+            ["eval"] => Input::Eval,
+            ["topmoves", rest @ ..] => Input::TopMoves(count(rest.first(), Some(DEFAULT_TOP_MOVES))?),
+            ["pv"] => Input::Pv,
+            ["go", rest @ ..] => Input::Go(count(rest.first(), None)?),
+            ["save"] => Input::Save,
+            ["help"] => Input::Help,
+            ["quit"] => Input::Quit,
+            [turn] => Input::Move(turn.to_string()),
+            _ => {
+                return Err(format!(
+                    "unknown command `{}`, type `help` for the commands",
+                    s.trim()
+                ))
+            }
+        })
+    }
+}
+
+/// Study a game interactively: moves are typed one at a time while the
+/// search keeps running in the background. Returns the player with the
+/// analysis of the moves that were kept.
+pub fn repl<'a, const N: usize>(
+    agent: &'a Ensemble<Network<N>>,
+    mut game: Game<N>,
+    mut player: AnalysisPlayer<'a, N>,
+    tree_path: &str,
+    threat_depth: u32,
+    threads: usize,
+) -> AnalysisPlayer<'a, N>
+where
+    Turn<N>: Lut,
+    [[Option<Tile>; N]; N]: Default,
+{
+    // played turns with the position before each and its search tree, which
+    // taking the turn back returns to
+    let mut history: Vec<(Turn<N>, Game<N>, Node<N>)> = Vec::new();
+
+    while matches!(game.winner(), GameResult::Ongoing) {
+        // Get input from user.
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            tx.send(get_input()).unwrap();
+        });
+
+        // Do rollouts while we wait for input.
+        let input = loop {
+            search(&mut player, &game, ROLLOUT_CHUNK, threads);
+            if let Ok(input) = rx.try_recv() {
+                break input;
+            }
+        };
+        clear_screen();
+
+        match input.parse() {
+            Ok(Input::Show) => println!("{}", player.debug(Some(5))),
+            Ok(Input::Move(ptn)) => {
+                let threat = Threat::find(&game, threat_depth);
+                let (before, tree) = (game.clone(), player.tree().clone());
+                match try_play_move(&mut player, &mut game, &ptn) {
+                    Ok((turn, reuse)) => {
+                        println!(
+                            "kept {} visits of the search, dropped {}",
+                            reuse.reused_visits, reuse.discarded_visits
+                        );
+                        if let Some(threat) = threat.filter(|threat| threat.missed_by(&turn)) {
+                            println!("{}", threat.comment(&turn));
+                        }
+                        history.push((turn, before, tree));
+                    }
+                    Err(err) => println!("{err}"),
+                }
+            }
+            Ok(Input::Undo) => match history.pop() {
+                Some((turn, before, tree)) => {
+                    game = before;
+                    player.take_back(tree);
+                    println!("took back {}", turn.to_ptn());
+                }
+                None => println!("there is no move to take back"),
+            },
+            Ok(Input::Eval) => {
+                let (_, network) = agent.policy_and_eval(&game);
+                println!(
+                    "{} to move, network {network:+.3}, search {:+.3} after {} visits",
+                    game.to_move,
+                    player.evaluation(),
+                    player.tree().visited_count
+                );
+            }
+            Ok(Input::TopMoves(count)) => print_top_moves(&player, count),
+            Ok(Input::Pv) => {
+                let pv: Vec<_> = player
+                    .tree()
+                    .continuation(PV_MIN_VISITS, PV_LENGTH)
+                    .iter()
+                    .map(ToPTN::to_ptn)
+                    .collect();
+                println!("{}", pv.join(" "));
+            }
+            Ok(Input::Go(rollouts)) => {
+                search(&mut player, &game, rollouts, threads);
+                print_top_moves(&player, DEFAULT_TOP_MOVES);
+            }
+            Ok(Input::Save) => match player.save_tree(&game, tree_path) {
+                Ok(()) => println!("saved the search tree to `{tree_path}`"),
+                Err(err) => println!("could not save the search tree: {err}"),
+            },
+            Ok(Input::Help) => println!("{HELP}"),
+            Ok(Input::Quit) => break,
+            Err(err) => println!("{err}"),
+        }
+
+        if let Some(threat) = Threat::find(&game, threat_depth) {
+            let wins: Vec<_> = threat.wins.iter().map(ToPTN::to_ptn).collect();
+            println!(
+                "{} to move has {}: {}",
+                game.to_move,
+                threat.name(),
+                wins.join(" ")
+            );
+        }
+    }
+    player
+}
+
+fn search<const N: usize>(player: &mut AnalysisPlayer<N>, game: &Game<N>, rollouts: usize, threads: usize)
+where
+    Turn<N>: Lut,
+{
+    if threads > 1 {
+        player.rollout_parallel(game, rollouts, threads);
+    } else {
+        player.rollout(game, rollouts);
+    }
+}
+
+fn print_top_moves<const N: usize>(player: &AnalysisPlayer<N>, count: usize)
+where
+    Turn<N>: Lut,
+{
+    let mut children: Vec<_> = match &player.tree().children {
+        Some(children) => children.iter().collect(),
+        None => {
+            println!("the position has not been searched yet");
+            return;
+        }
+    };
+    children.sort_by_key(|(_, child)| std::cmp::Reverse(child.visited_count));
+    for (turn, child) in children.into_iter().take(count) {
+        println!(
+            "{:<8} visits {:>7}  eval {:+.3}  policy {:.3}",
+            turn.to_ptn(),
+            child.visited_count,
+            child.expected_reward,
+            child.policy
+        );
+    }
+}
+
+fn clear_screen() {
+    print!("{esc}[2J{esc}[1;1H", esc = 27 as char);
+    stdout().flush().unwrap()
+}
+
+fn get_input() -> String {
+    print!("[a move, or `help` for the commands] > ");
+    stdout().flush().unwrap();
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line).unwrap();
+    line
+}
+
+fn try_play_move<const N: usize>(
+    player: &mut AnalysisPlayer<N>,
+    game: &mut Game<N>,
+    input: &str,
+) -> StrResult<(Turn<N>, ReuseStats)>
+where
+    Turn<N>: Lut,
+{
+    let turn = Turn::from_ptn(input)?;
+    let mut copy = game.clone();
+    copy.play(turn.clone())?;
+    let reuse = player.play_move(game, &turn);
+    game.play(turn.clone())?;
+    Ok((turn, reuse))
+}
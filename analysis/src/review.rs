@@ -0,0 +1,209 @@
+use std::error::Error;
+
+use alpha_tak::{agent::Agent, analysis::Grade, config::SOLVER_MAX_NODES, search::turn_map::Lut};
+use serde_json::{json, Value};
+use tak::*;
+
+use crate::{agreement::eval_after, effort::Effort, games::read_games};
+
+/// Evals at least this far from a draw count as decided, so they replace the
+/// engine's guess at earlier positions.
+const DECISIVE: f32 = 0.9;
+/// How many times more rollouts a position is searched again with when the
+/// end of the game disagrees with the first pass.
+const RESEARCH_FACTOR: usize = 4;
+
+/// The judgement of one played move. Evals are from the perspective of the
+/// player who moved.
+pub struct Judgement<const N: usize> {
+    pub ply: u64,
+    pub played: Turn<N>,
+    pub best: Turn<N>,
+    pub best_eval: f32,
+    pub played_eval: f32,
+    /// Grade of the forward pass, before the end of the game was known.
+    pub first_grade: Grade,
+    pub grade: Grade,
+}
+
+impl<const N: usize> Judgement<N> {
+    fn loss(&self) -> f32 {
+        (self.best_eval - self.played_eval).max(0.)
+    }
+}
+
+/// Both passes over one game.
+pub struct Review<const N: usize> {
+    pub source: String,
+    pub moves: Vec<Judgement<N>>,
+}
+
+/// Best move and its eval, by search or by the raw policy when the effort
+/// has no rollouts.
+fn best_move<const N: usize, A: Agent<N>>(agent: &A, game: &Game<N>, effort: Effort) -> (Turn<N>, f32)
+where
+    Turn<N>: Lut,
+{
+    if effort.is_raw_policy() {
+        let (policy, _) = agent.policy_and_eval(game);
+        let best = game
+            .possible_turns()
+            .into_iter()
+            .max_by(|a, b| policy[a.turn_map()].total_cmp(&policy[b.turn_map()]))
+            .unwrap();
+        let eval = eval_after(agent, game, &best);
+        return (best, eval);
+    }
+    let (best, child) = effort
+        .search(agent, game)
+        .children
+        .unwrap()
+        .into_iter()
+        .max_by_key(|(_, child)| child.visited_count)
+        .unwrap();
+    (best, child.expected_reward)
+}
+
+/// Certain value of a position for the player to move: the result of a
+/// finished game, or of best play if every line ends within `depth` plies.
+/// The solver gives up on positions too large to search, like in the tree.
+fn proven_value<const N: usize>(game: &Game<N>, depth: u32) -> Option<f32> {
+    match game.solve(depth, SOLVER_MAX_NODES)? {
+        GameResult::Winner { colour, .. } => Some(if colour == game.to_move { 1. } else { -1. }),
+        GameResult::Draw { .. } => Some(0.),
+        GameResult::Ongoing => None,
+    }
+}
+
+/// Judge every move of the games in two passes. The forward pass grades
+/// each move by the eval the engine lost compared to its own choice. The
+/// backward pass starts from the end of the game and carries decided values
+/// back: a move whose position later turned out to be lost is graded by
+/// that, and positions where it disagrees are searched again more deeply.
+pub fn review<const N: usize, A: Agent<N>>(
+    agent: &A,
+    paths: &[String],
+    effort: Effort,
+    depth: u32,
+) -> Result<Vec<Review<N>>, Box<dyn Error>>
+where
+    Turn<N>: Lut,
+    [[Option<Tile>; N]; N]: Default,
+{
+    let mut reviews = Vec::new();
+    for (source, ptn) in read_games::<N>(paths)? {
+        // forward pass
+        let (mut game, turns) = Game::<N>::from_ptn_with_turns(&ptn)?;
+        let mut positions = Vec::new();
+        let mut moves = Vec::new();
+        for turn in turns {
+            if !matches!(game.winner(), GameResult::Ongoing) {
+                break;
+            }
+            // the first two plies are placed for the opponent
+            if game.ply < 2 {
+                game.play(turn)?;
+                continue;
+            }
+            positions.push(game.clone());
+            let (best, best_eval) = best_move(agent, &game, effort);
+            let played_eval = if best == turn {
+                best_eval
+            } else {
+                eval_after(agent, &game, &turn)
+            };
+            let grade = Grade::from_loss((best_eval - played_eval).max(0.));
+            moves.push(Judgement {
+                ply: game.ply,
+                played: turn.clone(),
+                best,
+                best_eval,
+                played_eval,
+                first_grade: grade,
+                grade,
+            });
+            game.play(turn)?;
+        }
+
+        // backward pass, `known` is the value of the next position for the
+        // player to move there
+        let mut known = proven_value(&game, depth);
+        for (judgement, position) in moves.iter_mut().zip(&positions).rev() {
+            if let Some(value) = known.filter(|value| value.abs() >= DECISIVE) {
+                let played_eval = -value;
+                if Grade::from_loss((played_eval - judgement.played_eval).abs()) >= Grade::Mistake {
+                    // the first pass misjudged this position, look again
+                    let (best, best_eval) = best_move(
+                        agent,
+                        position,
+                        Effort::fixed(effort.max.max(1) * RESEARCH_FACTOR, effort.params),
+                    );
+                    if best != judgement.played || best_eval > judgement.best_eval {
+                        judgement.best = best;
+                        judgement.best_eval = best_eval;
+                    }
+                }
+                judgement.played_eval = played_eval;
+            }
+            let proven = proven_value(position, depth);
+            if proven == Some(-1.) {
+                // nothing better was available
+                judgement.best_eval = judgement.played_eval;
+            }
+            judgement.best_eval = judgement.best_eval.max(judgement.played_eval);
+            judgement.grade = Grade::from_loss(judgement.loss());
+            known = proven.or(Some(judgement.best_eval));
+        }
+
+        reviews.push(Review { source, moves });
+    }
+    Ok(reviews)
+}
+
+pub fn print_reviews<const N: usize>(reviews: &[Review<N>]) {
+    for review in reviews {
+        println!("{}", review.source);
+        println!("  ply   played     best   played eval   best eval   first pass   grade");
+        for judgement in review
+            .moves
+            .iter()
+            .filter(|j| j.grade != Grade::Good || j.first_grade != j.grade)
+        {
+            println!(
+                "{: >5} {: >8} {: >8} {: >13.3} {: >11.3} {: >12} {: >7}",
+                judgement.ply + 1,
+                judgement.played.to_ptn(),
+                judgement.best.to_ptn(),
+                judgement.played_eval,
+                judgement.best_eval,
+                judgement.first_grade,
+                judgement.grade
+            );
+        }
+    }
+}
+
+/// The same report as [`print_reviews`] as JSON, with every move.
+pub fn reviews_json<const N: usize>(reviews: &[Review<N>]) -> Value {
+    reviews
+        .iter()
+        .map(|review| {
+            let moves: Vec<_> = review
+                .moves
+                .iter()
+                .map(|judgement| {
+                    json!({
+                        "ply": judgement.ply,
+                        "played": judgement.played.to_ptn(),
+                        "best": judgement.best.to_ptn(),
+                        "played_eval": judgement.played_eval,
+                        "best_eval": judgement.best_eval,
+                        "first_grade": judgement.first_grade.to_string(),
+                        "grade": judgement.grade.to_string(),
+                    })
+                })
+                .collect();
+            json!({ "source": review.source, "moves": moves })
+        })
+        .collect()
+}
@@ -0,0 +1,228 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::read_to_string,
+    time::{Duration, Instant},
+};
+
+use alpha_tak::{
+    agent::Agent,
+    search::{node::Node, params::SearchParams, turn_map::Lut},
+};
+use serde_json::json;
+use tak::*;
+
+use crate::output::OutputFormat;
+
+/// Rollouts between checks of the currently preferred move.
+const ROLLOUT_CHUNK: usize = 50;
+
+pub struct SuitePosition<const N: usize> {
+    pub game: Game<N>,
+    pub best_moves: Vec<Turn<N>>,
+    pub themes: Vec<String>,
+}
+
+/// The position lines of a suite file, in the order [`load_suite`] loads them.
+pub fn suite_lines(path: &str) -> std::io::Result<Vec<String>> {
+    Ok(read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(ToString::to_string)
+        .collect())
+}
+
+/// Load a suite of test positions.
+/// Each line looks like `tps;best moves;themes`, for example
+/// `x5/x5/x5/x5/x5 1 1;a1 e1;opening`. Empty lines and lines starting with `#`
+/// are ignored.
+pub fn load_suite<const N: usize>(path: &str, komi: Komi) -> Result<Vec<SuitePosition<N>>, Box<dyn Error>>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    suite_lines(path)?
+        .into_iter()
+        .map(|line| -> Result<_, Box<dyn Error>> {
+            let mut chunks = line.split(';');
+            let tps = chunks.next().ok_or("missing position")?;
+            let mut game = Game::from_ptn(&format!("[TPS \"{tps}\"]"))?;
+            game.komi = komi;
+            let best_moves = chunks
+                .next()
+                .ok_or("missing best moves")?
+                .split_whitespace()
+                .map(Turn::from_ptn)
+                .collect::<StrResult<Vec<_>>>()?;
+            let themes = chunks
+                .next()
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|theme| !theme.is_empty())
+                .map(ToString::to_string)
+                .collect();
+            Ok(SuitePosition {
+                game,
+                best_moves,
+                themes,
+            })
+        })
+        .collect()
+}
+
+/// Search the position and return the picked move and
+/// the time after which the search settled on a correct move, if it did.
+fn solve<const N: usize, A: Agent<N>>(
+    position: &SuitePosition<N>,
+    agent: &A,
+    rollouts: usize,
+    params: &SearchParams,
+) -> (Turn<N>, Option<Duration>)
+where
+    Turn<N>: Lut,
+{
+    let start = Instant::now();
+    let mut node = Node::default();
+    let mut solved_at = None;
+    let mut done = 0;
+    while done < rollouts {
+        let amount = ROLLOUT_CHUNK.min(rollouts - done);
+        for _ in 0..amount {
+            node.rollout(position.game.clone(), agent, params);
+        }
+        done += amount;
+
+        if position.best_moves.contains(&node.pick_move(0.)) {
+            solved_at.get_or_insert_with(|| start.elapsed());
+        } else {
+            solved_at = None;
+        }
+    }
+    (node.pick_move(0.), solved_at)
+}
+
+#[derive(Default)]
+struct Tally {
+    solved: usize,
+    total: usize,
+}
+
+impl Tally {
+    fn add(&mut self, solved: bool) {
+        self.total += 1;
+        if solved {
+            self.solved += 1;
+        }
+    }
+
+    fn rate(&self) -> f64 {
+        100. * self.solved as f64 / self.total as f64
+    }
+}
+
+/// Run the suite with a fixed amount of rollouts per position and print a
+/// report.
+pub fn run_suite<const N: usize, A: Agent<N>>(
+    suite: &[SuitePosition<N>],
+    agent: &A,
+    rollouts: usize,
+    params: &SearchParams,
+    format: OutputFormat,
+) where
+    Turn<N>: Lut,
+{
+    let mut overall = Tally::default();
+    let mut themes: HashMap<&str, Tally> = HashMap::new();
+    let mut solve_times = Vec::new();
+    let mut results = Vec::new();
+
+    for (i, position) in suite.iter().enumerate() {
+        let (turn, solved_at) = solve(position, agent, rollouts, params);
+        let solved = solved_at.is_some();
+        overall.add(solved);
+        for theme in &position.themes {
+            themes.entry(theme).or_default().add(solved);
+        }
+
+        let expected = position
+            .best_moves
+            .iter()
+            .map(ToPTN::to_ptn)
+            .collect::<Vec<_>>()
+            .join(" ");
+        if format == OutputFormat::Json {
+            results.push(json!({
+                "tps": position.game.to_tps(),
+                "move": turn.to_ptn(),
+                "expected": expected,
+                "themes": position.themes,
+                "solved_in": solved_at.map(|time| time.as_secs_f32()),
+            }));
+            solve_times.extend(solved_at);
+            continue;
+        }
+        match solved_at {
+            Some(time) => {
+                solve_times.push(time);
+                println!(
+                    "{i: >4} solved   {: <8} in {:.3}s [{}]",
+                    turn.to_ptn(),
+                    time.as_secs_f32(),
+                    position.themes.join(", ")
+                );
+            }
+            None => println!(
+                "{i: >4} FAILED   {: <8} expected {expected} [{}]",
+                turn.to_ptn(),
+                position.themes.join(", ")
+            ),
+        }
+    }
+
+    if format == OutputFormat::Json {
+        let themes: serde_json::Map<_, _> = themes
+            .iter()
+            .map(|(theme, tally)| {
+                (
+                    theme.to_string(),
+                    json!({"solved": tally.solved, "total": tally.total}),
+                )
+            })
+            .collect();
+        let average = (!solve_times.is_empty())
+            .then(|| (solve_times.iter().sum::<Duration>() / solve_times.len() as u32).as_secs_f32());
+        let report = json!({
+            "rollouts": rollouts,
+            "solved": overall.solved,
+            "total": overall.total,
+            "average_solve_time": average,
+            "themes": themes,
+            "positions": results,
+        });
+        println!("{report}");
+        return;
+    }
+
+    println!(
+        "\nsolved {}/{} ({:.1}%) with {rollouts} rollouts per position",
+        overall.solved,
+        overall.total,
+        overall.rate()
+    );
+    if !solve_times.is_empty() {
+        let average = solve_times.iter().sum::<Duration>() / solve_times.len() as u32;
+        println!("average time to solution: {:.3}s", average.as_secs_f32());
+    }
+
+    let mut themes: Vec<_> = themes.into_iter().collect();
+    themes.sort_by_key(|(theme, _)| *theme);
+    for (theme, tally) in themes {
+        println!(
+            "{theme: <16} {: >4}/{: <4} ({:.1}%)",
+            tally.solved,
+            tally.total,
+            tally.rate()
+        );
+    }
+}
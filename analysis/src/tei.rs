@@ -0,0 +1,215 @@
+//! The Tak Engine Interface, a protocol like UCI spoken over stdin and stdout,
+//! so that GUIs and tournament managers can run the engine directly.
+
+use std::{
+    collections::VecDeque,
+    io::{stdin, stdout, BufRead, Write},
+    sync::mpsc::{channel, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+use alpha_tak::{
+    agent::Agent,
+    config::KOMI,
+    search::{node::Node, params::SearchParams, turn_map::Lut},
+};
+use tak::*;
+
+/// Rollouts between checks of the clock and for `stop`.
+const ROLLOUT_CHUNK: usize = 100;
+/// Fraction of the remaining time spent on a move when playing on a clock.
+const TIME_SHARE: u32 = 20;
+/// Nodes with at most this many visits end the principal variation.
+const PV_MIN_VISITS: u32 = 10;
+const PV_LENGTH: usize = 10;
+
+/// When a search stops.
+enum Limit {
+    Nodes(usize),
+    Time(Duration),
+    Infinite,
+}
+
+/// Answer TEI commands until `quit` or the end of the input.
+pub fn tei<const N: usize, A: Agent<N>>(agent: &A, params: &SearchParams)
+where
+    Turn<N>: Lut,
+    [[Option<Tile>; N]; N]: Default,
+{
+    // read input on another thread so that a search can be stopped
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        for line in stdin().lock().lines().flatten() {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut pending = VecDeque::new();
+    let mut komi = KOMI;
+    let mut game = Game::<N>::with_komi(komi);
+    while let Some(line) = pending.pop_front().or_else(|| rx.recv().ok()) {
+        let words: Vec<_> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["tei"] => {
+                send("id name AlphaTak");
+                send(&format!(
+                    "option name HalfKomi type spin default {} min 0 max 20",
+                    KOMI.half_flats()
+                ));
+                send("teiok");
+            }
+            ["isready"] => send("readyok"),
+            ["teinewgame", size] => {
+                if *size != N.to_string() {
+                    send(&format!("info string only size {N} is supported, not {size}"));
+                }
+                game = Game::with_komi(komi);
+            }
+            ["setoption", "name", "HalfKomi", "value", value] => match value.parse() {
+                Ok(half_komi) => komi = Komi::from_half_flats(half_komi),
+                Err(_) => send(&format!("info string unsupported half komi {value}")),
+            },
+            ["position", rest @ ..] => match position(rest, komi) {
+                Ok(position) => game = position,
+                Err(err) => send(&format!("info string invalid position: {err}")),
+            },
+            // there is nothing to search after the end of the game
+            ["go", ..] if !matches!(game.winner(), GameResult::Ongoing) => {
+                send("info string the game is already over");
+                send("bestmove none");
+            }
+            ["go", rest @ ..] => {
+                let limit = limit(rest, game.to_move);
+                let turn = search(agent, &game, limit, params, &rx, &mut pending);
+                send(&format!("bestmove {}", turn.to_ptn()));
+            }
+            ["quit"] => break,
+            // a search which already finished has nothing to stop
+            ["stop"] | [] => {}
+            _ => send(&format!("info string unknown command {line}")),
+        }
+    }
+}
+
+fn send(line: &str) {
+    println!("{line}");
+    stdout().flush().unwrap();
+}
+
+/// Parse `startpos` or `tps <tps>`, optionally followed by `moves` and the
+/// moves played from there.
+fn position<const N: usize>(words: &[&str], komi: Komi) -> StrResult<Game<N>>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    let (start, moves) = match words.iter().position(|word| *word == "moves") {
+        Some(i) => (&words[..i], &words[i + 1..]),
+        None => (words, &[][..]),
+    };
+    let mut game = match start {
+        ["startpos"] => Game::with_komi(komi),
+        ["tps", tps @ ..] => {
+            let mut game = Game::from_tps(&tps.join(" "))?;
+            game.komi = komi;
+            game
+        }
+        _ => return Err(format!("expected startpos or tps, got {}", start.join(" "))),
+    };
+    for turn in moves {
+        game.play(Turn::from_ptn(turn)?)?;
+    }
+    Ok(game)
+}
+
+/// Parse the arguments of `go`. With a clock a share of the remaining time is
+/// used, plus most of the increment.
+fn limit(words: &[&str], to_move: Colour) -> Limit {
+    let mut limit = Limit::Infinite;
+    let (mut time, mut increment) = (None, Duration::ZERO);
+    for pair in words.chunks(2) {
+        let value = pair.get(1).and_then(|value| value.parse::<u64>().ok());
+        match (pair[0], value, to_move) {
+            ("nodes", Some(nodes), _) => limit = Limit::Nodes(nodes as usize),
+            ("movetime", Some(millis), _) => limit = Limit::Time(Duration::from_millis(millis)),
+            ("wtime", Some(millis), Colour::White) | ("btime", Some(millis), Colour::Black) => {
+                time = Some(Duration::from_millis(millis))
+            }
+            ("winc", Some(millis), Colour::White) | ("binc", Some(millis), Colour::Black) => {
+                increment = Duration::from_millis(millis)
+            }
+            _ => {}
+        }
+    }
+    match (&limit, time) {
+        (Limit::Infinite, Some(time)) => Limit::Time(time / TIME_SHARE + increment * 3 / 4),
+        _ => limit,
+    }
+}
+
+/// Search until the limit is reached or `stop` arrives, keeping any other
+/// commands for later.
+fn search<const N: usize, A: Agent<N>>(
+    agent: &A,
+    game: &Game<N>,
+    limit: Limit,
+    params: &SearchParams,
+    rx: &Receiver<String>,
+    pending: &mut VecDeque<String>,
+) -> Turn<N>
+where
+    Turn<N>: Lut,
+{
+    let start = Instant::now();
+    let mut last_info = start;
+    let mut node = Node::default();
+    let mut rollouts = 0;
+    loop {
+        let chunk = match limit {
+            Limit::Nodes(nodes) => ROLLOUT_CHUNK.min(nodes.saturating_sub(rollouts)).max(1),
+            _ => ROLLOUT_CHUNK,
+        };
+        for _ in 0..chunk {
+            node.rollout(game.clone(), agent, params);
+        }
+        rollouts += chunk;
+
+        let mut stop = match limit {
+            Limit::Nodes(nodes) => rollouts >= nodes,
+            Limit::Time(time) => start.elapsed() >= time,
+            Limit::Infinite => false,
+        };
+        for line in rx.try_iter() {
+            match line.trim() {
+                "stop" => stop = true,
+                "isready" => send("readyok"),
+                _ => pending.push_back(line),
+            }
+        }
+        if stop {
+            info(&node, rollouts, start);
+            return node.pick_move(0.);
+        }
+        if last_info.elapsed() >= Duration::from_secs(1) {
+            info(&node, rollouts, start);
+            last_info = Instant::now();
+        }
+    }
+}
+
+fn info<const N: usize>(node: &Node<N>, rollouts: usize, start: Instant) {
+    let pv = node.continuation(PV_MIN_VISITS, PV_LENGTH);
+    let score = pv
+        .front()
+        .and_then(|turn| node.children.as_ref()?.get(turn))
+        .map_or(0., |child| child.expected_reward);
+    let pv: Vec<_> = pv.iter().map(ToPTN::to_ptn).collect();
+    send(&format!(
+        "info nodes {rollouts} time {} score cp {} pv {}",
+        start.elapsed().as_millis(),
+        (100. * score).round() as i32,
+        pv.join(" ")
+    ));
+}
@@ -0,0 +1,7 @@
+pub mod queue;
+pub mod service;
+pub mod worker;
+
+pub mod proto {
+    tonic::include_proto!("alphatak.coordinator.v1");
+}
@@ -0,0 +1,129 @@
+use std::{path::PathBuf, time::Duration};
+
+use alpha_tak::{
+    config::{FILTERS, KOMI, N, RES_BLOCKS},
+    init_logging,
+    model::network::Network,
+    use_cuda,
+};
+use clap::{Parser, Subcommand};
+use coordinator::{
+    proto::{coordinator_server::CoordinatorServer, Task},
+    queue::WorkQueue,
+    service::CoordinatorService,
+    worker::work,
+};
+use inference::client::RemoteAgent;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tonic::transport::Server;
+use tracing::info;
+
+/// Distribute self-play games over many machines
+#[derive(Parser)]
+struct Args {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Hand out games to workers and collect their examples
+    Serve {
+        /// Amount of games to play
+        games: u64,
+        /// Directory to write shards to
+        out_dir: PathBuf,
+        /// Address to listen on
+        #[clap(long, default_value = "0.0.0.0:50052")]
+        addr: String,
+        /// Seed for drawing openings
+        #[clap(long, default_value_t = 0)]
+        seed: u64,
+        /// Seconds after which the tasks of a silent worker are handed out
+        /// again
+        #[clap(long, default_value_t = 600)]
+        timeout: u64,
+    },
+    /// Play games handed out by a coordinator
+    Work {
+        /// Address of the coordinator, e.g. http://host:50052
+        addr: String,
+        /// Path to model, unless using a remote inference server
+        #[clap(long)]
+        model_path: Option<String>,
+        /// Address of an inference server to use instead of a local model
+        #[clap(long, conflicts_with = "model_path")]
+        remote: Option<String>,
+        /// Name to identify this worker in the coordinator logs
+        #[clap(long, default_value = "worker")]
+        name: String,
+        /// Residual blocks of the model, for distilled models
+        #[clap(long, default_value_t = RES_BLOCKS)]
+        blocks: usize,
+        /// Filters of the model, for distilled models
+        #[clap(long, default_value_t = FILTERS)]
+        filters: i64,
+    },
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    init_logging();
+
+    match args.command {
+        Command::Serve {
+            games,
+            out_dir,
+            addr,
+            seed,
+            timeout,
+        } => {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let tasks = (0..games)
+                .map(|id| Task {
+                    id,
+                    size: N as u32,
+                    half_komi: KOMI.half_flats() as i32,
+                    opening: rng.gen(),
+                    seed,
+                })
+                .collect();
+            let queue = WorkQueue::new(tasks, Duration::from_secs(timeout));
+            let service = std::sync::Arc::new(CoordinatorService::new(queue, out_dir)?);
+
+            let addr = addr.parse()?;
+            info!("Coordinating {games} games on {addr}");
+            tokio::runtime::Runtime::new()?.block_on(async {
+                let finished = service.clone();
+                Server::builder()
+                    .add_service(CoordinatorServer::from_arc(service))
+                    .serve_with_shutdown(addr, async move { finished.finished.notified().await })
+                    .await
+            })?;
+            info!("All games completed");
+        }
+        Command::Work {
+            addr,
+            model_path,
+            remote,
+            name,
+            blocks,
+            filters,
+        } => match (model_path, remote) {
+            (_, Some(remote)) => {
+                let agent = RemoteAgent::<N>::connect(remote)?;
+                // one thread per position of a batch, so that batches fill up
+                let threads = agent.batch_size();
+                info!("Searching with {threads} threads");
+                work(addr, name, &agent, threads)?
+            }
+            (Some(model_path), None) => {
+                info!("CUDA: {}", use_cuda());
+                let network = Network::<N>::load_with_size(model_path, blocks, filters)?;
+                work(addr, name, &network, 1)?
+            }
+            (None, None) => return Err("either a model path or a remote inference server is required".into()),
+        },
+    }
+    Ok(())
+}
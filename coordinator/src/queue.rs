@@ -0,0 +1,186 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::proto::Task;
+
+/// Tasks waiting for, leased to, or completed by workers.
+/// Tasks of workers that stop sending heartbeats go back to the front of the
+/// queue.
+#[derive(Debug)]
+pub struct WorkQueue {
+    total: usize,
+    pending: VecDeque<Task>,
+    /// Task id to leased task and worker id.
+    leases: HashMap<u64, (Task, u64)>,
+    /// Worker id to last time it was heard from.
+    workers: HashMap<u64, Instant>,
+    completed: HashSet<u64>,
+    /// Tasks whose results are being written, see [`WorkQueue::claim`].
+    claimed: HashSet<u64>,
+    next_worker: u64,
+    timeout: Duration,
+}
+
+impl WorkQueue {
+    pub fn new(tasks: Vec<Task>, timeout: Duration) -> Self {
+        WorkQueue {
+            total: tasks.len(),
+            pending: tasks.into(),
+            leases: HashMap::new(),
+            workers: HashMap::new(),
+            completed: HashSet::new(),
+            claimed: HashSet::new(),
+            next_worker: 0,
+            timeout,
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn register(&mut self, now: Instant) -> u64 {
+        let id = self.next_worker;
+        self.next_worker += 1;
+        self.workers.insert(id, now);
+        id
+    }
+
+    /// Returns whether the worker is still known.
+    pub fn heartbeat(&mut self, worker: u64, now: Instant) -> bool {
+        self.expire(now);
+        match self.workers.get_mut(&worker) {
+            Some(last_seen) => {
+                *last_seen = now;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Lease the next task to a worker, if it is known and there is one.
+    pub fn lease(&mut self, worker: u64, now: Instant) -> Option<Task> {
+        if !self.heartbeat(worker, now) {
+            return None;
+        }
+        let task = self.pending.pop_front()?;
+        self.leases.insert(task.id, (task.clone(), worker));
+        Some(task)
+    }
+
+    /// Mark a task as completed. Returns false if it was already completed.
+    /// Results from workers which timed out are still accepted.
+    pub fn complete(&mut self, task_id: u64) -> bool {
+        if self.completed.contains(&task_id) || task_id as usize >= self.total {
+            return false;
+        }
+        self.claimed.remove(&task_id);
+        self.leases.remove(&task_id);
+        self.pending.retain(|task| task.id != task_id);
+        self.completed.insert(task_id)
+    }
+
+    /// Reserve a task for a result which is about to be written, so that a
+    /// second result of the same task is not. Returns false if the task is
+    /// unknown, completed, or already claimed.
+    pub fn claim(&mut self, task_id: u64) -> bool {
+        (task_id as usize) < self.total && !self.completed.contains(&task_id) && self.claimed.insert(task_id)
+    }
+
+    /// Give up a claim after the result could not be written.
+    pub fn release(&mut self, task_id: u64) {
+        self.claimed.remove(&task_id);
+    }
+
+    /// Forget workers which timed out and requeue their tasks.
+    pub fn expire(&mut self, now: Instant) {
+        let timeout = self.timeout;
+        self.workers
+            .retain(|_, last_seen| now.saturating_duration_since(*last_seen) <= timeout);
+        let expired: Vec<_> = self
+            .leases
+            .iter()
+            .filter(|(_, (_, worker))| !self.workers.contains_key(worker))
+            .map(|(&id, _)| id)
+            .collect();
+        for id in expired {
+            let (task, _) = self.leases.remove(&id).unwrap();
+            self.pending.push_front(task);
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.completed.len() == self.total
+    }
+
+    /// Completed, leased, and pending task counts.
+    pub fn progress(&self) -> (usize, usize, usize) {
+        (self.completed.len(), self.leases.len(), self.pending.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::WorkQueue;
+    use crate::proto::Task;
+
+    fn tasks(amount: u64) -> Vec<Task> {
+        (0..amount)
+            .map(|id| Task {
+                id,
+                size: 5,
+                half_komi: 4,
+                opening: id,
+                seed: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hands_out_every_task_once() {
+        let now = Instant::now();
+        let mut queue = WorkQueue::new(tasks(3), Duration::from_secs(60));
+        let worker = queue.register(now);
+        let ids: Vec<_> = (0..3).map(|_| queue.lease(worker, now).unwrap().id).collect();
+        assert_eq!(ids, vec![0, 1, 2]);
+        assert!(queue.lease(worker, now).is_none());
+        assert!(ids.into_iter().all(|id| queue.complete(id)));
+        assert!(queue.is_finished());
+    }
+
+    #[test]
+    fn requeues_tasks_of_lost_workers() {
+        let now = Instant::now();
+        let timeout = Duration::from_secs(60);
+        let mut queue = WorkQueue::new(tasks(2), timeout);
+        let lost = queue.register(now);
+        assert_eq!(queue.lease(lost, now).unwrap().id, 0);
+
+        let later = now + 2 * timeout;
+        let worker = queue.register(later);
+        assert_eq!(queue.lease(worker, later).unwrap().id, 0);
+        assert!(!queue.heartbeat(lost, later));
+        assert!(queue.lease(lost, later).is_none());
+
+        // a late result from the lost worker still counts, but only once
+        assert!(queue.complete(0));
+        assert!(!queue.complete(0));
+        assert_eq!(queue.progress(), (1, 0, 1));
+    }
+
+    #[test]
+    fn claims_each_task_once() {
+        let mut queue = WorkQueue::new(tasks(2), Duration::from_secs(60));
+        assert!(!queue.claim(2));
+        assert!(queue.claim(0));
+        assert!(!queue.claim(0));
+        queue.release(0);
+        assert!(queue.claim(0));
+        assert!(queue.complete(0));
+        assert!(!queue.claim(0));
+    }
+}
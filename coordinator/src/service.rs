@@ -0,0 +1,118 @@
+use std::{
+    fs::{create_dir_all, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Instant,
+};
+
+use tokio::sync::Notify;
+use tonic::{Request, Response, Status};
+use tracing::info;
+
+use crate::{
+    proto::{
+        coordinator_server::Coordinator,
+        GetTaskRequest,
+        GetTaskResponse,
+        HeartbeatRequest,
+        HeartbeatResponse,
+        RegisterRequest,
+        RegisterResponse,
+        Shard,
+        SubmitResponse,
+    },
+    queue::WorkQueue,
+};
+
+/// Hands out tasks and writes submitted shards to `out_dir`.
+pub struct CoordinatorService {
+    queue: Mutex<WorkQueue>,
+    out_dir: PathBuf,
+    /// Notified once all tasks are completed.
+    pub finished: Notify,
+}
+
+impl CoordinatorService {
+    pub fn new(queue: WorkQueue, out_dir: PathBuf) -> std::io::Result<Self> {
+        create_dir_all(&out_dir)?;
+        Ok(CoordinatorService {
+            queue: Mutex::new(queue),
+            out_dir,
+            finished: Notify::new(),
+        })
+    }
+
+    fn queue(&self) -> Result<std::sync::MutexGuard<WorkQueue>, Status> {
+        self.queue.lock().map_err(|_| Status::internal("queue poisoned"))
+    }
+}
+
+fn write_shard(out_dir: &Path, shard: &Shard) -> io::Result<()> {
+    File::create(out_dir.join(format!("{}.txt", shard.task_id)))?.write_all(shard.examples.as_bytes())?;
+    File::create(out_dir.join(format!("{}.ptn", shard.task_id)))?.write_all(shard.ptn.as_bytes())
+}
+
+#[tonic::async_trait]
+impl Coordinator for CoordinatorService {
+    async fn register(
+        &self,
+        request: Request<RegisterRequest>,
+    ) -> Result<Response<RegisterResponse>, Status> {
+        let mut queue = self.queue()?;
+        let worker_id = queue.register(Instant::now());
+        info!("Registered worker {worker_id} ({})", request.into_inner().name);
+        Ok(Response::new(RegisterResponse {
+            worker_id,
+            timeout_secs: queue.timeout().as_secs(),
+        }))
+    }
+
+    async fn heartbeat(
+        &self,
+        request: Request<HeartbeatRequest>,
+    ) -> Result<Response<HeartbeatResponse>, Status> {
+        let known = self
+            .queue()?
+            .heartbeat(request.into_inner().worker_id, Instant::now());
+        Ok(Response::new(HeartbeatResponse { known }))
+    }
+
+    async fn get_task(&self, request: Request<GetTaskRequest>) -> Result<Response<GetTaskResponse>, Status> {
+        let mut queue = self.queue()?;
+        let task = queue.lease(request.into_inner().worker_id, Instant::now());
+        Ok(Response::new(GetTaskResponse {
+            task,
+            finished: queue.is_finished(),
+        }))
+    }
+
+    async fn submit_shard(&self, request: Request<Shard>) -> Result<Response<SubmitResponse>, Status> {
+        let shard = request.into_inner();
+        let (task_id, worker_id) = (shard.task_id, shard.worker_id);
+        if !self.queue()?.claim(task_id) {
+            return Ok(Response::new(SubmitResponse { accepted: false }));
+        }
+
+        // the task only counts as completed once the shard is on disk
+        let out_dir = self.out_dir.clone();
+        let written = tokio::task::spawn_blocking(move || write_shard(&out_dir, &shard))
+            .await
+            .unwrap_or_else(|err| Err(io::Error::new(io::ErrorKind::Other, err)));
+        let mut queue = self.queue()?;
+        if let Err(err) = written {
+            queue.release(task_id);
+            return Err(Status::internal(format!("could not write shard: {err}")));
+        }
+        queue.complete(task_id);
+        let (completed, leased, pending) = queue.progress();
+        info!(
+            "Task {} done by worker {}: {completed} completed, {leased} leased, {pending} pending",
+            task_id, worker_id
+        );
+        if queue.is_finished() {
+            self.finished.notify_one();
+        }
+        Ok(Response::new(SubmitResponse { accepted: true }))
+    }
+}
@@ -0,0 +1,132 @@
+use std::{error::Error, thread, time::Duration};
+
+use alpha_tak::{
+    agent::Agent,
+    analysis::Analysis,
+    config::{DIRICHLET_NOISE, NOISE_PLIES, NOISE_RATIO, ROLLOUTS_PER_MOVE, TEMPERATURE, TEMPERATURE_PLIES},
+    example::{examples_to_string, Example},
+    game_result,
+    player::Player,
+    search::turn_map::Lut,
+};
+use tak::*;
+use tokio::runtime::Runtime;
+use tonic::transport::Channel;
+use tracing::info;
+
+use crate::proto::{
+    coordinator_client::CoordinatorClient,
+    GetTaskRequest,
+    HeartbeatRequest,
+    RegisterRequest,
+    Shard,
+    Task,
+};
+
+/// How long to wait before asking again when no task is available.
+const IDLE_WAIT: Duration = Duration::from_secs(5);
+
+/// Play tasks from the coordinator until all of them are completed, searching
+/// with `threads` threads sharing the tree.
+pub fn work<const N: usize, A: Agent<N> + Sync>(
+    addr: String,
+    name: String,
+    agent: &A,
+    threads: usize,
+) -> Result<(), Box<dyn Error>>
+where
+    Turn<N>: Lut,
+    [[Option<Tile>; N]; N]: Default,
+{
+    let runtime = Runtime::new()?;
+    let mut client = runtime.block_on(CoordinatorClient::connect(addr))?;
+    let registration = runtime
+        .block_on(client.register(RegisterRequest { name }))?
+        .into_inner();
+    let worker_id = registration.worker_id;
+    info!("Registered as worker {worker_id}");
+
+    // keep leases alive while games are being played
+    let mut heartbeat_client = client.clone();
+    let interval = Duration::from_secs(registration.timeout_secs.max(3) / 3);
+    runtime.spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            let _ = heartbeat_client.heartbeat(HeartbeatRequest { worker_id }).await;
+        }
+    });
+
+    loop {
+        let response = runtime
+            .block_on(client.get_task(GetTaskRequest { worker_id }))?
+            .into_inner();
+        let task = match response.task {
+            Some(task) => task,
+            None if response.finished => break,
+            None => {
+                thread::sleep(IDLE_WAIT);
+                continue;
+            }
+        };
+        if task.size as usize != N {
+            return Err(format!("task for size {} but this worker plays size {N}", task.size).into());
+        }
+
+        let (examples, analysis) = play_task(agent, &task, threads)?;
+        let shard = Shard {
+            worker_id,
+            task_id: task.id,
+            examples: examples_to_string(&examples),
+            ptn: analysis.to_ptn(),
+        };
+        let accepted = runtime
+            .block_on(client.submit_shard(shard))?
+            .into_inner()
+            .accepted;
+        info!(
+            "Task {} {}",
+            task.id,
+            if accepted { "accepted" } else { "was already done" }
+        );
+    }
+    info!("All tasks completed");
+    Ok(())
+}
+
+/// Play one self-play game.
+fn play_task<const N: usize, A: Agent<N> + Sync>(
+    agent: &A,
+    task: &Task,
+    threads: usize,
+) -> Result<(Vec<Example<N>>, Analysis<N>), Box<dyn Error>>
+where
+    Turn<N>: Lut,
+    [[Option<Tile>; N]; N]: Default,
+{
+    let half_komi = i8::try_from(task.half_komi)
+        .map_err(|_| format!("komi of {} half flats is out of range", task.half_komi))?;
+    let mut game = Game::with_komi(Komi::from_half_flats(half_komi));
+    let opening = game.opening(task.opening as usize).unwrap();
+
+    let mut player = Player::new(agent, opening, game.komi);
+
+    while matches!(game_result(&game), GameResult::Ongoing) {
+        if game.ply < NOISE_PLIES {
+            player.apply_dirichlet(&game, DIRICHLET_NOISE, NOISE_RATIO);
+        }
+        if threads > 1 {
+            player.rollout_parallel(&game, ROLLOUTS_PER_MOVE, threads);
+        } else {
+            player.rollout(&game, ROLLOUTS_PER_MOVE);
+        }
+        let temperature = if game.ply <= TEMPERATURE_PLIES {
+            TEMPERATURE
+        } else {
+            0.
+        };
+        let turn = player.pick_move(&game, temperature);
+        game.play(turn).unwrap();
+    }
+
+    Ok((player.get_examples(game_result(&game)), player.get_analysis()))
+}
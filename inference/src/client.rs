@@ -0,0 +1,132 @@
+use std::{
+    error::Error,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{channel, Sender},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use alpha_tak::{
+    agent::Agent,
+    repr::{game_features, input_channels, moves_dims},
+};
+use tak::*;
+use tokio::runtime::Runtime;
+use tonic::transport::Channel;
+
+use crate::proto::{inference_client::InferenceClient, EvaluateRequest, InfoRequest};
+
+/// How long a position waits for others from concurrent rollouts before a
+/// partial batch is sent.
+const BATCH_TIMEOUT: Duration = Duration::from_millis(2);
+
+/// A position waiting to be evaluated, with where to send its output.
+type Pending<const N: usize> = (Game<N>, Sender<(Vec<f32>, f32)>);
+
+/// Agent which evaluates positions on an inference server. Positions from
+/// concurrent calls are sent together, in batches of up to the size the
+/// server recommends.
+pub struct RemoteAgent<const N: usize> {
+    runtime: Runtime,
+    client: InferenceClient<Channel>,
+    batch_size: usize,
+    pending: Mutex<Vec<Pending<N>>>,
+    /// Calls waiting for their output, so that a batch is sent as soon as
+    /// nobody else can add to it.
+    callers: AtomicUsize,
+}
+
+impl<const N: usize> RemoteAgent<N> {
+    /// Connect to a server, checking that it serves a model for this board
+    /// size.
+    pub fn connect(addr: String) -> Result<Self, Box<dyn Error>> {
+        let runtime = Runtime::new()?;
+        let mut client = runtime.block_on(InferenceClient::connect(addr))?;
+        let info = runtime.block_on(client.info(InfoRequest {}))?.into_inner();
+        if info.size as usize != N
+            || info.input_channels as usize != input_channels(N)
+            || info.policy_size as usize != moves_dims(N)
+        {
+            return Err(format!("server model does not match board size {N}: {info:?}").into());
+        }
+        Ok(RemoteAgent {
+            runtime,
+            client,
+            batch_size: (info.batch_size as usize).max(1),
+            pending: Mutex::new(Vec::new()),
+            callers: AtomicUsize::new(0),
+        })
+    }
+
+    /// Batch size the server recommends for its hardware, which is also how
+    /// many threads should search with this agent to fill the batches.
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// Evaluate many positions in one request.
+    pub fn policy_eval_batch(&self, games: &[Game<N>]) -> Result<(Vec<Vec<f32>>, Vec<f32>), Box<dyn Error>> {
+        let inputs = games.iter().flat_map(game_features).collect();
+        let request = EvaluateRequest {
+            size: N as u32,
+            batch: games.len() as u32,
+            inputs,
+        };
+        // channels are cheap to clone and allow concurrent requests
+        let mut client = self.client.clone();
+        let response = self.runtime.block_on(client.evaluate(request))?.into_inner();
+        if response.evals.len() != games.len() || response.policies.len() != games.len() * moves_dims(N) {
+            return Err("server returned a malformed response".into());
+        }
+        let policies = response
+            .policies
+            .chunks(moves_dims(N))
+            .map(<[f32]>::to_vec)
+            .collect();
+        Ok((policies, response.evals))
+    }
+
+    /// Evaluate all waiting positions.
+    fn flush(&self) {
+        let batch = std::mem::take(&mut *self.pending.lock().unwrap());
+        if batch.is_empty() {
+            return;
+        }
+        let games: Vec<_> = batch.iter().map(|(game, _)| game.clone()).collect();
+        let (policies, evals) = self.policy_eval_batch(&games).expect("remote inference failed");
+        for ((_, tx), output) in batch.into_iter().zip(policies.into_iter().zip(evals)) {
+            // the caller only goes away by panicking
+            tx.send(output).ok();
+        }
+    }
+}
+
+impl<const N: usize> Agent<N> for RemoteAgent<N> {
+    fn policy_and_eval(&self, game: &Game<N>) -> (Vec<f32>, f32) {
+        let (tx, rx) = channel();
+        self.callers.fetch_add(1, Ordering::SeqCst);
+        let full = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.push((game.clone(), tx));
+            // a single searching thread never has company to wait for
+            pending.len() >= self.batch_size.min(self.callers.load(Ordering::SeqCst))
+        };
+        if full {
+            self.flush();
+        }
+        let output = rx.recv_timeout(BATCH_TIMEOUT).unwrap_or_else(|_| {
+            // nobody else filled the batch, so send what there is, unless
+            // another thread already took this position
+            self.flush();
+            rx.recv().expect("remote inference failed")
+        });
+        self.callers.fetch_sub(1, Ordering::SeqCst);
+        output
+    }
+
+    fn policy_and_eval_batch(&self, games: &[Game<N>]) -> (Vec<Vec<f32>>, Vec<f32>) {
+        self.policy_eval_batch(games).expect("remote inference failed")
+    }
+}
@@ -0,0 +1,6 @@
+pub mod client;
+pub mod server;
+
+pub mod proto {
+    tonic::include_proto!("alphatak.inference.v1");
+}
@@ -0,0 +1,67 @@
+use alpha_tak::{
+    config::{FILTERS, N, RES_BLOCKS},
+    init_logging,
+    model::{
+        network::Network,
+        throughput::{pick_batch_size, CALIBRATION_BATCHES},
+    },
+    use_cuda,
+};
+use clap::Parser;
+use inference::{proto::inference_server::InferenceServer, server::InferenceService};
+use tonic::transport::Server;
+use tracing::info;
+
+/// Serve a model for remote inference over gRPC
+#[derive(Parser)]
+struct Args {
+    /// Path to model
+    model_path: String,
+    /// Address to listen on
+    #[clap(long, default_value = "0.0.0.0:50051")]
+    addr: String,
+    /// Residual blocks of the model, for distilled models
+    #[clap(long, default_value_t = RES_BLOCKS)]
+    blocks: usize,
+    /// Filters of the model, for distilled models
+    #[clap(long, default_value_t = FILTERS)]
+    filters: i64,
+    /// Batch size to recommend to clients, measured on startup if not given
+    #[clap(long)]
+    batch_size: Option<usize>,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = Args::parse();
+    init_logging();
+
+    info!("CUDA: {}", use_cuda());
+    let network = Network::<N>::load_with_size(&args.model_path, args.blocks, args.filters)?;
+    let batch_size = args.batch_size.unwrap_or_else(|| {
+        let timings = network.measure_batches(CALIBRATION_BATCHES, 10);
+        for timing in &timings {
+            info!(
+                batch_size = timing.batch_size,
+                latency_ms = timing.latency.as_secs_f64() * 1000.,
+                throughput = timing.throughput(),
+                "calibration"
+            );
+        }
+        pick_batch_size(&timings).unwrap_or(1)
+    });
+    info!("recommended batch size: {batch_size}");
+    info!(
+        "memory: {:.1} MiB of weights, about {:.1} MiB per batch",
+        network.parameter_bytes() as f64 / (1 << 20) as f64,
+        network.batch_bytes(batch_size) as f64 / (1 << 20) as f64
+    );
+
+    let addr = args.addr.parse()?;
+    info!("Serving {} on {addr}", args.model_path);
+    Server::builder()
+        .add_service(InferenceServer::new(InferenceService::new(network, batch_size)))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
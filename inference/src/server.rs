@@ -0,0 +1,82 @@
+use std::sync::{Arc, Mutex};
+
+use alpha_tak::{
+    model::network::Network,
+    repr::{input_channels, moves_dims},
+};
+use tch::{Kind, Tensor};
+use tonic::{Request, Response, Status};
+
+use crate::proto::{
+    inference_server::Inference,
+    EvaluateRequest,
+    EvaluateResponse,
+    InfoRequest,
+    InfoResponse,
+};
+
+/// Serves a network to remote searches.
+pub struct InferenceService<const N: usize> {
+    network: Arc<Mutex<Network<N>>>,
+    batch_size: usize,
+}
+
+impl<const N: usize> InferenceService<N> {
+    /// Serve the network, recommending `batch_size` to clients.
+    pub fn new(network: Network<N>, batch_size: usize) -> Self {
+        InferenceService {
+            network: Arc::new(Mutex::new(network)),
+            batch_size,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<const N: usize> Inference for InferenceService<N> {
+    async fn info(&self, _request: Request<InfoRequest>) -> Result<Response<InfoResponse>, Status> {
+        Ok(Response::new(InfoResponse {
+            size: N as u32,
+            input_channels: input_channels(N) as u32,
+            policy_size: moves_dims(N) as u32,
+            batch_size: self.batch_size as u32,
+        }))
+    }
+
+    async fn evaluate(
+        &self,
+        request: Request<EvaluateRequest>,
+    ) -> Result<Response<EvaluateResponse>, Status> {
+        let request = request.into_inner();
+        if request.size as usize != N {
+            return Err(Status::invalid_argument(format!(
+                "model is for size {N}, got inputs for size {}",
+                request.size
+            )));
+        }
+        let batch = request.batch as usize;
+        if request.inputs.len() != batch * input_channels(N) * N * N {
+            return Err(Status::invalid_argument("inputs do not match the batch size"));
+        }
+        if batch == 0 {
+            return Ok(Response::new(EvaluateResponse::default()));
+        }
+
+        // the forward pass blocks, so it runs outside of the async workers
+        let network = Arc::clone(&self.network);
+        let response = tokio::task::spawn_blocking(move || {
+            let network = network.lock().map_err(|_| Status::internal("network poisoned"))?;
+            let device = network.vs.device();
+            let input = Tensor::of_slice(&request.inputs)
+                .view([batch as i64, input_channels(N) as i64, N as i64, N as i64])
+                .to_device_(device, Kind::Float, true, false);
+            let (policy, eval) = tch::no_grad(|| network.forward_mcts(input));
+            let policies: Vec<f32> = policy.view([-1]).into();
+            let evals: Vec<f32> = eval.view([-1]).into();
+            Ok::<_, Status>(EvaluateResponse { policies, evals })
+        })
+        .await
+        .map_err(|err| Status::internal(format!("evaluation failed: {err}")))??;
+
+        Ok(Response::new(response))
+    }
+}
@@ -1,4 +1,9 @@
+use alpha_tak::{
+    agent::EnsembleMode,
+    config::{FILTERS, RES_BLOCKS},
+};
 use clap::Parser;
+use tak::{FromPTN, TimeControl};
 
 /// Run the bot on PlayTak
 #[derive(Parser)]
@@ -15,4 +20,23 @@ pub struct Args {
     /// Disable GPU usage
     #[clap(short, long)]
     pub no_gpu: bool,
+    /// Residual blocks of the model, for distilled models
+    #[clap(long, default_value_t = RES_BLOCKS)]
+    pub blocks: usize,
+    /// Filters of the model, for distilled models
+    #[clap(long, default_value_t = FILTERS)]
+    pub filters: i64,
+    /// Paths to more models which are searched together with the main one,
+    /// all with the default size
+    #[clap(long)]
+    pub ensemble: Vec<String>,
+    /// How the models of an ensemble are combined: average or interleave
+    #[clap(long, default_value = "average")]
+    pub ensemble_mode: EnsembleMode,
+    /// Record search events of each game as JSON lines in this directory
+    #[clap(long)]
+    pub event_log: Option<String>,
+    /// Time control of the seeks, as initial time and increment
+    #[clap(long, default_value = "10:00 +20", parse(try_from_str = TimeControl::from_ptn))]
+    pub time_control: TimeControl,
 }
@@ -1,13 +1,21 @@
 use std::{
     fs::File,
     io::Write,
-    str::FromStr,
     sync::mpsc::{channel, Receiver, TryRecvError},
     thread::spawn,
     time::Duration,
 };
 
-use alpha_tak::{config::KOMI, model::network::Network, player::Player, sys_time, use_cuda};
+use alpha_tak::{
+    agent::Ensemble,
+    config::KOMI,
+    init_logging,
+    model::network::Network,
+    player::Player,
+    search::event_log::EventLog,
+    sys_time,
+    use_cuda,
+};
 use clap::Parser;
 use cli::Args;
 use tak::*;
@@ -27,13 +35,17 @@ use tokio_takconnect::{
     GameUpdate,
     SeekParameters,
 };
+use tracing::{debug, info, warn};
 
 mod cli;
+mod notation;
 
 const WHITE_FIRST_MOVE: &str = "e5";
+/// Fraction of the remaining time spent thinking on a move.
+const THINK_SHARE: u32 = 30;
 
-async fn create_seek(client: &mut Client, color: Color) {
-    // Hardcoded for now
+async fn create_seek(client: &mut Client, color: Color, time_control: TimeControl) {
+    // size and reserves are hardcoded for now
     client
         .seek(
             SeekParameters::new(
@@ -41,9 +53,9 @@ async fn create_seek(client: &mut Client, color: Color) {
                 color,
                 GameParameters::new(
                     5,
-                    Duration::from_secs(10 * 60),
-                    Duration::from_secs(20),
-                    2 * KOMI,
+                    time_control.initial,
+                    time_control.increment,
+                    KOMI.half_flats() as i32,
                     21,
                     1,
                     false,
@@ -60,6 +72,7 @@ async fn create_seek(client: &mut Client, color: Color) {
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
+    init_logging();
     if !(args.no_gpu || use_cuda()) {
         panic!("Could not enable CUDA.");
     }
@@ -67,56 +80,101 @@ async fn main() {
     let (channel_tx, channel_rx) = channel::<(UnboundedSender<Move>, Receiver<Move>)>();
 
     spawn(move || {
-        let network = Network::<5>::load(&args.model_path)
-            .unwrap_or_else(|_| panic!("could not load model at {}", args.model_path));
+        let mut networks = vec![
+            Network::<5>::load_with_size(&args.model_path, args.blocks, args.filters)
+                .unwrap_or_else(|_| panic!("could not load model at {}", args.model_path)),
+        ];
+        for path in &args.ensemble {
+            networks
+                .push(Network::<5>::load(path).unwrap_or_else(|_| panic!("could not load model at {path}")));
+        }
+        let agent = Ensemble::new(networks, args.ensemble_mode);
 
         while let Ok((tx, rx)) = channel_rx.recv() {
             let mut game = Game::<5>::with_komi(KOMI);
+            // times are measured locally, so the opponent's include latency
+            let mut clock = Clock::new(args.time_control);
 
             let mut opening = Vec::new();
             if args.seek_as_white {
                 let first = Turn::from_ptn(WHITE_FIRST_MOVE).unwrap();
                 opening.push(first.clone());
+                clock.record(game.to_move, Duration::ZERO);
                 game.play(first.clone()).unwrap();
             }
-            let mut player = Player::<5, _>::new(&network, opening, KOMI);
+            let mut waiting = Instant::now();
+            let mut player = Player::<5, _>::new(&agent, opening, KOMI);
+            if let Some(dir) = &args.event_log {
+                match EventLog::create(format!("{dir}/events_{}.jsonl", sys_time())) {
+                    Ok(log) => player = player.with_event_log(log),
+                    Err(err) => warn!("could not create event log: {err}"),
+                }
+            }
 
             loop {
                 match rx.try_recv() {
                     Ok(m) => {
-                        print!("{}", player.debug(Some(5)));
+                        debug!("\n{}", player.debug(Some(5)));
 
-                        let turn = Turn::from_ptn(&m.to_string()).unwrap();
+                        let turn = match notation::to_turn(&m) {
+                            Ok(turn) if game.clone().play(turn.clone()).is_ok() => turn,
+                            Ok(turn) => {
+                                warn!("Opponent played illegal move {}", turn.to_ptn());
+                                break;
+                            }
+                            Err(err) => {
+                                warn!("{err}");
+                                break;
+                            }
+                        };
+                        if player.ponder_hit(&turn) {
+                            info!("Ponder hit");
+                        }
+                        clock.record(game.to_move, waiting.elapsed());
                         player.play_move(&game, &turn);
                         game.play(turn).unwrap();
 
                         if game.winner() != GameResult::Ongoing {
-                            println!("Opponent ended the game");
+                            info!("Opponent ended the game");
                             break;
                         }
 
-                        println!("My turn");
+                        info!("My turn");
 
                         let start = Instant::now();
-                        while Instant::now().duration_since(start) < Duration::from_secs(25) {
-                            player.rollout(&game, 500);
+                        let think_time =
+                            clock.remaining(game.to_move) / THINK_SHARE + args.time_control.increment / 2;
+                        let rollouts = player.think_for(&game, think_time);
+                        debug!("{rollouts} rollouts in {:?}", start.elapsed());
+                        debug!("\n{}", player.debug(Some(5)));
+
+                        let turn = player.pick_move(&game, 0.);
+                        match notation::to_move(&turn) {
+                            Ok(m) => tx.send(m).unwrap(),
+                            Err(err) => {
+                                warn!("{err}");
+                                break;
+                            }
                         }
-                        print!("{}", player.debug(Some(5)));
-
-                        let turn = player.pick_move(&game, true);
-                        tx.send(Move::from_str(&turn.to_ptn()).unwrap()).unwrap();
+                        clock.record(game.to_move, start.elapsed());
                         game.play(turn).unwrap();
+                        waiting = Instant::now();
                     }
                     // Ponder
-                    Err(TryRecvError::Empty) => player.rollout(&game, 100),
+                    Err(TryRecvError::Empty) => {
+                        player.ponder(&game, 100);
+                    }
                     // Game ended
                     Err(TryRecvError::Disconnected) => break,
                 }
             }
 
             // create analysis file
+            let mut analysis = player.get_analysis();
+            analysis.set_clock(clock);
+            analysis.set_date(sys_time());
             if let Ok(mut file) = File::create(format!("analysis_{}.ptn", sys_time())) {
-                file.write_all(player.get_analysis().to_ptn().as_bytes()).unwrap();
+                file.write_all(analysis.to_ptn().as_bytes()).unwrap();
             }
         }
     });
@@ -125,20 +183,25 @@ async fn main() {
     let mut client = if let (Some(username), Some(password)) = (args.username, args.password) {
         connect_as(username, password).await
     } else {
-        println!("Connecting as guest");
+        info!("Connecting as guest");
         connect_guest().await
     }
     .unwrap();
 
+    let time_control = args.time_control;
     select! {
         _ = ctrl_c() => (),
         _ = async move {
             loop {
-                create_seek(&mut client, if args.seek_as_white {Color::White} else {Color::Black}).await;
-                println!("Created seek");
+                create_seek(
+                    &mut client,
+                    if args.seek_as_white {Color::White} else {Color::Black},
+                    time_control,
+                ).await;
+                info!("Created seek");
 
                 let mut playtak_game = client.game().await.unwrap();
-                println!("Game started");
+                info!("Game started");
 
                 let (tx, mut rx) = {
                     let (outbound_tx, outbound_rx) = channel::<Move>();
@@ -152,22 +215,22 @@ async fn main() {
                 }
 
                 loop {
-                    println!("Opponent's turn");
+                    info!("Opponent's turn");
                     match playtak_game.update().await.unwrap() {
                         GameUpdate::Played(m) => {
-                            println!("Opponent played {m}");
+                            info!("Opponent played {m}");
 
                             tx.send(m).unwrap();
 
                             if let Some(m) = rx.recv().await {
-                                println!("Playing {m}");
+                                info!("Playing {m}");
                                 if playtak_game.play(m).await.is_err() {
-                                    println!("Failed to play move!");
+                                    warn!("Failed to play move!");
                                 }
                             }
                         }
                         GameUpdate::Ended(result) => {
-                            println!("Game over! {result:?}");
+                            info!("Game over! {result:?}");
                             break;
                         }
                         _ => {}
@@ -177,5 +240,5 @@ async fn main() {
         } => (),
     }
 
-    println!("Shutting down...");
+    info!("Shutting down...");
 }
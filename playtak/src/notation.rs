@@ -0,0 +1,17 @@
+//! Conversion between the moves of the PlayTak client and [`Turn`].
+
+use std::str::FromStr;
+
+use tak::*;
+use takparse::Move;
+
+/// Turn played by the opponent on the server.
+pub fn to_turn<const N: usize>(m: &Move) -> StrResult<Turn<N>> {
+    Turn::from_ptn(&m.to_string()).map_err(|err| format!("could not read server move {m}: {err}"))
+}
+
+/// Move to send to the server.
+pub fn to_move<const N: usize>(turn: &Turn<N>) -> StrResult<Move> {
+    let ptn = turn.to_ptn();
+    Move::from_str(&ptn).map_err(|err| format!("could not convert {ptn} for the server: {err:?}"))
+}
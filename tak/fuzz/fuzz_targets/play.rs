@@ -0,0 +1,41 @@
+#![no_main]
+
+use arbitrary::{Arbitrary, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use tak::*;
+
+/// Every piece is either in the reserves or on the board.
+fn check_pieces(game: &Game<5>) {
+    let (stones, caps) = default_starting_stones(5);
+    for colour in [Colour::White, Colour::Black] {
+        let (reserve_stones, reserve_caps) = game.reserves(colour);
+        let (board_stones, board_caps) = game.board.piece_counts(colour);
+        assert_eq!(reserve_stones as u32 + board_stones, stones as u32);
+        assert_eq!(reserve_caps as u32 + board_caps, caps as u32);
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut u = Unstructured::new(data);
+    let mut game = Game::<5>::default();
+    while !u.is_empty() && game.winner() == GameResult::Ongoing {
+        let turn = match Turn::arbitrary(&mut u) {
+            Ok(turn) => turn,
+            Err(_) => break,
+        };
+        let before = game.clone();
+        let legal = game.possible_turns().contains(&turn);
+        match game.play(turn) {
+            Ok(()) => {
+                assert!(legal, "played a turn which was not generated");
+                assert_eq!(game.ply, before.ply + 1);
+            }
+            Err(_) => {
+                assert!(!legal, "failed to play a generated turn");
+                assert_eq!(game, before, "an illegal turn changed the game");
+                assert_eq!(game.ply, before.ply);
+            }
+        }
+        check_pieces(&game);
+    }
+});
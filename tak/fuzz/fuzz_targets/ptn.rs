@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tak::*;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = std::str::from_utf8(data) {
+        let _ = Turn::<6>::from_ptn(s);
+        let _ = Pos::<6>::from_ptn(s);
+        if let Ok(positions) = Game::<6>::ptn_positions(s) {
+            for position in positions {
+                if position.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tak::*;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = std::str::from_utf8(data) {
+        if let Ok(board) = Board::<6>::from_tps(s) {
+            // whatever parses must survive a round trip
+            assert_eq!(Board::<6>::from_tps(&board.to_tps()), Ok(board));
+        }
+    }
+});
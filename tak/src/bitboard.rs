@@ -0,0 +1,154 @@
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, Not};
+
+use crate::pos::Pos;
+
+/// A set of squares, one bit per square in row-major order (bit `y * N + x`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Bitboard<const N: usize>(pub u64);
+
+impl<const N: usize> Bitboard<N> {
+    /// Squares of the last column, x = N - 1.
+    pub const EAST: Self = Self::column(N - 1);
+    pub const EMPTY: Self = Bitboard(0);
+    pub const FULL: Self = Bitboard(if N * N == 64 { u64::MAX } else { (1 << (N * N)) - 1 });
+    /// Squares of the last row, y = N - 1.
+    pub const NORTH: Self = Self::row(N - 1);
+    /// Squares of the first row, y = 0.
+    pub const SOUTH: Self = Self::row(0);
+    /// Squares of the first column, x = 0.
+    pub const WEST: Self = Self::column(0);
+
+    const fn column(x: usize) -> Self {
+        let mut bits = 0;
+        let mut y = 0;
+        while y < N {
+            bits |= 1 << (y * N + x);
+            y += 1;
+        }
+        Bitboard(bits)
+    }
+
+    const fn row(y: usize) -> Self {
+        Bitboard(((1 << N) - 1) << (y * N))
+    }
+
+    pub const fn from_pos(pos: Pos<N>) -> Self {
+        Bitboard(1 << (pos.y * N + pos.x))
+    }
+
+    pub const fn contains(self, pos: Pos<N>) -> bool {
+        self.0 & Self::from_pos(pos).0 != 0
+    }
+
+    pub fn insert(&mut self, pos: Pos<N>) {
+        self.0 |= Self::from_pos(pos).0;
+    }
+
+    pub fn remove(&mut self, pos: Pos<N>) {
+        self.0 &= !Self::from_pos(pos).0;
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    /// The squares orthogonally adjacent to any square of the set,
+    /// not including the set itself unless adjacent.
+    #[must_use]
+    pub const fn neighbors(self) -> Self {
+        let bits = self.0;
+        let east = (bits & !Self::EAST.0) << 1;
+        let west = (bits & !Self::WEST.0) >> 1;
+        let north = bits << N;
+        let south = bits >> N;
+        Bitboard((east | west | north | south) & Self::FULL.0)
+    }
+
+    /// Squares of `within` connected to this set through squares of `within`.
+    #[must_use]
+    pub fn flood_fill(self, within: Self) -> Self {
+        let mut filled = self & within;
+        loop {
+            let next = (filled | filled.neighbors()) & within;
+            if next == filled {
+                return filled;
+            }
+            filled = next;
+        }
+    }
+
+    /// Positions in the set, row by row.
+    pub fn iter(self) -> impl Iterator<Item = Pos<N>> {
+        let mut bits = self.0;
+        std::iter::from_fn(move || {
+            if bits == 0 {
+                return None;
+            }
+            let index = bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+            Some(Pos {
+                x: index % N,
+                y: index / N,
+            })
+        })
+    }
+}
+
+impl<const N: usize> FromIterator<Pos<N>> for Bitboard<N> {
+    fn from_iter<I: IntoIterator<Item = Pos<N>>>(iter: I) -> Self {
+        let mut bitboard = Bitboard::EMPTY;
+        for pos in iter {
+            bitboard.insert(pos);
+        }
+        bitboard
+    }
+}
+
+impl<const N: usize> BitAnd for Bitboard<N> {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Bitboard(self.0 & rhs.0)
+    }
+}
+
+impl<const N: usize> BitAndAssign for Bitboard<N> {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl<const N: usize> BitOr for Bitboard<N> {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Bitboard(self.0 | rhs.0)
+    }
+}
+
+impl<const N: usize> BitOrAssign for Bitboard<N> {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl<const N: usize> BitXor for Bitboard<N> {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self {
+        Bitboard(self.0 ^ rhs.0)
+    }
+}
+
+/// Complement within the board, bits outside of it stay unset.
+impl<const N: usize> Not for Bitboard<N> {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Bitboard(!self.0 & Self::FULL.0)
+    }
+}
@@ -1,82 +1,241 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet, VecDeque},
     fmt::Display,
-    ops::{Index, IndexMut},
+    ops::Index,
+    sync::Arc,
 };
 
 use crate::{
+    bitboard::Bitboard,
     colour::Colour,
     pos::Pos,
-    tile::{Piece, Shape, Tile},
+    tile::{Shape, Tile},
+    zobrist::square_hash,
 };
 
-#[derive(Clone, Debug)]
+/// Rows are shared between clones and only copied when mutated,
+/// so cloning a game for a rollout does not copy every stack.
+///
+/// Next to the stacks the board keeps a bitboard of the top pieces for each
+/// colour and shape, which is what road detection and move generation look
+/// at, and a Zobrist hash of the squares. Squares are changed with
+/// [`Board::set`] and [`Board::take`] so that all of them stay in sync.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Board<const N: usize> {
-    data: [[Option<Tile>; N]; N],
+    data: [Arc<[Option<Tile>; N]>; N],
+    /// Indexed by colour and then shape.
+    tops: [[Bitboard<N>; 3]; 2],
+    /// Zobrist hash of the squares, see [`Board::zobrist`].
+    hash: u64,
 }
 
 impl<const N: usize> Board<N> {
     pub fn empty(&self) -> bool {
-        self.data.iter().all(|row| row.iter().all(|x| x.is_none()))
+        self.occupied().is_empty()
     }
 
     pub fn full(&self) -> bool {
-        self.data.iter().all(|row| row.iter().all(|x| x.is_some()))
+        self.occupied() == Bitboard::FULL
     }
 
-    pub fn flat_diff(&self) -> i32 {
-        let mut diff = 0;
+    /// Put a tile on the square, replacing whatever was there.
+    pub fn set(&mut self, pos: Pos<N>, tile: Option<Tile>) {
+        self.take(pos);
+        if let Some(tile) = &tile {
+            self.tops[tile.top.colour as usize][tile.top.shape as usize].insert(pos);
+            self.hash ^= square_hash(pos, tile);
+        }
+        *self.square_mut(pos) = tile;
+    }
+
+    /// Remove the tile from the square.
+    pub fn take(&mut self, pos: Pos<N>) -> Option<Tile> {
+        // avoid copying a shared row
+        self[pos].as_ref()?;
+        let tile = self.square_mut(pos).take();
+        if let Some(tile) = &tile {
+            self.tops[tile.top.colour as usize][tile.top.shape as usize].remove(pos);
+            self.hash ^= square_hash(pos, tile);
+        }
+        tile
+    }
+
+    /// Zobrist hash of the squares, which [`Game::zobrist`] extends to the
+    /// whole position.
+    ///
+    /// [`Game::zobrist`]: crate::Game::zobrist
+    pub fn zobrist(&self) -> u64 {
+        self.hash
+    }
+
+    fn square_mut(&mut self, pos: Pos<N>) -> &mut Option<Tile> {
+        &mut Arc::make_mut(&mut self.data[pos.y])[pos.x]
+    }
+
+    /// Count the flats on top of stacks, returned as (white, black).
+    pub fn flat_counts(&self) -> (u32, u32) {
+        (
+            self.flats(Colour::White).count(),
+            self.flats(Colour::Black).count(),
+        )
+    }
+
+    /// Squares whose top piece has this colour and shape.
+    pub fn tops(&self, colour: Colour, shape: Shape) -> Bitboard<N> {
+        self.tops[colour as usize][shape as usize]
+    }
+
+    pub fn flats(&self, colour: Colour) -> Bitboard<N> {
+        self.tops(colour, Shape::Flat)
+    }
+
+    pub fn walls(&self, colour: Colour) -> Bitboard<N> {
+        self.tops(colour, Shape::Wall)
+    }
+
+    pub fn capstones(&self, colour: Colour) -> Bitboard<N> {
+        self.tops(colour, Shape::Capstone)
+    }
+
+    /// Squares controlled by the colour, whatever the top piece is.
+    pub fn controlled(&self, colour: Colour) -> Bitboard<N> {
+        self.flats(colour) | self.walls(colour) | self.capstones(colour)
+    }
+
+    /// Squares which count towards a road of the colour.
+    pub fn road_squares(&self, colour: Colour) -> Bitboard<N> {
+        self.flats(colour) | self.capstones(colour)
+    }
+
+    /// Squares with any piece on them.
+    pub fn occupied(&self) -> Bitboard<N> {
+        self.controlled(Colour::White) | self.controlled(Colour::Black)
+    }
+
+    /// Squares with a wall or capstone on top, which spreads cannot drop onto
+    /// except for a lone capstone flattening a wall.
+    pub fn blockers(&self) -> Bitboard<N> {
+        self.walls(Colour::White)
+            | self.walls(Colour::Black)
+            | self.capstones(Colour::White)
+            | self.capstones(Colour::Black)
+    }
+
+    /// Count the pieces of a colour anywhere on the board,
+    /// returned as (stones, capstones).
+    pub fn piece_counts(&self, colour: Colour) -> (u32, u32) {
+        let mut counts = (0, 0);
         for row in &self.data {
-            row.iter().flatten().for_each(|tile| {
-                if matches!(tile.top.shape, Shape::Flat) {
-                    match tile.top.colour {
-                        Colour::White => diff += 1,
-                        Colour::Black => diff -= 1,
+            for tile in row.iter().flatten() {
+                if tile.top.colour == colour {
+                    match tile.top.shape {
+                        Shape::Capstone => counts.1 += 1,
+                        _ => counts.0 += 1,
                     }
                 }
-            });
+                counts.0 += tile.stack.iter().filter(|&&c| c == colour).count() as u32;
+            }
         }
-        diff
+        counts
+    }
+
+    pub fn flat_diff(&self) -> i32 {
+        let (white, black) = self.flat_counts();
+        white as i32 - black as i32
     }
 
     pub fn find_paths(&self, colour: Colour) -> bool {
-        // check vertical paths
-        let mut seen = HashSet::new();
-        for x in 0..N {
-            let pos = Pos { x, y: 0 };
-            self.find_paths_recursive(pos, colour, &mut seen);
-        }
-        if (0..N).any(|x| seen.contains(&Pos { x, y: N - 1 })) {
-            return true;
-        }
-        // check horizontal paths
+        let road = self.road_squares(colour);
+        !(Bitboard::SOUTH.flood_fill(road) & Bitboard::NORTH).is_empty()
+            || !(Bitboard::WEST.flood_fill(road) & Bitboard::EAST).is_empty()
+    }
+
+    /// Find the shortest road of the colour, from one edge to the other.
+    pub fn find_road(&self, colour: Colour) -> Option<Vec<Pos<N>>> {
+        // vertical roads first, then horizontal roads
+        self.shortest_road(colour, (0..N).map(|x| Pos { x, y: 0 }), |pos| pos.y == N - 1)
+            .or_else(|| self.shortest_road(colour, (0..N).map(|y| Pos { x: 0, y }), |pos| pos.x == N - 1))
+    }
+
+    pub(crate) fn is_road(&self, pos: Pos<N>, colour: Colour) -> bool {
+        self.road_squares(colour).contains(pos)
+    }
+
+    /// Get the connected groups of road pieces of the colour.
+    pub fn road_groups(&self, colour: Colour) -> Vec<Vec<Pos<N>>> {
         let mut seen = HashSet::new();
+        let mut groups = Vec::new();
         for y in 0..N {
-            let pos = Pos { x: 0, y };
-            self.find_paths_recursive(pos, colour, &mut seen);
+            for x in 0..N {
+                let pos = Pos { x, y };
+                if seen.contains(&pos) || !self.is_road(pos, colour) {
+                    continue;
+                }
+                // flood fill the group
+                let mut group = Vec::new();
+                let mut stack = vec![pos];
+                seen.insert(pos);
+                while let Some(pos) = stack.pop() {
+                    group.push(pos);
+                    for neighbor in pos.neighbors() {
+                        if self.is_road(neighbor, colour) && seen.insert(neighbor) {
+                            stack.push(neighbor);
+                        }
+                    }
+                }
+                groups.push(group);
+            }
         }
-        (0..N).any(|y| seen.contains(&Pos { x: N - 1, y }))
+        groups
+    }
+
+    /// Get the number of rows or columns covered by the widest group of the
+    /// colour. A span of N means that the colour has a road.
+    pub fn road_span(&self, colour: Colour) -> usize {
+        self.road_groups(colour)
+            .iter()
+            .map(|group| {
+                let xs = group.iter().map(|pos| pos.x);
+                let ys = group.iter().map(|pos| pos.y);
+                let width = 1 + xs.clone().max().unwrap() - xs.min().unwrap();
+                let height = 1 + ys.clone().max().unwrap() - ys.min().unwrap();
+                width.max(height)
+            })
+            .max()
+            .unwrap_or_default()
     }
 
-    fn find_paths_recursive(&self, pos: Pos<N>, colour: Colour, seen: &mut HashSet<Pos<N>>) {
-        if seen.contains(&pos) {
-            return;
+    /// Breadth first search from the starting edge to the target edge.
+    fn shortest_road(
+        &self,
+        colour: Colour,
+        start: impl Iterator<Item = Pos<N>>,
+        is_target: impl Fn(Pos<N>) -> bool,
+    ) -> Option<Vec<Pos<N>>> {
+        let mut previous = HashMap::new();
+        let mut queue = VecDeque::new();
+        for pos in start.filter(|&pos| self.is_road(pos, colour)) {
+            previous.insert(pos, None);
+            queue.push_back(pos);
         }
-        if let Some(Tile {
-            top: Piece {
-                colour: piece_colour,
-                shape,
-            },
-            ..
-        }) = self[pos]
-        {
-            if piece_colour == colour && matches!(shape, Shape::Flat | Shape::Capstone) {
-                seen.insert(pos);
-                for neighbor in pos.neighbors() {
-                    self.find_paths_recursive(neighbor, colour, seen)
+        while let Some(pos) = queue.pop_front() {
+            if is_target(pos) {
+                // walk back to the starting edge
+                let mut road = vec![pos];
+                while let Some(&Some(prev)) = previous.get(road.last().unwrap()) {
+                    road.push(prev);
+                }
+                road.reverse();
+                return Some(road);
+            }
+            for neighbor in pos.neighbors() {
+                if !previous.contains_key(&neighbor) && self.is_road(neighbor, colour) {
+                    previous.insert(neighbor, Some(pos));
+                    queue.push_back(neighbor);
                 }
             }
         }
+        None
     }
 }
 
@@ -86,7 +245,9 @@ where
 {
     fn default() -> Self {
         Self {
-            data: <[[Option<Tile>; N]; N]>::default(),
+            data: [(); N].map(|_| Arc::new([(); N].map(|_| None))),
+            tops: Default::default(),
+            hash: 0,
         }
     }
 }
@@ -99,12 +260,6 @@ impl<const N: usize> Index<Pos<N>> for Board<N> {
     }
 }
 
-impl<const N: usize> IndexMut<Pos<N>> for Board<N> {
-    fn index_mut(&mut self, index: Pos<N>) -> &mut Self::Output {
-        self.data.index_mut(index.y).index_mut(index.x)
-    }
-}
-
 impl<const N: usize> Display for Board<N> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut output = String::new();
@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use crate::{
+    colour::Colour,
+    game::{GameResult, WinReason},
+    ptn::{FromPTN, ToPTN},
+    StrResult,
+};
+
+/// Time each player starts with and gains after every move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    pub initial: Duration,
+    pub increment: Duration,
+}
+
+impl TimeControl {
+    pub fn new(initial: Duration, increment: Duration) -> Self {
+        TimeControl { initial, increment }
+    }
+}
+
+impl FromPTN for TimeControl {
+    /// Parse the value of a `Clock` tag, like `10:00 +20`.
+    fn from_ptn(s: &str) -> StrResult<Self> {
+        let mut parts = s.split_whitespace();
+        let initial = parts.next().ok_or("missing initial time")?;
+        let (minutes, seconds) = initial.split_once(':').unwrap_or((initial, "0"));
+        let parse = |value: &str| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("cannot parse time control {s}"))
+        };
+        let (minutes, seconds) = (parse(minutes)?, parse(seconds)?);
+        let initial = minutes
+            .checked_mul(60)
+            .and_then(|minutes| minutes.checked_add(seconds))
+            .ok_or_else(|| format!("time control out of range {s}"))?;
+        let initial = Duration::from_secs(initial);
+        let increment = match parts.next() {
+            Some(increment) => Duration::from_secs(parse(increment.trim_start_matches('+'))?),
+            None => Duration::ZERO,
+        };
+        Ok(TimeControl { initial, increment })
+    }
+}
+
+impl ToPTN for TimeControl {
+    fn to_ptn(&self) -> String {
+        let seconds = self.initial.as_secs();
+        format!(
+            "{}:{:02} +{}",
+            seconds / 60,
+            seconds % 60,
+            self.increment.as_secs()
+        )
+    }
+}
+
+/// Clocks of both players over a game. Times are reported by the caller, so
+/// the same rules apply whether they come from a server or are measured
+/// locally.
+#[derive(Clone, Debug)]
+pub struct Clock {
+    pub control: TimeControl,
+    white: Duration,
+    black: Duration,
+    /// Time used on each recorded ply and the time left afterwards.
+    moves: Vec<(Duration, Duration)>,
+    flagged: Option<Colour>,
+}
+
+impl Clock {
+    pub fn new(control: TimeControl) -> Self {
+        Clock {
+            control,
+            white: control.initial,
+            black: control.initial,
+            moves: Vec::new(),
+            flagged: None,
+        }
+    }
+
+    /// Time the player has left for the rest of the game.
+    pub fn remaining(&self, colour: Colour) -> Duration {
+        match colour {
+            Colour::White => self.white,
+            Colour::Black => self.black,
+        }
+    }
+
+    /// Set the time left of a player, for example from a server update.
+    pub fn set_remaining(&mut self, colour: Colour, remaining: Duration) {
+        match colour {
+            Colour::White => self.white = remaining,
+            Colour::Black => self.black = remaining,
+        }
+    }
+
+    /// Time used on each recorded ply.
+    pub fn move_times(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.moves.iter().map(|&(used, _)| used)
+    }
+
+    /// Record a move of `colour` which took `used`. The increment is only
+    /// added if the player made the move in time, otherwise they lose on
+    /// time and the returned result says so.
+    pub fn record(&mut self, colour: Colour, used: Duration) -> GameResult {
+        let remaining = self.remaining(colour);
+        let left = match remaining.checked_sub(used) {
+            Some(left) => left + self.control.increment,
+            None => {
+                self.flagged = Some(colour);
+                Duration::ZERO
+            }
+        };
+        self.set_remaining(colour, left);
+        self.moves.push((used, left));
+        self.result()
+    }
+
+    /// Flag a player whose time ran out while thinking.
+    pub fn flag(&mut self, colour: Colour) -> GameResult {
+        self.set_remaining(colour, Duration::ZERO);
+        self.flagged = Some(colour);
+        self.result()
+    }
+
+    /// Loss on time of a player who ran out, or ongoing.
+    pub fn result(&self) -> GameResult {
+        match self.flagged {
+            Some(colour) => GameResult::Winner {
+                colour: colour.next(),
+                reason: WinReason::Time,
+            },
+            None => GameResult::Ongoing,
+        }
+    }
+
+    /// PTN comment with the time left after the `i`th recorded ply,
+    /// like `clk 9:41.5`.
+    pub fn comment(&self, i: usize) -> Option<String> {
+        self.moves.get(i).map(|&(_, left)| {
+            let tenths = left.as_millis() / 100;
+            format!("clk {}:{:02}.{}", tenths / 600, tenths / 10 % 60, tenths % 10)
+        })
+    }
+}
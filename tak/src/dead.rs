@@ -0,0 +1,152 @@
+use std::collections::VecDeque;
+
+use crate::{
+    board::Board,
+    colour::Colour,
+    game::{Game, GameResult, WinReason},
+    pos::Pos,
+    tile::{Shape, Tile},
+};
+
+impl<const N: usize> Board<N> {
+    /// Fewest squares `colour` has to take over to complete a road, or `None`
+    /// if every road is blocked by capstones and by walls it cannot flatten.
+    pub fn road_distance(&self, colour: Colour, has_capstone: bool) -> Option<usize> {
+        let cost = |pos: Pos<N>| match &self[pos] {
+            None => Some(1),
+            Some(tile) if tile.top.colour == colour => match tile.top.shape {
+                Shape::Wall => Some(1),
+                _ => Some(0),
+            },
+            Some(tile) => match tile.top.shape {
+                Shape::Flat => Some(1),
+                Shape::Wall if has_capstone => Some(1),
+                _ => None,
+            },
+        };
+        let bottom = (0..N).map(|x| Pos { x, y: 0 });
+        let left = (0..N).map(|y| Pos { x: 0, y });
+        let vertical = self.edge_distance(bottom, |pos| pos.y == N - 1, cost);
+        let horizontal = self.edge_distance(left, |pos| pos.x == N - 1, cost);
+        match (vertical, horizontal) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Cheapest path from the starting edge to the target edge, where each
+    /// square costs 0 or 1 and `None` squares are impassable.
+    fn edge_distance(
+        &self,
+        start: impl Iterator<Item = Pos<N>>,
+        is_target: impl Fn(Pos<N>) -> bool,
+        cost: impl Fn(Pos<N>) -> Option<usize>,
+    ) -> Option<usize> {
+        let mut distance = [[usize::MAX; N]; N];
+        let mut queue = VecDeque::new();
+        for pos in start {
+            if let Some(c) = cost(pos) {
+                distance[pos.y][pos.x] = c;
+                if c == 0 {
+                    queue.push_front(pos);
+                } else {
+                    queue.push_back(pos);
+                }
+            }
+        }
+        let mut best = None;
+        while let Some(pos) = queue.pop_front() {
+            let d = distance[pos.y][pos.x];
+            if is_target(pos) {
+                best = Some(best.map_or(d, |best: usize| best.min(d)));
+            }
+            for neighbor in pos.neighbors() {
+                if let Some(c) = cost(neighbor) {
+                    if d + c < distance[neighbor.y][neighbor.x] {
+                        distance[neighbor.y][neighbor.x] = d + c;
+                        if c == 0 {
+                            queue.push_front(neighbor);
+                        } else {
+                            queue.push_back(neighbor);
+                        }
+                    }
+                }
+            }
+        }
+        best
+    }
+}
+
+impl<const N: usize> Game<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// The result of a dead position, where the game is bound to end on
+    /// flats with a known winner, so it can be adjudicated.
+    ///
+    /// The player who is ahead can end the game by placing their remaining
+    /// pieces, which gives both players at most that many more moves. The
+    /// position is dead if neither player could take over enough squares
+    /// for a road in those moves, and the player who is behind could not
+    /// make up the flat difference either, with each move taking over as
+    /// many squares as the longest spread it could make.
+    pub fn dead_position_result(&self) -> Option<GameResult> {
+        let score = self.score();
+        let leader = match score {
+            s if s > 0 => Colour::White,
+            s if s < 0 => Colour::Black,
+            _ => return None,
+        };
+        let moves_left = self.pieces_left(leader) as usize;
+        // the squares whose top a move changes, the one it starts from and
+        // the ones it drops on
+        let touched: usize = self.move_reach(moves_left).map(|reach| reach + 1).sum();
+        for colour in [Colour::White, Colour::Black] {
+            let has_capstone = self.reserves(colour).1 > 0 || self.board.piece_counts(colour).1 > 0;
+            if let Some(distance) = self.board.road_distance(colour, has_capstone) {
+                if distance <= touched {
+                    return None;
+                }
+            }
+        }
+        // every changed square gains the trailing player at most two flats,
+        // when a flat of the leader becomes their own, which is four in the
+        // half flats of the score
+        if score.unsigned_abs() as usize > 4 * touched {
+            Some(GameResult::Winner {
+                colour: leader,
+                reason: WinReason::Flats,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Most squares a player can drop on in each of their next `moves`
+    /// moves. A placement drops on one, and a spread on at most as many as
+    /// it carries, which is bounded by the carry limit and by the tallest
+    /// stack. Moving a stack onto another at most doubles the tallest one,
+    /// so it can grow with every ply before the move.
+    fn move_reach(&self, moves: usize) -> impl Iterator<Item = usize> {
+        let tallest = Pos::all()
+            .filter_map(|pos| self.board[pos].as_ref().map(Tile::size))
+            .max()
+            .unwrap_or(0);
+        // the opponent may move first
+        let mut height = (2 * tallest).clamp(1, N);
+        (0..moves).map(move |_| {
+            let reach = height;
+            // and once more between two moves of the player
+            height = (4 * height).min(N);
+            reach
+        })
+    }
+
+    /// The result of the game, or of the adjudication of a dead position.
+    pub fn adjudicated_winner(&self) -> GameResult {
+        match self.winner() {
+            GameResult::Ongoing => self.dead_position_result().unwrap_or(GameResult::Ongoing),
+            result => result,
+        }
+    }
+}
@@ -7,6 +7,48 @@ pub enum Direction {
 }
 
 impl Direction {
+    pub const ALL: [Direction; 4] = [Direction::PosX, Direction::PosY, Direction::NegX, Direction::NegY];
+
+    /// The PTN symbol of the direction.
+    pub const fn symbol(self) -> char {
+        match self {
+            Direction::NegX => '<',
+            Direction::PosX => '>',
+            Direction::PosY => '+',
+            Direction::NegY => '-',
+        }
+    }
+
+    pub const fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '<' => Some(Direction::NegX),
+            '>' => Some(Direction::PosX),
+            '+' => Some(Direction::PosY),
+            '-' => Some(Direction::NegY),
+            _ => None,
+        }
+    }
+
+    /// Change in x and y when moving one step in this direction.
+    pub const fn offset(self) -> (isize, isize) {
+        match self {
+            Direction::PosX => (1, 0),
+            Direction::PosY => (0, 1),
+            Direction::NegX => (-1, 0),
+            Direction::NegY => (0, -1),
+        }
+    }
+
+    #[must_use]
+    pub const fn opposite(self) -> Self {
+        match self {
+            Direction::PosX => Direction::NegX,
+            Direction::PosY => Direction::NegY,
+            Direction::NegX => Direction::PosX,
+            Direction::NegY => Direction::PosY,
+        }
+    }
+
     /// rotates a direction 1 quarter turn counterclockwise
     #[must_use]
     pub const fn rotate(&self) -> Self {
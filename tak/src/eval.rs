@@ -0,0 +1,127 @@
+use crate::{
+    board::Board,
+    colour::Colour,
+    game::{Game, GameResult},
+    pos::Pos,
+    tile::Shape,
+};
+
+/// Tunable hand-crafted evaluation.
+/// All weights are in units of flats.
+#[derive(Clone, Debug)]
+pub struct Evaluator {
+    /// Flat on top of a stack.
+    pub flat: f32,
+    /// Wall on top of a stack.
+    pub wall: f32,
+    /// Capstone on top of a stack.
+    pub capstone: f32,
+    /// Opponent's piece buried under your top piece.
+    pub captive: f32,
+    /// Your own piece buried under your top piece.
+    pub reserve: f32,
+    /// Bonus for a capstone in the centre, scaled down towards the edges.
+    pub capstone_centre: f32,
+    /// Pair of adjacent road pieces.
+    pub connection: f32,
+    /// Largest span of a connected group, as a fraction of the board width,
+    /// squared.
+    pub road_span: f32,
+    /// Score that corresponds to a tanh eval of about 0.76.
+    pub scale: f32,
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Evaluator {
+            flat: 1.,
+            wall: 0.4,
+            capstone: 0.6,
+            captive: 0.3,
+            reserve: 0.2,
+            capstone_centre: 0.5,
+            connection: 0.15,
+            road_span: 3.,
+            scale: 5.,
+        }
+    }
+}
+
+impl Evaluator {
+    /// Raw score of the position from white's perspective.
+    pub fn score<const N: usize>(&self, game: &Game<N>) -> f32 {
+        self.material(&game.board) - game.komi.flats() * self.flat + self.roads(&game.board, Colour::White)
+            - self.roads(&game.board, Colour::Black)
+    }
+
+    /// Evaluation between -1 and 1 from the perspective of the player to move.
+    pub fn eval<const N: usize>(&self, game: &Game<N>) -> f32 {
+        match game.winner() {
+            GameResult::Winner { colour, .. } => {
+                if colour == game.to_move {
+                    1.
+                } else {
+                    -1.
+                }
+            }
+            GameResult::Draw { .. } => 0.,
+            GameResult::Ongoing => {
+                let eval = (self.score(game) / self.scale).tanh();
+                match game.to_move {
+                    Colour::White => eval,
+                    Colour::Black => -eval,
+                }
+            }
+        }
+    }
+
+    /// Score of pieces and stacks from white's perspective.
+    fn material<const N: usize>(&self, board: &Board<N>) -> f32 {
+        let centre = (N - 1) as f32 / 2.;
+        let mut score = 0.;
+        for y in 0..N {
+            for x in 0..N {
+                if let Some(tile) = &board[Pos { x, y }] {
+                    let mut value = match tile.top.shape {
+                        Shape::Flat => self.flat,
+                        Shape::Wall => self.wall,
+                        Shape::Capstone => {
+                            let distance = (x as f32 - centre).abs().max((y as f32 - centre).abs());
+                            self.capstone + self.capstone_centre * (1. - distance / centre.max(1.))
+                        }
+                    };
+                    for &colour in &tile.stack {
+                        value += if colour == tile.top.colour {
+                            self.reserve
+                        } else {
+                            self.captive
+                        };
+                    }
+                    score += match tile.top.colour {
+                        Colour::White => value,
+                        Colour::Black => -value,
+                    };
+                }
+            }
+        }
+        score
+    }
+
+    /// Road potential of the colour based on connected groups.
+    fn roads<const N: usize>(&self, board: &Board<N>, colour: Colour) -> f32 {
+        let connections: usize = board
+            .road_groups(colour)
+            .iter()
+            .flatten()
+            .map(|pos| {
+                pos.neighbors()
+                    .into_iter()
+                    .filter(|&neighbor| board.is_road(neighbor, colour))
+                    .count()
+            })
+            .sum();
+        let span = board.road_span(colour) as f32 / N as f32;
+        // every connection was counted from both sides
+        self.connection * (connections / 2) as f32 + self.road_span * span * span
+    }
+}
@@ -1,4 +1,7 @@
-use std::cmp::Ordering;
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+};
 
 use arrayvec::ArrayVec;
 
@@ -6,6 +9,7 @@ use crate::{
     board::Board,
     colour::Colour,
     direction::Direction,
+    komi::Komi,
     pos::Pos,
     tile::{Piece, Shape, Tile},
     turn::Turn,
@@ -26,11 +30,33 @@ pub const fn default_starting_stones(width: usize) -> (Stones, Capstones) {
     }
 }
 
+/// Check that a game of the size may start with `caps` capstones. Sizes
+/// without capstones by default are only played without them, since the
+/// policy of the network has no capstone placements for them.
+pub(crate) fn check_caps(width: usize, caps: Capstones) -> StrResult<()> {
+    if caps > 0 && default_starting_stones(width).1 == 0 {
+        Err(format!("capstones are not supported on {width}x{width}"))
+    } else {
+        Ok(())
+    }
+}
+
 const TURN_LIMIT: u64 = 400;
 
+/// How a game was won.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WinReason {
+    Road,
+    Flats,
+    /// The opponent ran out of time, see [`Clock`](crate::Clock).
+    Time,
+    /// The opponent gave up.
+    Resignation,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum GameResult {
-    Winner { colour: Colour, road: bool },
+    Winner { colour: Colour, reason: WinReason },
     Draw { turn_limit: bool },
     Ongoing,
 }
@@ -44,19 +70,34 @@ pub struct Game<const N: usize> {
     pub black_stones: Stones,
     pub white_caps: Capstones,
     pub black_caps: Capstones,
-    pub komi: i32,
+    pub komi: Komi,
 }
 
 impl<const N: usize> Game<N>
 where
     [[Option<Tile>; N]; N]: Default,
 {
-    pub fn with_komi(komi: i32) -> Self {
+    pub fn with_komi(komi: Komi) -> Self {
         Game {
             komi,
             ..Default::default()
         }
     }
+
+    /// A game where both players start with the given pieces instead of the
+    /// standard ones for the board size. Sizes without capstones by default
+    /// cannot be given any.
+    pub fn with_reserves(komi: Komi, stones: Stones, caps: Capstones) -> StrResult<Self> {
+        check_caps(N, caps)?;
+        Ok(Game {
+            komi,
+            white_stones: stones,
+            black_stones: stones,
+            white_caps: caps,
+            black_caps: caps,
+            ..Default::default()
+        })
+    }
 }
 
 impl<const N: usize> Default for Game<N>
@@ -73,11 +114,42 @@ where
             black_stones: stones,
             white_caps: capstones,
             black_caps: capstones,
-            komi: 0,
+            komi: Komi::default(),
         }
     }
 }
 
+/// Games are equal when they are in the same position: the board, player to
+/// move, reserves, komi, and whether the opening swap is still in effect.
+/// The ply count is otherwise ignored so that transpositions compare equal.
+impl<const N: usize> PartialEq for Game<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.board == other.board
+            && self.to_move == other.to_move
+            && self.swap() == other.swap()
+            && self.white_stones == other.white_stones
+            && self.black_stones == other.black_stones
+            && self.white_caps == other.white_caps
+            && self.black_caps == other.black_caps
+            && self.komi == other.komi
+    }
+}
+
+impl<const N: usize> Eq for Game<N> {}
+
+impl<const N: usize> Hash for Game<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.board.hash(state);
+        self.to_move.hash(state);
+        self.swap().hash(state);
+        self.white_stones.hash(state);
+        self.black_stones.hash(state);
+        self.white_caps.hash(state);
+        self.black_caps.hash(state);
+        self.komi.hash(state);
+    }
+}
+
 impl<const N: usize> Game<N> {
     pub fn swap(&self) -> bool {
         self.ply < 2
@@ -126,22 +198,50 @@ impl<const N: usize> Game<N> {
         self.play(turns.into_iter().nth(n).unwrap())
     }
 
+    /// Get the reserves that the player to move places pieces from,
+    /// which are the opponent's during the opening swap.
     pub fn get_counts(&self) -> (Stones, Capstones) {
-        match self.to_move {
+        self.reserves(self.colour())
+    }
+
+    /// Get the stones and capstones the player has left to place.
+    pub fn reserves(&self, colour: Colour) -> (Stones, Capstones) {
+        match colour {
             Colour::White => (self.white_stones, self.white_caps),
             Colour::Black => (self.black_stones, self.black_caps),
         }
     }
 
+    /// Flat count difference adjusted by komi in half flats, positive when
+    /// white is ahead.
+    pub fn score(&self) -> i32 {
+        2 * self.board.flat_diff() - self.komi.half_flats() as i32
+    }
+
+    /// The result of the game if it was decided by flat count right now.
+    pub fn flat_result(&self) -> GameResult {
+        match self.score().cmp(&0) {
+            Ordering::Greater => GameResult::Winner {
+                colour: Colour::White,
+                reason: WinReason::Flats,
+            },
+            Ordering::Less => GameResult::Winner {
+                colour: Colour::Black,
+                reason: WinReason::Flats,
+            },
+            Ordering::Equal => GameResult::Draw { turn_limit: false },
+        }
+    }
+
     fn dec_stones(&mut self) {
-        match self.to_move {
+        match self.colour() {
             Colour::White => self.white_stones -= 1,
             Colour::Black => self.black_stones -= 1,
         }
     }
 
     fn dec_caps(&mut self) {
-        match self.to_move {
+        match self.colour() {
             Colour::White => self.white_caps -= 1,
             Colour::Black => self.black_caps -= 1,
         }
@@ -170,10 +270,13 @@ impl<const N: usize> Game<N> {
                 self.ply
             ))
         } else {
-            self.board[pos] = Some(Tile::new(Piece {
-                colour: self.colour(),
-                shape,
-            }));
+            self.board.set(
+                pos,
+                Some(Tile::new(Piece {
+                    colour: self.colour(),
+                    shape,
+                })),
+            );
             if matches!(shape, Shape::Flat | Shape::Wall) {
                 self.dec_stones();
             } else {
@@ -184,16 +287,40 @@ impl<const N: usize> Game<N> {
     }
 
     fn execute_move(&mut self, pos: Pos<N>, direction: Direction, moves: ArrayVec<bool, N>) -> StrResult<()> {
-        // take the pieces
-        let on_square = self.board[pos].take().ok_or("cannot move from an empty square")?;
+        if self.swap() {
+            return Err(format!(
+                "cannot move a stack on the first two plies, ply={}",
+                self.ply
+            ));
+        }
+        let on_square = self.board[pos]
+            .as_ref()
+            .ok_or("cannot move from an empty square")?;
         if on_square.top.colour != self.to_move {
             return Err(format!(
                 "cannot move a stack that you do not own, pos={pos:?},\n{}",
                 self.board
             ));
         }
-        let (left, carry) = on_square.take::<N>(moves.len())?;
-        self.board[pos] = left;
+        let (left, carry) = on_square.clone().take::<N>(moves.len())?;
+
+        // check every drop before changing the board,
+        // so that an illegal spread leaves the game untouched
+        let mut next = pos.step(direction);
+        for (piece, &should_step) in carry.iter().rev().zip(&moves) {
+            let p = next.ok_or(format!(
+                "cannot move out of board, pos={pos:?}, direction={direction:?}, moves={moves:?}"
+            ))?;
+            if let Some(tile) = &self.board[p] {
+                tile.can_stack(*piece)?;
+            }
+            if should_step {
+                next = p.step(direction);
+            }
+        }
+
+        // take the pieces
+        self.board.set(pos, left);
 
         let mut next = pos.step(direction);
         for (carry, &should_step) in carry.into_iter().rev().zip(&moves) {
@@ -203,11 +330,11 @@ impl<const N: usize> Game<N> {
             ))?;
 
             // stack the dropped piece on top
-            if let Some(t) = self.board[p].take() {
-                self.board[p] = Some(t.stack(carry)?);
-            } else {
-                self.board[p] = Some(Tile::new(carry));
-            }
+            let tile = match self.board.take(p) {
+                Some(t) => t.stack(carry)?,
+                None => Tile::new(carry),
+            };
+            self.board.set(p, Some(tile));
             if should_step {
                 next = p.step(direction);
             }
@@ -230,33 +357,27 @@ impl<const N: usize> Game<N> {
         Ok(())
     }
 
+    /// Get the squares of the road that won the game, if it was won by a road.
+    pub fn road(&self) -> Option<Vec<Pos<N>>> {
+        // the player who just moved wins double roads
+        self.board
+            .find_road(self.to_move.next())
+            .or_else(|| self.board.find_road(self.to_move))
+    }
+
     pub fn winner(&self) -> GameResult {
         if self.board.find_paths(self.to_move.next()) {
             GameResult::Winner {
                 colour: self.to_move.next(),
-                road: true,
+                reason: WinReason::Road,
             }
         } else if self.board.find_paths(self.to_move) {
             GameResult::Winner {
                 colour: self.to_move,
-                road: true,
-            }
-        } else if self.white_caps == 0 && self.white_stones == 0
-            || self.black_caps == 0 && self.black_stones == 0
-            || self.board.full()
-        {
-            let flat_diff = self.board.flat_diff();
-            match flat_diff.cmp(&self.komi) {
-                Ordering::Greater => GameResult::Winner {
-                    colour: Colour::White,
-                    road: false,
-                },
-                Ordering::Less => GameResult::Winner {
-                    colour: Colour::Black,
-                    road: false,
-                },
-                Ordering::Equal => GameResult::Draw { turn_limit: false },
+                reason: WinReason::Road,
             }
+        } else if self.out_of_pieces() || self.board.full() {
+            self.flat_result()
         } else if self.ply >= TURN_LIMIT {
             GameResult::Draw { turn_limit: true }
         } else {
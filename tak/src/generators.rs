@@ -0,0 +1,94 @@
+//! Generators for property tests and fuzzing, enabled with the `arbitrary`
+//! feature.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use arrayvec::ArrayVec;
+
+use crate::{
+    colour::Colour,
+    direction::Direction,
+    game::{Game, GameResult},
+    pos::Pos,
+    tile::{Shape, Tile},
+    turn::Turn,
+};
+
+/// Longest random playout used to generate a game.
+const MAX_PLIES: usize = 120;
+
+impl<'a> Arbitrary<'a> for Colour {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(*u.choose(&[Colour::White, Colour::Black])?)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Shape {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(*u.choose(&[Shape::Flat, Shape::Wall, Shape::Capstone])?)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Direction {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(*u.choose(&Direction::ALL)?)
+    }
+}
+
+impl<'a, const N: usize> Arbitrary<'a> for Pos<N> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Pos {
+            x: u.int_in_range(0..=N - 1)?,
+            y: u.int_in_range(0..=N - 1)?,
+        })
+    }
+}
+
+/// A well-formed turn, which is not necessarily legal in any position.
+/// Use [`arbitrary_legal_turn`] for a turn in a specific game.
+impl<'a, const N: usize> Arbitrary<'a> for Turn<N> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let pos = Pos::arbitrary(u)?;
+        if u.arbitrary()? {
+            return Ok(Turn::Place {
+                pos,
+                shape: Shape::arbitrary(u)?,
+            });
+        }
+        // the last piece is always dropped without stepping further
+        let mut moves = ArrayVec::new();
+        for _ in 1..u.int_in_range(1..=N)? {
+            moves.push(u.arbitrary()?);
+        }
+        moves.push(false);
+        Ok(Turn::Move {
+            pos,
+            direction: Direction::arbitrary(u)?,
+            moves,
+        })
+    }
+}
+
+/// A game reached by playing random legal turns from the start.
+impl<'a, const N: usize> Arbitrary<'a> for Game<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut game = Game::default();
+        for _ in 0..u.int_in_range(0..=MAX_PLIES)? {
+            match arbitrary_legal_turn(&game, u)? {
+                Some(turn) => game.play(turn).expect("generated turn should be legal"),
+                None => break,
+            }
+        }
+        Ok(game)
+    }
+}
+
+/// Pick one of the legal turns of the game, or `None` if it is over.
+pub fn arbitrary_legal_turn<const N: usize>(game: &Game<N>, u: &mut Unstructured) -> Result<Option<Turn<N>>> {
+    if game.winner() != GameResult::Ongoing {
+        return Ok(None);
+    }
+    Ok(Some(u.choose(&game.possible_turns())?.clone()))
+}
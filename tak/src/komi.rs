@@ -0,0 +1,59 @@
+use std::{fmt::Display, str::FromStr};
+
+/// Flats added to black's count at the end of the game, stored in half flats
+/// so that the usual 2.5 komi can be represented.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Komi(i8);
+
+impl Komi {
+    /// Panics if the komi does not fit in half flats, see
+    /// [`Komi::checked_from_flats`].
+    pub const fn from_flats(flats: i8) -> Self {
+        match Self::checked_from_flats(flats) {
+            Some(komi) => komi,
+            None => panic!("komi out of range"),
+        }
+    }
+
+    /// Komi of whole flats, or `None` if it does not fit in half flats.
+    pub const fn checked_from_flats(flats: i8) -> Option<Self> {
+        match flats.checked_mul(2) {
+            Some(half_flats) => Some(Komi(half_flats)),
+            None => None,
+        }
+    }
+
+    pub const fn from_half_flats(half_flats: i8) -> Self {
+        Komi(half_flats)
+    }
+
+    pub const fn half_flats(self) -> i8 {
+        self.0
+    }
+
+    pub fn flats(self) -> f32 {
+        self.0 as f32 / 2.
+    }
+}
+
+/// Written like `2` or `2.5`.
+impl Display for Komi {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.flats())
+    }
+}
+
+/// Read a whole or half number of flats, like `2`, `2.0` or `2.5`.
+impl FromStr for Komi {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || format!("cannot parse komi {s}");
+        let flats: f64 = s.trim().parse().map_err(|_| error())?;
+        let half_flats = flats * 2.;
+        if half_flats.fract() != 0. || half_flats < i8::MIN as f64 || half_flats > i8::MAX as f64 {
+            return Err(error());
+        }
+        Ok(Komi(half_flats as i8))
+    }
+}
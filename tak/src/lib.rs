@@ -3,26 +3,47 @@
 #[macro_use]
 extern crate lazy_static;
 
+mod bitboard;
 mod board;
+mod clock;
 mod colour;
+mod dead;
 mod direction;
+mod eval;
 mod game;
+#[cfg(feature = "arbitrary")]
+mod generators;
+mod komi;
+mod perft;
+mod phase;
 mod pos;
 mod ptn;
+mod reserves;
+mod solve;
 mod symm;
 mod tile;
+mod tinue;
 mod tps;
 mod turn;
+mod zobrist;
 
 pub type StrResult<T> = Result<T, String>;
 
 // re-export so you can star import everything important
+pub use bitboard::Bitboard;
 pub use board::Board;
+pub use clock::{Clock, TimeControl};
 pub use colour::Colour;
-pub use game::{default_starting_stones, Game, GameResult};
+pub use direction::Direction;
+pub use eval::Evaluator;
+pub use game::{default_starting_stones, Game, GameResult, WinReason};
+#[cfg(feature = "arbitrary")]
+pub use generators::arbitrary_legal_turn;
+pub use komi::Komi;
+pub use phase::Phase;
 pub use pos::Pos;
-pub use ptn::{FromPTN, ToPTN};
+pub use ptn::{ptn_tags, split_ptn_games, FromPTN, Positions, ResultMismatch, ResultSource, ToPTN};
 pub use symm::Symmetry;
-pub use tile::{Piece, Shape, Tile};
+pub use tile::{Piece, Shape, Stack, Tile};
 pub use tps::{FromTPS, ToTPS};
 pub use turn::Turn;
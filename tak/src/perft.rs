@@ -0,0 +1,35 @@
+use crate::{
+    game::{Game, GameResult},
+    turn::Turn,
+};
+
+impl<const N: usize> Game<N> {
+    /// Count the positions reached after `depth` plies, the standard check of
+    /// move generation against the numbers of other engines. Games that are
+    /// over are not continued.
+    pub fn perft(&self, depth: u32) -> u64 {
+        if depth == 0 || !matches!(self.winner(), GameResult::Ongoing) {
+            1
+        } else if depth == 1 {
+            self.possible_turns().len() as u64
+        } else {
+            self.perft_divide(depth).into_iter().map(|(_, count)| count).sum()
+        }
+    }
+
+    /// The [`perft`](Self::perft) count split by the first turn, to narrow
+    /// down where move generation disagrees with another engine.
+    pub fn perft_divide(&self, depth: u32) -> Vec<(Turn<N>, u64)> {
+        if depth == 0 || !matches!(self.winner(), GameResult::Ongoing) {
+            return Vec::new();
+        }
+        self.possible_turns()
+            .into_iter()
+            .map(|turn| {
+                let mut game = self.clone();
+                game.play(turn.clone()).unwrap();
+                (turn, game.perft(depth - 1))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,42 @@
+use crate::{colour::Colour, game::Game, pos::Pos};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Phase {
+    Opening,
+    Midgame,
+    Endgame,
+}
+
+impl<const N: usize> Game<N> {
+    /// Roughly classify the position based on reserves, stacks, and road
+    /// proximity.
+    pub fn phase(&self) -> Phase {
+        let (mut occupied, mut stacks) = (0, 0);
+        for y in 0..N {
+            for x in 0..N {
+                if let Some(tile) = &self.board[Pos { x, y }] {
+                    occupied += 1;
+                    if tile.size() > 1 {
+                        stacks += 1;
+                    }
+                }
+            }
+        }
+
+        let few_reserves = [Colour::White, Colour::Black]
+            .into_iter()
+            .any(|colour| self.pieces_left(colour) as usize <= N);
+        let near_road = [Colour::White, Colour::Black]
+            .into_iter()
+            .any(|colour| self.board.road_span(colour) + 1 >= N);
+        let nearly_full = 5 * occupied >= 4 * N * N;
+
+        if few_reserves || near_road || nearly_full {
+            Phase::Endgame
+        } else if stacks == 0 && occupied <= N {
+            Phase::Opening
+        } else {
+            Phase::Midgame
+        }
+    }
+}
@@ -30,9 +30,36 @@ impl<const N: usize> Pos<N> {
     }
 
     pub fn step(self, direction: Direction) -> Option<Pos<N>> {
-        self.neighbors()
-            .into_iter()
-            .find(|&n| (n - self).unwrap() == direction)
+        let (dx, dy) = direction.offset();
+        self.offset(dx, dy)
+    }
+
+    /// Add an offset, if the result is still on the board.
+    pub fn offset(self, dx: isize, dy: isize) -> Option<Pos<N>> {
+        let (x, y) = (self.x as isize + dx, self.y as isize + dy);
+        let range = 0..N as isize;
+        if range.contains(&x) && range.contains(&y) {
+            Some(Pos {
+                x: x as usize,
+                y: y as usize,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Number of orthogonal steps between two positions.
+    pub const fn distance(self, other: Pos<N>) -> usize {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+
+    pub const fn is_edge(self) -> bool {
+        self.x == 0 || self.y == 0 || self.x == N - 1 || self.y == N - 1
+    }
+
+    /// All positions on the board, row by row.
+    pub fn all() -> impl Iterator<Item = Pos<N>> {
+        (0..N).flat_map(|y| (0..N).map(move |x| Pos { x, y }))
     }
 
     /// Rotates a position 1 quarter turn counterclockwise.
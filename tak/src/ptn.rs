@@ -1,3 +1,5 @@
+use std::{fmt::Display, fs::read_to_string, io, path::Path};
+
 use arrayvec::ArrayVec;
 use regex::Regex;
 
@@ -5,22 +7,27 @@ use crate::{
     board::Board,
     colour::Colour,
     direction::Direction,
-    game::{default_starting_stones, Game},
+    game::{check_caps, default_starting_stones, Game, GameResult, WinReason},
+    komi::Komi,
     pos::Pos,
     tile::{Shape, Tile},
-    tps::FromTPS,
+    tps::{remaining_reserves, tps_position},
     turn::Turn,
     StrResult,
 };
 
 lazy_static! {
-    // (count)(square)(direction)(drop counts)(stone)
-    static ref TURN_MOVE_RE: Regex = Regex::new(r"([1-9]*)([a-z][1-9])([<>+-])([1-9]*)").unwrap();
+    // (count)(square)(direction)(drop counts)(crush)
+    static ref TURN_MOVE_RE: Regex = Regex::new(r"^([1-9]?)([a-z][1-9])([<>+-])([1-9]*)(\*?)$").unwrap();
     // (stone)(square)
-    static ref TURN_PLACE_RE: Regex = Regex::new(r"([CS]?)([a-z][1-9])").unwrap();
+    static ref TURN_PLACE_RE: Regex = Regex::new(r"^([FCS]?)([a-z][1-9])$").unwrap();
     static ref OPTIONS_RE: Regex = Regex::new(r#"\[(\S+) ["'](.*?)["']\]"#).unwrap();
-    static ref COMMENTS_RE: Regex = Regex::new(r"\{.*?\}").unwrap();
-    static ref PLY_SPLIT_RE: Regex = Regex::new(r"\s*\d*\. |\s+|1-0|R-0|F-0|0-1|0-R|0-F|1/2-1/2|--").unwrap();
+    // numeric annotation glyphs like $3
+    static ref NAG_RE: Regex = Regex::new(r"\$\d+").unwrap();
+    // a result at the end of the moves
+    static ref RESULT_RE: Regex = Regex::new(r"(?:^|\s)(R-0|0-R|F-0|0-F|1-0|0-1|1/2-1/2|0-0)\s*$").unwrap();
+    static ref PLY_SPLIT_RE: Regex =
+        Regex::new(r"\s*\d+\.+\s*|\s+|1-0|R-0|F-0|0-1|0-R|0-F|1/2-1/2|0-0|--").unwrap();
 }
 
 pub trait FromPTN: Sized {
@@ -33,11 +40,9 @@ pub trait ToPTN {
 
 impl FromPTN for Direction {
     fn from_ptn(s: &str) -> StrResult<Self> {
-        match s {
-            "<" => Ok(Direction::NegX),
-            ">" => Ok(Direction::PosX),
-            "+" => Ok(Direction::PosY),
-            "-" => Ok(Direction::NegY),
+        let mut chars = s.chars();
+        match (chars.next().and_then(Direction::from_symbol), chars.next()) {
+            (Some(direction), None) => Ok(direction),
             _ => Err(format!("unknown direction {s}")),
         }
     }
@@ -45,23 +50,21 @@ impl FromPTN for Direction {
 
 impl ToPTN for Direction {
     fn to_ptn(&self) -> String {
-        match self {
-            Direction::NegX => "<",
-            Direction::PosX => ">",
-            Direction::PosY => "+",
-            Direction::NegY => "-",
-        }
-        .to_string()
+        self.symbol().to_string()
     }
 }
 
 impl<const N: usize> FromPTN for Pos<N> {
     fn from_ptn(s: &str) -> StrResult<Self> {
-        let x = (s.bytes().next().ok_or("position is too short")? - b'a') as usize;
-        let y = s[1..]
-            .parse::<usize>()
-            .map_err(|_| format!("couldn't parse vertical position {s}"))?
-            - 1;
+        let x = match s.bytes().next() {
+            Some(file @ b'a'..=b'z') => (file - b'a') as usize,
+            _ => return Err(format!("couldn't parse horizontal position {s}")),
+        };
+        let y = s
+            .get(1..)
+            .and_then(|rank| rank.parse::<usize>().ok())
+            .and_then(|rank| rank.checked_sub(1))
+            .ok_or_else(|| format!("couldn't parse vertical position {s}"))?;
         if x >= N || y >= N {
             return Err(format!("position x={x} y={y} is out of bounds"));
         }
@@ -80,7 +83,7 @@ impl FromPTN for Shape {
         match s {
             "C" => Ok(Shape::Capstone),
             "S" => Ok(Shape::Wall),
-            "" => Ok(Shape::Flat),
+            "F" | "" => Ok(Shape::Flat),
             _ => Err(format!("unknown shape {s}")),
         }
     }
@@ -117,12 +120,21 @@ impl FromPTN for Colour {
     }
 }
 
+/// A single ply like `Sc3`, `3b4>12` or `d4-*`. Annotations like `'` or
+/// `!?` are ignored, and so is the crush mark `*`, since whether a spread
+/// flattens a wall depends on the board. The whole string has to be a ply.
 impl<const N: usize> FromPTN for Turn<N> {
     fn from_ptn(s: &str) -> StrResult<Self> {
         assert!(N < 10); // the drop notation doesn't support N >= 10
 
+        let s = s.trim().trim_end_matches(['\'', '"', '!', '?']);
         if let Some(cap) = TURN_MOVE_RE.captures(s) {
             let carry_amount = cap[1].parse().unwrap_or(1);
+            if carry_amount as usize > N {
+                return Err(format!(
+                    "cannot carry more than {N} pieces, amount={carry_amount}"
+                ));
+            }
             let pos = Pos::from_ptn(&cap[2])?;
             let direction = Direction::from_ptn(&cap[3])?;
 
@@ -135,6 +147,9 @@ impl<const N: usize> FromPTN for Turn<N> {
                     "picked up {carry_amount} and tried dropping {drop_counts:?} which does not match"
                 ));
             }
+            if &cap[5] == "*" && drop_counts.last() != Some(&1) {
+                return Err(format!("{s} crushes a wall with more than the capstone"));
+            }
 
             let mut moves = ArrayVec::new();
             for drops in drop_counts {
@@ -196,13 +211,59 @@ impl<const N: usize> ToPTN for Turn<N> {
     }
 }
 
+impl<const N: usize> Turn<N> {
+    /// PTN of the turn played on `board`, which marks a capstone flattening
+    /// a wall with `*`.
+    pub fn to_ptn_on(&self, board: &Board<N>) -> String {
+        let mut ptn = self.to_ptn();
+        if self.crushes(board) {
+            ptn.push('*');
+        }
+        ptn
+    }
+
+    /// Whether the turn flattens a wall with a capstone when played on
+    /// `board`.
+    pub fn crushes(&self, board: &Board<N>) -> bool {
+        match self {
+            Turn::Move {
+                pos,
+                direction,
+                moves,
+            } => {
+                let is =
+                    |pos: Pos<N>, shape: Shape| matches!(&board[pos], Some(tile) if tile.top.shape == shape);
+                // a step for the first drop and one for every later one
+                let steps = 1 + moves.iter().filter(|step| **step).count();
+                let last = (0..steps).try_fold(*pos, |pos, _| pos.step(*direction));
+                is(*pos, Shape::Capstone) && matches!(last, Some(last) if is(last, Shape::Wall))
+            }
+            Turn::Place { .. } => false,
+        }
+    }
+}
+
 impl<const N: usize> FromPTN for Game<N>
 where
     [[Option<Tile>; N]; N]: Default,
 {
     fn from_ptn(s: &str) -> StrResult<Game<N>> {
+        let (mut game, turns) = Game::from_ptn_with_turns(s)?;
+        for turn in turns {
+            game.play(turn)?;
+        }
+        Ok(game)
+    }
+}
+
+impl<const N: usize> Game<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// Parse a PTN into the starting position and the turns played from it.
+    pub fn from_ptn_with_turns(s: &str) -> StrResult<(Game<N>, Vec<Turn<N>>)> {
         // parse game options
-        let mut komi = 0;
+        let mut komi = Komi::default();
         let (mut stones, mut caps) = default_starting_stones(N);
         let mut ply = 0;
         let mut board = Board::default();
@@ -211,7 +272,7 @@ where
             let key = &option[1];
             let value = &option[2];
             match key {
-                "Komi" => komi = value.parse::<i32>().map_err(|_| "cannot parse komi")?,
+                "Komi" => komi = value.parse()?,
                 "Flats" => stones = value.parse::<u8>().map_err(|_| "cannot parse flats")?,
                 "Caps" => caps = value.parse::<u8>().map_err(|_| "cannot parse caps")?,
                 "Size" => {
@@ -220,28 +281,17 @@ where
                     }
                 }
                 "TPS" => {
-                    let mut tps = value.split_whitespace();
-                    board = Board::from_tps(tps.next().ok_or("missing board in TPS")?)?;
-                    to_move = Colour::from_ptn(tps.next().ok_or("missing who is moving in TPS")?)?;
-                    ply = (tps
-                        .next()
-                        .ok_or("missing move number in TPS")?
-                        .parse::<u64>()
-                        .map_err(|_| "cannot parse move number")?
-                        - 1)
-                        * 2
-                        + match to_move {
-                            Colour::White => 0,
-                            Colour::Black => 1,
-                        };
+                    (board, to_move, ply) = tps_position(&mut value.split_whitespace())?;
                 }
                 _ => {}
             }
         }
 
-        // remove comments
+        check_caps(N, caps)?;
+
+        // remove tags and commentary
         let s = OPTIONS_RE.replace_all(s, "");
-        let s = COMMENTS_RE.replace_all(&s, "");
+        let s = strip_commentary(&s);
 
         // get individual plies (split at move numbers, space, and game result)
         let moves = PLY_SPLIT_RE
@@ -249,19 +299,287 @@ where
             .filter(|ss| !ss.is_empty())
             .collect::<Vec<_>>();
 
-        let mut game = Game {
+        // pieces already on the board of a TPS start are taken from the reserves
+        let (white_stones, white_caps) = remaining_reserves(&board, Colour::White, stones, caps)?;
+        let (black_stones, black_caps) = remaining_reserves(&board, Colour::Black, stones, caps)?;
+
+        let game = Game {
             komi,
-            white_stones: stones,
-            black_stones: stones,
-            white_caps: caps,
-            black_caps: caps,
+            white_stones,
+            black_stones,
+            white_caps,
+            black_caps,
             board,
             to_move,
             ply,
         };
-        game.play_ptn_moves(&moves)?;
-        Ok(game)
+        let turns = moves
+            .into_iter()
+            .map(Turn::from_ptn)
+            .collect::<StrResult<Vec<_>>>()?;
+        Ok((game, turns))
+    }
+}
+
+/// Positions of a PTN game in order, see [`Game::ptn_positions`].
+pub struct Positions<const N: usize> {
+    next: Option<StrResult<Game<N>>>,
+    turns: std::vec::IntoIter<Turn<N>>,
+}
+
+impl<const N: usize> Iterator for Positions<N> {
+    type Item = StrResult<(u64, Game<N>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let game = match self.next.take()? {
+            Ok(game) => game,
+            Err(err) => return Some(Err(err)),
+        };
+        if let Some(turn) = self.turns.next() {
+            let mut following = game.clone();
+            self.next = Some(following.play(turn).map(|()| following));
+        }
+        Some(Ok((game.ply, game)))
+    }
+}
+
+impl<const N: usize> Game<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// Parse a PTN and lazily replay it, yielding the starting position and
+    /// the position after every turn together with its ply. An illegal turn
+    /// ends the iteration with an error.
+    pub fn ptn_positions(s: &str) -> StrResult<Positions<N>> {
+        let (game, turns) = Game::from_ptn_with_turns(s)?;
+        Ok(Positions {
+            next: Some(Ok(game)),
+            turns: turns.into_iter(),
+        })
     }
+
+    /// Parse a PTN and replay it only up to the position at `ply`.
+    pub fn from_ptn_at(s: &str, ply: u64) -> StrResult<Game<N>> {
+        for position in Game::ptn_positions(s)? {
+            let (current, game) = position?;
+            if current == ply {
+                return Ok(game);
+            }
+        }
+        Err(format!("game does not reach ply {ply}"))
+    }
+}
+
+/// Remove everything from the moves of a PTN which is not a ply: comments
+/// in braces, which may span lines, variations in parentheses, which may be
+/// nested, and numeric annotation glyphs like `$3`. Annotations attached to
+/// a ply, like `!?`, are left for [`Turn::from_ptn`].
+fn strip_commentary(s: &str) -> String {
+    let mut stripped = String::with_capacity(s.len());
+    let mut in_comment = false;
+    let mut variation_depth = 0usize;
+    for c in s.chars() {
+        if in_comment {
+            if c == '}' {
+                in_comment = false;
+                stripped.push(' ');
+            }
+            continue;
+        }
+        match c {
+            '{' => in_comment = true,
+            '(' => variation_depth += 1,
+            ')' if variation_depth > 0 => {
+                variation_depth -= 1;
+                stripped.push(' ');
+            }
+            _ if variation_depth > 0 => {}
+            _ => stripped.push(c),
+        }
+    }
+    NAG_RE.replace_all(&stripped, " ").into_owned()
+}
+
+/// Split a file with several PTN games into the individual games. A game
+/// starts with its tags, so a tag following moves begins the next game.
+/// Text without any moves, such as trailing blank lines, is dropped.
+/// Comments are not moves, and lines inside them are never tags.
+pub fn split_ptn_games(s: &str) -> Vec<&str> {
+    let mut games = Vec::new();
+    let mut start = 0;
+    let mut has_moves = false;
+    let mut in_comment = false;
+    let mut offset = 0;
+    for line in s.split_inclusive('\n') {
+        // the part of the line outside of comments
+        let mut visible = String::new();
+        for c in line.chars() {
+            match c {
+                '{' if !in_comment => in_comment = true,
+                '}' if in_comment => in_comment = false,
+                _ if !in_comment => visible.push(c),
+                _ => {}
+            }
+        }
+        let trimmed = visible.trim();
+        if trimmed.starts_with('[') {
+            if has_moves {
+                games.push(&s[start..offset]);
+                start = offset;
+                has_moves = false;
+            }
+        } else if !trimmed.is_empty() {
+            has_moves = true;
+        }
+        offset += line.len();
+    }
+    if has_moves || (start == 0 && !s.trim().is_empty()) {
+        games.push(&s[start..]);
+    }
+    games
+}
+
+impl<const N: usize> Game<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// Parse every game of a file with several PTN games, see
+    /// [`split_ptn_games`]. Each game is parsed on its own so one broken
+    /// game does not hide the others.
+    pub fn from_ptn_games(s: &str) -> Vec<StrResult<Game<N>>> {
+        split_ptn_games(s).into_iter().map(Game::from_ptn).collect()
+    }
+
+    /// Read a PTN file, such as a dump of PlayTak games, and parse every game
+    /// in it like [`Game::from_ptn_games`]. Only failing to read the file is
+    /// an error for all of them.
+    pub fn games_from_ptn_file(path: impl AsRef<Path>) -> io::Result<Vec<StrResult<Game<N>>>> {
+        Ok(Game::from_ptn_games(&read_to_string(path)?))
+    }
+}
+
+/// Where a PTN records the result of its game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResultSource {
+    /// The `Result` tag.
+    Tag,
+    /// The result at the end of the moves.
+    Moves,
+}
+
+/// A result recorded in a PTN which the rules do not agree with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResultMismatch {
+    pub source: ResultSource,
+    pub recorded: String,
+    pub computed: GameResult,
+}
+
+impl Display for ResultMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let source = match self.source {
+            ResultSource::Tag => "result tag",
+            ResultSource::Moves => "result after the moves",
+        };
+        write!(
+            f,
+            "{source} is {} but the game ends in {:?}",
+            self.recorded, self.computed
+        )
+    }
+}
+
+/// Whether a PTN result agrees with the result of the rules. Results which
+/// the rules cannot see, like resignations and time losses, only need the
+/// game to be unfinished or won by the same player.
+fn result_agrees(recorded: &str, computed: GameResult) -> bool {
+    match (recorded, computed) {
+        (
+            "R-0",
+            GameResult::Winner {
+                colour: Colour::White,
+                reason,
+            },
+        ) => reason == WinReason::Road,
+        (
+            "0-R",
+            GameResult::Winner {
+                colour: Colour::Black,
+                reason,
+            },
+        ) => reason == WinReason::Road,
+        (
+            "F-0",
+            GameResult::Winner {
+                colour: Colour::White,
+                reason,
+            },
+        ) => reason == WinReason::Flats,
+        (
+            "0-F",
+            GameResult::Winner {
+                colour: Colour::Black,
+                reason,
+            },
+        ) => reason == WinReason::Flats,
+        ("1/2-1/2", GameResult::Draw { .. }) => true,
+        (
+            "1-0",
+            GameResult::Winner {
+                colour: Colour::White,
+                ..
+            },
+        ) => true,
+        (
+            "0-1",
+            GameResult::Winner {
+                colour: Colour::Black,
+                ..
+            },
+        ) => true,
+        ("1/2-1/2" | "1-0" | "0-1" | "0-0", GameResult::Ongoing) => true,
+        _ => false,
+    }
+}
+
+impl<const N: usize> Game<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// Replay a PTN and compare the results it records, in the `Result` tag
+    /// and after the moves, with the result of the rules. Mismatches are
+    /// returned rather than treated as errors, since they are common in
+    /// community data and the game itself is still usable.
+    pub fn ptn_result_mismatches(s: &str) -> StrResult<Vec<ResultMismatch>> {
+        let computed = Game::<N>::from_ptn(s)?.winner();
+        let tag = OPTIONS_RE
+            .captures_iter(s)
+            .find(|option| &option[1] == "Result")
+            .map(|option| (ResultSource::Tag, option[2].trim().to_string()));
+        let moves = OPTIONS_RE.replace_all(s, "");
+        let moves = strip_commentary(&moves);
+        let marker = RESULT_RE
+            .captures(&moves)
+            .map(|result| (ResultSource::Moves, result[1].to_string()));
+        Ok(tag
+            .into_iter()
+            .chain(marker)
+            .filter(|(_, recorded)| !recorded.is_empty() && !result_agrees(recorded, computed))
+            .map(|(source, recorded)| ResultMismatch {
+                source,
+                recorded,
+                computed,
+            })
+            .collect())
+    }
+}
+
+/// Get the tags of a PTN, such as `[Player1 "name"]`, in order.
+pub fn ptn_tags(s: &str) -> Vec<(String, String)> {
+    OPTIONS_RE
+        .captures_iter(s)
+        .map(|tag| (tag[1].to_string(), tag[2].to_string()))
+        .collect()
 }
 
 impl<const N: usize> Game<N> {
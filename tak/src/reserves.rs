@@ -0,0 +1,101 @@
+use crate::{
+    colour::Colour,
+    game::{default_starting_stones, Game, GameResult},
+    tile::Tile,
+    StrResult,
+};
+
+impl<const N: usize> Game<N> {
+    /// Stones and capstones that the player has left to place.
+    pub fn pieces_left(&self, colour: Colour) -> u32 {
+        let (stones, caps) = self.reserves(colour);
+        stones as u32 + caps as u32
+    }
+
+    /// Stones and capstones that the player started with, the ones on the
+    /// board and the ones left to place.
+    pub fn starting_reserves(&self, colour: Colour) -> (u32, u32) {
+        let (stones, caps) = self.board.piece_counts(colour);
+        let (left_stones, left_caps) = self.reserves(colour);
+        (stones + left_stones as u32, caps + left_caps as u32)
+    }
+
+    /// Whether a player has placed all of their pieces, which ends the game.
+    pub fn out_of_pieces(&self) -> bool {
+        self.pieces_left(Colour::White) == 0 || self.pieces_left(Colour::Black) == 0
+    }
+
+    /// Fewest plies after which a player could run out of pieces,
+    /// if both players only placed from now on.
+    pub fn plies_until_out_of_pieces(&self) -> u32 {
+        let mine = self.pieces_left(self.to_move);
+        let theirs = self.pieces_left(self.to_move.next());
+        if mine == 0 || theirs == 0 {
+            0
+        } else {
+            (2 * mine - 1).min(2 * theirs)
+        }
+    }
+
+    /// Whether the game could end by a player running out of pieces
+    /// on this ply or the next one.
+    pub fn out_of_pieces_imminent(&self) -> bool {
+        self.plies_until_out_of_pieces() <= 2
+    }
+}
+
+impl<const N: usize> Game<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// Check that a position set up by hand could occur in a game: the
+    /// pieces on the board and in reserve fit the starting stones, the side
+    /// to move matches the ply, and the game is not over yet.
+    pub fn validate(&self) -> StrResult<()> {
+        let (stones, caps) = default_starting_stones(N);
+        for colour in [Colour::White, Colour::Black] {
+            let (used_stones, used_caps) = self.board.piece_counts(colour);
+            let (left_stones, left_caps) = self.reserves(colour);
+            if used_stones + left_stones as u32 > stones as u32 {
+                return Err(format!(
+                    "{colour} has {used_stones} stones on the board and {left_stones} in reserve, but only \
+                     {stones} to start with"
+                ));
+            }
+            if used_caps + left_caps as u32 > caps as u32 {
+                return Err(format!(
+                    "{colour} has {used_caps} capstones on the board and {left_caps} in reserve, but only \
+                     {caps} to start with"
+                ));
+            }
+        }
+        let expected = match self.ply % 2 {
+            0 => Colour::White,
+            _ => Colour::Black,
+        };
+        if self.to_move != expected {
+            return Err(format!("{expected} is to move on ply {}", self.ply));
+        }
+        let on_board: u32 = [Colour::White, Colour::Black]
+            .into_iter()
+            .map(|colour| {
+                let (stones, caps) = self.board.piece_counts(colour);
+                stones + caps
+            })
+            .sum();
+        if self.ply < 2 && on_board as u64 != self.ply {
+            return Err(format!(
+                "only {} pieces can be on the board on ply {}",
+                self.ply, self.ply
+            ));
+        }
+        // the first piece is a flat of the opponent of the player placing it
+        if self.ply == 1 && self.board.flats(Colour::Black).count() != 1 {
+            return Err("the piece placed on ply 0 has to be a black flat".to_string());
+        }
+        if self.winner() != GameResult::Ongoing {
+            return Err("the game is already over".to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use crate::{
+    colour::Colour,
+    game::{Game, GameResult},
+};
+
+impl<const N: usize> Game<N> {
+    /// The result of the game with best play from both sides, if every line
+    /// ends within `depth` plies, by a road or by flats. At most `max_nodes`
+    /// positions are searched, so a position which is too large to solve in
+    /// time is given up on instead.
+    pub fn solve(&self, depth: u32, max_nodes: u64) -> Option<GameResult> {
+        let mut nodes = max_nodes;
+        self.solve_within(depth, &mut nodes)
+    }
+
+    /// Fewest plies until the game could end on flats, by filling the board
+    /// or by a player running out of pieces. Positions closer to that end
+    /// than the depth of [`Game::solve`] are the ones it can prove.
+    pub fn plies_until_flat_end(&self) -> u32 {
+        let empty = (N * N) as u32 - self.board.occupied().count();
+        self.plies_until_out_of_pieces().min(empty)
+    }
+
+    /// Depth-limited negamax over the three results, where a win cuts off
+    /// the remaining moves.
+    fn solve_within(&self, depth: u32, nodes: &mut u64) -> Option<GameResult> {
+        let result = self.winner();
+        if result != GameResult::Ongoing {
+            return Some(result);
+        }
+        if depth == 0 || *nodes == 0 {
+            return None;
+        }
+        *nodes -= 1;
+
+        let me = self.to_move;
+        let children: Vec<_> = self
+            .possible_turns()
+            .into_iter()
+            .filter_map(|turn| {
+                let mut game = self.clone();
+                game.play(turn).ok().map(|()| game)
+            })
+            .collect();
+        // immediate wins first, which spares searching the other moves
+        if let Some(win) = children
+            .iter()
+            .map(Game::winner)
+            .find(|result| score(*result, me) == 1)
+        {
+            return Some(win);
+        }
+
+        let mut best = None;
+        let mut unknown = false;
+        for child in children {
+            match child.solve_within(depth - 1, nodes) {
+                Some(result) if score(result, me) == 1 => return Some(result),
+                Some(result) => match best {
+                    Some(best) if score(best, me) >= score(result, me) => {}
+                    _ => best = Some(result),
+                },
+                None => unknown = true,
+            }
+        }
+        // a move which was not solved could be better than the best one
+        if unknown {
+            None
+        } else {
+            best
+        }
+    }
+}
+
+/// Order of the results for `colour`, 1 for a win and -1 for a loss.
+fn score(result: GameResult, colour: Colour) -> i8 {
+    match result {
+        GameResult::Winner { colour: winner, .. } if winner == colour => 1,
+        GameResult::Winner { .. } => -1,
+        _ => 0,
+    }
+}
@@ -1,6 +1,7 @@
 use std::iter::once;
 
 use arrayvec::ArrayVec;
+use smallvec::SmallVec;
 
 use crate::{colour::Colour, StrResult};
 
@@ -17,17 +18,21 @@ pub struct Piece {
     pub shape: Shape,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+/// Pieces below the top of a tile, ordered bottom to top.
+/// Stacks up to the largest carry limit are stored inline.
+pub type Stack = SmallVec<[Colour; 8]>;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Tile {
     pub top: Piece,
-    pub stack: Vec<Colour>,
+    pub stack: Stack,
 }
 
 impl Tile {
     pub fn new(top: Piece) -> Self {
         Tile {
             top,
-            stack: Vec::new(),
+            stack: Stack::new(),
         }
     }
 
@@ -36,8 +41,8 @@ impl Tile {
         1 + self.stack.len()
     }
 
-    /// Try to stack the piece on this tile.
-    pub fn stack(mut self, piece: Piece) -> StrResult<Self> {
+    /// Check whether the piece could be stacked on this tile.
+    pub fn can_stack(&self, piece: Piece) -> StrResult<()> {
         // Only allow stacking on top of flats, or flattening walls.
         match self.top.shape {
             Shape::Flat => Ok(()),
@@ -45,11 +50,16 @@ impl Tile {
                 if matches!(piece.shape, Shape::Capstone) {
                     Ok(())
                 } else {
-                    Err("can only flatten a wall with a capstone")
+                    Err("can only flatten a wall with a capstone".to_string())
                 }
             }
-            Shape::Capstone => Err("cannot create a stack on top of a capstone"),
-        }?;
+            Shape::Capstone => Err("cannot create a stack on top of a capstone".to_string()),
+        }
+    }
+
+    /// Try to stack the piece on this tile.
+    pub fn stack(mut self, piece: Piece) -> StrResult<Self> {
+        self.can_stack(piece)?;
 
         self.stack.push(self.top.colour);
         Ok(Tile {
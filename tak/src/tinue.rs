@@ -0,0 +1,50 @@
+use crate::{
+    game::{Game, GameResult},
+    turn::Turn,
+};
+
+impl<const N: usize> Game<N> {
+    /// Turns which force a win within `depth` plies, counting the plies of
+    /// both players. A depth of 1 finds immediate wins, 3 finds tinue.
+    pub fn winning_turns(&self, depth: u32) -> Vec<Turn<N>> {
+        self.possible_turns()
+            .into_iter()
+            .filter(|turn| self.turn_wins(turn, depth))
+            .collect()
+    }
+
+    /// Whether the player to move can force a win within `depth` plies.
+    pub fn can_force_win(&self, depth: u32) -> bool {
+        self.possible_turns()
+            .into_iter()
+            .any(|turn| self.turn_wins(&turn, depth))
+    }
+
+    fn turn_wins(&self, turn: &Turn<N>, depth: u32) -> bool {
+        if depth == 0 || !matches!(self.winner(), GameResult::Ongoing) {
+            return false;
+        }
+        let me = self.to_move;
+        let mut game = self.clone();
+        if game.play(turn.clone()).is_err() {
+            return false;
+        }
+        match game.winner() {
+            GameResult::Winner { colour, .. } => colour == me,
+            GameResult::Draw { .. } => false,
+            // every reply has to lose
+            GameResult::Ongoing => {
+                depth >= 3
+                    && game.possible_turns().into_iter().all(|reply| {
+                        let mut game = game.clone();
+                        game.play(reply).is_ok()
+                            && match game.winner() {
+                                GameResult::Winner { colour, .. } => colour == me,
+                                GameResult::Draw { .. } => false,
+                                GameResult::Ongoing => game.can_force_win(depth - 2),
+                            }
+                    })
+            }
+        }
+    }
+}
@@ -1,12 +1,15 @@
+use std::str::SplitWhitespace;
+
 use regex::Regex;
 
 use crate::{
     board::Board,
     colour::Colour,
-    game::Game,
+    game::{default_starting_stones, Game},
+    komi::Komi,
     pos::Pos,
     ptn::{FromPTN, ToPTN},
-    tile::{Piece, Shape, Tile},
+    tile::{Piece, Shape, Stack, Tile},
     StrResult,
 };
 
@@ -41,6 +44,121 @@ impl<const N: usize> ToTPS for Game<N> {
     }
 }
 
+impl<const N: usize> Game<N> {
+    /// Standard TPS as used by the `TPS` tag of a PTN,
+    /// without the reserves and komi of [`ToTPS::to_tps`].
+    pub fn ptn_tps(&self) -> String {
+        format!(
+            "{} {} {}",
+            self.board.to_tps(),
+            self.to_move.to_ptn(),
+            (self.ply / 2) + 1
+        )
+    }
+}
+
+impl<const N: usize> FromTPS for Game<N>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    /// Read a standard TPS, where the reserves are the default ones minus the
+    /// pieces on the board and komi is 0, or the modified TPS of
+    /// [`ToTPS::to_tps`] which also has the reserves and komi.
+    fn from_tps(s: &str) -> StrResult<Self> {
+        let mut fields = s.split_whitespace();
+        let (board, to_move, ply) = tps_position(&mut fields)?;
+
+        let (stones, caps) = default_starting_stones(N);
+        let (white_stones, white_caps) = match fields.next() {
+            Some(reserves) => parse_reserves(reserves)?,
+            None => remaining_reserves(&board, Colour::White, stones, caps)?,
+        };
+        let (black_stones, black_caps) = match fields.next() {
+            Some(reserves) => parse_reserves(reserves)?,
+            None => remaining_reserves(&board, Colour::Black, stones, caps)?,
+        };
+        let komi = match fields.next() {
+            Some(komi) => komi.parse()?,
+            None => Komi::default(),
+        };
+        if let Some(extra) = fields.next() {
+            return Err(format!("unexpected {extra} at the end of the TPS"));
+        }
+
+        Ok(Game {
+            board,
+            to_move,
+            ply,
+            white_stones,
+            black_stones,
+            white_caps,
+            black_caps,
+            komi,
+        })
+    }
+}
+
+/// Read the board, player to move, and move number of a TPS, returning the
+/// ply they correspond to.
+pub(crate) fn tps_position<const N: usize>(fields: &mut SplitWhitespace) -> StrResult<(Board<N>, Colour, u64)>
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    let board = Board::from_tps(fields.next().ok_or("missing board in TPS")?)?;
+    let to_move = Colour::from_ptn(fields.next().ok_or("missing who is moving in TPS")?)?;
+    let number = fields
+        .next()
+        .ok_or("missing move number in TPS")?
+        .parse::<u64>()
+        .map_err(|_| "cannot parse move number")?;
+    let ply = number
+        .checked_sub(1)
+        .and_then(|moves| moves.checked_mul(2))
+        .and_then(|ply| {
+            ply.checked_add(match to_move {
+                Colour::White => 0,
+                Colour::Black => 1,
+            })
+        })
+        .ok_or("move number out of range")?;
+    Ok((board, to_move, ply))
+}
+
+/// Reserves left of `stones` and `caps` once the pieces of `colour` on the
+/// board are taken from them.
+pub(crate) fn remaining_reserves<const N: usize>(
+    board: &Board<N>,
+    colour: Colour,
+    stones: u8,
+    caps: u8,
+) -> StrResult<(u8, u8)> {
+    let (used_stones, used_caps) = board.piece_counts(colour);
+    Ok((
+        (stones as u32)
+            .checked_sub(used_stones)
+            .ok_or("too many stones on the board")? as u8,
+        (caps as u32)
+            .checked_sub(used_caps)
+            .ok_or("too many capstones on the board")? as u8,
+    ))
+}
+
+/// Parse reserves written as `(stones/caps)`.
+fn parse_reserves(s: &str) -> StrResult<(u8, u8)> {
+    let (stones, caps) = s
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .and_then(|s| s.split_once('/'))
+        .ok_or_else(|| format!("cannot parse reserves {s}"))?;
+    Ok((
+        stones
+            .parse()
+            .map_err(|_| format!("cannot parse stones {stones}"))?,
+        caps.parse()
+            .map_err(|_| format!("cannot parse capstones {caps}"))?,
+    ))
+}
+
 impl<const N: usize> ToTPS for Board<N> {
     /// Get board TPS
     fn to_tps(&self) -> String {
@@ -97,11 +215,14 @@ where
         }
         for (i, row) in s.split('/').enumerate() {
             let y = N - i - 1;
-            let mut x = 0;
+            let mut x: usize = 0;
             for tile in row.split(',') {
                 if let Some(cap) = EMPTY_TILE_RE.captures(tile) {
-                    x += cap[1].parse::<usize>().unwrap_or(1);
+                    x = x.saturating_add(cap[1].parse::<usize>().unwrap_or(1));
                 } else {
+                    if x >= N {
+                        return Err(format!("too many squares in row {row}"));
+                    }
                     let pos = Pos { x, y };
                     let cap = STACK_TILE_RE
                         .captures(tile)
@@ -109,12 +230,12 @@ where
                     let stack = cap[1]
                         .chars()
                         .map(|c| Colour::from_ptn(&c.to_string()))
-                        .collect::<StrResult<Vec<_>>>()?;
+                        .collect::<StrResult<Stack>>()?;
                     let piece = Piece {
                         shape: Shape::from_ptn(&cap[3])?,
                         colour: Colour::from_ptn(&cap[2])?,
                     };
-                    board[pos] = Some(Tile { top: piece, stack });
+                    board.set(pos, Some(Tile { top: piece, stack }));
                     x += 1;
                 }
             }
@@ -1,12 +1,14 @@
 use std::cmp::min;
 
 use arrayvec::ArrayVec;
+use smallvec::{smallvec, SmallVec};
 
 use crate::{
+    colour::Colour,
     direction::Direction,
     game::Game,
     pos::Pos,
-    tile::{Piece, Shape, Tile},
+    tile::{Shape, Tile},
 };
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
@@ -54,29 +56,28 @@ impl<const N: usize> Game<N> {
 
     /// Add all possible move turns.
     fn add_moves(&self, turns: &mut Vec<Turn<N>>, pos: Pos<N>, tile: &Tile) {
+        let capstone = matches!(tile.top.shape, Shape::Capstone);
+        let blockers = self.board.blockers();
+        let walls = self.board.walls(Colour::White) | self.board.walls(Colour::Black);
         for neighbour in pos.neighbors() {
             let direction = (neighbour - pos).unwrap();
             let max_carry = min(tile.size(), N);
             for drop_choices in 0..max_carry {
-                let capstone = matches!(tile.top.shape, Shape::Capstone);
-                let mut tries = vec![(neighbour, drop_choices, ArrayVec::new())];
-                let mut possible_moves = Vec::new();
+                let mut tries: SmallVec<[_; 8]> = smallvec![(neighbour, drop_choices, ArrayVec::new())];
                 while let Some((current, drop_choices, mut moves)) = tries.pop() {
-                    #[rustfmt::skip]
-                    let can_drop = match self.board[current] {
-                        None => true,
-                        Some(Tile {top: Piece {shape: Shape::Flat, ..}, ..}) => true,
-                        Some(Tile {top: Piece {shape: Shape::Wall, ..}, ..})
-                            if drop_choices == 0 && capstone => true,
-                        _ => false,
-                    };
+                    let can_drop = !blockers.contains(current)
+                        || (drop_choices == 0 && capstone && walls.contains(current));
 
                     if !can_drop {
                         continue;
                     }
                     if drop_choices == 0 {
                         moves.push(false);
-                        possible_moves.push(moves);
+                        turns.push(Turn::Move {
+                            pos,
+                            direction,
+                            moves,
+                        });
                         continue;
                     }
 
@@ -88,12 +89,6 @@ impl<const N: usize> Game<N> {
                     moves.push(false);
                     tries.push((current, drop_choices - 1, moves));
                 }
-
-                turns.extend(possible_moves.into_iter().map(|moves| Turn::Move {
-                    pos,
-                    direction,
-                    moves,
-                }));
             }
         }
     }
@@ -0,0 +1,76 @@
+//! Zobrist hashing of positions, for transposition tables and repetition
+//! detection.
+//!
+//! Every feature of a position, like a piece at some height of a square, has
+//! a pseudo-random key, and the hash of a position is the XOR of the keys of
+//! its features. The board updates its part whenever a square changes, so
+//! hashing a position never looks at the whole board.
+
+use crate::{
+    colour::Colour,
+    game::Game,
+    pos::Pos,
+    tile::{Shape, Tile},
+};
+
+// namespaces of the features, in the top bits
+const TOP: u64 = 1 << 56;
+const STACK: u64 = 2 << 56;
+const TO_MOVE: u64 = 3 << 56;
+const SWAP: u64 = 4 << 56;
+const RESERVES: u64 = 5 << 56;
+const KOMI: u64 = 6 << 56;
+
+/// Key of a feature, with SplitMix64 standing in for a table of random
+/// numbers so that any board size works.
+const fn key(feature: u64) -> u64 {
+    let mut z = feature.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+fn colour_bit(colour: Colour) -> u64 {
+    match colour {
+        Colour::White => 0,
+        Colour::Black => 1,
+    }
+}
+
+/// Hash of the tile on a square, the XOR of its top piece and of each piece
+/// below it at its height.
+pub(crate) fn square_hash<const N: usize>(pos: Pos<N>, tile: &Tile) -> u64 {
+    let square = (pos.y * N + pos.x) as u64;
+    let shape = match tile.top.shape {
+        Shape::Flat => 0,
+        Shape::Wall => 1,
+        Shape::Capstone => 2,
+    };
+    let mut hash = key(TOP | square << 8 | shape << 1 | colour_bit(tile.top.colour));
+    for (height, &colour) in tile.stack.iter().enumerate() {
+        hash ^= key(STACK | square << 32 | (height as u64) << 1 | colour_bit(colour));
+    }
+    hash
+}
+
+impl<const N: usize> Game<N> {
+    /// Zobrist hash of the position. Positions which are equal have the same
+    /// hash, so the ply count only matters through the opening swap.
+    pub fn zobrist(&self) -> u64 {
+        let mut hash = self.board.zobrist();
+        hash ^= key(TO_MOVE | colour_bit(self.to_move));
+        if self.swap() {
+            hash ^= key(SWAP);
+        }
+        let reserves = [
+            self.white_stones,
+            self.white_caps,
+            self.black_stones,
+            self.black_caps,
+        ];
+        for (i, count) in reserves.into_iter().enumerate() {
+            hash ^= key(RESERVES | (i as u64) << 8 | count as u64);
+        }
+        hash ^ key(KOMI | self.komi.half_flats() as u8 as u64)
+    }
+}
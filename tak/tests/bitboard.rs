@@ -0,0 +1,57 @@
+use tak::*;
+
+#[test]
+fn edges() {
+    assert_eq!(Bitboard::<5>::FULL.count(), 25);
+    assert_eq!(Bitboard::<8>::FULL.0, u64::MAX);
+    let edges = Bitboard::<5>::WEST | Bitboard::EAST | Bitboard::NORTH | Bitboard::SOUTH;
+    assert_eq!(edges.count(), 16);
+    assert!(edges.iter().all(Pos::is_edge));
+    assert_eq!((!edges).count(), 9);
+}
+
+#[test]
+fn neighbors() {
+    let corner = Bitboard::<5>::from_pos(Pos { x: 4, y: 0 });
+    let neighbors: Vec<_> = corner.neighbors().iter().collect();
+    assert_eq!(neighbors, [Pos { x: 3, y: 0 }, Pos { x: 4, y: 1 }]);
+
+    let centre = Pos::<5> { x: 2, y: 2 };
+    let expected: Bitboard<5> = centre.neighbors().into_iter().collect();
+    assert_eq!(Bitboard::from_pos(centre).neighbors(), expected);
+}
+
+#[test]
+fn occupancy() -> StrResult<()> {
+    let game = Game::<5>::from_ptn("1. a1 e5 2. Cc3 Sd4 3. c3< b1")?;
+    let board = &game.board;
+    let white_flats: Vec<_> = board
+        .flats(Colour::White)
+        .iter()
+        .map(|pos| pos.to_ptn())
+        .collect();
+    assert_eq!(white_flats, ["e5"]);
+    assert_eq!(board.walls(Colour::Black).iter().next(), Some(Pos { x: 3, y: 3 }));
+    assert_eq!(
+        board.capstones(Colour::White).iter().next(),
+        Some(Pos { x: 1, y: 2 })
+    );
+    assert_eq!(board.capstones(Colour::Black).count(), 0);
+    assert_eq!(board.controlled(Colour::Black).count(), 3);
+    assert_eq!(board.occupied().count(), 5);
+    assert_eq!(
+        board.road_squares(Colour::White) | board.controlled(Colour::Black),
+        board.occupied()
+    );
+    Ok(())
+}
+
+#[test]
+fn flood_fill() -> StrResult<()> {
+    let game = Game::<5>::from_ptn("1. a1 e5 2. b1 a5 3. c1 a4 4. c2 e3")?;
+    let white = game.board.road_squares(Colour::White);
+    let group = Bitboard::from_pos(Pos { x: 1, y: 0 }).flood_fill(white);
+    assert_eq!(group.count(), 3);
+    assert!(!group.contains(Pos { x: 4, y: 4 }));
+    Ok(())
+}
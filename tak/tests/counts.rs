@@ -0,0 +1,117 @@
+use tak::*;
+
+#[test]
+fn flat_counts() -> StrResult<()> {
+    let game = Game::<5>::from_ptn(
+        "[Komi \"2\"]
+        1. a1 e1
+        2. b1 Sa2
+        3. Cc3 b2
+        4. c3- d1",
+    )?;
+    assert_eq!(game.board.flat_counts(), (2, 3));
+    assert_eq!(game.reserves(Colour::White), (19, 0));
+    assert_eq!(game.reserves(Colour::Black), (17, 1));
+    assert_eq!(game.score(), -6);
+    assert_eq!(game.flat_result(), GameResult::Winner {
+        colour: Colour::Black,
+        reason: WinReason::Flats
+    });
+    Ok(())
+}
+
+#[test]
+fn half_komi_breaks_ties() -> StrResult<()> {
+    let game = Game::<5>::from_ptn("[Komi \"0.5\"]\n1. a1 e1")?;
+    assert_eq!(game.komi, Komi::from_half_flats(1));
+    assert_eq!(game.score(), -1);
+    assert_eq!(game.flat_result(), GameResult::Winner {
+        colour: Colour::Black,
+        reason: WinReason::Flats
+    });
+    Ok(())
+}
+
+#[test]
+fn komi_strings() {
+    assert_eq!("2.5".parse(), Ok(Komi::from_half_flats(5)));
+    assert_eq!("2".parse(), Ok(Komi::from_flats(2)));
+    assert_eq!("-0.5".parse(), Ok(Komi::from_half_flats(-1)));
+    assert!("2.25".parse::<Komi>().is_err());
+    assert!("x".parse::<Komi>().is_err());
+    assert_eq!(Komi::from_half_flats(5).to_string(), "2.5");
+    assert_eq!(Komi::from_flats(2).to_string(), "2");
+}
+
+#[test]
+fn komi_out_of_range() {
+    assert_eq!(Komi::checked_from_flats(-64), Some(Komi::from_half_flats(-128)));
+    assert_eq!(Komi::checked_from_flats(64), None);
+    assert!("64".parse::<Komi>().is_err());
+}
+
+#[test]
+fn pieces_left() -> StrResult<()> {
+    let game = Game::<5>::from_ptn("1. a1 e1 2. b1 Sa2 3. Cc3 b2")?;
+    assert_eq!(game.pieces_left(Colour::White), 19);
+    assert_eq!(game.pieces_left(Colour::Black), 19);
+    assert_eq!(game.plies_until_out_of_pieces(), 37);
+    assert!(!game.out_of_pieces_imminent());
+    assert!(!game.out_of_pieces());
+
+    let mut game = Game::<3> {
+        white_stones: 2,
+        ..Default::default()
+    };
+    game.play_ptn_moves(&["a1", "c3"])?;
+    assert_eq!(game.plies_until_out_of_pieces(), 1);
+    assert!(game.out_of_pieces_imminent());
+    game.play_ptn_moves(&["b2"])?;
+    assert!(game.out_of_pieces());
+    assert_eq!(game.winner(), GameResult::Winner {
+        colour: Colour::White,
+        reason: WinReason::Flats
+    });
+    Ok(())
+}
+
+#[test]
+fn custom_reserves() -> StrResult<()> {
+    let mut game = Game::<5>::with_reserves(Komi::default(), 12, 2)?;
+    game.play_ptn_moves(&["a1", "e5", "Cb2"])?;
+    assert_eq!(game.reserves(Colour::White), (11, 1));
+    assert_eq!(game.starting_reserves(Colour::White), (12, 2));
+    assert_eq!(game.starting_reserves(Colour::Black), (12, 2));
+
+    // there are no capstone moves on the sizes without capstones
+    assert!(Game::<4>::with_reserves(Komi::default(), 12, 1).is_err());
+    assert!(Game::<4>::with_reserves(Komi::default(), 12, 0).is_ok());
+    assert!(Game::<3>::from_ptn("[Caps \"1\"]\n1. a1 c3").is_err());
+
+    let game = Game::<6>::from_ptn("[Flats \"25\"]\n[Caps \"2\"]\n1. a1 f6")?;
+    assert_eq!(game.reserves(Colour::White), (24, 2));
+    assert_eq!(game.starting_reserves(Colour::Black), (25, 2));
+    Ok(())
+}
+
+fn standard_reserves<const N: usize>()
+where
+    [[Option<Tile>; N]; N]: Default,
+{
+    let mut game = Game::<N>::default();
+    game.play_ptn_moves(&["a1", "b1", "a2"]).unwrap();
+    let (stones, caps) = default_starting_stones(N);
+    for colour in [Colour::White, Colour::Black] {
+        assert_eq!(game.starting_reserves(colour), (stones as u32, caps as u32));
+    }
+}
+
+#[test]
+fn standard_reserves_of_every_size() {
+    standard_reserves::<3>();
+    standard_reserves::<4>();
+    standard_reserves::<5>();
+    standard_reserves::<6>();
+    standard_reserves::<7>();
+    standard_reserves::<8>();
+}
@@ -0,0 +1,35 @@
+use tak::*;
+
+#[test]
+fn empty_board_is_even() {
+    let evaluator = Evaluator::default();
+    assert_eq!(evaluator.eval(&Game::<5>::default()), 0.);
+}
+
+#[test]
+fn road_threat_is_good() -> StrResult<()> {
+    let game = Game::<5>::from_ptn(
+        "1. e5 a1
+        2. b1 e4
+        3. c1 e3
+        4. d1",
+    )?;
+    let evaluator = Evaluator::default();
+    assert!(evaluator.score(&game) > 0.);
+    // black is to move and is losing
+    assert!(evaluator.eval(&game) < 0.);
+    Ok(())
+}
+
+#[test]
+fn finished_game_is_decided() -> StrResult<()> {
+    let game = Game::<5>::from_ptn(
+        "1. e5 a1
+        2. b1 e4
+        3. c1 e3
+        4. d1 a5
+        5. e1",
+    )?;
+    assert_eq!(Evaluator::default().eval(&game), -1.);
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use tak::*;
+
+#[test]
+fn position1_perft() -> StrResult<()> {
+    let mut game = Game::<5>::default();
+    game.play_ptn_moves(&["d3", "c3", "c4", "1d3<", "1c4-", "Sc4"])?;
+    assert_eq!(game.perft(1), 87);
+    assert_eq!(game.perft(2), 6_155);
+    assert_eq!(game.perft(3), 461_800);
+    Ok(())
+}
+
+#[test]
+fn position2_perft() -> StrResult<()> {
+    let mut game = Game::<5>::default();
+    game.play_ptn_moves(&[
+        "c2", "c3", "d3", "b3", "c4", "1c2+", "1d3<", "1b3>", "1c4-", "Cc2", "a1", "1c2+", "a2",
+    ])?;
+    assert_eq!(game.perft(1), 104);
+    assert_eq!(game.perft(2), 7_743);
+    assert_eq!(game.perft(3), 592_645);
+    Ok(())
+}
+
+#[test]
+fn position3_perft() -> StrResult<()> {
+    let mut game = Game::<5>::default();
+    game.play_ptn_moves(&[
+        "c4", "c2", "d2", "c3", "b2", "d3", "1d2+", "b3", "d2", "b4", "1c2+", "1b3>", "2d3<", "1c4-", "d4",
+        "5c3<23", "c2", "c4", "1d4<", "d3", "1d2+", "1c3+", "Cc3", "2c4>", "1c3<", "d2", "c3", "1d2+",
+        "1c3+", "1b4>", "2b3>11", "3c4-12", "d2", "c4", "b4", "c5", "1b3>", "1c4<", "3c3-", "e5", "e2",
+    ])?;
+    assert_eq!(game.perft(1), 85);
+    assert_eq!(game.perft(2), 11_206);
+    assert_eq!(game.perft(3), 957_000);
+    Ok(())
+}
+
+#[test]
+fn perft_5() {
+    assert_eq!(Game::<5>::default().perft(0), 1);
+    assert_eq!(Game::<5>::default().perft(1), 25);
+    assert_eq!(Game::<5>::default().perft(2), 600);
+    assert_eq!(Game::<5>::default().perft(3), 43_320);
+    assert_eq!(Game::<5>::default().perft(4), 2_999_784);
+}
+
+#[test]
+fn perft_6() {
+    assert_eq!(Game::<6>::default().perft(0), 1);
+    assert_eq!(Game::<6>::default().perft(1), 36);
+    assert_eq!(Game::<6>::default().perft(2), 1_260);
+    assert_eq!(Game::<6>::default().perft(3), 132_720);
+    assert_eq!(Game::<6>::default().perft(4), 13_586_048);
+    // assert_eq!(Game::<6>::default().perft(5), 1_253_506_520);
+}
+
+#[test]
+fn perft_3() {
+    assert_eq!(Game::<3>::default().perft(1), 9);
+    assert_eq!(Game::<3>::default().perft(2), 72);
+    assert_eq!(Game::<3>::default().perft(3), 1_200);
+    assert_eq!(Game::<3>::default().perft(4), 17_792);
+}
+
+#[test]
+fn perft_4() {
+    assert_eq!(Game::<4>::default().perft(1), 16);
+    assert_eq!(Game::<4>::default().perft(2), 240);
+    assert_eq!(Game::<4>::default().perft(3), 7_440);
+    assert_eq!(Game::<4>::default().perft(4), 216_464);
+}
+
+#[test]
+fn perft_divide_sums_to_perft() -> StrResult<()> {
+    let mut game = Game::<5>::default();
+    game.play_ptn_moves(&["d3", "c3", "c4", "1d3<", "1c4-", "Sc4"])?;
+    let divide = game.perft_divide(2);
+    assert_eq!(divide.len(), 87);
+    assert_eq!(divide.iter().map(|(_, count)| count).sum::<u64>(), 6_155);
+    let (turn, count) = &divide[0];
+    let mut after = game.clone();
+    after.play(turn.clone())?;
+    assert_eq!(after.perft(1), *count);
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use tak::*;
+
+#[test]
+fn opening() -> StrResult<()> {
+    let game = Game::<6>::from_ptn("1. a1 f6 2. c3 d4")?;
+    assert_eq!(game.phase(), Phase::Opening);
+    Ok(())
+}
+
+#[test]
+fn midgame() -> StrResult<()> {
+    let game = Game::<6>::from_ptn("1. a1 f6 2. c3 d4 3. d3 c4 4. d3< d4< 5. b3 e4")?;
+    assert_eq!(game.phase(), Phase::Midgame);
+    Ok(())
+}
+
+#[test]
+fn endgame_near_road() -> StrResult<()> {
+    let game = Game::<5>::from_ptn("1. e5 a1 2. b1 e4 3. c1 e3 4. d1")?;
+    assert_eq!(game.phase(), Phase::Endgame);
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use tak::*;
+
+#[test]
+fn step() {
+    let corner = Pos::<5> { x: 0, y: 0 };
+    assert_eq!(corner.step(Direction::PosX), Some(Pos { x: 1, y: 0 }));
+    assert_eq!(corner.step(Direction::PosY), Some(Pos { x: 0, y: 1 }));
+    assert_eq!(corner.step(Direction::NegX), None);
+    assert_eq!(corner.step(Direction::NegY), None);
+    assert_eq!(Pos::<5> { x: 4, y: 2 }.step(Direction::PosX), None);
+}
+
+#[test]
+fn directions() -> StrResult<()> {
+    let centre = Pos::<5> { x: 2, y: 2 };
+    for direction in Direction::ALL {
+        let next = centre.step(direction).unwrap();
+        assert_eq!((next - centre)?, direction);
+        assert_eq!(next.step(direction.opposite()), Some(centre));
+        assert_eq!(Direction::from_ptn(&direction.symbol().to_string())?, direction);
+    }
+    assert_eq!(Direction::from_symbol('x'), None);
+    Ok(())
+}
+
+#[test]
+fn neighbors_and_edges() {
+    assert_eq!(Pos::<5> { x: 0, y: 0 }.neighbors().len(), 2);
+    assert_eq!(Pos::<5> { x: 0, y: 2 }.neighbors().len(), 3);
+    assert_eq!(Pos::<5> { x: 2, y: 2 }.neighbors().len(), 4);
+    assert_eq!(Pos::<5>::all().filter(|pos| pos.is_edge()).count(), 16);
+    assert_eq!(Pos::<5> { x: 0, y: 0 }.distance(Pos { x: 3, y: 4 }), 7);
+}
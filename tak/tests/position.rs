@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+
+use tak::*;
+
+#[test]
+fn transpositions_are_equal() {
+    let a = Game::<5>::from_ptn("1. a1 e5 2. b2 c3 3. d4 b4").unwrap();
+    let b = Game::<5>::from_ptn("1. a1 e5 2. d4 b4 3. b2 c3").unwrap();
+    assert_eq!(a, b);
+
+    let mut set = HashSet::new();
+    set.insert(a);
+    assert!(set.contains(&b));
+}
+
+#[test]
+fn ply_is_ignored() {
+    let game = Game::<5>::from_ptn("1. a1 e5 2. b2 c3").unwrap();
+    let mut later = game.clone();
+    later.ply += 10;
+    assert_eq!(game, later);
+}
+
+#[test]
+fn different_positions() {
+    let game = Game::<5>::from_ptn("1. a1 e5 2. b2 c3").unwrap();
+
+    let mut other = game.clone();
+    other.to_move = other.to_move.next();
+    assert_ne!(game, other);
+
+    let mut other = game.clone();
+    other.white_stones -= 1;
+    assert_ne!(game, other);
+
+    let mut other = game.clone();
+    other.komi = Komi::from_flats(2);
+    assert_ne!(game, other);
+
+    let moved = Game::<5>::from_ptn("1. a1 e5 2. b2 c3 3. b2+").unwrap();
+    assert_ne!(game, moved);
+}
+
+#[test]
+fn opening_swap_is_part_of_position() {
+    let empty = Game::<5>::default();
+    let after_swap = Game::<5> {
+        ply: 2,
+        ..Default::default()
+    };
+    assert_ne!(empty, after_swap);
+}
+
+#[test]
+fn clones_are_independent() {
+    let game = Game::<5>::from_ptn("1. a1 e5 2. b2 c3").unwrap();
+    let mut clone = game.clone();
+    clone.play(Turn::from_ptn("b2+").unwrap()).unwrap();
+
+    let (b2, b3) = (Pos { x: 1, y: 1 }, Pos { x: 1, y: 2 });
+    assert!(game.board[b2].is_some());
+    assert!(game.board[b3].is_none());
+    assert!(clone.board[b2].is_none());
+    assert!(clone.board[b3].is_some());
+}
+
+#[test]
+fn validate_positions() {
+    let game = Game::<5>::from_ptn("1. a1 e5 2. b2 c3").unwrap();
+    assert!(game.validate().is_ok());
+
+    let mut wrong_side = game.clone();
+    wrong_side.to_move = wrong_side.to_move.next();
+    assert!(wrong_side.validate().is_err());
+
+    let mut too_many = game.clone();
+    too_many.white_stones = 21;
+    assert!(too_many.validate().is_err());
+
+    let opening = Game::<5> {
+        board: Board::from_tps("1,x4/x5/x5/x5/x5").unwrap(),
+        ..Default::default()
+    };
+    assert!(opening.validate().is_err());
+
+    let mut swapped = Game::<5>::from_ptn("1. a1").unwrap();
+    assert!(swapped.validate().is_ok());
+    swapped.board = Board::from_tps("x4,1/x5/x5/x5/x5").unwrap();
+    assert!(swapped.validate().is_err());
+
+    let over = Game::<5>::from_ptn("1. e5 a1 2. a2 b1 3. a3 c1 4. a4 d1 5. a5").unwrap();
+    assert!(over.validate().is_err());
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "arbitrary")]
+
+use arbitrary::{Arbitrary, Unstructured};
+use tak::*;
+
+/// Run `f` on many deterministic pseudo-random inputs.
+fn cases(count: usize, mut f: impl FnMut(&mut Unstructured)) {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    for _ in 0..count {
+        let bytes: Vec<u8> = (0..4096)
+            .map(|_| {
+                // xorshift
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        f(&mut Unstructured::new(&bytes));
+    }
+}
+
+#[test]
+fn turn_ptn_round_trip() {
+    cases(1000, |u| {
+        let turn = Turn::<6>::arbitrary(u).unwrap();
+        assert_eq!(Turn::from_ptn(&turn.to_ptn()), Ok(turn));
+    });
+}
+
+#[test]
+fn board_tps_round_trip() {
+    cases(100, |u| {
+        let game = Game::<5>::arbitrary(u).unwrap();
+        let board = Board::<5>::from_tps(&game.board.to_tps()).unwrap();
+        assert_eq!(board, game.board);
+    });
+}
+
+#[test]
+fn symmetries_preserve_legality() {
+    cases(100, |u| {
+        let game = Game::<5>::arbitrary(u).unwrap();
+        let turn = match arbitrary_legal_turn(&game, u).unwrap() {
+            Some(turn) => turn,
+            None => return,
+        };
+        let count = game.possible_turns().len();
+        for (mut game, turn) in game.symmetries().into_iter().zip(turn.symmetries()) {
+            assert_eq!(game.possible_turns().len(), count);
+            game.play(turn).unwrap();
+        }
+    });
+}
+
+#[test]
+fn bitboards_match_squares() {
+    cases(100, |u| {
+        let game = Game::<5>::arbitrary(u).unwrap();
+        for board in game.board.clone().symmetries().iter().chain([&game.board]) {
+            for colour in [Colour::White, Colour::Black] {
+                for shape in [Shape::Flat, Shape::Wall, Shape::Capstone] {
+                    let expected: Bitboard<5> = Pos::all()
+                        .filter(
+                            |&pos| matches!(&board[pos], Some(tile) if tile.top == Piece { colour, shape }),
+                        )
+                        .collect();
+                    assert_eq!(board.tops(colour, shape), expected);
+                }
+            }
+        }
+    });
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use tak::*;
 
 const PLIES: &[&str] = &[
@@ -15,6 +17,32 @@ fn ptn_consistency() -> StrResult<()> {
     Ok(())
 }
 
+#[test]
+fn turn_notation() -> StrResult<()> {
+    // annotations, the explicit flat and the crush mark do not change the turn
+    assert_eq!(Turn::<5>::from_ptn("Fa1")?, Turn::from_ptn("a1")?);
+    assert_eq!(Turn::<5>::from_ptn("Sc3'!?")?, Turn::from_ptn("Sc3")?);
+    assert_eq!(Turn::<5>::from_ptn("2c3>11*\"")?, Turn::from_ptn("2c3>11")?);
+    assert_eq!(Turn::<5>::from_ptn(" d4- ")?, Turn::from_ptn("d4-")?);
+
+    for ptn in ["", "a1x", "xa1", "Xa1", "a1 b1", "2a1>2*", "10a1>", "a1>0"] {
+        assert!(Turn::<5>::from_ptn(ptn).is_err(), "{ptn} should not parse");
+    }
+    Ok(())
+}
+
+#[test]
+fn crush_mark() -> StrResult<()> {
+    let game = Game::<5>::from_ptn("1. a1 e1 2. Cc3 Sd3")?;
+    let crush = Turn::from_ptn("c3>")?;
+    assert!(crush.crushes(&game.board));
+    assert_eq!(crush.to_ptn_on(&game.board), "c3>*");
+    let other = Turn::from_ptn("c3<")?;
+    assert!(!other.crushes(&game.board));
+    assert_eq!(other.to_ptn_on(&game.board), "c3<");
+    Ok(())
+}
+
 #[test]
 fn move_gen_ptn_consistency() -> StrResult<()> {
     let game = Game::<6>::from_ptn(
@@ -73,7 +101,165 @@ fn game_options() -> StrResult<()> {
     assert_eq!(game.ply, 12);
     assert_eq!(game.winner(), GameResult::Winner {
         colour: Colour::Black,
-        road: true
+        reason: WinReason::Road
     });
     Ok(())
 }
+
+#[test]
+fn ptn_with_turns() -> StrResult<()> {
+    let (mut game, turns) = Game::<5>::from_ptn_with_turns("1. a1 e1 2. Cc3 Sd4 3. c3<")?;
+    assert_eq!(game.ply, 0);
+    assert_eq!(turns.len(), 5);
+    for turn in turns {
+        game.play(turn)?;
+    }
+    assert_eq!(game.board.to_tps(), "x5/x3,2S,x/x,1C,x3/x5/2,x3,1");
+    Ok(())
+}
+
+#[test]
+fn tags() {
+    let tags = ptn_tags("[Player1 \"alice\"]\n[Player2 'bob']\n[Komi \"2\"]\n\n1. a1 e1");
+    assert_eq!(tags, vec![
+        ("Player1".to_string(), "alice".to_string()),
+        ("Player2".to_string(), "bob".to_string()),
+        ("Komi".to_string(), "2".to_string()),
+    ]);
+}
+
+#[test]
+fn tps_start() -> StrResult<()> {
+    let tps = "2,x4/x5/x2,12,x2/x5/x4,1C 2 3";
+    let (game, turns) = Game::<5>::from_ptn_with_turns(&format!("[TPS \"{tps}\"]\n\n3. -- a1 4. b1 b2"))?;
+    assert_eq!(game.ptn_tps(), tps);
+    assert_eq!(game.ply, 5);
+    assert_eq!(game.reserves(Colour::White), (20, 0));
+    assert_eq!(game.reserves(Colour::Black), (19, 1));
+    assert_eq!(turns.len(), 3);
+
+    // move numbers which do not fit a ply are an error, not an overflow
+    for number in ["0", "9223372036854775809", "18446744073709551615"] {
+        let ptn = format!("[TPS \"x5/x5/x5/x5/x5 1 {number}\"]");
+        assert!(Game::<5>::from_ptn(&ptn).is_err(), "{number} should not parse");
+    }
+    Ok(())
+}
+
+#[test]
+fn positions() -> StrResult<()> {
+    let ptn = "1. a1 e1 2. Cc3 Sd4 3. c3<";
+    let positions = Game::<5>::ptn_positions(ptn)?.collect::<StrResult<Vec<_>>>()?;
+    let plies: Vec<_> = positions.iter().map(|(ply, _)| *ply).collect();
+    assert_eq!(plies, [0, 1, 2, 3, 4, 5]);
+    assert_eq!(positions[0].1, Game::default());
+    assert_eq!(positions[5].1, Game::from_ptn(ptn)?);
+
+    assert_eq!(
+        Game::<5>::from_ptn_at(ptn, 3)?,
+        Game::from_ptn("1. a1 e1 2. Cc3")?
+    );
+    assert!(Game::<5>::from_ptn_at(ptn, 6).is_err());
+
+    let illegal = Game::<5>::ptn_positions("1. a1 e1 2. a1- b1")?.collect::<Vec<_>>();
+    assert_eq!(illegal.len(), 4);
+    assert!(illegal[3].is_err());
+    Ok(())
+}
+
+#[test]
+fn multiple_games() -> StrResult<()> {
+    let ptn = "[Size \"5\"]\n[Player1 \"alice\"]\n\n1. a1 e1\n2. b1 R-0\n\n[Size \"5\"]\n[Player1 \
+               \"bob\"]\n\n1. e5 a5\n\n\n";
+    let games = split_ptn_games(ptn);
+    assert_eq!(games.len(), 2);
+    assert_eq!(ptn_tags(games[1])[1].1, "bob");
+    assert_eq!(Game::<5>::from_ptn(games[0])?.ply, 3);
+
+    let parsed = Game::<5>::from_ptn_games(ptn);
+    assert_eq!(parsed.len(), 2);
+    assert_eq!(parsed[1].as_ref()?.ply, 2);
+
+    assert_eq!(split_ptn_games("1. a1 e1"), ["1. a1 e1"]);
+    assert!(split_ptn_games("\n\n").is_empty());
+    Ok(())
+}
+
+#[test]
+fn result_mismatches() -> StrResult<()> {
+    let road = "[Result \"R-0\"]\n\n1. e5 a1 2. a2 e4 3. a3 e3 4. a4 e2 5. a5 R-0";
+    assert!(Game::<5>::ptn_result_mismatches(road)?.is_empty());
+
+    let wrong = "[Result \"0-R\"]\n\n1. e5 a1 2. a2 e4 3. a3 e3 4. a4 e2 5. a5 F-0";
+    let mismatches = Game::<5>::ptn_result_mismatches(wrong)?;
+    assert_eq!(
+        mismatches
+            .iter()
+            .map(|m| (m.source, m.recorded.as_str()))
+            .collect::<Vec<_>>(),
+        [(ResultSource::Tag, "0-R"), (ResultSource::Moves, "F-0")]
+    );
+
+    // resignations are not visible to the rules
+    assert!(Game::<5>::ptn_result_mismatches("[Result \"0-1\"]\n\n1. a1 e5 2. a2 0-1")?.is_empty());
+    let unfinished = Game::<5>::ptn_result_mismatches("1. a1 e5 2. a2 R-0")?;
+    assert_eq!(unfinished[0].computed, GameResult::Ongoing);
+    Ok(())
+}
+
+#[test]
+fn time_control_ptn() {
+    let control = TimeControl::from_ptn("10:00 +20").unwrap();
+    assert_eq!(control.initial, Duration::from_secs(600));
+    assert_eq!(control.increment, Duration::from_secs(20));
+    assert_eq!(control.to_ptn(), "10:00 +20");
+    assert!(TimeControl::from_ptn("307445734561825861:00 +0").is_err());
+    assert!(TimeControl::from_ptn("0:18446744073709551615 +0").is_ok());
+    assert!(TimeControl::from_ptn("1:18446744073709551615 +0").is_err());
+}
+
+#[test]
+fn clock_forfeit() {
+    let mut clock = Clock::new(TimeControl::new(Duration::from_secs(60), Duration::from_secs(5)));
+    assert_eq!(
+        clock.record(Colour::White, Duration::from_secs(30)),
+        GameResult::Ongoing
+    );
+    assert_eq!(clock.remaining(Colour::White), Duration::from_secs(35));
+    assert_eq!(clock.comment(0).as_deref(), Some("clk 0:35.0"));
+    assert_eq!(
+        clock.record(Colour::Black, Duration::from_secs(61)),
+        GameResult::Winner {
+            colour: Colour::White,
+            reason: WinReason::Time
+        }
+    );
+}
+
+#[test]
+fn commentary() -> StrResult<()> {
+    let plain = Game::<5>::from_ptn("1. a1 e1 2. Cc3 d3 3. c3> e2")?;
+    let annotated = "[Size \"5\"]\n\n{opening\ncomment}\n1. a1 e1 {a\n[Tag \"in a comment\"]}\n2. Cc3! (2. \
+                     c3 (2. b3 $2) d3) d3?! $14\n3.c3>' 3... e2 0-0\n";
+    assert_eq!(Game::<5>::from_ptn(annotated)?, plain);
+    assert_eq!(split_ptn_games(annotated).len(), 1);
+    Ok(())
+}
+
+#[test]
+fn games_from_ptn_file() -> StrResult<()> {
+    let path = std::env::temp_dir().join(format!("tak-games-{}.ptn", std::process::id()));
+    std::fs::write(
+        &path,
+        "[Size \"5\"]\n\n1. a1 e1 {ok}\n\n[Size \"5\"]\n\n1. a1 a1\n\n[Size \"5\"]\n\n1. e5 a5 2. b2\n",
+    )
+    .map_err(|err| err.to_string())?;
+    let games = Game::<5>::games_from_ptn_file(&path).map_err(|err| err.to_string());
+    std::fs::remove_file(&path).ok();
+    let games = games?;
+    assert_eq!(games.len(), 3);
+    assert!(games[1].is_err());
+    assert_eq!(games[2].as_ref()?.ply, 3);
+    assert!(Game::<5>::games_from_ptn_file(std::env::temp_dir().join("missing.ptn")).is_err());
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use tak::*;
+
+#[test]
+fn road_in_one() -> StrResult<()> {
+    let game = Game::<5>::from_ptn(r#"[TPS "x5/x4,2/x,2,2,x2/x5/1,1,1,1,x 1 5"]"#)?;
+    assert_eq!(
+        game.solve(1, 1000),
+        Some(GameResult::Winner {
+            colour: Colour::White,
+            reason: WinReason::Road
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn tinue_is_a_proven_loss() -> StrResult<()> {
+    let mut game = Game::<5>::from_ptn(r#"[TPS "x4,2/1,x,2,2,2/1,x,2,2,x/1,x4/x,1,1,1,x 1 7"]"#)?;
+    let win = GameResult::Winner {
+        colour: Colour::White,
+        reason: WinReason::Road,
+    };
+    assert_eq!(game.solve(1, 1_000_000), None);
+    assert_eq!(game.solve(3, 1_000_000), Some(win));
+    // every reply of black loses
+    game.play(Turn::from_ptn("a1")?)?;
+    assert_eq!(game.solve(2, 1_000_000), Some(win));
+    Ok(())
+}
+
+#[test]
+fn flats_on_full_board() -> StrResult<()> {
+    // the walls leave white nothing but to fill the board
+    let game = Game::<3>::from_ptn(r#"[TPS "1,2S,1/2S,1,2S/1,2S,x 1 5"]"#)?;
+    assert_eq!(game.plies_until_flat_end(), 1);
+    assert_eq!(
+        game.solve(1, 1000),
+        Some(GameResult::Winner {
+            colour: Colour::White,
+            reason: WinReason::Flats
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn gives_up_over_budget() -> StrResult<()> {
+    let game = Game::<5>::from_ptn("1. a1 e5 2. c3 d4")?;
+    assert_eq!(game.solve(3, 100), None);
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use tak::*;
+
+#[test]
+fn road_in_one() -> StrResult<()> {
+    let game = Game::<5>::from_ptn(r#"[TPS "x5/x4,2/x,2,2,x2/x5/1,1,1,1,x 1 5"]"#)?;
+    let winning: Vec<_> = game.winning_turns(1).iter().map(ToPTN::to_ptn).collect();
+    assert_eq!(winning.len(), 2);
+    assert!(winning.contains(&"e1".to_string()));
+    assert!(winning.contains(&"Ce1".to_string()));
+    Ok(())
+}
+
+#[test]
+fn tinue_in_three() -> StrResult<()> {
+    let game = Game::<5>::from_ptn(r#"[TPS "x4,2/1,x,2,2,2/1,x,2,2,x/1,x4/x,1,1,1,x 1 7"]"#)?;
+    assert!(!game.can_force_win(1));
+    // each creates two road threats, with a flat or the capstone
+    let mut winning: Vec<_> = game.winning_turns(3).iter().map(ToPTN::to_ptn).collect();
+    winning.sort();
+    assert_eq!(winning, vec!["Ca1", "Ca5", "Cb2", "Ce1", "a1", "a5", "b2", "e1"]);
+    Ok(())
+}
+
+#[test]
+fn no_win() -> StrResult<()> {
+    let game = Game::<5>::from_ptn("1. a1 e5 2. c3 d4")?;
+    assert!(!game.can_force_win(3));
+    Ok(())
+}
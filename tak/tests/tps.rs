@@ -49,3 +49,35 @@ fn tps_consistency() -> StrResult<()> {
     }
     Ok(())
 }
+
+#[test]
+fn game_tps() -> StrResult<()> {
+    // standard TPS takes the pieces on the board from the default reserves
+    let game = Game::<5>::from_tps("2,x4/x5/x2,12,x2/x5/x4,1C 2 3")?;
+    assert_eq!(game.to_move, Colour::Black);
+    assert_eq!(game.ply, 5);
+    assert_eq!(game.reserves(Colour::White), (20, 0));
+    assert_eq!(game.reserves(Colour::Black), (19, 1));
+    assert_eq!(game.komi, Komi::default());
+    assert_eq!(game.ptn_tps(), "2,x4/x5/x2,12,x2/x5/x4,1C 2 3");
+
+    // the modified TPS keeps reserves and komi
+    let mut game = Game::<5>::with_komi(Komi::from_half_flats(5));
+    game.nth_move(9576890767)?;
+    game.nth_move(9576890767)?;
+    game.nth_move(9576890767)?;
+    let copy = Game::<5>::from_tps(&game.to_tps())?;
+    assert!(copy == game);
+    assert_eq!(copy.ply, game.ply);
+
+    assert!(Game::<5>::from_tps("x5/x5/x5/x5/x5 1").is_err());
+    assert!(Game::<5>::from_tps("x5/x5/x5/x5/x5 1 1 (21/1)").is_ok());
+    assert!(Game::<5>::from_tps("x5/x5/x5/x5/x5 1 1 (21/1) (21/1) 2 x").is_err());
+    // the ply of the move number must fit in a u64
+    assert!(Game::<5>::from_tps("x5/x5/x5/x5/x5 1 9223372036854775809").is_err());
+    assert_eq!(
+        Game::<5>::from_tps("x5/x5/x5/x5/x5 2 9223372036854775808")?.ply,
+        u64::MAX
+    );
+    Ok(())
+}
@@ -14,8 +14,10 @@ fn double_road_correct_win() -> StrResult<()> {
     )?;
     assert_eq!(game.winner(), GameResult::Winner {
         colour: Colour::White,
-        road: true
+        reason: WinReason::Road
     });
+    let road: Vec<_> = game.road().unwrap().iter().map(ToPTN::to_ptn).collect();
+    assert_eq!(road, ["a3", "b3", "c3", "d3", "e3", "f3"]);
     Ok(())
 }
 
@@ -30,8 +32,9 @@ fn flat_win() -> StrResult<()> {
     )?;
     assert_eq!(game.winner(), GameResult::Winner {
         colour: Colour::White,
-        road: false
+        reason: WinReason::Flats
     });
+    assert_eq!(game.road(), None);
     Ok(())
 }
 
@@ -48,8 +51,10 @@ fn road_win() -> StrResult<()> {
     )?;
     assert_eq!(game.winner(), GameResult::Winner {
         colour: Colour::Black,
-        road: true
+        reason: WinReason::Road
     });
+    let road: Vec<_> = game.road().unwrap().iter().map(ToPTN::to_ptn).collect();
+    assert_eq!(road, ["a1", "b1", "c1", "c2", "d2", "d3", "e3"]);
     Ok(())
 }
 
@@ -62,7 +67,47 @@ fn road_beats_flats() -> StrResult<()> {
     game.play(Turn::from_ptn("a4")?)?;
     assert_eq!(game.winner(), GameResult::Winner {
         colour: Colour::White,
-        road: true
+        reason: WinReason::Road
     });
     Ok(())
 }
+
+#[test]
+fn dead_position() {
+    // the black walls block every road of white
+    let mut game = Game::<5> {
+        board: Board::from_tps("1,1,2S,1,1/1,1,2S,1,1/2S,2S,2S,2S,2S/1,1,2S,1,x/1,1,2S,1,x").unwrap(),
+        white_stones: 1,
+        white_caps: 0,
+        black_stones: 10,
+        black_caps: 0,
+        ply: 40,
+        ..Default::default()
+    };
+    assert_eq!(game.board.road_distance(Colour::White, false), None);
+    assert_eq!(game.board.road_distance(Colour::Black, false), Some(5));
+    assert_eq!(
+        game.dead_position_result(),
+        Some(GameResult::Winner {
+            colour: Colour::White,
+            reason: WinReason::Flats
+        })
+    );
+
+    // with another move black could spread far enough for a road
+    game.white_stones = 2;
+    assert_eq!(game.dead_position_result(), None);
+    assert_eq!(game.adjudicated_winner(), GameResult::Ongoing);
+
+    // a single spread of a tall stack could cross the whole board
+    let game = Game::<5> {
+        board: Board::from_tps("1,1,2S,1,1/1,1,2S,1,1/2S,2S,2S,2S,2S/1,1,2S,1,x/1,1,2S,1,22222").unwrap(),
+        white_stones: 1,
+        white_caps: 0,
+        black_stones: 10,
+        black_caps: 0,
+        ply: 40,
+        ..Default::default()
+    };
+    assert_eq!(game.dead_position_result(), None);
+}
@@ -0,0 +1,32 @@
+use tak::*;
+
+#[test]
+fn transpositions_hash_equal() -> StrResult<()> {
+    let a = Game::<5>::from_ptn("1. a1 e5 2. c3 c2 3. d3 b2")?;
+    let b = Game::<5>::from_ptn("1. a1 e5 2. d3 b2 3. c3 c2")?;
+    assert!(a == b);
+    assert_eq!(a.zobrist(), b.zobrist());
+
+    let c = Game::<5>::from_ptn("1. a1 e5 2. d3 b2 3. c3 Sc2")?;
+    assert_ne!(a.zobrist(), c.zobrist());
+    Ok(())
+}
+
+#[test]
+fn hash_is_incremental() -> StrResult<()> {
+    let mut game = Game::<5>::with_komi(Komi::from_flats(2));
+    let mut seen = vec![game.zobrist()];
+    for _ in 0..60 {
+        if game.winner() != GameResult::Ongoing {
+            break;
+        }
+        game.nth_move(9576890767)?;
+        // the same position built from scratch
+        assert_eq!(Game::<5>::from_tps(&game.to_tps())?.zobrist(), game.zobrist());
+        seen.push(game.zobrist());
+    }
+    seen.sort_unstable();
+    seen.dedup();
+    assert!(seen.len() > 50);
+    Ok(())
+}
@@ -1,16 +1,257 @@
-use clap::Parser;
+use clap::{Args as ClapArgs, Parser, Subcommand};
+use tak::Komi;
 
 /// Train AlphaTak
 #[derive(Parser)]
+#[clap(args_conflicts_with_subcommands = true)]
 pub struct Args {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
     /// Path to model, use "random" or leave blank if you want a new model
     pub model_path: Option<String>,
     /// Paths to example files
     pub examples: Vec<String>,
+    /// Example files of other board sizes for the auxiliary heads, given as
+    /// `size:path`. The sizes must be listed in `AUX_SIZES` in the config
+    #[clap(long)]
+    pub aux_examples: Vec<String>,
+    /// Example files which are never trained on, used to report the
+    /// calibration of the value head after every generation. Without them
+    /// the newest self-play games are used before they join the training
+    /// window
+    #[clap(long)]
+    pub holdout: Vec<String>,
+    /// Directory to write TensorBoard events to, in addition to the CSV file
+    /// of generation statistics
+    #[clap(long)]
+    pub tensorboard: Option<String>,
     /// Disable GPU usage
     #[clap(short, long)]
     pub no_gpu: bool,
     /// Only do self-play, no training
     #[clap(short, long)]
     pub only_self_play: bool,
+    #[clap(flatten)]
+    pub config: ConfigArgs,
+    /// Address to serve Prometheus metrics on, e.g. 0.0.0.0:9090
+    #[clap(long)]
+    pub metrics_addr: Option<String>,
+    /// Seed of the run, random by default, a resumed run continues with the
+    /// seed from the history
+    #[clap(long)]
+    pub seed: Option<u64>,
+    /// Directory of a previous run to continue, with the network, replay
+    /// buffer and seed it saved when it stopped
+    #[clap(long, conflicts_with_all = &["model_path", "seed", "only_self_play"])]
+    pub resume: Option<String>,
+}
+
+/// Hyperparameters of the run, see [`crate::train_config::TrainConfig`].
+#[derive(ClapArgs)]
+pub struct ConfigArgs {
+    /// TOML file with hyperparameters, missing ones keep their defaults
+    #[clap(long)]
+    pub config: Option<String>,
+    /// Komi in flats, overrides the config file
+    #[clap(long)]
+    pub komi: Option<Komi>,
+    /// Self-play games per iteration, overrides the config file
+    #[clap(long)]
+    pub self_play_games: Option<usize>,
+    /// Self-play games played at the same time, measured on startup if not
+    /// given, overrides the config file
+    #[clap(long)]
+    pub self_play_workers: Option<usize>,
+    /// Rollouts per move in self-play and the pit, overrides the config file
+    #[clap(long)]
+    pub rollouts: Option<usize>,
+    /// Plies in which self-play picks moves by visits, overrides the config
+    /// file
+    #[clap(long)]
+    pub temperature_plies: Option<u64>,
+    /// Concentration of the Dirichlet noise in self-play, by default scaled to
+    /// the board size, overrides the config file
+    #[clap(long)]
+    pub dirichlet_alpha: Option<f32>,
+    /// Share of the Dirichlet noise in the policy at the root, overrides the
+    /// config file
+    #[clap(long)]
+    pub noise_ratio: Option<f32>,
+    /// Exploration rate of the search at a node without visits, overrides
+    /// the config file
+    #[clap(long)]
+    pub cpuct: Option<f32>,
+    /// Value of unvisited moves below the value of their position, by default
+    /// they are valued as a draw, overrides the config file
+    #[clap(long)]
+    pub fpu_reduction: Option<f32>,
+    /// Temperature of the policy in the search, overrides the config file
+    #[clap(long)]
+    pub policy_temperature: Option<f32>,
+    /// Choose self-play moves by Gumbel search over this many sampled moves
+    /// instead of PUCT, overrides the config file
+    #[clap(long)]
+    pub gumbel_moves: Option<usize>,
+    /// Overrides the config file
+    #[clap(long)]
+    pub learning_rate: Option<f64>,
+    /// Overrides the config file
+    #[clap(long)]
+    pub batch_size: Option<i64>,
+    /// Sampling weight of examples relative to those one generation newer,
+    /// 1 samples the example window uniformly
+    #[clap(long)]
+    pub generation_decay: Option<f32>,
+    /// Share of self-play games played against a randomly chosen older
+    /// checkpoint instead
+    #[clap(long)]
+    pub league_fraction: Option<f64>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Find and download published checkpoints
+    Model {
+        #[clap(subcommand)]
+        command: ModelCommand,
+    },
+    /// Print the training history as CSV for plotting
+    History {
+        /// Print the loss of every batch instead of one row per checkpoint
+        #[clap(short, long)]
+        curves: bool,
+    },
+    /// Compare the value head predictions with game results, printed as CSV
+    Calibration {
+        /// Path to model
+        model_path: String,
+        /// Paths to example files
+        examples: Vec<String>,
+        /// Number of buckets to split the predictions into
+        #[clap(short, long, default_value_t = 10)]
+        buckets: usize,
+    },
+    /// Train a smaller network to imitate a larger one on the example positions
+    Distill {
+        /// Path to the teacher model
+        teacher_path: String,
+        /// Path to save the student model to
+        student_path: String,
+        /// Paths to example files
+        examples: Vec<String>,
+        /// Residual blocks of the student
+        #[clap(long, default_value_t = 4)]
+        blocks: usize,
+        /// Filters of the student
+        #[clap(long, default_value_t = 64)]
+        filters: i64,
+        /// Passes over the positions
+        #[clap(long, default_value_t = 1)]
+        epochs: usize,
+    },
+    /// Re-evaluate stored examples with a newer network and rewrite their
+    /// targets, one file at a time
+    Relabel {
+        /// Path to model
+        model_path: String,
+        /// Directory to write the relabelled files to, under the same names
+        out_dir: String,
+        /// Paths to example files
+        examples: Vec<String>,
+        /// Weight of the network eval in the new value targets, from 0 to 1
+        #[clap(long, default_value_t = 0.5)]
+        value_weight: f32,
+        /// Weight of the network policy in the new policy targets, from 0 to 1
+        #[clap(long, default_value_t = 0.5)]
+        policy_weight: f32,
+    },
+    /// Tune the search parameters by playing the model against itself with
+    /// slightly different values, and write the result as constants for
+    /// `config.rs`
+    Tune {
+        /// Path to model
+        model_path: String,
+        /// Path to write the tuned constants to
+        #[clap(short, long, default_value = "tuned.rs")]
+        out: String,
+        /// Number of tuning iterations
+        #[clap(short, long, default_value_t = 100)]
+        iterations: usize,
+        /// Pairs of games played per iteration
+        #[clap(short, long, default_value_t = 32)]
+        matches: usize,
+        /// Rollouts per move, kept low for quick games
+        #[clap(short, long, default_value_t = 200)]
+        rollouts: usize,
+        /// Size of the perturbations, in steps of each parameter
+        #[clap(long, default_value_t = 1.)]
+        perturbation: f32,
+        /// Size of the updates, in steps of each parameter
+        #[clap(long, default_value_t = 2.)]
+        learning_rate: f32,
+    },
+    /// Summarize example files: results, plies, duplicates, and optionally
+    /// print some of the positions
+    Inspect {
+        /// Paths to example files
+        examples: Vec<String>,
+        /// Number of randomly chosen positions to print
+        #[clap(short, long, default_value_t = 0)]
+        samples: usize,
+        /// Draw the sampled boards in addition to their TPS
+        #[clap(short, long)]
+        render: bool,
+    },
+    /// Write examples as numpy tensors with a manifest, for training in
+    /// Python
+    Export {
+        /// Directory to write the shards and the manifest to
+        out_dir: String,
+        /// Paths to example files
+        examples: Vec<String>,
+        /// Examples per shard
+        #[clap(long, default_value_t = 100_000)]
+        shard_size: usize,
+        /// Write all eight symmetries of every example
+        #[clap(long)]
+        symmetries: bool,
+    },
+    /// Turn human games from a directory of PTN files into examples, with the
+    /// played move as policy and the result as value, to train a network
+    /// before self-play
+    ImportPtn {
+        /// Directory of PTN files, each of which may hold several games
+        dir: String,
+        /// Path to write the examples to
+        #[clap(short, long, default_value = "examples/imported.data")]
+        out: String,
+        /// Skip games where either player is rated lower
+        #[clap(long)]
+        min_rating: Option<u32>,
+        /// Only import games played with this komi
+        #[clap(long)]
+        komi: Option<Komi>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ModelCommand {
+    /// List the checkpoints of a model index
+    List {
+        /// URL or path of the model index, defaults to $ALPHA_TAK_MODEL_INDEX
+        #[clap(long)]
+        index: Option<String>,
+    },
+    /// Download a checkpoint, verify its hash, and store it with its
+    /// metadata
+    Fetch {
+        /// Name of the checkpoint in the index
+        name: String,
+        /// URL or path of the model index, defaults to $ALPHA_TAK_MODEL_INDEX
+        #[clap(long)]
+        index: Option<String>,
+        /// Directory to store the checkpoint in
+        #[clap(long, default_value = "models/pretrained")]
+        out_dir: String,
+    },
 }
@@ -0,0 +1,94 @@
+use std::{
+    error::Error,
+    fs::{create_dir_all, remove_file, write},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Environment variable with the URL or path of the model index, used when
+/// none is given on the command line.
+pub const INDEX_VAR: &str = "ALPHA_TAK_MODEL_INDEX";
+
+/// A published checkpoint as listed in a model index, which is a JSON array
+/// of these.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModelEntry {
+    pub name: String,
+    pub url: String,
+    /// Hex encoded SHA-256 of the checkpoint.
+    pub sha256: String,
+    pub size: usize,
+    pub blocks: usize,
+    pub filters: i64,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Read a file, or download it if `source` is an HTTP(S) URL.
+fn read_source(source: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let mut bytes = Vec::new();
+        ureq::get(source).call()?.into_reader().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    } else {
+        Ok(std::fs::read(source)?)
+    }
+}
+
+pub fn load_index(index: &str) -> Result<Vec<ModelEntry>, Box<dyn Error>> {
+    Ok(serde_json::from_slice(&read_source(index)?)?)
+}
+
+pub fn print_index(entries: &[ModelEntry]) {
+    println!("name                 size blocks filters  description");
+    for entry in entries {
+        println!(
+            "{: <20} {: >4} {: >6} {: >7}  {}",
+            entry.name, entry.size, entry.blocks, entry.filters, entry.description
+        );
+    }
+}
+
+/// Download a checkpoint into `out_dir` as `<name>.model` next to its
+/// metadata in `<name>.json`. The checkpoint is only kept if its hash
+/// matches the index. A checkpoint that is already there and intact is not
+/// downloaded again.
+pub fn fetch(entry: &ModelEntry, out_dir: impl AsRef<Path>) -> Result<PathBuf, Box<dyn Error>> {
+    if !is_safe_name(&entry.name) {
+        return Err(format!("invalid model name {:?} in the index", entry.name).into());
+    }
+    create_dir_all(&out_dir)?;
+    let model_path = out_dir.as_ref().join(format!("{}.model", entry.name));
+    let metadata_path = out_dir.as_ref().join(format!("{}.json", entry.name));
+
+    let intact = std::fs::read(&model_path).map_or(false, |bytes| hash_matches(&bytes, &entry.sha256));
+    if !intact {
+        let bytes = read_source(&entry.url)?;
+        if !hash_matches(&bytes, &entry.sha256) {
+            remove_file(&model_path).ok();
+            return Err(format!("hash of {} does not match the index", entry.url).into());
+        }
+        write(&model_path, bytes)?;
+    }
+    write(&metadata_path, serde_json::to_string_pretty(entry)?)?;
+    Ok(model_path)
+}
+
+/// Whether a name from the index can be used as a file name in the output
+/// directory without escaping it.
+fn is_safe_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.contains("..")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+fn hash_matches(bytes: &[u8], expected: &str) -> bool {
+    let digest = Sha256::digest(bytes);
+    let hex: String = digest.iter().map(|byte| format!("{byte:02x}")).collect();
+    hex.eq_ignore_ascii_case(expected.trim())
+}
@@ -0,0 +1,179 @@
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::Path,
+};
+
+use alpha_tak::{
+    calibration::Calibration,
+    config::{CALIBRATION_BUCKETS, N},
+    example::Example,
+    model::{network::Network, train::Loss},
+};
+use tracing::{debug, info, warn};
+
+use crate::{pit::PitResult, self_play::SelfPlayStats, tensorboard::EventWriter};
+
+const CSV_HEADER: &str = "iteration,time,policy_loss,value_loss,wins,draws,losses,promoted,games,mean_plies,\
+                          resignation_rate,false_resignation_rate,rollouts_per_second,brier_score,\
+                          calibration_error,examples";
+
+/// What happened in one iteration of the training loop.
+#[derive(Debug, Default)]
+pub struct Generation {
+    pub iteration: u64,
+    pub time: u64,
+    /// Mean loss of the training batches, if the network was trained.
+    pub loss: Option<Loss>,
+    pub pit: Option<PitResult>,
+    pub promoted: bool,
+    pub self_play: SelfPlayStats,
+    pub calibration: Option<Calibration>,
+    /// Examples in the replay buffer.
+    pub examples: usize,
+}
+
+impl Generation {
+    pub fn set_losses(&mut self, losses: &[Loss]) {
+        if losses.is_empty() {
+            return;
+        }
+        let count = losses.len() as f32;
+        self.loss = Some(Loss {
+            policy: losses.iter().map(|loss| loss.policy).sum::<f32>() / count,
+            value: losses.iter().map(|loss| loss.value).sum::<f32>() / count,
+        });
+    }
+
+    /// Values which are known, for plotting.
+    fn scalars(&self) -> Vec<(&'static str, f64)> {
+        let mut scalars = Vec::new();
+        if let Some(loss) = self.loss {
+            scalars.push(("loss/policy", loss.policy as f64));
+            scalars.push(("loss/value", loss.value as f64));
+        }
+        if let Some(pit) = self.pit {
+            scalars.push(("pit/win_rate", pit.win_rate()));
+            scalars.push(("pit/promoted", self.promoted as u8 as f64));
+        }
+        scalars.push(("self_play/mean_plies", self.self_play.mean_plies()));
+        scalars.push(("self_play/resignation_rate", self.self_play.resignation_rate()));
+        scalars.push((
+            "self_play/false_resignation_rate",
+            self.self_play.false_resignation_rate(),
+        ));
+        scalars.push((
+            "self_play/rollouts_per_second",
+            self.self_play.rollouts_per_second(),
+        ));
+        if let Some(calibration) = &self.calibration {
+            scalars.push(("value/brier_score", calibration.brier_score));
+            scalars.push(("value/calibration_error", calibration.expected_error()));
+        }
+        scalars.push(("examples", self.examples as f64));
+        scalars
+    }
+
+    /// One line of the CSV file, with unknown values left empty.
+    fn to_line(&self) -> String {
+        fn optional<T: ToString>(value: Option<T>) -> String {
+            value.map(|value| value.to_string()).unwrap_or_default()
+        }
+        let stats = &self.self_play;
+        format!(
+            "{},{},{},{},{},{},{},{},{},{:.2},{:.4},{:.4},{:.1},{},{},{}\n",
+            self.iteration,
+            self.time,
+            optional(self.loss.map(|loss| loss.policy)),
+            optional(self.loss.map(|loss| loss.value)),
+            optional(self.pit.map(|pit| pit.wins)),
+            optional(self.pit.map(|pit| pit.draws)),
+            optional(self.pit.map(|pit| pit.losses)),
+            self.promoted,
+            stats.games,
+            stats.mean_plies(),
+            stats.resignation_rate(),
+            stats.false_resignation_rate(),
+            stats.rollouts_per_second(),
+            optional(
+                self.calibration
+                    .as_ref()
+                    .map(|calibration| calibration.brier_score)
+            ),
+            optional(self.calibration.as_ref().map(Calibration::expected_error)),
+            self.examples
+        )
+    }
+}
+
+/// Records a [`Generation`] per iteration as a line of a CSV file, and
+/// optionally as TensorBoard scalars.
+pub struct GenerationLog {
+    path: String,
+    tensorboard: Option<EventWriter>,
+    /// Examples which are never trained on, for the calibration of the value
+    /// head.
+    holdout: Vec<Example<N>>,
+}
+
+impl GenerationLog {
+    pub fn new(path: &str, tensorboard_dir: Option<&str>, holdout: Vec<Example<N>>) -> Self {
+        let tensorboard = tensorboard_dir.and_then(|dir| {
+            EventWriter::create(dir)
+                .map_err(|err| warn!("could not create TensorBoard events in {dir}: {err}"))
+                .ok()
+        });
+        GenerationLog {
+            path: path.to_string(),
+            tensorboard,
+            holdout,
+        }
+    }
+
+    /// Compare the value head with the results of the held-out examples, or of
+    /// the given ones if there are none, to notice when it drifts away from
+    /// them.
+    pub fn calibrate(&self, network: &Network<N>, new_examples: &[Example<N>], generation: &mut Generation) {
+        let examples = if self.holdout.is_empty() {
+            new_examples
+        } else {
+            &self.holdout
+        };
+        if examples.is_empty() {
+            return;
+        }
+        let calibration = Calibration::from_examples(network, examples, CALIBRATION_BUCKETS);
+        calibration.report();
+        debug!("value head calibration\n{}", calibration.to_csv());
+        generation.calibration = Some(calibration);
+    }
+
+    pub fn record(&mut self, generation: &Generation) {
+        let stats = &generation.self_play;
+        info!("self-play: {stats}");
+        if let Some(calibration) = &generation.calibration {
+            info!(
+                brier_score = calibration.brier_score,
+                calibration_error = calibration.expected_error(),
+                "value head calibration"
+            );
+        }
+
+        self.append(generation)
+            .unwrap_or_else(|err| warn!("could not write to {}: {err}", self.path));
+        if let Some(tensorboard) = &mut self.tensorboard {
+            tensorboard
+                .scalars(generation.iteration, &generation.scalars())
+                .unwrap_or_else(|err| warn!("could not write TensorBoard events: {err}"));
+        }
+    }
+
+    fn append(&self, generation: &Generation) -> io::Result<()> {
+        let new = !Path::new(&self.path).exists();
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        if new {
+            writeln!(file, "{CSV_HEADER}")?;
+        }
+        file.write_all(generation.to_line().as_bytes())
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    error::Error,
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+};
+
+use alpha_tak::model::train::Loss;
+
+use crate::pit::PitResult;
+
+/// Summary of one training iteration, stored as one line in the history file.
+#[derive(Debug, Default)]
+pub struct Checkpoint {
+    pub time: u64,
+    /// Path to the saved model, empty if the network was not promoted.
+    pub model: String,
+    pub pit: PitResult,
+    /// Elo of the trained network relative to the initial network.
+    pub elo: f64,
+    pub losses: Vec<Loss>,
+    /// Seed of the run, see [`alpha_tak::rng`].
+    pub seed: u64,
+    /// Iteration of the run the checkpoint was made in.
+    pub iteration: u64,
+}
+
+impl Checkpoint {
+    pub fn promoted(&self) -> bool {
+        !self.model.is_empty()
+    }
+
+    fn to_line(&self) -> String {
+        let policy: Vec<_> = self.losses.iter().map(|l| l.policy.to_string()).collect();
+        let value: Vec<_> = self.losses.iter().map(|l| l.value.to_string()).collect();
+        format!(
+            "{},{},{},{},{},{:.1},{},{},{},{}\n",
+            self.time,
+            self.model,
+            self.pit.wins,
+            self.pit.draws,
+            self.pit.losses,
+            self.elo,
+            policy.join(" "),
+            value.join(" "),
+            self.seed,
+            self.iteration
+        )
+    }
+
+    fn from_line(line: &str) -> Result<Self, Box<dyn Error>> {
+        let fields: Vec<_> = line.split(',').collect();
+        // older histories do not have the seed and iteration
+        if fields.len() != 8 && fields.len() != 10 {
+            return Err(format!("expected 10 fields in history line, got {}", fields.len()).into());
+        }
+        let policy = fields[6].split_whitespace().map(str::parse::<f32>);
+        let value = fields[7].split_whitespace().map(str::parse::<f32>);
+        let losses = policy
+            .zip(value)
+            .map(|(policy, value)| {
+                Ok(Loss {
+                    policy: policy?,
+                    value: value?,
+                })
+            })
+            .collect::<Result<_, Box<dyn Error>>>()?;
+        Ok(Checkpoint {
+            time: fields[0].parse()?,
+            model: fields[1].to_string(),
+            pit: PitResult {
+                wins: fields[2].parse()?,
+                draws: fields[3].parse()?,
+                losses: fields[4].parse()?,
+            },
+            elo: fields[5].parse()?,
+            losses,
+            seed: fields.get(8).map_or(Ok(0), |seed| seed.parse())?,
+            iteration: fields.get(9).map_or(Ok(0), |iteration| iteration.parse())?,
+        })
+    }
+
+    /// Append the checkpoint to the history file.
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(self.to_line().as_bytes())?;
+        Ok(())
+    }
+}
+
+pub fn load_history(path: &str) -> Result<Vec<Checkpoint>, Box<dyn Error>> {
+    let mut file = File::open(path)?;
+    let mut s = String::new();
+    file.read_to_string(&mut s)?;
+    s.lines().map(Checkpoint::from_line).collect()
+}
+
+/// Print the history as CSV for plotting.
+/// With `curves` every training batch gets its own row, otherwise every
+/// checkpoint does.
+pub fn dump_history(history: &[Checkpoint], curves: bool) {
+    if curves {
+        println!("step,checkpoint,policy_loss,value_loss");
+        let mut step = 0;
+        for (i, checkpoint) in history.iter().enumerate() {
+            for loss in &checkpoint.losses {
+                println!("{step},{i},{},{}", loss.policy, loss.value);
+                step += 1;
+            }
+        }
+    } else {
+        println!("checkpoint,time,policy_loss,value_loss,wins,draws,losses,win_rate,promoted,elo");
+        for (i, checkpoint) in history.iter().enumerate() {
+            // report the loss at the end of training
+            let last = checkpoint.losses.last().copied().unwrap_or(Loss {
+                policy: f32::NAN,
+                value: f32::NAN,
+            });
+            println!(
+                "{i},{},{},{},{},{},{},{:.4},{},{:.1}",
+                checkpoint.time,
+                last.policy,
+                last.value,
+                checkpoint.pit.wins,
+                checkpoint.pit.draws,
+                checkpoint.pit.losses,
+                checkpoint.pit.win_rate(),
+                checkpoint.promoted(),
+                checkpoint.elo
+            );
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::{read_dir, read_to_string},
+    io,
+};
+
+use alpha_tak::{config::N, example::Example};
+use tak::*;
+use tracing::warn;
+
+/// Which human games are turned into examples by [`import_ptn_dir`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ImportFilter {
+    /// Lowest rating of both players, from the `Rating1` and `Rating2` tags.
+    /// Games without ratings are skipped when it is set.
+    pub min_rating: Option<u32>,
+    /// Only games played with this komi.
+    pub komi: Option<Komi>,
+}
+
+/// What happened to the games found by [`import_ptn_dir`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ImportStats {
+    pub games: usize,
+    pub examples: usize,
+    /// Games which could not be parsed or replayed.
+    pub broken: usize,
+    /// Games left out by the [`ImportFilter`] or played on another board size.
+    pub filtered: usize,
+    /// Games without a result, or with one the rules do not agree with.
+    pub unfinished: usize,
+}
+
+impl Display for ImportStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} examples from {} games, skipped {} broken, {} filtered and {} unfinished games",
+            self.examples, self.games, self.broken, self.filtered, self.unfinished
+        )
+    }
+}
+
+/// Turn every game in the PTN files of `dir` into examples for supervised
+/// training. The policy of a position is the move played in it and the value
+/// is the result of the game, from the perspective of the player to move.
+pub fn import_ptn_dir(dir: &str, filter: ImportFilter) -> io::Result<(Vec<Example<N>>, ImportStats)> {
+    let mut paths: Vec<_> = read_dir(dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().map_or(false, |extension| extension == "ptn"))
+        .collect();
+    paths.sort();
+
+    let mut examples = Vec::new();
+    let mut stats = ImportStats::default();
+    for path in paths {
+        let ptn = match read_to_string(&path) {
+            Ok(ptn) => ptn,
+            Err(err) => {
+                warn!("could not read {}: {err}", path.display());
+                continue;
+            }
+        };
+        for game in split_ptn_games(&ptn) {
+            match import_game(game, filter) {
+                Ok(Some(game_examples)) => {
+                    stats.games += 1;
+                    stats.examples += game_examples.len();
+                    examples.extend(game_examples);
+                }
+                Ok(None) => stats.filtered += 1,
+                Err(Skip::Unfinished) => stats.unfinished += 1,
+                Err(Skip::Broken(err)) => {
+                    warn!("could not import a game in {}: {err}", path.display());
+                    stats.broken += 1;
+                }
+            }
+        }
+    }
+    Ok((examples, stats))
+}
+
+enum Skip {
+    Unfinished,
+    Broken(String),
+}
+
+/// Examples of one game, or `None` if the filter leaves it out or it was
+/// played on another board size.
+fn import_game(ptn: &str, filter: ImportFilter) -> Result<Option<Vec<Example<N>>>, Skip> {
+    let tags: HashMap<_, _> = ptn_tags(ptn).into_iter().collect();
+    if matches!(tags.get("Size").map(|size| size.trim().parse::<usize>()), Some(Ok(size)) if size != N) {
+        return Ok(None);
+    }
+    if let Some(min_rating) = filter.min_rating {
+        let rating = |tag: &str| tags.get(tag).and_then(|rating| rating.trim().parse::<u32>().ok());
+        match (rating("Rating1"), rating("Rating2")) {
+            (Some(white), Some(black)) if white.min(black) >= min_rating => {}
+            _ => return Ok(None),
+        }
+    }
+
+    let (mut game, turns) = Game::<N>::from_ptn_with_turns(ptn).map_err(Skip::Broken)?;
+    if matches!(filter.komi, Some(komi) if komi != game.komi) {
+        return Ok(None);
+    }
+    if !Game::<N>::ptn_result_mismatches(ptn)
+        .map_err(Skip::Broken)?
+        .is_empty()
+    {
+        return Err(Skip::Unfinished);
+    }
+
+    let mut positions = Vec::with_capacity(turns.len());
+    for turn in turns {
+        positions.push((game.clone(), turn.clone()));
+        game.play(turn).map_err(Skip::Broken)?;
+    }
+    // resignations and time losses are only in the tag
+    let white_result = match game.winner() {
+        GameResult::Winner {
+            colour: Colour::White,
+            ..
+        } => 1.,
+        GameResult::Winner { .. } => -1.,
+        GameResult::Draw { .. } => 0.,
+        GameResult::Ongoing => tags
+            .get("Result")
+            .map(String::as_str)
+            .and_then(tag_result)
+            .ok_or(Skip::Unfinished)?,
+    };
+
+    let mut examples: Vec<_> = positions
+        .into_iter()
+        .map(|(game, turn)| {
+            let result = if game.to_move == Colour::White {
+                white_result
+            } else {
+                -white_result
+            };
+            Example {
+                game,
+                policy: HashMap::from([(turn, 1)]),
+                result,
+                reply: HashMap::new(),
+            }
+        })
+        .collect();
+    // the move played next is the reply target, like in self-play
+    for i in 1..examples.len() {
+        examples[i - 1].reply = examples[i].policy.clone();
+    }
+    Ok(Some(examples))
+}
+
+/// Result of a PTN `Result` tag for white, if the game was finished.
+fn tag_result(result: &str) -> Option<f32> {
+    match result.trim() {
+        "R-0" | "F-0" | "1-0" => Some(1.),
+        "0-R" | "0-F" | "0-1" => Some(-1.),
+        "1/2-1/2" => Some(0.),
+        _ => None,
+    }
+}
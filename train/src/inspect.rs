@@ -0,0 +1,95 @@
+use std::collections::{BTreeMap, HashSet};
+
+use alpha_tak::{config::N, example::Example};
+use rand::{prelude::SliceRandom, thread_rng};
+use tak::*;
+
+/// Width of the ply histogram buckets.
+const PLY_BUCKET: u64 = 10;
+
+/// Overview of the contents of example files.
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub positions: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    /// Positions per ply bucket, keyed by the first ply of the bucket.
+    pub plies: BTreeMap<u64, usize>,
+    /// Positions which are equal to an earlier one, ignoring the ply.
+    pub duplicates: usize,
+    /// Positions whose policy has no visits at all.
+    pub empty_policies: usize,
+}
+
+impl Summary {
+    pub fn new(examples: &[Example<N>]) -> Self {
+        let mut summary = Summary {
+            positions: examples.len(),
+            ..Default::default()
+        };
+        let mut seen = HashSet::new();
+        for example in examples {
+            // results are from the perspective of the player to move
+            if example.result > 0. {
+                summary.wins += 1;
+            } else if example.result < 0. {
+                summary.losses += 1;
+            } else {
+                summary.draws += 1;
+            }
+            *summary
+                .plies
+                .entry(example.game.ply / PLY_BUCKET * PLY_BUCKET)
+                .or_default() += 1;
+            if !seen.insert(&example.game) {
+                summary.duplicates += 1;
+            }
+            if example.policy.values().all(|&visits| visits == 0) {
+                summary.empty_policies += 1;
+            }
+        }
+        summary
+    }
+
+    pub fn print(&self) {
+        let share = |count: usize| 100. * count as f64 / self.positions.max(1) as f64;
+        println!("positions: {}", self.positions);
+        println!(
+            "results for the player to move: {} wins ({:.1}%), {} draws ({:.1}%), {} losses ({:.1}%)",
+            self.wins,
+            share(self.wins),
+            self.draws,
+            share(self.draws),
+            self.losses,
+            share(self.losses)
+        );
+        println!("duplicates: {} ({:.1}%)", self.duplicates, share(self.duplicates));
+        println!("empty policies: {}", self.empty_policies);
+        println!("plies:");
+        let largest = self.plies.values().copied().max().unwrap_or_default().max(1);
+        for (&start, &count) in &self.plies {
+            let bar = "#".repeat(count * 50 / largest);
+            println!("{: >4}-{: <4} {count: >8} {bar}", start, start + PLY_BUCKET - 1);
+        }
+    }
+}
+
+/// Print randomly chosen examples, as TPS or as a drawn board.
+pub fn print_samples(examples: &[Example<N>], count: usize, render: bool) {
+    for example in examples.choose_multiple(&mut thread_rng(), count) {
+        let mut policy: Vec<_> = example.policy.iter().collect();
+        policy.sort_by(|a, b| b.1.cmp(a.1));
+        let policy: Vec<_> = policy
+            .into_iter()
+            .take(5)
+            .map(|(turn, visits)| format!("{} {visits}", turn.to_ptn()))
+            .collect();
+        println!();
+        if render {
+            println!("{}", example.game.board);
+        }
+        println!("{}", example.game.to_tps());
+        println!("result: {}, policy: {}", example.result, policy.join(", "));
+    }
+}
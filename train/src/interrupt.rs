@@ -0,0 +1,25 @@
+//! Stopping a run with Ctrl-C. The first interrupt lets the self-play games
+//! in flight finish and the run save its state at the next safe point, a
+//! second one stops immediately.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tracing::warn;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+pub fn install_handler() {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            warn!("interrupted again, stopping without saving");
+            std::process::exit(130);
+        }
+        warn!("interrupted, finishing the games in progress, press Ctrl-C again to stop immediately");
+    })
+    .unwrap_or_else(|err| warn!("could not install the Ctrl-C handler: {err}"));
+}
+
+/// Whether the run should save its state and stop.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
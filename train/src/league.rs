@@ -0,0 +1,73 @@
+use alpha_tak::{config::N, example::Example, model::network::Network, rng, threadpool::thread_pool_2};
+use rand::prelude::SliceRandom;
+use tracing::{info, warn};
+
+use crate::{
+    history::{load_history, Checkpoint},
+    pit::pit_game,
+    train_config::TrainConfig,
+    HISTORY_FILE,
+};
+
+/// An older checkpoint which plays the league fraction of the self-play games
+/// in place of the current network, keeping the examples of both sides.
+/// Playing only against an identical copy leaves blind spots which other
+/// opponents find.
+pub struct League {
+    model: String,
+    opponent: Network<N>,
+    /// Every match is a game with each colour.
+    matches: usize,
+}
+
+impl League {
+    /// Pick the opponent at random from the promoted checkpoints, or `None`
+    /// if no games go to the league or there is no older checkpoint.
+    pub fn choose(config: &TrainConfig) -> Option<Self> {
+        let matches = (config.league_fraction * config.self_play_games as f64 / 2.).round() as usize;
+        let matches = matches.min(config.self_play_games / 2);
+        if matches == 0 {
+            return None;
+        }
+
+        // the last promoted checkpoint is the current network
+        let mut models: Vec<_> = load_history(HISTORY_FILE)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(Checkpoint::promoted)
+            .map(|checkpoint| checkpoint.model)
+            .collect();
+        models.pop();
+        rng::seed_task(u64::MAX);
+        let model = rng::with_rng(|rng| models.choose(rng).cloned())?;
+        match Network::<N>::load(&model) {
+            Ok(opponent) => Some(League {
+                model,
+                opponent,
+                matches,
+            }),
+            Err(err) => {
+                warn!("could not load league opponent {model}: {err}");
+                None
+            }
+        }
+    }
+
+    /// Self-play games the league plays instead.
+    pub fn games(&self) -> usize {
+        2 * self.matches
+    }
+
+    pub fn play(&self, network: &Network<N>, config: &TrainConfig) -> Vec<Example<N>> {
+        const WORKERS: usize = 64;
+
+        info!("playing {} league matches against {}", self.matches, self.model);
+        let config = *config;
+        thread_pool_2::<N, WORKERS, _, _>(network, &self.opponent, self.matches, move |new, old, index| {
+            pit_game(new, old, index, &config)
+        })
+        .into_iter()
+        .flat_map(|(_, _, examples, _)| examples)
+        .collect()
+    }
+}
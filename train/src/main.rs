@@ -1,42 +1,280 @@
+#[macro_use]
+extern crate lazy_static;
+
 mod cli;
+mod fetch;
+mod generation_log;
+mod history;
+mod import;
+mod inspect;
+mod interrupt;
+mod league;
 mod pit;
+mod resume;
 mod self_play;
+mod tensorboard;
+mod train_config;
 mod training_loop;
+mod tune;
 
-use std::fs::create_dir_all;
+use std::{
+    collections::HashMap,
+    env::set_current_dir,
+    fs::{create_dir_all, remove_file},
+    path::Path,
+};
 
 use alpha_tak::{
-    config::N,
-    example::{load_examples, save_examples},
-    model::network::Network,
+    calibration::Calibration,
+    config::{AUX_SIZES, MAX_EXAMPLES, N},
+    example::{load_examples, save_examples, Example},
+    export::export_examples,
+    init_logging,
+    metrics,
+    model::{aux_head::AuxExamples, network::Network},
+    relabel::{relabel, Blend},
+    replay::ReplayBuffer,
+    rng,
     sys_time,
     use_cuda,
 };
 use clap::Parser;
-use cli::Args;
+use cli::{Args, Command, ModelCommand};
+use fetch::{fetch, load_index, print_index, INDEX_VAR};
+use generation_log::GenerationLog;
+use history::{dump_history, load_history};
+use import::{import_ptn_dir, ImportFilter};
+use inspect::{print_samples, Summary};
+use interrupt::interrupted;
+use resume::Manifest;
 use self_play::self_play;
+use tracing::{error, info, warn};
+use train_config::TrainConfig;
 use training_loop::training_loop;
+use tune::{default_params, save_params, Tuner};
 
 const MODEL_DIR: &str = "models";
 const EXAMPLE_DIR: &str = "examples";
 const GAME_DIR: &str = "games";
+/// Games of the current self-play iteration, so that it can be resumed.
+const PROGRESS_DIR: &str = "games/in_progress";
+const HISTORY_FILE: &str = "history.csv";
+/// Statistics of every iteration, see [`generation_log::Generation`].
+const GENERATIONS_FILE: &str = "generations.csv";
+/// Examples of the last generations, so that a run can resume with them.
+const REPLAY_FILE: &str = "replay.data";
+/// Written when a run is interrupted, see [`resume::Manifest`].
+const MANIFEST_FILE: &str = "resume.json";
 
 fn main() {
     let args = Args::parse();
+    init_logging();
+
+    if let Some(command) = args.command {
+        match command {
+            Command::Model { command } => model_command(command),
+            Command::History { curves } => match load_history(HISTORY_FILE) {
+                Ok(history) => dump_history(&history, curves),
+                Err(err) => error!("could not load history at {HISTORY_FILE}: {err}"),
+            },
+            Command::Calibration {
+                model_path,
+                examples,
+                buckets,
+            } => {
+                use_cuda();
+                let network = get_network(Some(model_path));
+                let examples = load_all_examples(examples);
+                let calibration = Calibration::from_examples(&network, &examples, buckets);
+                print!("{}", calibration.to_csv());
+                println!("brier score: {:.4}", calibration.brier_score);
+            }
+            Command::Distill {
+                teacher_path,
+                student_path,
+                examples,
+                blocks,
+                filters,
+                epochs,
+            } => {
+                use_cuda();
+                let teacher = get_network(Some(teacher_path));
+                let games: Vec<_> = load_all_examples(examples)
+                    .into_iter()
+                    .map(|ex| ex.game)
+                    .collect();
+                let mut student = Network::<N>::with_size(blocks, filters);
+                student.distill(&teacher, &games, epochs);
+                student
+                    .save(&student_path)
+                    .unwrap_or_else(|err| error!("could not save student at {student_path}: {err}"));
+            }
+            Command::Relabel {
+                model_path,
+                out_dir,
+                examples,
+                value_weight,
+                policy_weight,
+            } => {
+                use_cuda();
+                let network = get_network(Some(model_path));
+                let blend = Blend {
+                    value: value_weight,
+                    policy: policy_weight,
+                };
+                create_dir_all(&out_dir).unwrap();
+                for path in examples {
+                    info!("relabelling {path}");
+                    let mut shard = load_examples::<N>(&path)
+                        .unwrap_or_else(|_| panic!("could not load example at {path}"));
+                    relabel(&network, &mut shard, blend);
+                    let name = Path::new(&path).file_name().expect("example path is not a file");
+                    save_examples(&shard, Path::new(&out_dir).join(name));
+                }
+            }
+            Command::Tune {
+                model_path,
+                out,
+                iterations,
+                matches,
+                rollouts,
+                perturbation,
+                learning_rate,
+            } => {
+                use_cuda();
+                let network = get_network(Some(model_path));
+                let tuner = Tuner {
+                    iterations,
+                    matches,
+                    rollouts,
+                    perturbation,
+                    learning_rate,
+                };
+                let params = tuner.run(&network, default_params());
+                save_params(&params, &out)
+                    .unwrap_or_else(|err| error!("could not save parameters to {out}: {err}"));
+            }
+            Command::Inspect {
+                examples,
+                samples,
+                render,
+            } => {
+                let examples = load_all_examples(examples);
+                Summary::new(&examples).print();
+                print_samples(&examples, samples, render);
+            }
+            Command::Export {
+                out_dir,
+                examples,
+                shard_size,
+                symmetries,
+            } => {
+                let examples = load_all_examples(examples);
+                create_dir_all(&out_dir).unwrap();
+                match export_examples(&examples, &out_dir, shard_size, symmetries) {
+                    Ok(()) => info!("exported {} examples to {out_dir}", examples.len()),
+                    Err(err) => error!("could not export examples to {out_dir}: {err}"),
+                }
+            }
+            Command::ImportPtn {
+                dir,
+                out,
+                min_rating,
+                komi,
+            } => match import_ptn_dir(&dir, ImportFilter { min_rating, komi }) {
+                Ok((examples, stats)) => {
+                    info!("imported {stats}");
+                    if let Some(parent) = Path::new(&out).parent() {
+                        create_dir_all(parent).unwrap();
+                    }
+                    save_examples(&examples, &out);
+                }
+                Err(err) => error!("could not import games from {dir}: {err}"),
+            },
+        }
+        return;
+    }
+
     if !(args.no_gpu || use_cuda()) {
-        println!("Could not enable CUDA.");
+        warn!("Could not enable CUDA.");
         return;
     }
 
+    // read before entering the run directory, so that the path is relative
+    // to where the trainer was started
+    let config = TrainConfig::from_args(&args.config)
+        .unwrap_or_else(|err| panic!("could not load training config: {err}"));
+    info!(?config, "training config");
+
+    // paths of a run are relative to its directory
+    if let Some(dir) = &args.resume {
+        set_current_dir(dir).unwrap_or_else(|err| panic!("could not enter run directory {dir}: {err}"));
+        info!("resuming the run in {dir}");
+    }
+
     // Make folders if they do not exist yet
     create_dir_all(format!("./{MODEL_DIR}/")).unwrap();
     create_dir_all(format!("./{EXAMPLE_DIR}/")).unwrap();
     create_dir_all(format!("./{GAME_DIR}/")).unwrap();
 
+    interrupt::install_handler();
+
+    if let Some(addr) = &args.metrics_addr {
+        metrics::serve(addr).unwrap_or_else(|err| panic!("could not serve metrics on {addr}: {err}"));
+    }
+
     if args.only_self_play {
-        only_self_play(args.model_path)
+        only_self_play(args.model_path, &config)
     } else {
-        train(args.model_path, args.examples)
+        train(
+            args.model_path,
+            args.examples,
+            args.aux_examples,
+            GenerationLog::new(
+                GENERATIONS_FILE,
+                args.tensorboard.as_deref(),
+                load_all_examples(args.holdout),
+            ),
+            config,
+            args.seed,
+            args.resume.is_some(),
+        )
+    }
+}
+
+fn model_command(command: ModelCommand) {
+    let index_source = |index: Option<String>| {
+        index
+            .or_else(|| std::env::var(INDEX_VAR).ok())
+            .unwrap_or_else(|| panic!("no model index given, use --index or set {INDEX_VAR}"))
+    };
+    match command {
+        ModelCommand::List { index } => {
+            let index = index_source(index);
+            match load_index(&index) {
+                Ok(entries) => print_index(&entries),
+                Err(err) => error!("could not load model index {index}: {err}"),
+            }
+        }
+        ModelCommand::Fetch { name, index, out_dir } => {
+            let index = index_source(index);
+            let entries =
+                load_index(&index).unwrap_or_else(|err| panic!("could not load model index {index}: {err}"));
+            match entries.iter().find(|entry| entry.name == name) {
+                Some(entry) => match fetch(entry, &out_dir) {
+                    Ok(path) => info!(
+                        "fetched {name} to {}, a {}x{} network with {} blocks and {} filters",
+                        path.display(),
+                        entry.size,
+                        entry.size,
+                        entry.blocks,
+                        entry.filters
+                    ),
+                    Err(err) => error!("could not fetch {name}: {err}"),
+                },
+                None => error!("{name} is not in the model index {index}"),
+            }
+        }
     }
 }
 
@@ -47,34 +285,151 @@ fn get_network(model_path: Option<String>) -> Network<N> {
             Network::<N>::load(m).unwrap_or_else(|_| panic!("couldn't load model at {m}"))
         }
         _ => {
-            println!("generating random model");
+            info!("generating random model");
             Network::<N>::default()
         }
     }
 }
 
-fn only_self_play(model_path: Option<String>) {
+fn only_self_play(model_path: Option<String>, config: &TrainConfig) {
     let network = get_network(model_path);
+    let mut config = *config;
+    config.calibrate(&network);
+    // games are seeded by their index, so every batch needs a new seed
+    rng::set_stream(rng::Stream::SelfPlay);
     loop {
-        let examples = self_play(&network);
+        rng::set_run(rand::random(), 0);
+        let (examples, stats) = self_play(&network, &config);
+        info!("self-play: {stats}");
+        if interrupted() {
+            // the finished games are kept as progress of the next batch
+            info!("stopped self-play");
+            return;
+        }
         save_examples(&examples, format!("{EXAMPLE_DIR}/{}.data", sys_time()));
     }
 }
 
-fn train(model_path: Option<String>, example_paths: Vec<String>) {
-    let network = get_network(model_path);
+fn train(
+    model_path: Option<String>,
+    example_paths: Vec<String>,
+    aux_example_paths: Vec<String>,
+    log: GenerationLog,
+    mut config: TrainConfig,
+    seed: Option<u64>,
+    resume: bool,
+) {
+    // an interrupted run left a manifest, one that crashed only its history
+    let manifest = if resume { load_manifest() } else { None };
+    let network = match &manifest {
+        Some(manifest) => get_network(Some(manifest.model.clone())),
+        None if resume => get_network(latest_model()),
+        None => get_network(model_path),
+    };
+    config.calibrate(&network);
+
+    // continue with the examples of the previous run, and optionally more
+    let replay_path = manifest
+        .as_ref()
+        .map_or(REPLAY_FILE, |manifest| manifest.replay.as_str());
+    let mut replay = if Path::new(replay_path).exists() {
+        info!("loading {replay_path}");
+        ReplayBuffer::load(replay_path, MAX_EXAMPLES)
+            .unwrap_or_else(|err| panic!("could not load replay buffer at {replay_path}: {err}"))
+    } else {
+        ReplayBuffer::new(MAX_EXAMPLES)
+    };
+    replay.extend(load_all_examples(example_paths));
+    let aux_examples = load_aux_examples(aux_example_paths);
+
+    // begin training loop
+    training_loop(network, replay, aux_examples, log, config, seed, manifest)
+}
 
-    // optionally load examples
+/// Read the state an interrupted run saved, if there is one.
+fn load_manifest() -> Option<Manifest> {
+    if !Path::new(MANIFEST_FILE).exists() {
+        return None;
+    }
+    let manifest =
+        Manifest::load(MANIFEST_FILE).unwrap_or_else(|err| panic!("could not load {MANIFEST_FILE}: {err}"));
+    info!(
+        "continuing at iteration {} with {}",
+        manifest.iteration, manifest.model
+    );
+    // the files it points to stay, but a later crash should resume from the
+    // history instead of going back to this state
+    remove_file(MANIFEST_FILE).unwrap_or_else(|err| warn!("could not remove {MANIFEST_FILE}: {err}"));
+    Some(manifest)
+}
+
+/// The newest promoted network in the history of the run.
+fn latest_model() -> Option<String> {
+    let model = load_history(HISTORY_FILE)
+        .ok()?
+        .into_iter()
+        .rev()
+        .find(|checkpoint| checkpoint.promoted())
+        .map(|checkpoint| checkpoint.model);
+    if model.is_none() {
+        warn!("no promoted network in {HISTORY_FILE}, starting from a random one");
+    }
+    model
+}
+
+fn load_all_examples(example_paths: Vec<String>) -> Vec<Example<N>> {
     let mut examples = Vec::new();
     for ex_path in example_paths {
-        println!("loading {ex_path}");
+        info!("loading {ex_path}");
         examples.extend(
             load_examples(&ex_path)
                 .unwrap_or_else(|_| panic!("could not load example at {ex_path}"))
                 .into_iter(),
         );
     }
+    examples
+}
 
-    // begin training loop
-    training_loop(network, examples)
+/// Load examples of other board sizes, given as `size:path`. Each size needs
+/// an auxiliary head in `AUX_SIZES`, otherwise nothing would train on them.
+fn load_aux_examples(specs: Vec<String>) -> Vec<AuxExamples> {
+    let mut paths: HashMap<usize, Vec<String>> = HashMap::new();
+    for spec in specs {
+        let (size, path) = spec
+            .split_once(':')
+            .unwrap_or_else(|| panic!("expected size:path, got {spec}"));
+        let size = size.parse().unwrap_or_else(|_| panic!("invalid size in {spec}"));
+        if size == N || !AUX_SIZES.iter().any(|&(aux_size, _)| aux_size == size) {
+            panic!("no auxiliary head for size {size} in {spec}, add it to AUX_SIZES in config.rs");
+        }
+        paths.entry(size).or_default().push(path.to_string());
+    }
+
+    macro_rules! load {
+        ($n:literal, $paths:expr) => {{
+            let mut examples = Vec::new();
+            for path in $paths {
+                info!("loading {path}");
+                examples.extend(
+                    load_examples::<$n>(&path)
+                        .unwrap_or_else(|_| panic!("could not load example at {path}"))
+                        .into_iter(),
+                );
+            }
+            AuxExamples::new(&examples)
+        }};
+    }
+
+    paths
+        .into_iter()
+        .map(|(size, paths)| match size {
+            3 => load!(3, paths),
+            4 => load!(4, paths),
+            5 => load!(5, paths),
+            6 => load!(6, paths),
+            7 => load!(7, paths),
+            8 => load!(8, paths),
+            _ => panic!("unsupported board size {size}"),
+        })
+        .collect()
 }
@@ -4,12 +4,14 @@ use std::{
 };
 
 use alpha_tak::{
-    agent::Agent,
+    agent::{Agent, RandomSymmetry},
     analysis::Analysis,
-    config::{KOMI, N, PIT_MATCHES, ROLLOUTS_PER_MOVE},
+    config::{N, PIT_CONFIDENCE_Z, PIT_MATCHES, RANDOM_SYMMETRY, WIN_RATE_THRESHOLD},
     example::Example,
+    game_result,
     model::network::Network,
     player::Player,
+    rng,
     search::turn_map::Lut,
     sys_time,
     threadpool::thread_pool_2,
@@ -17,13 +19,13 @@ use alpha_tak::{
 use arrayvec::ArrayVec;
 use tak::*;
 
-use crate::GAME_DIR;
+use crate::{train_config::TrainConfig, GAME_DIR};
 
-#[derive(Debug, Default)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct PitResult {
-    wins: u32,
-    draws: u32,
-    losses: u32,
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
 }
 
 impl PitResult {
@@ -34,6 +36,39 @@ impl PitResult {
         self.wins as f64 / (self.wins + self.losses) as f64
     }
 
+    /// Wilson score interval of the win rate, `z` standard deviations to
+    /// either side.
+    pub fn confidence_interval(&self, z: f64) -> (f64, f64) {
+        let games = (self.wins + self.losses) as f64;
+        if games == 0. {
+            return (0., 1.);
+        }
+        let rate = self.win_rate();
+        let centre = rate + z * z / (2. * games);
+        let spread = z * (rate * (1. - rate) / games + z * z / (4. * games * games)).sqrt();
+        let scale = 1. + z * z / games;
+        ((centre - spread) / scale, (centre + spread) / scale)
+    }
+
+    /// Whether the new network is better by enough to replace the old one,
+    /// with the lower end of the confidence interval above the threshold.
+    pub fn promotes(&self) -> bool {
+        let (low, _) = self.confidence_interval(PIT_CONFIDENCE_Z);
+        low > WIN_RATE_THRESHOLD
+    }
+
+    /// Estimated Elo difference of the new network compared to the old one.
+    pub fn elo_difference(&self) -> f64 {
+        let games = self.wins + self.draws + self.losses;
+        if games == 0 {
+            return 0.;
+        }
+        // clamp the score so that a clean sweep does not give infinite Elo
+        let score = (self.wins as f64 + self.draws as f64 / 2.) / games as f64;
+        let score = score.clamp(0.5 / games as f64, 1. - 0.5 / games as f64);
+        -400. * (1. / score - 1.).log10()
+    }
+
     fn update(&mut self, result: GameResult, colour: Colour) {
         match result {
             GameResult::Winner { colour: winner, .. } => {
@@ -49,10 +84,13 @@ impl PitResult {
     }
 }
 
-pub fn pit(new: &Network<N>, old: &Network<N>) -> (PitResult, Vec<Example<N>>) {
+pub fn pit(new: &Network<N>, old: &Network<N>, config: &TrainConfig) -> (PitResult, Vec<Example<N>>) {
     const WORKERS: usize = 64;
 
-    let outputs = thread_pool_2::<N, WORKERS, _, _>(new, old, PIT_MATCHES, pit_game);
+    let config = *config;
+    let outputs = thread_pool_2::<N, WORKERS, _, _>(new, old, PIT_MATCHES, move |new, old, index| {
+        pit_game(new, old, index, &config)
+    });
 
     let mut result = PitResult::default();
     let mut examples = Vec::new();
@@ -78,10 +116,33 @@ pub fn pit(new: &Network<N>, old: &Network<N>) -> (PitResult, Vec<Example<N>>) {
 }
 
 /// Play an opening from both sides with two different agents.
-fn pit_game<A: Agent<N>>(
+pub fn pit_game<A: Agent<N>>(
     new: &A,
     old: &A,
-    _index: usize,
+    index: usize,
+    config: &TrainConfig,
+) -> (GameResult, GameResult, Vec<Example<N>>, ArrayVec<Analysis<N>, 4>)
+where
+    [[Option<Tile>; N]; N]: Default,
+    Turn<N>: Lut,
+{
+    if RANDOM_SYMMETRY {
+        play_pit_game(
+            &RandomSymmetry::new(new),
+            &RandomSymmetry::new(old),
+            index,
+            config,
+        )
+    } else {
+        play_pit_game(new, old, index, config)
+    }
+}
+
+fn play_pit_game<A: Agent<N>>(
+    new: &A,
+    old: &A,
+    index: usize,
+    config: &TrainConfig,
 ) -> (GameResult, GameResult, Vec<Example<N>>, ArrayVec<Analysis<N>, 4>)
 where
     [[Option<Tile>; N]; N]: Default,
@@ -90,32 +151,33 @@ where
     let mut results = ArrayVec::<_, 2>::new();
     let mut analyses = ArrayVec::<_, 4>::new();
     let mut examples = Vec::new();
+    rng::seed_task(index as u64);
 
     // Play one game as white and one game as black from the same opening.
     for my_colour in [Colour::White, Colour::Black] {
-        let mut game = Game::with_komi(KOMI);
+        let mut game = Game::with_komi(config.komi);
 
         // TODO proper opening book using index
-        let opening = game.opening(rand::random()).unwrap();
+        let opening = game.opening(rng::random()).unwrap();
 
-        let mut new_player = Player::new(new, opening.clone(), game.komi);
-        let mut old_player = Player::new(old, opening, game.komi);
+        let mut new_player = Player::new(new, opening.clone(), game.komi).with_params(config.search_params());
+        let mut old_player = Player::new(old, opening, game.komi).with_params(config.search_params());
 
-        while matches!(game.winner(), GameResult::Ongoing) {
+        while matches!(game_result(&game), GameResult::Ongoing) {
             let turn;
             if game.to_move == my_colour {
-                new_player.rollout(&game, ROLLOUTS_PER_MOVE);
-                turn = new_player.pick_move(&game, true);
+                new_player.rollout(&game, config.rollouts_per_move);
+                turn = new_player.pick_move(&game, 0.);
                 old_player.play_move(&game, &turn);
             } else {
-                old_player.rollout(&game, ROLLOUTS_PER_MOVE);
-                turn = old_player.pick_move(&game, true);
+                old_player.rollout(&game, config.rollouts_per_move);
+                turn = old_player.pick_move(&game, 0.);
                 new_player.play_move(&game, &turn);
             };
             game.play(turn).unwrap();
         }
 
-        let winner = game.winner();
+        let winner = game_result(&game);
         results.push(winner);
 
         examples.extend(
@@ -131,8 +193,15 @@ where
                 .filter(|ex| ex.game.to_move != my_colour),
         );
 
-        analyses.push(new_player.get_analysis());
-        analyses.push(old_player.get_analysis());
+        let (white, black) = match my_colour {
+            Colour::White => ("new", "old"),
+            Colour::Black => ("old", "new"),
+        };
+        for mut analysis in [new_player.get_analysis(), old_player.get_analysis()] {
+            analysis.set_players(white, black);
+            analysis.set_date(sys_time());
+            analyses.push(analysis);
+        }
     }
 
     (results[0], results[1], examples, analyses)
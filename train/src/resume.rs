@@ -0,0 +1,36 @@
+use std::{
+    error::Error,
+    fs::{read_to_string, write},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// What an interrupted run needs to continue where it stopped, written next
+/// to the history.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub time: u64,
+    /// Path to the network the run was playing with.
+    pub model: String,
+    /// Path to the replay buffer.
+    pub replay: String,
+    /// Seed of the run, see [`alpha_tak::rng`].
+    pub seed: u64,
+    /// Iteration to continue with.
+    pub iteration: u64,
+    /// Whether the iteration was stopped during self-play, in which case
+    /// training and the pit were already done and its finished games are
+    /// kept in the progress directory.
+    pub in_self_play: bool,
+}
+
+impl Manifest {
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(&read_to_string(path)?)?)
+    }
+}
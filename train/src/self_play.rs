@@ -1,70 +1,297 @@
 use std::{
-    fs::{create_dir_all, File},
+    fmt::Display,
+    fs::{create_dir_all, read_dir, remove_dir_all, rename, File},
     io::Write,
+    path::Path,
+    time::Instant,
 };
 
 use alpha_tak::{
-    agent::Agent,
+    agent::{Agent, RandomSymmetry},
     analysis::Analysis,
     config::{
-        DIRICHLET_NOISE,
-        KOMI,
         N,
         NOISE_PLIES,
-        NOISE_RATIO,
-        ROLLOUTS_PER_MOVE,
-        SELF_PLAY_GAMES,
-        TEMPERATURE_PLIES,
+        NO_RESIGN_FRACTION,
+        OPENING_TREE_MAX_VISITS,
+        OPENING_TREE_MIN_ROLLOUTS,
+        OPENING_TREE_PLIES,
+        RANDOM_SYMMETRY,
+        RESIGN_MOVES,
+        RESIGN_THRESHOLD,
+        SELF_PLAY_WORKERS,
+        TEMPERATURE,
     },
-    example::Example,
+    example::{load_examples, save_examples, Example},
+    game_result,
+    metrics::METRICS,
     model::network::Network,
     player::Player,
+    rng,
+    search::opening_tree::OpeningTree,
     sys_time,
-    threadpool::thread_pool,
+    threadpool::thread_pool_indices,
 };
 use tak::*;
+use tracing::{info, warn};
 
-use crate::GAME_DIR;
+use crate::{interrupt::interrupted, train_config::TrainConfig, GAME_DIR, PROGRESS_DIR};
 
-pub fn self_play(network: &Network<N>) -> Vec<Example<N>> {
-    const WORKERS: usize = 128;
+/// Name of both players in the PTN of self-play games.
+const SELF_PLAY_NAME: &str = "alpha-tak";
 
-    let outputs = thread_pool::<N, WORKERS, _, _>(network, SELF_PLAY_GAMES, self_play_game);
+lazy_static! {
+    /// Opening statistics shared by all self-play games of the current network.
+    pub static ref OPENING_TREE: OpeningTree<N> = OpeningTree::new(OPENING_TREE_MAX_VISITS);
+}
+
+/// What happened in the games played by one call of [`self_play`], not
+/// counting games completed before an interruption.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SelfPlayStats {
+    pub games: usize,
+    pub plies: usize,
+    pub resignations: u64,
+    /// Games played out which one player would have resigned.
+    pub resignations_checked: u64,
+    /// Of those, the games which that player did not lose.
+    pub false_resignations: u64,
+    pub rollouts: u64,
+    pub seconds: f64,
+}
+
+impl SelfPlayStats {
+    pub fn mean_plies(&self) -> f64 {
+        self.plies as f64 / self.games.max(1) as f64
+    }
+
+    pub fn resignation_rate(&self) -> f64 {
+        self.resignations as f64 / self.games.max(1) as f64
+    }
+
+    pub fn false_resignation_rate(&self) -> f64 {
+        self.false_resignations as f64 / self.resignations_checked.max(1) as f64
+    }
+
+    pub fn rollouts_per_second(&self) -> f64 {
+        self.rollouts as f64 / self.seconds.max(f64::EPSILON)
+    }
+}
+
+impl Display for SelfPlayStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} games of {:.1} plies on average, {:.1}% resigned, {} of {} played out would have been \
+             resigned falsely, {:.0} rollouts per second",
+            self.games,
+            self.mean_plies(),
+            100. * self.resignation_rate(),
+            self.false_resignations,
+            self.resignations_checked,
+            self.rollouts_per_second()
+        )
+    }
+}
+
+pub fn self_play(network: &Network<N>, config: &TrainConfig) -> (Vec<Example<N>>, SelfPlayStats) {
+    // resume an interrupted iteration
+    create_dir_all(PROGRESS_DIR).unwrap();
     let mut examples = Vec::new();
-    let mut analyses = Vec::new();
-    for output in outputs {
-        examples.extend(output.0.into_iter());
-        analyses.push(output.1);
+    let mut remaining = Vec::new();
+    for index in 0..config.self_play_games {
+        match load_examples(&format!("{PROGRESS_DIR}/{index}.data")) {
+            Ok(game_examples) => examples.extend(game_examples),
+            Err(_) => remaining.push(index),
+        }
+    }
+    if remaining.len() < config.self_play_games {
+        info!(
+            "resuming self-play, {} of {} games already completed",
+            config.self_play_games - remaining.len(),
+            config.self_play_games
+        );
+    }
+
+    let start = Instant::now();
+    let resignations = METRICS.resignations.get();
+    let checked = METRICS.resignations_checked.get();
+    let false_resignations = METRICS.false_resignations.get();
+    let rollouts = METRICS.rollouts.get();
+    let config = *config;
+    let workers = config.self_play_workers.unwrap_or(SELF_PLAY_WORKERS);
+    let outputs = thread_pool_indices(network, remaining, workers, move |agent, index| {
+        self_play_game(agent, index, &config)
+    });
+    let mut stats = SelfPlayStats {
+        resignations: METRICS.resignations.get() - resignations,
+        resignations_checked: METRICS.resignations_checked.get() - checked,
+        false_resignations: METRICS.false_resignations.get() - false_resignations,
+        rollouts: METRICS.rollouts.get() - rollouts,
+        seconds: start.elapsed().as_secs_f64(),
+        ..Default::default()
+    };
+    // games skipped because of an interrupt have no examples
+    for output in outputs.into_iter().filter(|output| !output.is_empty()) {
+        stats.games += 1;
+        stats.plies += output.len();
+        examples.extend(output.into_iter());
+    }
+    if interrupted() {
+        // the finished games stay in the progress directory for the next run
+        return (examples, stats);
     }
 
     // TODO Do some opening analysis on the analyses
     let time = sys_time();
     if create_dir_all(format!("{GAME_DIR}/{time}")).is_ok() {
-        for (i, analysis) in analyses.into_iter().enumerate() {
-            if let Ok(mut file) = File::create(format!("{GAME_DIR}/{time}/{i}.ptn")) {
-                file.write_all(analysis.to_ptn().as_bytes()).unwrap();
+        for entry in read_dir(PROGRESS_DIR).unwrap().flatten() {
+            let path = entry.path();
+            if path.extension().map_or(false, |extension| extension == "ptn") {
+                rename(
+                    &path,
+                    format!("{GAME_DIR}/{time}/{}", entry.file_name().to_string_lossy()),
+                )
+                .ok();
             }
         }
     }
+    clear_progress();
 
-    examples
+    (examples, stats)
+}
+
+/// Forget the games of an interrupted iteration, e.g. because the network
+/// changed.
+pub fn clear_progress() {
+    if Path::new(PROGRESS_DIR).exists() {
+        remove_dir_all(PROGRESS_DIR).unwrap_or_else(|err| warn!("could not clear self-play progress: {err}"));
+    }
+}
+
+/// Play a game and record it in the progress directory, so that it is not
+/// played again if the iteration is interrupted.
+fn self_play_game<A: Agent<N>>(agent: &A, index: usize, config: &TrainConfig) -> Vec<Example<N>> {
+    // games which have not started yet are left for the next run
+    if interrupted() {
+        return Vec::new();
+    }
+    if RANDOM_SYMMETRY {
+        play_game(&RandomSymmetry::new(agent), index, config)
+    } else {
+        play_game(agent, index, config)
+    }
 }
 
-fn self_play_game<A: Agent<N>>(agent: &A, _index: usize) -> (Vec<Example<N>>, Analysis<N>) {
-    let mut game = Game::with_komi(KOMI);
+fn play_game<A: Agent<N>>(agent: &A, index: usize, config: &TrainConfig) -> Vec<Example<N>> {
+    rng::seed_task(index as u64);
+    let mut game = Game::with_komi(config.komi);
     // TODO proper opening book using index
-    let opening = game.opening(rand::random()).unwrap();
+    let opening = game.opening(rng::random()).unwrap();
 
-    let mut player = Player::new(agent, opening, game.komi);
+    let mut player = Player::new(agent, opening, game.komi).with_params(config.search_params());
+    if let Some(gumbel) = config.gumbel_params() {
+        player = player.with_gumbel(gumbel);
+    }
+    let may_resign = rng::random::<f64>() >= NO_RESIGN_FRACTION;
+    let mut resignation = Resignation::default();
 
-    while matches!(game.winner(), GameResult::Ongoing) {
-        if game.ply < NOISE_PLIES {
-            player.apply_dirichlet(&game, DIRICHLET_NOISE, NOISE_RATIO);
+    while matches!(game_result(&game), GameResult::Ongoing) {
+        // Gumbel search explores by sampling, so it needs neither noise nor
+        // temperature
+        let chosen = if config.gumbel_moves.is_some() {
+            Some(player.gumbel_move(&game, config.rollouts_per_move))
+        } else {
+            if game.ply < NOISE_PLIES {
+                player.apply_dirichlet(&game, config.dirichlet_alpha, config.noise_ratio);
+            }
+            if game.ply < OPENING_TREE_PLIES {
+                player.rollout_shared(
+                    &game,
+                    &OPENING_TREE,
+                    config.rollouts_per_move,
+                    OPENING_TREE_MIN_ROLLOUTS,
+                );
+            } else {
+                player.rollout(&game, config.rollouts_per_move);
+            }
+            None
+        };
+        if resignation.update(&game, player.evaluation()) {
+            if may_resign {
+                player.resign(&game);
+                break;
+            }
+            resignation.checked.get_or_insert(game.to_move);
         }
-        player.rollout(&game, ROLLOUTS_PER_MOVE);
-        let turn = player.pick_move(&game, game.ply > TEMPERATURE_PLIES);
+        let turn = match chosen {
+            Some(turn) => {
+                player.play_move(&game, &turn);
+                turn
+            }
+            None => {
+                let temperature = if game.ply <= config.temperature_plies {
+                    TEMPERATURE
+                } else {
+                    0.
+                };
+                player.pick_move(&game, temperature)
+            }
+        };
         game.play(turn).unwrap();
     }
 
-    (player.get_examples(game.winner()), player.get_analysis())
+    let mut analysis = player.get_analysis();
+    analysis.set_players(SELF_PLAY_NAME, SELF_PLAY_NAME);
+    analysis.set_date(sys_time());
+    let result = analysis.result();
+    if matches!(result, GameResult::Winner {
+        reason: WinReason::Resignation,
+        ..
+    }) {
+        METRICS.resignations.inc_by(1);
+    }
+    if let Some(colour) = resignation.checked {
+        METRICS.resignations_checked.inc_by(1);
+        if !matches!(result, GameResult::Winner { colour: winner, .. } if winner != colour) {
+            METRICS.false_resignations.inc_by(1);
+        }
+    }
+    let examples = player.get_examples(result);
+    save_game(index, &examples, &analysis);
+    examples
+}
+
+/// Tracks how long each player's evaluation has been hopeless.
+#[derive(Default)]
+struct Resignation {
+    /// Consecutive moves below the threshold of white and black.
+    hopeless: [usize; 2],
+    /// Player who would have resigned first in a game which is played out.
+    checked: Option<Colour>,
+}
+
+impl Resignation {
+    /// Record the evaluation of the player to move and return whether they
+    /// should resign.
+    fn update(&mut self, game: &Game<N>, evaluation: f32) -> bool {
+        let hopeless = &mut self.hopeless[game.to_move as usize];
+        if evaluation < RESIGN_THRESHOLD {
+            *hopeless += 1;
+        } else {
+            *hopeless = 0;
+        }
+        *hopeless >= RESIGN_MOVES
+    }
+}
+
+fn save_game(index: usize, examples: &[Example<N>], analysis: &Analysis<N>) {
+    if let Ok(mut file) = File::create(format!("{PROGRESS_DIR}/{index}.ptn")) {
+        file.write_all(analysis.to_ptn().as_bytes()).unwrap();
+    }
+    // the examples mark the game as completed, so write them atomically
+    let temp = format!("{PROGRESS_DIR}/{index}.data.tmp");
+    save_examples(examples, &temp);
+    rename(&temp, format!("{PROGRESS_DIR}/{index}.data"))
+        .unwrap_or_else(|err| warn!("could not save game: {err}"));
 }
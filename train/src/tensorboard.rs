@@ -0,0 +1,137 @@
+//! Writer of TensorBoard event files with scalar summaries only, encoded by
+//! hand so that no protobuf code has to be generated.
+
+use std::{
+    fs::{create_dir_all, File},
+    io::{self, BufWriter, Write},
+    time::SystemTime,
+};
+
+use alpha_tak::sys_time;
+
+pub struct EventWriter {
+    file: BufWriter<File>,
+}
+
+impl EventWriter {
+    /// Start a new event file in the directory, which TensorBoard shows as
+    /// one run.
+    pub fn create(dir: &str) -> io::Result<Self> {
+        create_dir_all(dir)?;
+        let file = File::create(format!("{dir}/events.out.tfevents.{}.alpha-tak", sys_time()))?;
+        let mut writer = EventWriter {
+            file: BufWriter::new(file),
+        };
+        // Event.file_version, which TensorBoard expects first
+        let mut event = event_header(0);
+        length_delimited(&mut event, 3, b"brain.Event:2");
+        write_record(&mut writer.file, &event)?;
+        writer.file.flush()?;
+        Ok(writer)
+    }
+
+    /// Write the values as scalars of one step.
+    pub fn scalars(&mut self, step: u64, values: &[(&str, f64)]) -> io::Result<()> {
+        let mut summary = Vec::new();
+        for (tag, value) in values {
+            // Summary.Value with tag and simple_value
+            let mut entry = Vec::new();
+            length_delimited(&mut entry, 1, tag.as_bytes());
+            entry.push((2 << 3) | 5);
+            entry.extend((*value as f32).to_le_bytes());
+            length_delimited(&mut summary, 1, &entry);
+        }
+        let mut event = event_header(step);
+        length_delimited(&mut event, 5, &summary);
+        write_record(&mut self.file, &event)?;
+        self.file.flush()
+    }
+}
+
+/// Frame the event like a TFRecord, with masked CRCs of the length and the
+/// data.
+fn write_record(out: &mut impl Write, data: &[u8]) -> io::Result<()> {
+    let length = (data.len() as u64).to_le_bytes();
+    out.write_all(&length)?;
+    out.write_all(&masked_crc(&length).to_le_bytes())?;
+    out.write_all(data)?;
+    out.write_all(&masked_crc(data).to_le_bytes())
+}
+
+/// Event.wall_time and Event.step.
+fn event_header(step: u64) -> Vec<u8> {
+    let wall_time = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64();
+    let mut event = vec![(1 << 3) | 1];
+    event.extend(wall_time.to_le_bytes());
+    event.push(2 << 3);
+    varint(&mut event, step);
+    event
+}
+
+fn length_delimited(out: &mut Vec<u8>, field: u8, bytes: &[u8]) {
+    out.push((field << 3) | 2);
+    varint(out, bytes.len() as u64);
+    out.extend(bytes);
+}
+
+fn varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn masked_crc(data: &[u8]) -> u32 {
+    let crc = crc32c(data);
+    ((crc >> 15) | (crc << 17)).wrapping_add(0xa282_ead8)
+}
+
+/// CRC-32C (Castagnoli), bit by bit since the records are small.
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::{crc32c, masked_crc, write_record};
+
+    #[test]
+    fn crc32c_check_value() {
+        assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+        assert_eq!(crc32c(b""), 0);
+    }
+
+    #[test]
+    fn record_round_trip() {
+        let data = b"brain.Event:2";
+        let mut out = Vec::new();
+        write_record(&mut out, data).unwrap();
+        assert_eq!(out.len(), 8 + 4 + data.len() + 4);
+
+        let (length, rest) = out.split_at(8);
+        let (length_crc, rest) = rest.split_at(4);
+        let (record, data_crc) = rest.split_at(rest.len() - 4);
+        assert_eq!(u64::from_le_bytes(length.try_into().unwrap()), data.len() as u64);
+        assert_eq!(
+            u32::from_le_bytes(length_crc.try_into().unwrap()),
+            masked_crc(length)
+        );
+        assert_eq!(record, data);
+        assert_eq!(u32::from_le_bytes(data_crc.try_into().unwrap()), masked_crc(data));
+    }
+}
@@ -0,0 +1,199 @@
+use std::{error::Error, fs::read_to_string};
+
+use alpha_tak::{
+    config::{
+        BATCH_SIZE,
+        DIRICHLET_NOISE,
+        GENERATION_DECAY,
+        KOMI,
+        LEAGUE_FRACTION,
+        LEARNING_RATE,
+        N,
+        NOISE_RATIO,
+        ROLLOUTS_PER_MOVE,
+        SELF_PLAY_GAMES,
+        SELF_PLAY_WORKERS,
+        TEMPERATURE_PLIES,
+    },
+    model::{
+        network::Network,
+        throughput::{pick_batch_size, CALIBRATION_BATCHES},
+        train::TrainParams,
+    },
+    search::{gumbel::GumbelParams, params::SearchParams},
+};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tak::Komi;
+use tracing::info;
+
+use crate::cli::ConfigArgs;
+
+/// Settings of a training run which can change between experiments without
+/// recompiling. Missing keys in the file keep the defaults from
+/// [`alpha_tak::config`], and flags override both.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrainConfig {
+    /// Written in flats, like `2` or `2.5`.
+    #[serde(serialize_with = "serialize_komi", deserialize_with = "deserialize_komi")]
+    pub komi: Komi,
+    pub self_play_games: usize,
+    /// Self-play games played at the same time, whose positions make up the
+    /// batches of the network. Measured on startup if not given.
+    pub self_play_workers: Option<usize>,
+    pub rollouts_per_move: usize,
+    /// Plies in which self-play picks moves by visits instead of the best one.
+    pub temperature_plies: u64,
+    /// Concentration of the Dirichlet noise added to the policy at the root
+    /// in self-play, smaller values put the noise on fewer moves.
+    pub dirichlet_alpha: f32,
+    /// Share of the noise in the noisy policy.
+    pub noise_ratio: f32,
+    /// See [`SearchParams`].
+    pub cpuct: f32,
+    pub fpu_reduction: Option<f32>,
+    pub policy_temperature: f32,
+    /// Moves sampled at the root when self-play chooses moves by Gumbel
+    /// search, which suits small rollout budgets. Without it self-play uses
+    /// PUCT with Dirichlet noise.
+    pub gumbel_moves: Option<usize>,
+    pub learning_rate: f64,
+    pub batch_size: i64,
+    pub generation_decay: f32,
+    pub league_fraction: f64,
+}
+
+impl Default for TrainConfig {
+    fn default() -> Self {
+        let search = SearchParams::default();
+        TrainConfig {
+            komi: KOMI,
+            self_play_games: SELF_PLAY_GAMES,
+            self_play_workers: None,
+            rollouts_per_move: ROLLOUTS_PER_MOVE,
+            temperature_plies: TEMPERATURE_PLIES,
+            dirichlet_alpha: DIRICHLET_NOISE,
+            noise_ratio: NOISE_RATIO,
+            cpuct: search.cpuct,
+            fpu_reduction: search.fpu_reduction,
+            policy_temperature: search.policy_temperature,
+            gumbel_moves: None,
+            learning_rate: LEARNING_RATE,
+            batch_size: BATCH_SIZE,
+            generation_decay: GENERATION_DECAY,
+            league_fraction: LEAGUE_FRACTION,
+        }
+    }
+}
+
+impl TrainConfig {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str(&read_to_string(path)?)?)
+    }
+
+    /// Read the config file if one is given and apply the flags on top.
+    pub fn from_args(args: &ConfigArgs) -> Result<Self, Box<dyn Error>> {
+        let mut config = match &args.config {
+            Some(path) => Self::load(path)?,
+            None => Self::default(),
+        };
+        if let Some(komi) = args.komi {
+            config.komi = komi;
+        }
+        if let Some(games) = args.self_play_games {
+            config.self_play_games = games;
+        }
+        if let Some(workers) = args.self_play_workers {
+            config.self_play_workers = Some(workers);
+        }
+        if let Some(rollouts) = args.rollouts {
+            config.rollouts_per_move = rollouts;
+        }
+        if let Some(plies) = args.temperature_plies {
+            config.temperature_plies = plies;
+        }
+        if let Some(alpha) = args.dirichlet_alpha {
+            config.dirichlet_alpha = alpha;
+        }
+        if let Some(ratio) = args.noise_ratio {
+            config.noise_ratio = ratio;
+        }
+        if let Some(cpuct) = args.cpuct {
+            config.cpuct = cpuct;
+        }
+        if let Some(reduction) = args.fpu_reduction {
+            config.fpu_reduction = Some(reduction);
+        }
+        if let Some(temperature) = args.policy_temperature {
+            config.policy_temperature = temperature;
+        }
+        if let Some(moves) = args.gumbel_moves {
+            config.gumbel_moves = Some(moves);
+        }
+        if let Some(learning_rate) = args.learning_rate {
+            config.learning_rate = learning_rate;
+        }
+        if let Some(batch_size) = args.batch_size {
+            config.batch_size = batch_size;
+        }
+        if let Some(decay) = args.generation_decay {
+            config.generation_decay = decay;
+        }
+        if let Some(fraction) = args.league_fraction {
+            config.league_fraction = fraction;
+        }
+        Ok(config)
+    }
+
+    /// Pick the number of self-play workers from the throughput of the
+    /// network at each batch size, unless it was given.
+    pub fn calibrate(&mut self, network: &Network<N>) {
+        if self.self_play_workers.is_some() {
+            return;
+        }
+        let timings = network.measure_batches(CALIBRATION_BATCHES, 10);
+        for timing in &timings {
+            info!(
+                batch_size = timing.batch_size,
+                latency_ms = timing.latency.as_secs_f64() * 1000.,
+                throughput = timing.throughput(),
+                "calibration"
+            );
+        }
+        let workers = pick_batch_size(&timings).unwrap_or(SELF_PLAY_WORKERS);
+        info!("playing {workers} self-play games at the same time");
+        self.self_play_workers = Some(workers);
+    }
+
+    pub fn search_params(&self) -> SearchParams {
+        SearchParams {
+            cpuct: self.cpuct,
+            fpu_reduction: self.fpu_reduction,
+            policy_temperature: self.policy_temperature,
+            ..SearchParams::default()
+        }
+    }
+
+    pub fn gumbel_params(&self) -> Option<GumbelParams> {
+        self.gumbel_moves.map(|considered| GumbelParams {
+            considered,
+            ..GumbelParams::default()
+        })
+    }
+
+    pub fn train_params(&self) -> TrainParams {
+        TrainParams {
+            learning_rate: self.learning_rate,
+            batch_size: self.batch_size,
+        }
+    }
+}
+
+fn serialize_komi<S: Serializer>(komi: &Komi, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f32(komi.flats())
+}
+
+fn deserialize_komi<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Komi, D::Error> {
+    let flats = f32::deserialize(deserializer)?;
+    flats.to_string().parse().map_err(serde::de::Error::custom)
+}
@@ -1,50 +1,192 @@
 use alpha_tak::{
-    config::{MAX_EXAMPLES, N, WIN_RATE_THRESHOLD},
-    example::{save_examples, Example},
-    model::network::Network,
+    config::{N, PIT_CONFIDENCE_Z},
+    example::save_examples,
+    metrics::METRICS,
+    model::{aux_head::AuxExamples, network::Network},
+    replay::ReplayBuffer,
+    rng::{self, Stream},
     sys_time,
 };
+use tracing::{info, warn};
 
-use crate::{pit::pit, self_play::self_play, EXAMPLE_DIR, MODEL_DIR};
+use crate::{
+    generation_log::{Generation, GenerationLog},
+    history::{load_history, Checkpoint},
+    interrupt::interrupted,
+    league::League,
+    pit::pit,
+    resume::Manifest,
+    self_play::{clear_progress, self_play, OPENING_TREE},
+    train_config::TrainConfig,
+    EXAMPLE_DIR,
+    HISTORY_FILE,
+    MANIFEST_FILE,
+    MODEL_DIR,
+    REPLAY_FILE,
+};
+
+pub fn training_loop(
+    mut network: Network<N>,
+    mut replay: ReplayBuffer<N>,
+    aux_examples: Vec<AuxExamples>,
+    mut log: GenerationLog,
+    config: TrainConfig,
+    seed: Option<u64>,
+    resume: Option<Manifest>,
+) {
+    let history = load_history(HISTORY_FILE).unwrap_or_default();
+
+    // continue the Elo from previous runs
+    let mut elo = history
+        .iter()
+        .rev()
+        .find(|checkpoint| checkpoint.promoted())
+        .map(|checkpoint| checkpoint.elo)
+        .unwrap_or_default();
+
+    // continue the random streams of the previous run, histories from before
+    // seeds were stored start a new run
+    let (seed, mut iteration) = match (seed, &resume, history.last()) {
+        (_, Some(manifest), _) => (manifest.seed, manifest.iteration),
+        (Some(seed), ..) => (seed, 0),
+        (None, None, Some(last)) if last.seed != 0 => (last.seed, last.iteration + 1),
+        _ => (rand::random(), 0),
+    };
+    info!("seed of the run is {seed}, starting at iteration {iteration}");
+    // an iteration stopped during self-play has already trained and pitted
+    let mut skip_training = resume.map_or(false, |manifest| manifest.in_self_play);
 
-pub fn training_loop(mut network: Network<N>, mut examples: Vec<Example<N>>) -> ! {
     loop {
-        if !examples.is_empty() {
-            let new_network = {
+        if interrupted() {
+            save_state(&network, &replay, seed, iteration, false);
+            return;
+        }
+        rng::set_run(seed, iteration);
+        let mut generation = Generation {
+            iteration,
+            ..Default::default()
+        };
+
+        if !replay.is_empty() && !skip_training {
+            let (new_network, losses) = {
+                rng::set_stream(Stream::Training);
+                rng::seed_torch(rng::seed_task(0));
                 let mut nn = copy(&network);
-                nn.train(&examples);
-                nn
+                let refs: Vec<_> = replay.examples().collect();
+                let weights = replay.weights(config.generation_decay);
+                let losses = nn.train_weighted(&refs, &weights, &aux_examples, config.train_params());
+                if let Some(loss) = losses.last() {
+                    METRICS.policy_loss.set(loss.policy as f64);
+                    METRICS.value_loss.set(loss.value as f64);
+                }
+                (nn, losses)
             };
 
-            println!("pitting two networks against each other");
-            let (results, more_examples) = pit(&new_network, &network);
+            info!("pitting two networks against each other");
+            rng::set_stream(Stream::Pit);
+            let (results, more_examples) = pit(&new_network, &network, &config);
             save_examples(&more_examples, format!("{EXAMPLE_DIR}/pit_{}.data", sys_time()));
-            examples.extend(more_examples.into_iter());
+            replay.extend(more_examples);
 
-            println!("{:?}", results);
-            if results.win_rate() > WIN_RATE_THRESHOLD {
+            let (low, high) = results.confidence_interval(PIT_CONFIDENCE_Z);
+            info!(
+                "{:?}, win rate {:.3} in [{low:.3}, {high:.3}]",
+                results,
+                results.win_rate()
+            );
+            generation.set_losses(&losses);
+            generation.pit = Some(results);
+            generation.promoted = results.promotes();
+            let mut checkpoint = Checkpoint {
+                time: sys_time(),
+                elo: elo + results.elo_difference(),
+                losses,
+                seed,
+                iteration,
+                ..Default::default()
+            };
+            if generation.promoted {
                 network = new_network;
-                println!("saving model");
-                network.save(format!("{MODEL_DIR}/{}.model", sys_time())).unwrap();
+                info!("saving model");
+                checkpoint.model = format!("{MODEL_DIR}/{}.model", sys_time());
+                network.save(&checkpoint.model).unwrap();
+                elo = checkpoint.elo;
+                // opening statistics from the old network are outdated
+                OPENING_TREE.clear();
+                clear_progress();
             }
+            checkpoint.pit = results;
+            checkpoint
+                .save(HISTORY_FILE)
+                .unwrap_or_else(|err| warn!("could not save history: {err}"));
         }
+        skip_training = false;
+
+        // some of the games go to an older checkpoint
+        rng::set_stream(Stream::League);
+        let league = League::choose(&config);
+        let self_play_config = TrainConfig {
+            self_play_games: config.self_play_games - league.as_ref().map_or(0, League::games),
+            ..config
+        };
 
         // do self-play to get new examples
-        println!("starting self-play");
-        let new_examples = self_play(&network);
+        info!("starting self-play");
+        rng::set_stream(Stream::SelfPlay);
+        let (mut new_examples, stats) = self_play(&network, &self_play_config);
+        if interrupted() {
+            save_state(&network, &replay, seed, iteration, true);
+            return;
+        }
+        generation.self_play = stats;
+        if let Some(league) = &league {
+            rng::set_stream(Stream::League);
+            new_examples.extend(league.play(&network, &config));
+        }
         save_examples(&new_examples, format!("{EXAMPLE_DIR}/{}.data", sys_time()));
 
-        // keep only the latest MAX_EXAMPLES examples
-        examples.extend(new_examples.into_iter());
-        if examples.len() > MAX_EXAMPLES {
-            examples.reverse();
-            examples.truncate(MAX_EXAMPLES);
-            examples.reverse();
-        }
+        // neither the held-out nor the new examples have been trained on
+        log.calibrate(&network, &new_examples, &mut generation);
+
+        replay.next_generation();
+        replay.extend(new_examples);
+        replay
+            .save(REPLAY_FILE)
+            .unwrap_or_else(|err| warn!("could not save replay buffer: {err}"));
+        METRICS.examples.set(replay.len() as f64);
+
+        generation.time = sys_time();
+        generation.examples = replay.len();
+        log.record(&generation);
+        iteration += 1;
+    }
+}
+
+/// Save what the run needs to continue after an interrupt, see [`Manifest`].
+fn save_state(network: &Network<N>, replay: &ReplayBuffer<N>, seed: u64, iteration: u64, in_self_play: bool) {
+    let time = sys_time();
+    let model = format!("{MODEL_DIR}/{time}.model");
+    network
+        .save(&model)
+        .unwrap_or_else(|err| warn!("could not save model: {err}"));
+    replay
+        .save(REPLAY_FILE)
+        .unwrap_or_else(|err| warn!("could not save replay buffer: {err}"));
+    let manifest = Manifest {
+        time,
+        model,
+        replay: REPLAY_FILE.to_string(),
+        seed,
+        iteration,
+        in_self_play,
+    };
+    match manifest.save(MANIFEST_FILE) {
+        Ok(()) => info!("saved the state of the run to {MANIFEST_FILE}"),
+        Err(err) => warn!("could not save {MANIFEST_FILE}: {err}"),
     }
 }
 
-fn copy<const N: usize>(network: &Network<N>) -> Network<N> {
+fn copy(network: &Network<N>) -> Network<N> {
     // copy network values by file (ugly but works)
     let mut dir = std::env::temp_dir();
     dir.push("model");
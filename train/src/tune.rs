@@ -0,0 +1,225 @@
+use std::{error::Error, fs::File, io::Write};
+
+use alpha_tak::{
+    agent::Agent,
+    config::{
+        DIRICHLET_NOISE,
+        EXPLORATION_BASE,
+        EXPLORATION_INIT,
+        FPU_REDUCTION,
+        KOMI,
+        N,
+        NOISE_PLIES,
+        NOISE_RATIO,
+    },
+    game_result,
+    model::network::Network,
+    player::Player,
+    search::{params::SearchParams, turn_map::Lut},
+    threadpool::thread_pool_2,
+};
+use rand::random;
+use tak::*;
+use tracing::info;
+
+/// Number of parameters being tuned.
+const PARAMS: usize = 5;
+
+/// A search parameter of the tuner, values are given as in `config.rs`.
+#[derive(Clone, Copy, Debug)]
+pub struct Param {
+    /// Name of the constant in `config.rs`.
+    pub name: &'static str,
+    pub value: f32,
+    /// Typical size of a meaningful change, the tuner works in these units.
+    pub step: f32,
+    pub min: f32,
+    pub max: f32,
+    /// Whether the constant is an `Option`, which the tuned value is `Some`
+    /// of.
+    pub optional: bool,
+}
+
+/// The parameters which the search takes at runtime, with their current
+/// values as the starting point.
+pub fn default_params() -> [Param; PARAMS] {
+    [
+        Param {
+            name: "DIRICHLET_NOISE",
+            value: DIRICHLET_NOISE,
+            step: 0.05,
+            min: 0.01,
+            max: 2.,
+            optional: false,
+        },
+        Param {
+            name: "NOISE_RATIO",
+            value: NOISE_RATIO,
+            step: 0.05,
+            min: 0.,
+            max: 1.,
+            optional: false,
+        },
+        Param {
+            name: "EXPLORATION_INIT",
+            value: EXPLORATION_INIT,
+            step: 0.25,
+            min: 0.1,
+            max: 10.,
+            optional: false,
+        },
+        Param {
+            name: "EXPLORATION_BASE",
+            value: EXPLORATION_BASE,
+            step: 50.,
+            min: 10.,
+            max: 10000.,
+            optional: false,
+        },
+        // the tuned search always has a reduction, which starts at zero if
+        // the config values unvisited children as a draw instead
+        Param {
+            name: "FPU_REDUCTION",
+            value: FPU_REDUCTION.unwrap_or(0.),
+            step: 0.05,
+            min: 0.,
+            max: 1.,
+            optional: true,
+        },
+    ]
+}
+
+/// Search parameters of the player using the tuned values, in the order of
+/// [`default_params`].
+fn search_params([_, _, cpuct, cpuct_base, fpu_reduction]: [f32; PARAMS]) -> SearchParams {
+    SearchParams {
+        cpuct,
+        cpuct_base,
+        fpu_reduction: Some(fpu_reduction),
+        ..Default::default()
+    }
+}
+
+/// Settings of the SPSA tuner.
+#[derive(Clone, Copy, Debug)]
+pub struct Tuner {
+    pub iterations: usize,
+    /// Pairs of games, one with each colour, played per iteration.
+    pub matches: usize,
+    pub rollouts: usize,
+    /// Size of the perturbations in steps at the first iteration.
+    pub perturbation: f32,
+    /// Size of the updates in steps at the first iteration.
+    pub learning_rate: f32,
+}
+
+impl Tuner {
+    /// Tune the parameters with simultaneous perturbation stochastic
+    /// approximation: each iteration plays the parameters shifted randomly
+    /// in both directions against each other and moves towards the winner.
+    pub fn run(&self, network: &Network<N>, mut params: [Param; PARAMS]) -> [Param; PARAMS] {
+        const WORKERS: usize = 64;
+        // usual SPSA decay, with the stability constant at a tenth of the run
+        let stability = self.iterations as f32 / 10.;
+
+        for k in 1..=self.iterations {
+            let c = self.perturbation / (k as f32).powf(0.101);
+            let a = self.learning_rate * (1. + stability).powf(0.602) / (k as f32 + stability).powf(0.602);
+            let delta: [f32; PARAMS] = [(); PARAMS].map(|_| if random() { 1. } else { -1. });
+
+            let shifted = |sign: f32| {
+                let mut shifted = [0.; PARAMS];
+                for (i, param) in params.iter().enumerate() {
+                    shifted[i] = (param.value + sign * c * delta[i] * param.step).clamp(param.min, param.max);
+                }
+                shifted
+            };
+            let (plus, minus) = (shifted(1.), shifted(-1.));
+            let rollouts = self.rollouts;
+
+            let outputs = thread_pool_2::<N, WORKERS, _, _>(
+                network,
+                network,
+                self.matches,
+                move |plus_agent, minus_agent, _| tune_match(plus_agent, minus_agent, plus, minus, rollouts),
+            );
+            let games = 2 * self.matches.max(1);
+            let score = outputs.into_iter().sum::<f32>() / games as f32;
+
+            for (i, param) in params.iter_mut().enumerate() {
+                param.value =
+                    (param.value + a * score / (2. * c) * delta[i] * param.step).clamp(param.min, param.max);
+            }
+            info!(
+                iteration = k,
+                score,
+                params = ?params.iter().map(|param| (param.name, param.value)).collect::<Vec<_>>(),
+                "tuning iteration"
+            );
+        }
+        params
+    }
+}
+
+/// Play an opening from both sides with the two parameter sets, returning
+/// the score of the first one from -2 to 2.
+fn tune_match<A: Agent<N>>(
+    plus_agent: &A,
+    minus_agent: &A,
+    plus: [f32; PARAMS],
+    minus: [f32; PARAMS],
+    rollouts: usize,
+) -> f32
+where
+    [[Option<Tile>; N]; N]: Default,
+    Turn<N>: Lut,
+{
+    let mut score = 0.;
+    let opening_index = random();
+    for plus_colour in [Colour::White, Colour::Black] {
+        let mut game = Game::with_komi(KOMI);
+        let opening = game.opening(opening_index).unwrap();
+        let mut plus_player =
+            Player::new(plus_agent, opening.clone(), game.komi).with_params(search_params(plus));
+        let mut minus_player = Player::new(minus_agent, opening, game.komi).with_params(search_params(minus));
+
+        while matches!(game_result(&game), GameResult::Ongoing) {
+            let (player, other, [alpha, ratio, ..]) = if game.to_move == plus_colour {
+                (&mut plus_player, &mut minus_player, plus)
+            } else {
+                (&mut minus_player, &mut plus_player, minus)
+            };
+            if game.ply < NOISE_PLIES {
+                player.apply_dirichlet(&game, alpha, ratio);
+            }
+            player.rollout(&game, rollouts);
+            let turn = player.pick_move(&game, 0.);
+            other.play_move(&game, &turn);
+            game.play(turn).unwrap();
+        }
+
+        score += match game_result(&game) {
+            GameResult::Winner { colour, .. } if colour == plus_colour => 1.,
+            GameResult::Winner { .. } => -1.,
+            _ => 0.,
+        };
+    }
+    score
+}
+
+/// Write the tuned values as constants to paste into `config.rs`.
+pub fn save_params(params: &[Param], path: &str) -> Result<(), Box<dyn Error>> {
+    let mut file = File::create(path)?;
+    for param in params {
+        if param.optional {
+            writeln!(
+                file,
+                "pub const {}: Option<f32> = Some({:.4});",
+                param.name, param.value
+            )?;
+        } else {
+            writeln!(file, "pub const {}: f32 = {:.4};", param.name, param.value)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,178 @@
+//! Browser build of the alpha-tak search.
+//!
+//! The network runs outside of wasm (onnxruntime-web with the WebGPU backend),
+//! so the search is driven from JS one evaluation at a time:
+//!
+//! ```js
+//! let input;
+//! while (analyzer.rollouts() < target && (input = analyzer.request())) {
+//!     const { policy, eval } = await evaluate(input);
+//!     analyzer.respond(policy, eval);
+//! }
+//! ```
+//!
+//! See `www/analyze.js` for the inference side.
+
+use alpha_tak::{
+    config::{KOMI, N},
+    repr::{game_features, input_channels, moves_dims},
+    search::{
+        node::Node,
+        params::SearchParams,
+        step::{Descent, PendingLeaf},
+    },
+};
+use js_sys::Float32Array;
+use tak::*;
+use wasm_bindgen::prelude::*;
+
+/// How many rollouts ending in finished games to do before giving up on a
+/// request.
+const TERMINAL_ROLLOUTS: usize = 1000;
+
+#[wasm_bindgen]
+pub struct Analyzer {
+    game: Game<N>,
+    node: Node<N>,
+    pending: Option<PendingLeaf<N>>,
+}
+
+#[wasm_bindgen]
+impl Analyzer {
+    /// Start analysing from the given PTN, or from an empty board if it is
+    /// empty. Without a `Komi` tag the game has the komi the network was
+    /// trained with.
+    #[wasm_bindgen(constructor)]
+    pub fn new(ptn: &str) -> Result<Analyzer, JsValue> {
+        let mut game = if ptn.trim().is_empty() {
+            Game::default()
+        } else {
+            Game::from_ptn(ptn).map_err(JsValue::from)?
+        };
+        if !ptn_tags(ptn).iter().any(|(key, _)| key == "Komi") {
+            game.komi = KOMI;
+        }
+        Ok(Analyzer {
+            game,
+            node: Node::default(),
+            pending: None,
+        })
+    }
+
+    /// Board size the network was trained for.
+    pub fn size() -> usize {
+        N
+    }
+
+    /// Shape of the network input, `[channels, size, size]`.
+    #[wasm_bindgen(js_name = inputShape)]
+    pub fn input_shape() -> Vec<u32> {
+        vec![input_channels(N) as u32, N as u32, N as u32]
+    }
+
+    /// Length of the policy expected by `respond`.
+    #[wasm_bindgen(js_name = policySize)]
+    pub fn policy_size() -> usize {
+        moves_dims(N)
+    }
+
+    /// Play a move in PTN, keeping the relevant part of the search tree.
+    pub fn play(&mut self, ptn: &str) -> Result<(), JsValue> {
+        let turn = Turn::from_ptn(ptn).map_err(JsValue::from)?;
+        self.game.play(turn.clone()).map_err(JsValue::from)?;
+        if let Some(leaf) = self.pending.take() {
+            self.node.abandon(&leaf);
+        }
+        let node = std::mem::take(&mut self.node);
+        self.node = match node.children {
+            Some(_) => node.play(&turn),
+            None => Node::default(),
+        };
+        Ok(())
+    }
+
+    /// Network input for the next position to evaluate,
+    /// or `undefined` if the game is over or every line searched ends the game.
+    /// Rollouts that end in a finished game are resolved without the network.
+    pub fn request(&mut self) -> Option<Float32Array> {
+        if !matches!(self.game.winner(), GameResult::Ongoing) {
+            return None;
+        }
+        for _ in 0..TERMINAL_ROLLOUTS {
+            if self.pending.is_some() {
+                break;
+            }
+            if let Descent::Leaf(leaf) = self.node.descend(self.game.clone(), &SearchParams::default()) {
+                self.pending = Some(leaf);
+            }
+        }
+        let features = game_features(&self.pending.as_ref()?.game);
+        Some(Float32Array::from(features.as_slice()))
+    }
+
+    /// Answer the last request with the network output.
+    pub fn respond(&mut self, policy: &[f32], eval: f32) -> Result<(), JsValue> {
+        if policy.len() != moves_dims(N) {
+            return Err(JsValue::from(format!(
+                "expected a policy of length {}, got {}",
+                moves_dims(N),
+                policy.len()
+            )));
+        }
+        let leaf = self
+            .pending
+            .take()
+            .ok_or_else(|| JsValue::from("no position was requested"))?;
+        self.node.ascend(&leaf, policy, eval, &SearchParams::default());
+        Ok(())
+    }
+
+    /// Number of rollouts in the current search tree.
+    pub fn rollouts(&self) -> u32 {
+        self.node.visited_count
+    }
+
+    /// Current analysis as JSON:
+    /// `{"eval", "rollouts", "pv", "moves": [{"move", "visits", "eval",
+    /// "policy"}]}`, with evals from the perspective of the player to move.
+    pub fn analysis(&self, limit: usize) -> String {
+        let mut moves: Vec<_> = self.node.children.iter().flatten().collect();
+        moves.sort_by_key(|(_, child)| std::cmp::Reverse(child.visited_count));
+        let moves: Vec<_> = moves
+            .into_iter()
+            .take(limit)
+            .map(|(turn, child)| {
+                format!(
+                    r#"{{"move":"{}","visits":{},"eval":{},"policy":{}}}"#,
+                    turn.to_ptn(),
+                    child.visited_count,
+                    child.expected_reward,
+                    child.policy
+                )
+            })
+            .collect();
+        let pv: Vec<_> = if self.node.children.is_some() {
+            self.node
+                .continuation(0, 10)
+                .iter()
+                .map(|turn| format!(r#""{}""#, turn.to_ptn()))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        format!(
+            r#"{{"eval":{},"rollouts":{},"pv":[{}],"moves":[{}]}}"#,
+            -self.node.expected_reward,
+            self.node.visited_count,
+            pv.join(","),
+            moves.join(",")
+        )
+    }
+
+    /// Best move found so far in PTN.
+    #[wasm_bindgen(js_name = bestMove)]
+    pub fn best_move(&self) -> Option<String> {
+        self.node.children.as_ref()?;
+        Some(self.node.pick_move(0.).to_ptn())
+    }
+}
@@ -0,0 +1,36 @@
+// Drives the wasm search with a network running in onnxruntime-web.
+//
+// Build with `wasm-pack build --target web` and serve this directory
+// next to `pkg/` and an exported `model.onnx` with an `input` tensor
+// and `policy` and `eval` outputs.
+
+import * as ort from "https://cdn.jsdelivr.net/npm/onnxruntime-web/dist/esm/ort.webgpu.min.js";
+import init, { Analyzer } from "../pkg/web.js";
+
+export async function createAnalyzer(modelUrl = "model.onnx") {
+    await init();
+    const session = await ort.InferenceSession.create(modelUrl, {
+        executionProviders: ["webgpu", "wasm"],
+    });
+    const shape = [1, ...Analyzer.inputShape()];
+
+    async function evaluate(input) {
+        const output = await session.run({ input: new ort.Tensor("float32", input, shape) });
+        return { policy: output.policy.data, eval: output.eval.data[0] };
+    }
+
+    // Search `ptn` for `rollouts` rollouts and return the analysis object.
+    return async function analyze(ptn, rollouts = 800, limit = 10) {
+        const analyzer = new Analyzer(ptn);
+        try {
+            let input;
+            while (analyzer.rollouts() < rollouts && (input = analyzer.request())) {
+                const { policy, eval: value } = await evaluate(input);
+                analyzer.respond(policy, value);
+            }
+            return JSON.parse(analyzer.analysis(limit));
+        } finally {
+            analyzer.free();
+        }
+    };
+}